@@ -0,0 +1,765 @@
+/*
+mc_schem is a rust library to generate, load, manipulate and save minecraft schematic files.
+Copyright (C) 2024  joseph
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use std::collections::{BTreeMap, HashMap};
+use fastnbt::Value;
+use crate::block::Block;
+use crate::error::Error;
+use crate::unwrap_opt_tag;
+use crate::schem::id_of_nbt_tag;
+
+/// Block version of Minecraft Bedrock 1.21.0, it's stored as `version` in bedrock block palette.
+/// The version is encoded as `major << 24 | minor << 16 | patch << 8 | revision`
+pub const BEDROCK_BLOCK_VERSION_1_21_0: i32 = 0x01_15_00_00;
+
+/// A block in Minecraft Bedrock edition. Different from java edition, states of bedrock blocks are
+/// typed, and they can be byte, int or string.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BedrockBlock {
+    /// Full name of the block with namespace, like `minecraft:stone`
+    pub name: String,
+    /// Block states
+    pub states: HashMap<String, Value>,
+}
+
+impl BedrockBlock {
+    /// Create a block without states
+    pub fn new(name: &str) -> BedrockBlock {
+        return BedrockBlock {
+            name: name.to_string(),
+            states: HashMap::new(),
+        };
+    }
+
+    /// Bedrock water block, used in the second layer for waterlogged blocks
+    pub fn water() -> BedrockBlock {
+        let mut result = Self::new("minecraft:water");
+        result.states.insert("liquid_depth".to_string(), Value::Int(0));
+        return result;
+    }
+
+    /// Returns true if the block is still or flowing water
+    pub fn is_water(&self) -> bool {
+        return self.name == "minecraft:water" || self.name == "minecraft:flowing_water";
+    }
+
+    /// Returns true if the block is air
+    pub fn is_air(&self) -> bool {
+        return self.name == "minecraft:air";
+    }
+
+    /// Parse a bedrock block in palette
+    pub fn from_nbt(nbt: &HashMap<String, Value>, tag_path: &str) -> Result<BedrockBlock, Error> {
+        let name = unwrap_opt_tag!(nbt.get("name"),String,String::new(),&*format!("{}/name", tag_path));
+        let mut result = Self::new(name);
+        if let Some(states) = nbt.get("states") {
+            let states = crate::unwrap_tag!(states,Compound,HashMap::new(),&*format!("{}/states", tag_path));
+            result.states = states.clone();
+        }
+        return Ok(result);
+    }
+
+    /// Convert the block into bedrock block palette format
+    pub fn to_nbt(&self, version: i32) -> HashMap<String, Value> {
+        let mut nbt = HashMap::new();
+        nbt.insert("name".to_string(), Value::String(self.name.clone()));
+        nbt.insert("states".to_string(), Value::Compound(self.states.clone()));
+        nbt.insert("version".to_string(), Value::Int(version));
+        return nbt;
+    }
+}
+
+/// Blocks that are only renamed between java and bedrock edition, states are converted by other rules.
+/// `(java id, bedrock id)`
+const RENAMED_BLOCKS: &[(&str, &str)] = &[
+    ("dirt_path", "grass_path"),
+    ("cobweb", "web"),
+    ("dead_bush", "deadbush"),
+    ("lily_pad", "waterlily"),
+    ("nether_portal", "portal"),
+    ("magma_block", "magma"),
+    ("nether_bricks", "nether_brick"),
+    ("red_nether_bricks", "red_nether_brick"),
+    ("terracotta", "hardened_clay"),
+    ("melon", "melon_block"),
+    ("slime_block", "slime"),
+    ("powered_rail", "golden_rail"),
+    ("sugar_cane", "reeds"),
+    ("bricks", "brick_block"),
+    ("spawner", "mob_spawner"),
+    ("end_stone_bricks", "end_bricks"),
+    ("moving_piston", "moving_block"),
+    ("beetroots", "beetroot"),
+    ("tripwire", "trip_wire"),
+    ("oak_trapdoor", "trapdoor"),
+    ("oak_door", "wooden_door"),
+    ("oak_button", "wooden_button"),
+    ("oak_pressure_plate", "wooden_pressure_plate"),
+    ("oak_fence_gate", "fence_gate"),
+    ("snow_block", "snow"),
+    ("jack_o_lantern", "lit_pumpkin"),
+    ("note_block", "noteblock"),
+    ("shulker_box", "undyed_shulker_box"),
+    ("light_gray_glazed_terracotta", "silver_glazed_terracotta"),
+    ("kelp_plant", "kelp"),
+    ("tall_seagrass", "seagrass"),
+    ("water_cauldron", "cauldron"),
+    ("oak_sign", "standing_sign"),
+    ("oak_wall_sign", "wall_sign"),
+    ("stone_slab", "normal_stone_slab"),
+];
+
+/// Blocks that have `lit` property in java, but have a `lit_` prefixed id in bedrock when lit
+const LIT_PREFIXED_BLOCKS: &[&str] = &["furnace", "blast_furnace", "smoker", "redstone_ore",
+    "deepslate_redstone_ore", "redstone_lamp"];
+
+/// Crops whose `age` property is named `growth` in bedrock
+const GROWTH_CROPS: &[&str] = &["wheat", "carrots", "potatoes", "beetroots", "melon_stem", "pumpkin_stem"];
+
+/// Java properties that are renamed in bedrock without changing value `(java, bedrock)`
+const RENAMED_PROPERTIES: &[(&str, &str)] = &[
+    ("open", "open_bit"),
+    ("in_wall", "in_wall_bit"),
+    ("persistent", "persistent_bit"),
+    ("power", "redstone_signal"),
+    ("moisture", "moisturized_amount"),
+    ("bites", "bite_counter"),
+    ("charges", "respawn_anchor_charge"),
+    ("rotation", "ground_sign_direction"),
+    ("triggered", "triggered_bit"),
+    ("conditional", "conditional_bit"),
+    ("attached", "attached_bit"),
+    ("disarmed", "disarmed_bit"),
+    ("drag", "drag_down"),
+    ("occupied", "occupied_bit"),
+];
+
+/// Java properties that don't exist in bedrock, bedrock computes them from neighbors or stores
+/// them in block entities
+fn is_dropped_property(java_id: &str, key: &str) -> bool {
+    let is_connection = ["north", "south", "east", "west"].contains(&key);
+    if is_connection && (java_id.ends_with("_fence") || java_id.ends_with("_pane")
+        || java_id == "iron_bars" || java_id == "redstone_wire" || java_id == "tripwire") {
+        return true;
+    }
+    return match key {
+        "waterlogged" | "snowy" => true,
+        "shape" => java_id.ends_with("_stairs"),
+        "distance" => java_id.ends_with("_leaves"),
+        "powered" => java_id.ends_with("_door") || java_id.ends_with("_trapdoor")
+            || java_id.ends_with("_fence_gate") || java_id == "note_block",
+        "instrument" | "note" => java_id == "note_block",
+        "extended" => java_id == "piston" || java_id == "sticky_piston",
+        "short" => java_id == "piston_head",
+        "locked" => java_id == "repeater",
+        "type" => java_id == "chest" || java_id == "trapped_chest",
+        _ => false,
+    };
+}
+
+/// How `facing` of java blocks is stored in bedrock
+enum FacingState {
+    /// Stored as an int, the value is index of facing in the list
+    Indexed(&'static str, &'static [&'static str]),
+    /// Stored as a string, the value is the same as java
+    Named(&'static str),
+}
+
+const WEIRDO_DIRECTIONS: &[&str] = &["east", "west", "south", "north"];
+const DOOR_DIRECTIONS: &[&str] = &["east", "south", "west", "north"];
+const LEGACY_DIRECTIONS: &[&str] = &["south", "west", "north", "east"];
+const FACING_DIRECTIONS: &[&str] = &["down", "up", "north", "south", "west", "east"];
+
+fn facing_state_of(java_id: &str) -> Option<FacingState> {
+    if java_id.ends_with("_stairs") {
+        return Some(FacingState::Indexed("weirdo_direction", WEIRDO_DIRECTIONS));
+    }
+    if java_id.ends_with("_trapdoor") {
+        return Some(FacingState::Indexed("direction", WEIRDO_DIRECTIONS));
+    }
+    if java_id.ends_with("_door") {
+        return Some(FacingState::Indexed("direction", DOOR_DIRECTIONS));
+    }
+    if java_id.ends_with("_fence_gate") || java_id.ends_with("_bed") {
+        return Some(FacingState::Indexed("direction", LEGACY_DIRECTIONS));
+    }
+    if java_id.ends_with("_wall_sign") || java_id.ends_with("_wall_banner") {
+        return Some(FacingState::Indexed("facing_direction", FACING_DIRECTIONS));
+    }
+    return match java_id {
+        "cocoa" | "end_portal_frame" | "tripwire_hook" =>
+            Some(FacingState::Indexed("direction", LEGACY_DIRECTIONS)),
+        "furnace" | "blast_furnace" | "smoker" | "chest" | "trapped_chest" | "ender_chest" |
+        "carved_pumpkin" | "jack_o_lantern" | "repeater" | "comparator" | "anvil" | "chipped_anvil" |
+        "damaged_anvil" | "stonecutter" | "loom" =>
+            Some(FacingState::Named("minecraft:cardinal_direction")),
+        "dispenser" | "dropper" | "piston" | "sticky_piston" | "piston_head" | "hopper" | "barrel" |
+        "end_rod" | "command_block" | "chain_command_block" | "repeating_command_block" |
+        "lightning_rod" | "ladder" =>
+            Some(FacingState::Indexed("facing_direction", FACING_DIRECTIONS)),
+        "observer" => Some(FacingState::Named("minecraft:facing_direction")),
+        _ => None,
+    };
+}
+
+/// Convert a java property value to bedrock state, booleans are stored as bytes.
+fn java_value_to_state(value: &str) -> Value {
+    if value == "true" {
+        return Value::Byte(1);
+    }
+    if value == "false" {
+        return Value::Byte(0);
+    }
+    if let Ok(num) = value.parse::<i32>() {
+        return Value::Int(num);
+    }
+    return Value::String(value.to_string());
+}
+
+/// Convert a bedrock state to java property value
+fn state_to_java_value(state: &Value) -> String {
+    return match state {
+        Value::Byte(0) => "false".to_string(),
+        Value::Byte(1) => "true".to_string(),
+        Value::Byte(b) => b.to_string(),
+        Value::Short(s) => s.to_string(),
+        Value::Int(i) => i.to_string(),
+        Value::Long(l) => l.to_string(),
+        Value::String(s) => s.clone(),
+        other => format!("{:?}", other),
+    };
+}
+
+fn state_as_i32(state: Option<&Value>) -> Option<i32> {
+    return match state {
+        Some(Value::Byte(b)) => Some(*b as i32),
+        Some(Value::Short(s)) => Some(*s as i32),
+        Some(Value::Int(i)) => Some(*i),
+        Some(Value::String(s)) => s.parse().ok(),
+        _ => None,
+    };
+}
+
+fn state_as_bool(state: Option<&Value>) -> bool {
+    return state_as_i32(state).unwrap_or(0) != 0;
+}
+
+fn bool_state(value: bool) -> Value {
+    return Value::Byte(value as i8);
+}
+
+fn take_bool(attrs: &mut BTreeMap<String, String>, key: &str) -> bool {
+    return attrs.remove(key).map(|v| v == "true").unwrap_or(false);
+}
+
+fn take_i32(attrs: &mut BTreeMap<String, String>, key: &str) -> Option<i32> {
+    return attrs.remove(key).and_then(|v| v.parse().ok());
+}
+
+/// Convert java block id to bedrock, properties that are encoded in id will be consumed
+fn java_id_to_bedrock(java_id: &str, attrs: &mut BTreeMap<String, String>, states: &mut HashMap<String, Value>) -> String {
+    match java_id {
+        "cave_air" | "void_air" => return "air".to_string(),
+        "grass" => return "short_grass".to_string(),
+        "water" | "lava" => {
+            let level = take_i32(attrs, "level").unwrap_or(0);
+            states.insert("liquid_depth".to_string(), Value::Int(level));
+            return if level == 0 { java_id.to_string() } else { format!("flowing_{java_id}") };
+        }
+        "snow" => {
+            let layers = take_i32(attrs, "layers").unwrap_or(1);
+            states.insert("height".to_string(), Value::Int(layers - 1));
+            states.insert("covered_bit".to_string(), bool_state(false));
+            return "snow_layer".to_string();
+        }
+        "torch" | "soul_torch" => {
+            states.insert("torch_facing_direction".to_string(), Value::String("top".to_string()));
+            return java_id.to_string();
+        }
+        "wall_torch" | "soul_wall_torch" => {
+            let facing = attrs.remove("facing").unwrap_or("north".to_string());
+            states.insert("torch_facing_direction".to_string(), Value::String(facing));
+            return java_id.replace("wall_", "");
+        }
+        "redstone_torch" | "redstone_wall_torch" => {
+            let facing = attrs.remove("facing").unwrap_or("top".to_string());
+            states.insert("torch_facing_direction".to_string(), Value::String(facing));
+            let lit = attrs.remove("lit").map(|v| v == "true").unwrap_or(true);
+            return if lit { "redstone_torch".to_string() } else { "unlit_redstone_torch".to_string() };
+        }
+        "repeater" => {
+            let delay = take_i32(attrs, "delay").unwrap_or(1);
+            states.insert("repeater_delay".to_string(), Value::Int(delay - 1));
+            return if take_bool(attrs, "powered") { "powered_repeater" } else { "unpowered_repeater" }.to_string();
+        }
+        "comparator" => {
+            let subtract = attrs.remove("mode").map(|m| m == "subtract").unwrap_or(false);
+            let powered = take_bool(attrs, "powered");
+            states.insert("output_subtract_bit".to_string(), bool_state(subtract));
+            states.insert("output_lit_bit".to_string(), bool_state(powered));
+            return if powered { "powered_comparator" } else { "unpowered_comparator" }.to_string();
+        }
+        "daylight_detector" => {
+            return if take_bool(attrs, "inverted") { "daylight_detector_inverted" } else { "daylight_detector" }.to_string();
+        }
+        "piston_head" => {
+            let sticky = attrs.remove("type").map(|t| t == "sticky").unwrap_or(false);
+            return if sticky { "sticky_piston_arm_collision" } else { "piston_arm_collision" }.to_string();
+        }
+        "lever" => {
+            let face = attrs.remove("face").unwrap_or("wall".to_string());
+            let facing = attrs.remove("facing").unwrap_or("north".to_string());
+            let along_x = facing == "east" || facing == "west";
+            let direction = match face.as_str() {
+                "floor" => if along_x { "up_east_west".to_string() } else { "up_north_south".to_string() },
+                "ceiling" => if along_x { "down_east_west".to_string() } else { "down_north_south".to_string() },
+                _ => facing,
+            };
+            states.insert("lever_direction".to_string(), Value::String(direction));
+            states.insert("open_bit".to_string(), bool_state(take_bool(attrs, "powered")));
+            return java_id.to_string();
+        }
+        "campfire" | "soul_campfire" => {
+            let lit = attrs.remove("lit").map(|v| v == "true").unwrap_or(true);
+            states.insert("extinguished".to_string(), bool_state(!lit));
+            attrs.remove("signal_fire");
+            return java_id.to_string();
+        }
+        _ => {}
+    }
+
+    if LIT_PREFIXED_BLOCKS.contains(&java_id) {
+        return if take_bool(attrs, "lit") { format!("lit_{java_id}") } else { java_id.to_string() };
+    }
+
+    if java_id.ends_with("_button") {
+        let face = attrs.remove("face").unwrap_or("wall".to_string());
+        let facing = attrs.remove("facing").unwrap_or("north".to_string());
+        let facing_direction = match face.as_str() {
+            "floor" => 1,
+            "ceiling" => 0,
+            _ => FACING_DIRECTIONS.iter().position(|f| *f == facing).unwrap_or(2) as i32,
+        };
+        states.insert("facing_direction".to_string(), Value::Int(facing_direction));
+        states.insert("button_pressed_bit".to_string(), bool_state(take_bool(attrs, "powered")));
+    }
+
+    if java_id.ends_with("_bed") {
+        // color of bed is stored in block entity in bedrock
+        let head = attrs.remove("part").map(|p| p == "head").unwrap_or(false);
+        states.insert("head_piece_bit".to_string(), bool_state(head));
+        return "bed".to_string();
+    }
+    if java_id.ends_with("_wall_banner") {
+        return "wall_banner".to_string();
+    }
+    if java_id.ends_with("_banner") {
+        return "standing_banner".to_string();
+    }
+    if java_id.ends_with("_slab") {
+        let slab_type = attrs.remove("type").unwrap_or("bottom".to_string());
+        let id = match RENAMED_BLOCKS.iter().find(|(j, _)| *j == java_id) {
+            Some((_, b)) => b.to_string(),
+            None => java_id.to_string(),
+        };
+        if slab_type == "double" {
+            return format!("{}_double_slab", id.strip_suffix("_slab").unwrap());
+        }
+        states.insert("minecraft:vertical_half".to_string(), Value::String(slab_type));
+        return id;
+    }
+
+    if let Some((_, bedrock_id)) = RENAMED_BLOCKS.iter().find(|(j, _)| *j == java_id) {
+        return bedrock_id.to_string();
+    }
+    if java_id.ends_with("_sign") && !java_id.ends_with("_wall_sign") && !java_id.ends_with("_hanging_sign") {
+        return format!("{}_standing_sign", java_id.strip_suffix("_sign").unwrap());
+    }
+    return java_id.to_string();
+}
+
+/// Convert java properties that still exist in bedrock with different name or value
+fn java_properties_to_bedrock(java_id: &str, attrs: &mut BTreeMap<String, String>, states: &mut HashMap<String, Value>) {
+    attrs.retain(|key, _| !is_dropped_property(java_id, key));
+
+    if let Some(facing) = attrs.get("facing") {
+        match facing_state_of(java_id) {
+            Some(FacingState::Indexed(name, list)) => {
+                if let Some(idx) = list.iter().position(|f| f == facing) {
+                    states.insert(name.to_string(), Value::Int(idx as i32));
+                    attrs.remove("facing");
+                }
+            }
+            Some(FacingState::Named(name)) => {
+                states.insert(name.to_string(), Value::String(attrs.remove("facing").unwrap()));
+            }
+            None => {}
+        }
+    }
+
+    if let Some(half) = attrs.remove("half") {
+        match half.as_str() {
+            "upper" | "lower" => states.insert("upper_block_bit".to_string(), bool_state(half == "upper")),
+            _ => states.insert("upside_down_bit".to_string(), bool_state(half == "top")),
+        };
+    }
+    if let Some(hinge) = attrs.remove("hinge") {
+        states.insert("door_hinge_bit".to_string(), bool_state(hinge == "right"));
+    }
+    if let Some(axis) = attrs.remove("axis") {
+        let name = if java_id == "nether_portal" { "portal_axis" } else { "pillar_axis" };
+        states.insert(name.to_string(), Value::String(axis));
+    }
+    if let Some(age) = attrs.get("age") {
+        if GROWTH_CROPS.contains(&java_id) {
+            let mut age: i32 = age.parse().unwrap_or(0);
+            if java_id == "beetroots" {
+                age = [0, 2, 4, 7][age.clamp(0, 3) as usize];
+            }
+            states.insert("growth".to_string(), Value::Int(age));
+            attrs.remove("age");
+        }
+    }
+    if java_id == "composter" {
+        if let Some(level) = take_i32(attrs, "level") {
+            states.insert("composter_fill_level".to_string(), Value::Int(level));
+        }
+    }
+    if java_id == "hopper" {
+        if let Some(enabled) = attrs.remove("enabled") {
+            states.insert("toggle_bit".to_string(), bool_state(enabled == "false"));
+        }
+    }
+    if java_id.ends_with("_wall") {
+        for dir in ["north", "south", "east", "west"] {
+            if let Some(conn) = attrs.remove(dir) {
+                let conn = match conn.as_str() {
+                    "low" | "true" => "short",
+                    "tall" => "tall",
+                    _ => "none",
+                };
+                states.insert(format!("wall_connection_type_{dir}"), Value::String(conn.to_string()));
+            }
+        }
+        if let Some(up) = attrs.remove("up") {
+            states.insert("wall_post_bit".to_string(), bool_state(up == "true"));
+        }
+    }
+    if java_id == "vine" {
+        let mut bits = 0;
+        for (idx, dir) in ["south", "west", "north", "east"].iter().enumerate() {
+            if take_bool(attrs, dir) {
+                bits |= 1 << idx;
+            }
+        }
+        attrs.remove("up");
+        states.insert("vine_direction_bits".to_string(), Value::Int(bits));
+    }
+    if java_id.ends_with("rail") {
+        if let Some(shape) = attrs.remove("shape") {
+            const RAIL_SHAPES: [&str; 10] = ["north_south", "east_west", "ascending_east",
+                "ascending_west", "ascending_north", "ascending_south", "south_east", "south_west",
+                "north_west", "north_east"];
+            let idx = RAIL_SHAPES.iter().position(|s| *s == shape).unwrap_or(0);
+            states.insert("rail_direction".to_string(), Value::Int(idx as i32));
+        }
+        if let Some(powered) = attrs.remove("powered") {
+            states.insert("rail_data_bit".to_string(), bool_state(powered == "true"));
+        }
+    }
+    if java_id == "candle" || java_id.ends_with("_candle") {
+        if let Some(candles) = take_i32(attrs, "candles") {
+            states.insert("candles".to_string(), Value::Int(candles - 1));
+        }
+    }
+
+    for (java_key, bedrock_key) in RENAMED_PROPERTIES {
+        if let Some(value) = attrs.remove(*java_key) {
+            states.insert(bedrock_key.to_string(), java_value_to_state(&value));
+        }
+    }
+}
+
+/// Convert bedrock block id to java, states that are encoded in id will be consumed
+fn bedrock_id_to_java(bedrock_id: &str, states: &mut HashMap<String, Value>, attrs: &mut BTreeMap<String, String>) -> String {
+    match bedrock_id {
+        "water" | "lava" | "flowing_water" | "flowing_lava" => {
+            let level = state_as_i32(states.remove("liquid_depth").as_ref()).unwrap_or(0);
+            attrs.insert("level".to_string(), level.to_string());
+            return bedrock_id.replace("flowing_", "");
+        }
+        "snow_layer" => {
+            let height = state_as_i32(states.remove("height").as_ref()).unwrap_or(0);
+            states.remove("covered_bit");
+            attrs.insert("layers".to_string(), (height + 1).to_string());
+            return "snow".to_string();
+        }
+        "torch" | "soul_torch" | "redstone_torch" | "unlit_redstone_torch" => {
+            let facing = match states.remove("torch_facing_direction") {
+                Some(Value::String(f)) => f,
+                _ => "top".to_string(),
+            };
+            let is_redstone = bedrock_id.ends_with("redstone_torch");
+            if is_redstone {
+                attrs.insert("lit".to_string(), (bedrock_id == "redstone_torch").to_string());
+            }
+            let base = if is_redstone { "redstone_torch" } else { bedrock_id };
+            if facing == "top" || facing == "unknown" {
+                return base.to_string();
+            }
+            attrs.insert("facing".to_string(), facing);
+            return base.replace("torch", "wall_torch");
+        }
+        "powered_repeater" | "unpowered_repeater" => {
+            let delay = state_as_i32(states.remove("repeater_delay").as_ref()).unwrap_or(0);
+            attrs.insert("delay".to_string(), (delay + 1).to_string());
+            attrs.insert("powered".to_string(), (bedrock_id == "powered_repeater").to_string());
+            return "repeater".to_string();
+        }
+        "powered_comparator" | "unpowered_comparator" => {
+            let subtract = state_as_bool(states.remove("output_subtract_bit").as_ref());
+            states.remove("output_lit_bit");
+            attrs.insert("mode".to_string(), if subtract { "subtract" } else { "compare" }.to_string());
+            attrs.insert("powered".to_string(), (bedrock_id == "powered_comparator").to_string());
+            return "comparator".to_string();
+        }
+        "daylight_detector" | "daylight_detector_inverted" => {
+            attrs.insert("inverted".to_string(), (bedrock_id == "daylight_detector_inverted").to_string());
+            return "daylight_detector".to_string();
+        }
+        "piston_arm_collision" | "sticky_piston_arm_collision" => {
+            let sticky = bedrock_id.starts_with("sticky");
+            attrs.insert("type".to_string(), if sticky { "sticky" } else { "normal" }.to_string());
+            return "piston_head".to_string();
+        }
+        "lever" => {
+            let direction = match states.remove("lever_direction") {
+                Some(Value::String(d)) => d,
+                _ => "north".to_string(),
+            };
+            let (face, facing) = match direction.as_str() {
+                "up_east_west" => ("floor", "east"),
+                "up_north_south" => ("floor", "north"),
+                "down_east_west" => ("ceiling", "east"),
+                "down_north_south" => ("ceiling", "north"),
+                other => ("wall", other),
+            };
+            attrs.insert("face".to_string(), face.to_string());
+            attrs.insert("facing".to_string(), facing.to_string());
+            attrs.insert("powered".to_string(), state_as_bool(states.remove("open_bit").as_ref()).to_string());
+            return "lever".to_string();
+        }
+        "campfire" | "soul_campfire" => {
+            let extinguished = state_as_bool(states.remove("extinguished").as_ref());
+            attrs.insert("lit".to_string(), (!extinguished).to_string());
+            return bedrock_id.to_string();
+        }
+        "bed" => {
+            let head = state_as_bool(states.remove("head_piece_bit").as_ref());
+            attrs.insert("part".to_string(), if head { "head" } else { "foot" }.to_string());
+            return "red_bed".to_string();
+        }
+        "standing_banner" => return "white_banner".to_string(),
+        "wall_banner" => return "white_wall_banner".to_string(),
+        _ => {}
+    }
+
+    if let Some(unlit) = bedrock_id.strip_prefix("lit_") {
+        if LIT_PREFIXED_BLOCKS.contains(&unlit) {
+            attrs.insert("lit".to_string(), "true".to_string());
+            return unlit.to_string();
+        }
+    }
+    if LIT_PREFIXED_BLOCKS.contains(&bedrock_id) {
+        attrs.insert("lit".to_string(), "false".to_string());
+        return bedrock_id.to_string();
+    }
+
+    if let Some(base) = bedrock_id.strip_suffix("_double_slab") {
+        attrs.insert("type".to_string(), "double".to_string());
+        return bedrock_id_to_java(&format!("{base}_slab"), states, attrs);
+    }
+    if bedrock_id.ends_with("_slab") {
+        if let Some(Value::String(half)) = states.remove("minecraft:vertical_half") {
+            attrs.insert("type".to_string(), half);
+        }
+    }
+
+    let java_id = match RENAMED_BLOCKS.iter().find(|(_, b)| *b == bedrock_id) {
+        Some((java_id, _)) => java_id.to_string(),
+        None => match bedrock_id.strip_suffix("_standing_sign") {
+            Some(wood) => format!("{wood}_sign"),
+            None => bedrock_id.to_string(),
+        },
+    };
+
+    if java_id.ends_with("_button") {
+        let facing_direction = state_as_i32(states.remove("facing_direction").as_ref()).unwrap_or(1);
+        let (face, facing) = match facing_direction {
+            0 => ("ceiling", "north"),
+            1 => ("floor", "north"),
+            d => ("wall", *FACING_DIRECTIONS.get(d as usize).unwrap_or(&"north")),
+        };
+        attrs.insert("face".to_string(), face.to_string());
+        attrs.insert("facing".to_string(), facing.to_string());
+        attrs.insert("powered".to_string(), state_as_bool(states.remove("button_pressed_bit").as_ref()).to_string());
+    }
+    return java_id;
+}
+
+/// Convert bedrock states that exist in java with different name or value
+fn bedrock_states_to_java(java_id: &str, states: &mut HashMap<String, Value>, attrs: &mut BTreeMap<String, String>) {
+    match facing_state_of(java_id) {
+        Some(FacingState::Indexed(name, list)) => {
+            if let Some(idx) = state_as_i32(states.get(name)) {
+                if let Some(facing) = list.get(idx as usize) {
+                    attrs.insert("facing".to_string(), facing.to_string());
+                    states.remove(name);
+                }
+            }
+        }
+        Some(FacingState::Named(name)) => {
+            if let Some(Value::String(facing)) = states.remove(name) {
+                attrs.insert("facing".to_string(), facing);
+            }
+        }
+        None => {}
+    }
+
+    if let Some(upper) = states.remove("upper_block_bit") {
+        let upper = state_as_bool(Some(&upper));
+        attrs.insert("half".to_string(), if upper { "upper" } else { "lower" }.to_string());
+    }
+    if let Some(upside_down) = states.remove("upside_down_bit") {
+        let upside_down = state_as_bool(Some(&upside_down));
+        attrs.insert("half".to_string(), if upside_down { "top" } else { "bottom" }.to_string());
+    }
+    if let Some(hinge) = states.remove("door_hinge_bit") {
+        let right = state_as_bool(Some(&hinge));
+        attrs.insert("hinge".to_string(), if right { "right" } else { "left" }.to_string());
+    }
+    for name in ["pillar_axis", "portal_axis"] {
+        if let Some(Value::String(axis)) = states.remove(name) {
+            attrs.insert("axis".to_string(), axis);
+        }
+    }
+    if let Some(growth) = state_as_i32(states.get("growth")) {
+        if GROWTH_CROPS.contains(&java_id) {
+            let age = if java_id == "beetroots" { (growth * 3 + 3) / 7 } else { growth };
+            attrs.insert("age".to_string(), age.to_string());
+            states.remove("growth");
+        }
+    }
+    if let Some(level) = state_as_i32(states.get("composter_fill_level")) {
+        attrs.insert("level".to_string(), level.to_string());
+        states.remove("composter_fill_level");
+    }
+    if java_id == "hopper" {
+        if let Some(toggle) = states.remove("toggle_bit") {
+            attrs.insert("enabled".to_string(), (!state_as_bool(Some(&toggle))).to_string());
+        }
+    }
+    for dir in ["north", "south", "east", "west"] {
+        if let Some(Value::String(conn)) = states.remove(&format!("wall_connection_type_{dir}")) {
+            let conn = match conn.as_str() {
+                "short" => "low",
+                "tall" => "tall",
+                _ => "none",
+            };
+            attrs.insert(dir.to_string(), conn.to_string());
+        }
+    }
+    if let Some(post) = states.remove("wall_post_bit") {
+        attrs.insert("up".to_string(), state_as_bool(Some(&post)).to_string());
+    }
+    if let Some(bits) = state_as_i32(states.get("vine_direction_bits")) {
+        for (idx, dir) in ["south", "west", "north", "east"].iter().enumerate() {
+            attrs.insert(dir.to_string(), ((bits >> idx) & 1 != 0).to_string());
+        }
+        states.remove("vine_direction_bits");
+    }
+    if let Some(idx) = state_as_i32(states.get("rail_direction")) {
+        const RAIL_SHAPES: [&str; 10] = ["north_south", "east_west", "ascending_east",
+            "ascending_west", "ascending_north", "ascending_south", "south_east", "south_west",
+            "north_west", "north_east"];
+        if let Some(shape) = RAIL_SHAPES.get(idx as usize) {
+            attrs.insert("shape".to_string(), shape.to_string());
+            states.remove("rail_direction");
+        }
+    }
+    if let Some(powered) = states.remove("rail_data_bit") {
+        attrs.insert("powered".to_string(), state_as_bool(Some(&powered)).to_string());
+    }
+    if java_id == "candle" || java_id.ends_with("_candle") {
+        if let Some(candles) = state_as_i32(states.get("candles")) {
+            attrs.insert("candles".to_string(), (candles + 1).to_string());
+            states.remove("candles");
+        }
+    }
+    states.remove("update_bit");
+
+    for (java_key, bedrock_key) in RENAMED_PROPERTIES {
+        if let Some(value) = states.remove(*bedrock_key) {
+            attrs.insert(java_key.to_string(), state_to_java_value(&value));
+        }
+    }
+}
+
+impl Block {
+    /// Convert a java block to bedrock block. Properties that don't exist in bedrock (like
+    /// `waterlogged` and connections of fences) are dropped, and unknown properties are kept with
+    /// booleans stored as bytes and numbers stored as ints. Blocks of other namespaces are
+    /// converted by properties only.
+    pub fn to_bedrock(&self) -> BedrockBlock {
+        let mut attrs = self.attributes.clone();
+        let mut states = HashMap::new();
+        let name = if self.namespace.is_empty() || self.namespace == "minecraft" {
+            let id = java_id_to_bedrock(&self.id, &mut attrs, &mut states);
+            java_properties_to_bedrock(&self.id, &mut attrs, &mut states);
+            format!("minecraft:{id}")
+        } else {
+            format!("{}:{}", self.namespace, self.id)
+        };
+        for (key, value) in attrs {
+            states.insert(key, java_value_to_state(&value));
+        }
+        return BedrockBlock { name, states };
+    }
+
+    /// Convert a bedrock block to java block. Properties that don't exist in bedrock are not
+    /// restored, so it's not guaranteed that `Block::from_bedrock(&blk.to_bedrock()) == blk`.
+    pub fn from_bedrock(bedrock: &BedrockBlock) -> Block {
+        let (namespace, bedrock_id) = match bedrock.name.split_once(':') {
+            Some((ns, id)) => (ns, id),
+            None => ("minecraft", bedrock.name.as_str()),
+        };
+        let mut states = bedrock.states.clone();
+        let mut block = Block::new();
+        block.namespace = namespace.to_string();
+        if namespace == "minecraft" {
+            block.id = bedrock_id_to_java(bedrock_id, &mut states, &mut block.attributes);
+            bedrock_states_to_java(&block.id.clone(), &mut states, &mut block.attributes);
+        } else {
+            block.id = bedrock_id.to_string();
+        }
+        for (key, value) in &states {
+            block.attributes.insert(key.clone(), state_to_java_value(value));
+        }
+        return block;
+    }
+}
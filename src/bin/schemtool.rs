@@ -17,9 +17,11 @@ along with this program.  If not, see <https://www.gnu.org/licenses/>.
 */
 
 use chrono::DateTime;
-use clap::{command, Parser, Subcommand};
-use mc_schem::schem;
+use clap::{command, Parser, Subcommand, ValueEnum};
+use mc_schem::{schem, MaterialList, MaterialListOption};
+use mc_schem::material_list::{ItemBreakdown, stack_size};
 use mc_schem::schem::{RawMetaData, Schematic};
+use mc_schem::render::{BlockColorTable, IsometricOption, parse_hex_color, ViewDirection};
 
 /// Read, write, convert minecraft schematic files via different versions
 #[derive(Parser, Debug)]
@@ -73,6 +75,96 @@ enum Commands {
         #[arg(long, default_value_t = false)]
         benchmark: bool,
     },
+    /// Print items required to build a schematic, sorted by count
+    MaterialList {
+        /// Schematic file to load
+        #[arg()]
+        file: String,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = MaterialListFormat::Table)]
+        format: MaterialListFormat,
+        /// Count items in containers, like chests and shulker boxes
+        #[arg(long, default_value_t = false)]
+        include_containers: bool,
+    },
+    /// Remove margins of air and structure void around blocks
+    Crop {
+        /// Input file
+        #[arg()]
+        input: String,
+
+        /// Output file
+        #[arg(short, long, default_value_t = String::from("out.litematic"))]
+        output: String,
+    },
+    /// Render an isometric preview as png
+    Render {
+        /// Schematic file to load
+        #[arg()]
+        file: String,
+
+        /// Output png file
+        #[arg(short, long, default_value_t = String::from("out.png"))]
+        output: String,
+        /// The corner where viewer stands
+        #[arg(long, value_enum, default_value_t = View::SouthEast)]
+        view: View,
+        /// A block is rendered as 4*scale pixels wide
+        #[arg(long, default_value_t = 4)]
+        scale: u32,
+        /// Background color, like #RRGGBB or #RRGGBBAA
+        #[arg(long, default_value_t = String::from("#00000000"))]
+        background: String,
+        /// Csv file of block colors, each line is like `minecraft:stone,#707070`. Other blocks use map colors
+        #[arg(long)]
+        palette: Option<String>,
+    },
+}
+
+#[derive(ValueEnum, Clone, Debug)]
+enum View {
+    SouthEast,
+    SouthWest,
+    NorthWest,
+    NorthEast,
+}
+
+#[derive(ValueEnum, Clone, Debug)]
+enum MaterialListFormat {
+    Csv,
+    Json,
+    Table,
+}
+
+fn print_material_table(list: &MaterialList) {
+    let header = ["Item", "Total", "Shulker boxes", "Stacks", "Items"];
+    let mut rows: Vec<[String; 5]> = Vec::with_capacity(list.items.len());
+    for (item, count) in list.sorted() {
+        let bd = ItemBreakdown::new(count, stack_size(item));
+        rows.push([item.to_string(), count.to_string(), bd.shulker_boxes.to_string(),
+            bd.stacks.to_string(), bd.items.to_string()]);
+    }
+    let mut widths = header.map(|h| h.len());
+    for row in &rows {
+        for (col, cell) in row.iter().enumerate() {
+            widths[col] = widths[col].max(cell.len());
+        }
+    }
+
+    let mut line = format!("{:<w$}", header[0], w = widths[0]);
+    for col in 1..5 {
+        line.push_str(&format!(" | {:>w$}", header[col], w = widths[col]));
+    }
+    println!("{line}");
+    println!("{}", "-".repeat(line.len()));
+    for row in &rows {
+        let mut line = format!("{:<w$}", row[0], w = widths[0]);
+        for col in 1..5 {
+            line.push_str(&format!(" | {:>w$}", row[col], w = widths[col]));
+        }
+        println!("{line}");
+    }
+    println!("Total: {} items of {} types", list.total(), list.items.len());
 }
 
 
@@ -183,9 +275,95 @@ fn main() {
                             println!("\tWEOrigin{}: {}", dim_letters[dim], raw.we_origin[dim]);
                         }
                     },
+                    RawMetaData::BedrockStructure(raw) => {
+                        println!("\tFormatVersion: {}", raw.format_version);
+                        println!("\tStructureWorldOrigin: {}", schem::common::format_size(&raw.structure_world_origin));
+                    },
                 }
             }
         }
+        Commands::MaterialList { file, format, include_containers } => {
+            let schematic = match Schematic::from_file(&file) {
+                Ok(s) => s.0,
+                Err(e) => {
+                    eprintln!("Failed to load {}: {e}", file);
+                    std::process::exit(1);
+                }
+            };
+            let list = schematic.material_list(&MaterialListOption { include_containers });
+            match format {
+                MaterialListFormat::Csv => print!("{}", list.to_csv()),
+                MaterialListFormat::Json => print!("{}", list.to_json()),
+                MaterialListFormat::Table => print_material_table(&list),
+            }
+        }
+        Commands::Crop { input, output } => {
+            let mut schem = match Schematic::from_file(&input) {
+                Ok(s) => s.0,
+                Err(e) => {
+                    eprintln!("Failed to load {}: {e}", input);
+                    std::process::exit(1);
+                }
+            };
+            let old_shape = schem.shape();
+            if let Err(e) = schem.trim(|blk| !blk.is_air() && !blk.is_structure_void()) {
+                eprintln!("Failed to crop {}: {e}", input);
+                std::process::exit(1);
+            }
+            println!("Size: {} -> {}", schem::common::format_size(&old_shape), schem::common::format_size(&schem.shape()));
+
+            if let Err(e) = schem.save_to_file(&output) {
+                eprintln!("Failed to save {}: {e}", output);
+                std::process::exit(2);
+            }
+        }
+        Commands::Render { file, output, view, scale, background, palette } => {
+            let schematic = match Schematic::from_file(&file) {
+                Ok(s) => s.0,
+                Err(e) => {
+                    eprintln!("Failed to load {}: {e}", file);
+                    std::process::exit(1);
+                }
+            };
+            let background = match parse_hex_color(&background) {
+                Some(c) => c,
+                None => {
+                    eprintln!("Invalid background color {background}");
+                    std::process::exit(1);
+                }
+            };
+            if scale == 0 {
+                eprintln!("Scale must be positive");
+                std::process::exit(1);
+            }
+            let colors = match palette {
+                Some(palette) => {
+                    let table = std::fs::read_to_string(&palette)
+                        .map_err(|e| e.to_string())
+                        .and_then(|text| BlockColorTable::from_csv(&text).map_err(|e| e.to_string()));
+                    match table {
+                        Ok(t) => t,
+                        Err(e) => {
+                            eprintln!("Failed to load palette {palette}: {e}");
+                            std::process::exit(1);
+                        }
+                    }
+                }
+                None => BlockColorTable::default(),
+            };
+            let view = match view {
+                View::SouthEast => ViewDirection::SouthEast,
+                View::SouthWest => ViewDirection::SouthWest,
+                View::NorthWest => ViewDirection::NorthWest,
+                View::NorthEast => ViewDirection::NorthEast,
+            };
+            let image = schematic.render_isometric(&IsometricOption { view, scale, background, colors });
+            if let Err(e) = image.save_png(&output) {
+                eprintln!("Failed to save {}: {e}", output);
+                std::process::exit(2);
+            }
+            println!("Rendered {}x{} image to {output}", image.width(), image.height());
+        }
         Commands::Print { supported_formats, loadable_formats, savable_formats } => {
             if supported_formats {
                 println!("Supported formats:");
@@ -101,6 +101,70 @@ impl Biome {
         }
         return None;
     }
+
+    /// Convert number id used before 1.18 to biome. Biomes removed in 1.18 are converted to their
+    /// replacement, like `mountains` to `windswept_hills` and `desert_hills` to `desert`.
+    pub fn from_old_id(id: i32) -> Option<Self> {
+        let biome = match id {
+            0 => Biome::ocean,
+            1 => Biome::plains,
+            2 | 17 | 130 => Biome::desert,
+            3 | 20 => Biome::windswept_hills,
+            4 | 18 => Biome::forest,
+            5 | 19 | 133 => Biome::taiga,
+            6 | 134 => Biome::swamp,
+            7 => Biome::river,
+            8 => Biome::nether_wastes,
+            9 => Biome::the_end,
+            10 => Biome::frozen_ocean,
+            11 => Biome::frozen_river,
+            12 | 13 => Biome::snowy_plains,
+            14 | 15 => Biome::mushroom_fields,
+            16 => Biome::beach,
+            21 | 22 | 149 => Biome::jungle,
+            23 | 151 => Biome::sparse_jungle,
+            24 => Biome::deep_ocean,
+            25 => Biome::stony_shore,
+            26 => Biome::snowy_beach,
+            27 | 28 => Biome::birch_forest,
+            29 | 157 => Biome::dark_forest,
+            30 | 31 | 158 => Biome::snowy_taiga,
+            32 | 33 => Biome::old_growth_pine_taiga,
+            34 => Biome::windswept_forest,
+            35 => Biome::savanna,
+            36 => Biome::savanna_plateau,
+            37 | 39 | 167 => Biome::badlands,
+            38 | 166 => Biome::wooded_badlands,
+            40 => Biome::small_end_islands,
+            41 => Biome::end_midlands,
+            42 => Biome::end_highlands,
+            43 => Biome::end_barrens,
+            44 | 47 => Biome::warm_ocean,
+            45 => Biome::lukewarm_ocean,
+            48 => Biome::deep_lukewarm_ocean,
+            46 => Biome::cold_ocean,
+            49 => Biome::deep_cold_ocean,
+            50 => Biome::deep_frozen_ocean,
+            127 => Biome::the_void,
+            129 => Biome::sunflower_plains,
+            131 | 162 => Biome::windswept_gravelly_hills,
+            132 => Biome::flower_forest,
+            140 => Biome::ice_spikes,
+            155 | 156 => Biome::old_growth_birch_forest,
+            160 | 161 => Biome::old_growth_spruce_taiga,
+            163 | 164 => Biome::windswept_savanna,
+            165 => Biome::eroded_badlands,
+            168 | 169 => Biome::bamboo_jungle,
+            170 => Biome::soul_sand_valley,
+            171 => Biome::crimson_forest,
+            172 => Biome::warped_forest,
+            173 => Biome::basalt_deltas,
+            174 => Biome::dripstone_caves,
+            175 => Biome::lush_caves,
+            _ => return None,
+        };
+        return Some(biome);
+    }
 }
 
 impl Default for Biome {
@@ -346,6 +346,35 @@ impl Block {
         }
         return true;
     }
+    /// Returns true if the block can be waterlogged in java edition, i.e. it has or should have
+    /// the `waterlogged` property. Blocks of other namespaces are only checked by properties.
+    pub fn is_waterloggable(&self) -> bool {
+        if self.attributes.contains_key("waterlogged") {
+            return true;
+        }
+        if self.namespace != "minecraft" {
+            return false;
+        }
+        let id = self.id.as_str();
+        let suffixes = ["_stairs", "_slab", "_wall", "_pane", "_trapdoor", "_sign", "_coral",
+            "_coral_fan", "_candle", "_bud", "_rail", "_grate", "_bulb", "_shelf"];
+        for suffix in suffixes {
+            if id.ends_with(suffix) {
+                return true;
+            }
+        }
+        if id.ends_with("_fence") && !id.ends_with("_fence_gate") {
+            return true;
+        }
+        let ids = ["iron_bars", "chest", "trapped_chest", "ender_chest", "ladder", "lantern",
+            "soul_lantern", "chain", "conduit", "scaffolding", "sea_pickle", "campfire", "soul_campfire",
+            "candle", "amethyst_cluster", "pointed_dripstone", "lightning_rod", "big_dripleaf",
+            "big_dripleaf_stem", "small_dripleaf", "glow_lichen", "sculk_vein", "sculk_sensor",
+            "calibrated_sculk_sensor", "sculk_shrieker", "hanging_roots", "mangrove_roots",
+            "mangrove_propagule", "decorated_pot", "heavy_core", "light", "barrier", "rail",
+            "copper_chain", "copper_lantern"];
+        return ids.contains(&id);
+    }
     /// Returns `minecraft:air`
     pub fn air() -> Block {
         return Block {
@@ -25,6 +25,7 @@ use serde::de::{StdError};
 use strum::Display;
 use crate::block::{Block, BlockIdParseError};
 use crate::item::Item;
+use crate::map::MAP_BASE_COLORS;
 use crate::old_block::OldBlockParseError;
 use crate::region::Region;
 use crate::schem::common::{format_range, format_size};
@@ -95,14 +96,33 @@ pub enum Error {
     UnrecognisedExtension {
         extension: String,
     },
+    UnrecognisedFormat,
     //write error
     NBTWriteError(fastnbt::error::Error),
+    StringTooLongInNBT {
+        length: usize,
+        max_length: usize,
+    },
     NegativeSize { size: [i32; 3], region_name: String },
     BlockIndexOutOfRangeWriting { r_pos: [i32; 3], block_index: u16, max_index: u16 },
     FileCreateError(std::io::Error),
     DuplicatedRegionName { name: String },
     SizeTooLarge { size: [u64; 3], max_size: [u64; 3] },
     UnsupportedVersion { data_version_i32: i32 },
+    InvalidTargetVersion {
+        current: i32,
+        target: i32,
+        reason: String,
+    },
+    NoNumberIdForBlock {
+        block: String,
+        pos: [i32; 3],
+    },
+    NumberIdTooLarge {
+        block: String,
+        id: u16,
+        damage: u8,
+    },
     UnsupportedWorldEdit13Version {
         version: i32,
         supported_versions: Vec<i32>,
@@ -120,7 +140,19 @@ pub enum Error {
     InvalidMCACompressType {
         compress_label: u8,
     },
+    UnsupportedMCACustomCompression {
+        algorithm: String,
+    },
+    UnsupportedChunkVersion {
+        chunk_x: i32,
+        chunk_z: i32,
+        data_version: i32,
+    },
+    LZ4DecompressError {
+        detail: String,
+    },
     IOReadError(std::io::Error),
+    IOWriteError(std::io::Error),
     SevenZipDecompressError(sevenz_rust::Error),
     NoSuchFile {
         filename: String,
@@ -155,10 +187,22 @@ pub enum Error {
         exception_chunk_z: i32,
         exception_value: Range<i32>,
     },
+    BlockPosOutOfDimension {
+        pos: [i32; 3],
+    },
     MultipleItemsInOneSlot {
         slot: i8,
-        former: (Item, String),
-        latter: (Item, String),
+        former: Box<(Item, String)>,
+        latter: Box<(Item, String)>,
+    },
+    NoBlockForMapArt,
+    InvalidMapBaseColor {
+        block: Block,
+        base_color: u8,
+    },
+    InvalidBlockColorTable {
+        line: usize,
+        detail: String,
     },
     CustomError(String),
 }
@@ -197,8 +241,12 @@ impl Display for Error {
             => write!(f, "Invalid number id at {tag_path}, detail: {detail}"),
             Error::UnrecognisedExtension { extension }
             => write!(f, "Unrecognised extension {extension}, can not deduce schematic format from filename extension, try loading with explicit format."),
+            Error::UnrecognisedFormat
+            => write!(f, "Can not detect schematic format from content, try loading with explicit format."),
 
             Error::NBTWriteError(err) => write!(f, "Failed to write nbt, detail: {}", err),
+            Error::StringTooLongInNBT { length, max_length }
+            => write!(f, "String of {length} bytes is too long to write in nbt, the max length is {max_length} bytes"),
             Error::NegativeSize { size, region_name }
             => write!(f, "region \"{}\" has negative size: {}", region_name, format_size(size)),
             Error::BlockIndexOutOfRangeWriting { r_pos, block_index, max_index }
@@ -212,6 +260,12 @@ impl Display for Error {
             => write!(f, "Schematic size {} exceeds maximum size {} of current format.", format_size(size), format_size(max_size)),
             Error::UnsupportedVersion { data_version_i32 }
             => write!(f, "Data version {data_version_i32} is not supported."),
+            Error::InvalidTargetVersion { current, target, reason }
+            => write!(f, "Can not convert from data version {current} to {target}: {reason}"),
+            Error::NoNumberIdForBlock { block, pos }
+            => write!(f, "Block {block} at {} has no number id, it doesn't exist in 1.12 or earlier versions.", format_size(pos)),
+            Error::NumberIdTooLarge { block, id, damage }
+            => write!(f, "Number id of block {block} is {id}:{damage}, but number id should be less than 4096 and damage should be less than 16."),
             Error::UnsupportedWorldEdit13Version { version, supported_versions }
             => write!(f, "World edit format version(not minecraft version) {version} is not supported, supported versions: {supported_versions:?}"),
             Error::IncompleteSegmentInMCA { bytes }
@@ -221,9 +275,17 @@ impl Display for Error {
             , {}) segments, the file has {total_segments} segments, the range is invalid",
                       offset_by_segment + num_segments),
             Error::InvalidMCACompressType { compress_label }
-            => write!(f, "Invalid compress type {compress_label}, valid values: [1, 2, 3, 128, 129, 130]"),
+            => write!(f, "Invalid compress type {compress_label}, valid values: [1, 2, 3, 4, 127, 128, 129, 130, 132, 255]"),
+            Error::UnsupportedMCACustomCompression { algorithm }
+            => write!(f, "Chunk is compressed with custom algorithm \"{algorithm}\", which is not supported"),
+            Error::UnsupportedChunkVersion { chunk_x, chunk_z, data_version }
+            => write!(f, "Chunk ({chunk_x}, {chunk_z}) has data version {data_version}, but only chunks of 21w43a(1.18) or later can be saved"),
+            Error::LZ4DecompressError { detail }
+            => write!(f, "Failed to decompress LZ4 data, detail: {detail}"),
             Error::IOReadError(e)
             => write!(f, "IOReadError, detail: {e}"),
+            Error::IOWriteError(e)
+            => write!(f, "IOWriteError, detail: {e}"),
             Error::SevenZipDecompressError(e7z)
             => write!(f, "7z decompress failed, detail: {e7z}"),
             Error::NoSuchFile { filename, expected_to_exist_in }
@@ -240,8 +302,16 @@ impl Display for Error {
             => write!(f, "Chunk ({exception_chunk_x}, {exception_chunk_z}) has different y range ({}) different from majority value({})", format_range(exception_value), format_range(majority_y_range)),
             Error::IncorrectYRangeInChunk { dimension_id, dimension_y_range, exception_chunk_x, exception_chunk_z, exception_value }
             => write!(f, "The y range of dimension {dimension_id} is {}, but y range of chunk ({exception_chunk_x}, {exception_chunk_z}) is {}", format_range(dimension_y_range), format_range(exception_value)),
+            Error::BlockPosOutOfDimension { pos }
+            => write!(f, "Block pos {} is outside of any chunk in this dimension", format_size(pos)),
             Error::MultipleItemsInOneSlot { slot, former, latter }
             => write!(f, "Found multiple items in slot {slot} when parsing inventory, the former is {:?}, defined at {}, the latter is {:?}, defined at {}", former.0, former.1, latter.0, latter.1),
+            Error::NoBlockForMapArt
+            => write!(f, "No block is allowed to build map art"),
+            Error::InvalidMapBaseColor { block, base_color }
+            => write!(f, "Block {block} has invalid map base color {base_color}, base color should be in range [1, {})", MAP_BASE_COLORS.len()),
+            Error::InvalidBlockColorTable { line, detail }
+            => write!(f, "Invalid block color table at line {line}: {detail}"),
             Error::CustomError(s)
             => write!(f, "Custom error : \"{s}\"")
         }
@@ -321,6 +391,23 @@ pub fn unwrap_opt_f64(nbt: &HashMap<String, Value>, key: &str, nbt_path: &str) -
     Ok(*unwrap_opt_tag!(nbt.get(key),Double,0.0,format!("{nbt_path}/{key}")))
 }
 
+/// Getter of a tag like `unwrap_opt_i32`, the arguments are nbt, key and nbt path
+pub type TagGetter<T> = fn(&HashMap<String, Value>, &str, &str) -> Result<T, Error>;
+
+/// Get an optional tag with `getter`, returns `default` if the tag doesn't exist.
+pub fn unwrap_opt_or<T>(nbt: &HashMap<String, Value>, key: &str, nbt_path: &str, default: T,
+                        getter: TagGetter<T>) -> Result<T, Error> {
+    if nbt.contains_key(key) {
+        return getter(nbt, key, nbt_path);
+    }
+    return Ok(default);
+}
+
+/// Get an optional byte tag as bool, returns `default` if the tag doesn't exist.
+pub fn unwrap_opt_bool_or(nbt: &HashMap<String, Value>, key: &str, nbt_path: &str, default: bool) -> Result<bool, Error> {
+    return Ok(unwrap_opt_or(nbt, key, nbt_path, default as i8, unwrap_opt_i8)? != 0);
+}
+
 pub fn unwrap_opt_string<'a>(nbt: &'a HashMap<String, Value>, key: &str, nbt_path: &str) -> Result<&'a String, Error> {
     Ok(unwrap_opt_tag!(nbt.get(key),String,"".to_string(),format!("{nbt_path}/{key}")))
 }
@@ -0,0 +1,177 @@
+/*
+mc_schem is a rust library to generate, load, manipulate and save minecraft schematic files.
+Copyright (C) 2024  joseph
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use std::fs::File;
+use std::io::Write;
+use flate2::Compression;
+use flate2::Crc;
+use flate2::write::ZlibEncoder;
+use crate::error::Error;
+
+/// An image of 8-bit RGBA pixels, stored row by row from the top-left corner.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RgbaImage {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl RgbaImage {
+    /// Create a fully transparent image
+    pub fn new(width: u32, height: u32) -> RgbaImage {
+        return RgbaImage {
+            width,
+            height,
+            pixels: vec![0; width as usize * height as usize * 4],
+        };
+    }
+
+    /// Wrap raw RGBA bytes, returns `None` if the length doesn't match the size.
+    pub fn from_raw(width: u32, height: u32, pixels: Vec<u8>) -> Option<RgbaImage> {
+        if pixels.len() != width as usize * height as usize * 4 {
+            return None;
+        }
+        return Some(RgbaImage { width, height, pixels });
+    }
+
+    pub fn width(&self) -> u32 {
+        return self.width;
+    }
+
+    pub fn height(&self) -> u32 {
+        return self.height;
+    }
+
+    /// Raw RGBA bytes, 4 bytes per pixel
+    pub fn pixels(&self) -> &[u8] {
+        return &self.pixels;
+    }
+
+    pub fn into_raw(self) -> Vec<u8> {
+        return self.pixels;
+    }
+
+    fn offset(&self, x: u32, y: u32) -> usize {
+        assert!(x < self.width && y < self.height, "Pixel ({x}, {y}) is out of image size {}x{}", self.width, self.height);
+        return (y as usize * self.width as usize + x as usize) * 4;
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let offset = self.offset(x, y);
+        let mut result = [0; 4];
+        result.copy_from_slice(&self.pixels[offset..offset + 4]);
+        return result;
+    }
+
+    pub fn set_pixel(&mut self, x: u32, y: u32, rgba: [u8; 4]) {
+        let offset = self.offset(x, y);
+        self.pixels[offset..offset + 4].copy_from_slice(&rgba);
+    }
+
+    /// Alpha-blend `rgba` over the existing pixel
+    pub fn blend_pixel(&mut self, x: u32, y: u32, rgba: [u8; 4]) {
+        if rgba[3] == 255 {
+            self.set_pixel(x, y, rgba);
+            return;
+        }
+        if rgba[3] == 0 {
+            return;
+        }
+        let dst = self.pixel(x, y);
+        let src_a = rgba[3] as u32;
+        let dst_a = dst[3] as u32 * (255 - src_a) / 255;
+        let out_a = src_a + dst_a;
+        let mut result = [0u8; 4];
+        for c in 0..3 {
+            result[c] = ((rgba[c] as u32 * src_a + dst[c] as u32 * dst_a) / out_a) as u8;
+        }
+        result[3] = out_a as u8;
+        self.set_pixel(x, y, result);
+    }
+
+    /// Encode as a non-interlaced 8-bit RGBA PNG
+    pub fn to_png_bytes(&self) -> Vec<u8> {
+        let mut result: Vec<u8> = Vec::with_capacity(self.pixels.len() / 2 + 64);
+        result.extend_from_slice(&[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]);
+
+        let mut ihdr = Vec::with_capacity(13);
+        ihdr.extend_from_slice(&self.width.to_be_bytes());
+        ihdr.extend_from_slice(&self.height.to_be_bytes());
+        // bit depth 8, color type 6(RGBA), deflate, adaptive filtering, no interlace
+        ihdr.extend_from_slice(&[8, 6, 0, 0, 0]);
+        write_png_chunk(&mut result, b"IHDR", &ihdr);
+
+        // every scanline starts with filter type 0(None)
+        let row_bytes = self.width as usize * 4;
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        for row in 0..self.height as usize {
+            let row = &self.pixels[row * row_bytes..(row + 1) * row_bytes];
+            encoder.write_all(&[0]).expect("Writing to Vec should never fail");
+            encoder.write_all(row).expect("Writing to Vec should never fail");
+        }
+        let idat = encoder.finish().expect("Writing to Vec should never fail");
+        write_png_chunk(&mut result, b"IDAT", &idat);
+        write_png_chunk(&mut result, b"IEND", &[]);
+        return result;
+    }
+
+    /// Encode as PNG and save to `filename`
+    pub fn save_png(&self, filename: &str) -> Result<(), Error> {
+        let mut file = match File::create(filename) {
+            Ok(f) => f,
+            Err(e) => return Err(Error::FileCreateError(e)),
+        };
+        if let Err(e) = file.write_all(&self.to_png_bytes()) {
+            return Err(Error::IOWriteError(e));
+        }
+        return Ok(());
+    }
+}
+
+fn write_png_chunk(dest: &mut Vec<u8>, chunk_type: &[u8; 4], data: &[u8]) {
+    dest.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    dest.extend_from_slice(chunk_type);
+    dest.extend_from_slice(data);
+    let mut crc = Crc::new();
+    crc.update(chunk_type);
+    crc.update(data);
+    dest.extend_from_slice(&crc.sum().to_be_bytes());
+}
+
+#[test]
+fn test_png_encoding() {
+    use std::io::Read;
+    use flate2::read::ZlibDecoder;
+
+    let mut img = RgbaImage::new(3, 2);
+    img.set_pixel(0, 0, [255, 0, 0, 255]);
+    img.set_pixel(2, 1, [0, 0, 255, 128]);
+    let png = img.to_png_bytes();
+    assert_eq!(&png[0..8], &[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]);
+    assert_eq!(&png[12..16], b"IHDR");
+    // crc of IEND chunk is a well known constant
+    assert_eq!(&png[png.len() - 8..], &[b'I', b'E', b'N', b'D', 0xAE, 0x42, 0x60, 0x82]);
+
+    let idat_len = u32::from_be_bytes([png[33], png[34], png[35], png[36]]) as usize;
+    assert_eq!(&png[37..41], b"IDAT");
+    let mut raw = Vec::new();
+    ZlibDecoder::new(&png[41..41 + idat_len]).read_to_end(&mut raw).unwrap();
+    assert_eq!(raw.len(), 2 * (1 + 3 * 4));
+    assert_eq!(&raw[1..5], &[255, 0, 0, 255]);
+    assert_eq!(&raw[raw.len() - 4..], &[0, 0, 255, 128]);
+}
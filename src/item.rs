@@ -2,7 +2,7 @@ use std::collections::{BTreeMap, HashMap};
 use fastnbt::Value;
 use serde::Deserialize;
 use crate::{Error, unwrap_tag};
-use crate::error::{unwrap_opt_i8, unwrap_opt_string};
+use crate::error::{unwrap_opt_i32, unwrap_opt_i8, unwrap_opt_or, unwrap_opt_string};
 use crate::schem::id_of_nbt_tag;
 //use crate::error::NBTWithPath;
 
@@ -11,16 +11,25 @@ pub struct Item {
     #[serde(rename = "Count")]
     pub count: i8,
     pub id: String,
+    /// `tag` before 1.20.5, or `components` since 1.20.5
     #[serde(rename = "tag")]
     pub tags: HashMap<String, Value>,
 }
 
 impl Item {
+    /// Parse item stack of any version. Before 1.20.5, count is a byte named `Count` and tags are
+    /// in `tag`. Since 1.20.5, count is an int named `count`(1 if missing) and tags are replaced by
+    /// `components`.
     pub fn from_nbt(nbt: &HashMap<String, Value>, tag_path: &str) -> Result<Item, Error> {
-        let count = unwrap_opt_i8(&nbt, "Count", tag_path)?;
-        let id = unwrap_opt_string(&nbt, "id", tag_path)?.clone();
-        let tags = if let Some(t) = nbt.get("tag") {
-            unwrap_tag!(t,Compound,HashMap::new(),format!("{tag_path}/tag")).clone()
+        let count = if nbt.contains_key("Count") {
+            unwrap_opt_i8(nbt, "Count", tag_path)?
+        } else {
+            unwrap_opt_or(nbt, "count", tag_path, 1, unwrap_opt_i32)?.clamp(i8::MIN as i32, i8::MAX as i32) as i8
+        };
+        let id = unwrap_opt_string(nbt, "id", tag_path)?.clone();
+        let tags_key = if nbt.contains_key("components") { "components" } else { "tag" };
+        let tags = if let Some(t) = nbt.get(tags_key) {
+            unwrap_tag!(t,Compound,HashMap::new(),format!("{tag_path}/{tags_key}")).clone()
         } else {
             HashMap::new()
         };
@@ -57,8 +66,8 @@ impl Inventory {
             if result.contains_key(&slot) {
                 return Err(Error::MultipleItemsInOneSlot {
                     slot,
-                    former: (result.remove(&slot).unwrap(), parsed.remove(&slot).unwrap()),
-                    latter: (item, tag_path),
+                    former: Box::new((result.remove(&slot).unwrap(), parsed.remove(&slot).unwrap())),
+                    latter: Box::new((item, tag_path)),
                 });
             }
             result.insert(slot, item);
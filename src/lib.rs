@@ -22,6 +22,7 @@ along with this program.  If not, see <https://www.gnu.org/licenses/>.
 //! - Vanilla structure(`.nbt`)
 //! - WorldEdit schem (1.13+)(`.schem`)
 //! - WorldEdit schem (1.12-)(`.schematic`)
+//! - Bedrock structure(`.mcstructure`)
 //!
 //! ## Contents
 //! 1. `mc_schem` (rlib)
@@ -45,20 +46,36 @@ use strum::Display;
 pub mod block;
 /// Number id parsing
 pub mod old_block;
+/// Java <-> Bedrock block state translation
+pub mod bedrock_block;
 /// Errors in loading, saving and manipulating
 pub mod error;
 /// Implement region, entity, block entity and pending ticks
 pub mod region;
 /// Implement metadata, schematics and loading/saving
 pub mod schem;
+/// Rotate and mirror blocks, regions and schematics
+pub mod transform;
+/// Count items required to build a schematic
+pub mod material_list;
 
 pub mod world;
+/// Players, mobs and their common entity fields
+pub mod player;
+/// Items and inventories
+pub mod item;
+/// Map item data and map colors
+pub mod map;
+/// Generate map art schematics from images
+pub mod mapart;
+/// RGBA image buffer and png encoding
+pub mod image;
+/// Render schematics to images
+pub mod render;
 
 mod c_ffi;
 mod biome;
 mod raid;
-mod player;
-mod item;
 
 /// `Block` is a type of block with namespace and properties(aka attributes) in MC.
 pub type Block = block::Block;
@@ -94,6 +111,18 @@ pub type WorldEdit13LoadOption = schem::WorldEdit13LoadOption;
 pub type WorldEdit13SaveOption = schem::WorldEdit13SaveOption;
 /// Options to load litematica
 pub type WorldEdit12LoadOption = schem::WorldEdit12LoadOption;
+/// Options to save world edit 1.12-
+pub type WorldEdit12SaveOption = schem::WorldEdit12SaveOption;
+/// Options to load bedrock structure
+pub type BedrockStructureLoadOption = schem::BedrockStructureLoadOption;
+/// Options to save bedrock structure
+pub type BedrockStructureSaveOption = schem::BedrockStructureSaveOption;
+/// How to replace new blocks when downgrading a schematic
+pub type DowngradePolicy = schem::downgrade::DowngradePolicy;
+/// Items required to build a schematic
+pub type MaterialList = material_list::MaterialList;
+/// Options to generate material list
+pub type MaterialListOption = material_list::MaterialListOption;
 /// Minecraft data versions.
 pub type DataVersion = schem::DataVersion;
 /// Errors when loading and saving schematic
@@ -107,6 +136,7 @@ pub enum SchemFormat {
     VanillaStructure = 1,
     WorldEdit13 = 2,
     WorldEdit12 = 3,
+    BedrockStructure = 4,
 }
 
 impl SchemFormat {
@@ -117,6 +147,7 @@ impl SchemFormat {
             SchemFormat::VanillaStructure => ".nbt",
             SchemFormat::WorldEdit13 => ".schem",
             SchemFormat::WorldEdit12 => ".schematic",
+            SchemFormat::BedrockStructure => ".mcstructure",
         }
     }
 
@@ -127,12 +158,12 @@ impl SchemFormat {
     /// Return all loadable formats
     pub fn loadable_formats() -> &'static [SchemFormat] {
         use SchemFormat::*;
-        return &[Litematica, VanillaStructure, WorldEdit13, WorldEdit12];
+        return &[Litematica, VanillaStructure, WorldEdit13, WorldEdit12, BedrockStructure];
     }
     /// Return all savable formats
     pub fn savable_formats() -> &'static [SchemFormat] {
         use SchemFormat::*;
-        return &[Litematica, VanillaStructure, WorldEdit13];
+        return &[Litematica, VanillaStructure, WorldEdit13, WorldEdit12, BedrockStructure];
     }
     /// Return if the format can be loaded
     pub fn loadable(&self) -> bool {
@@ -0,0 +1,241 @@
+/*
+mc_schem is a rust library to generate, load, manipulate and save minecraft schematic files.
+Copyright (C) 2024  joseph
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use crate::block::Block;
+
+/// Dye colors in the order of map base colors, starting from `color_orange`(15). White is special
+/// since it maps to `snow`(8).
+const DYE_COLORS: [&str; 16] = ["white", "orange", "magenta", "light_blue", "yellow", "lime", "pink", "gray",
+    "light_gray", "cyan", "purple", "blue", "brown", "green", "red", "black"];
+
+/// Suffixes of blocks that share map color with their material
+const SHAPE_SUFFIXES: [&str; 10] = ["_stairs", "_slab", "_wall", "_fence_gate", "_fence", "_pressure_plate",
+    "_door", "_trapdoor", "_sign", "_button"];
+
+fn dye_base_color(dye_idx: usize) -> u8 {
+    return if dye_idx == 0 { 8 } else { 14 + dye_idx as u8 };
+}
+
+/// Map color of blocks whose id is exactly `id`
+fn base_color_of_id(id: &str) -> Option<u8> {
+    let color = match id {
+        "air" | "cave_air" | "void_air" | "glass" | "glass_pane" | "barrier" | "light" | "structure_void"
+        | "torch" | "wall_torch" | "soul_torch" | "soul_wall_torch" | "redstone_torch" | "redstone_wall_torch"
+        | "redstone_wire" | "rail" | "powered_rail" | "detector_rail" | "activator_rail" | "lever"
+        | "tripwire" | "tripwire_hook" | "repeater" | "comparator" | "ladder" | "iron_bars" | "end_rod"
+        | "flower_pot" | "moving_piston" | "chain" | "lantern" | "soul_lantern" => 0,
+        "grass_block" | "slime_block" => 1,
+        "sand" | "sandstone" | "chiseled_sandstone" | "cut_sandstone" | "smooth_sandstone" | "birch_planks"
+        | "glowstone" | "end_stone" | "end_stone_bricks" | "bone_block" | "scaffolding" | "candle" => 2,
+        "cobweb" | "mushroom_stem" => 3,
+        "lava" | "fire" | "tnt" | "redstone_block" => 4,
+        "ice" | "packed_ice" | "blue_ice" | "frosted_ice" => 5,
+        "iron_block" | "iron_door" | "iron_trapdoor" | "heavy_weighted_pressure_plate" | "brewing_stand"
+        | "anvil" | "chipped_anvil" | "damaged_anvil" | "lodestone" | "grindstone" => 6,
+        "short_grass" | "grass" | "tall_grass" | "fern" | "large_fern" | "dandelion" | "poppy" | "blue_orchid"
+        | "allium" | "azure_bluet" | "red_tulip" | "orange_tulip" | "white_tulip" | "pink_tulip"
+        | "oxeye_daisy" | "cornflower" | "lily_of_the_valley" | "wither_rose" | "sunflower" | "lilac"
+        | "rose_bush" | "peony" | "sugar_cane" | "vine" | "lily_pad" | "wheat" | "carrots" | "potatoes"
+        | "beetroots" | "sweet_berry_bush" | "cactus" | "bamboo" | "azalea" | "flowering_azalea"
+        | "big_dripleaf" | "small_dripleaf" | "azalea_leaves" | "flowering_azalea_leaves" => 7,
+        "snow" | "snow_block" | "powder_snow" => 8,
+        "clay" | "infested_stone" => 9,
+        "dirt" | "coarse_dirt" | "rooted_dirt" | "dirt_path" | "farmland" | "packed_mud" | "granite"
+        | "polished_granite" | "jungle_planks" | "brown_mushroom_block" => 10,
+        "stone" | "cobblestone" | "mossy_cobblestone" | "stone_bricks" | "mossy_stone_bricks"
+        | "cracked_stone_bricks" | "chiseled_stone_bricks" | "smooth_stone" | "andesite" | "polished_andesite"
+        | "gravel" | "bedrock" | "furnace" | "blast_furnace" | "smoker" | "dispenser" | "dropper" | "observer"
+        | "piston" | "sticky_piston" | "hopper" | "cauldron" | "stonecutter" | "coal_ore" | "iron_ore"
+        | "gold_ore" | "copper_ore" | "redstone_ore" | "lapis_ore" | "diamond_ore" | "emerald_ore"
+        | "spawner" => 11,
+        "water" | "bubble_column" | "kelp" | "kelp_plant" | "seagrass" | "tall_seagrass" => 12,
+        "oak_planks" | "petrified_oak" | "crafting_table" | "bookshelf" | "chiseled_bookshelf" | "note_block"
+        | "jukebox" | "chest" | "trapped_chest" | "barrel" | "lectern" | "loom" | "cartography_table"
+        | "fletching_table" | "smithing_table" | "composter" | "beehive" | "bee_nest" | "dead_bush"
+        | "daylight_detector" | "campfire" | "soul_campfire" => 13,
+        "diorite" | "polished_diorite" | "quartz_block" | "chiseled_quartz_block" | "quartz_pillar"
+        | "quartz_bricks" | "smooth_quartz" | "sea_lantern" | "target" => 14,
+        "red_sand" | "red_sandstone" | "chiseled_red_sandstone" | "cut_red_sandstone" | "smooth_red_sandstone"
+        | "acacia_planks" | "pumpkin" | "carved_pumpkin" | "jack_o_lantern" | "terracotta" | "honey_block"
+        | "honeycomb_block" | "copper_block" | "cut_copper" | "raw_copper_block" | "lightning_rod" => 15,
+        "purpur_block" | "purpur_pillar" => 16,
+        "hay_block" | "sponge" | "wet_sponge" | "bamboo_planks" | "bamboo_mosaic" | "bamboo_block" => 18,
+        "melon" => 19,
+        "cherry_leaves" => 20,
+        "tinted_glass" => 21,
+        "prismarine" => 23,
+        "mycelium" | "amethyst_block" | "budding_amethyst" | "shulker_box" => 24,
+        "dark_oak_planks" | "soul_sand" | "soul_soil" => 26,
+        "moss_block" | "moss_carpet" | "dried_kelp_block" => 27,
+        "bricks" | "mangrove_planks" | "nether_wart_block" | "shroomlight" | "red_mushroom_block"
+        | "enchanting_table" => 28,
+        "obsidian" | "crying_obsidian" | "coal_block" | "netherite_block" | "basalt" | "polished_basalt"
+        | "smooth_basalt" | "blackstone" | "polished_blackstone" | "polished_blackstone_bricks"
+        | "cracked_polished_blackstone_bricks" | "chiseled_polished_blackstone" | "gilded_blackstone"
+        | "sculk" | "sculk_sensor" | "sculk_catalyst" | "sculk_shrieker" | "respawn_anchor" | "end_portal_frame"
+        | "dragon_egg" => 29,
+        "gold_block" | "raw_gold_block" | "bell" | "light_weighted_pressure_plate" => 30,
+        "diamond_block" | "prismarine_bricks" | "dark_prismarine" | "beacon" | "conduit" => 31,
+        "lapis_block" => 32,
+        "emerald_block" => 33,
+        "spruce_planks" | "podzol" | "mangrove_roots" | "muddy_mangrove_roots" => 34,
+        "netherrack" | "nether_bricks" | "cracked_nether_bricks" | "chiseled_nether_bricks"
+        | "red_nether_bricks" | "magma_block" | "nether_gold_ore" | "nether_quartz_ore" | "nether_wart" => 35,
+        "calcite" | "cherry_planks" => 36,
+        "tuff" => 43,
+        "exposed_copper" | "exposed_cut_copper" | "mud_bricks" => 44,
+        "mud" => 45,
+        "dripstone_block" | "pointed_dripstone" => 48,
+        "crimson_nylium" => 52,
+        "crimson_planks" | "crimson_stem" | "stripped_crimson_stem" | "crimson_fungus" | "crimson_roots" => 53,
+        "crimson_hyphae" | "stripped_crimson_hyphae" => 54,
+        "warped_nylium" | "oxidized_copper" | "oxidized_cut_copper" => 55,
+        "warped_planks" | "warped_stem" | "stripped_warped_stem" | "weathered_copper" | "weathered_cut_copper"
+        | "warped_fungus" | "warped_roots" => 56,
+        "warped_hyphae" | "stripped_warped_hyphae" => 57,
+        "warped_wart_block" => 58,
+        "deepslate" | "cobbled_deepslate" | "polished_deepslate" | "deepslate_bricks" | "cracked_deepslate_bricks"
+        | "deepslate_tiles" | "cracked_deepslate_tiles" | "chiseled_deepslate" | "reinforced_deepslate" => 59,
+        "raw_iron_block" => 60,
+        "glow_lichen" => 61,
+        _ => return None,
+    };
+    return Some(color);
+}
+
+/// Map color of blocks with a dye color prefix, like wool, concrete and stained glass
+fn base_color_of_dyed(id: &str) -> Option<u8> {
+    // light_blue and light_gray must be tested before blue and gray
+    let mut dyes: Vec<(usize, &str)> = DYE_COLORS.iter().copied().enumerate().collect();
+    dyes.sort_by_key(|(_, dye)| usize::MAX - dye.len());
+    for (dye_idx, dye) in dyes {
+        let kind = match id.strip_prefix(dye).and_then(|s| s.strip_prefix('_')) {
+            Some(kind) => kind,
+            None => continue,
+        };
+        return match kind {
+            "terracotta" => Some(36 + dye_idx as u8),
+            "wool" | "carpet" | "concrete" | "concrete_powder" | "stained_glass" | "stained_glass_pane"
+            | "glazed_terracotta" | "shulker_box" | "candle" | "bed" => Some(dye_base_color(dye_idx)),
+            "banner" | "wall_banner" => Some(13),
+            _ => None,
+        };
+    }
+    return None;
+}
+
+/// Map color of wooden blocks. Logs show planks color on top and bark color on sides, so the color
+/// depends on axis.
+fn base_color_of_wood(id: &str, block: &Block) -> Option<u8> {
+    let (stripped, id) = match id.strip_prefix("stripped_") {
+        Some(id) => (true, id),
+        None => (false, id),
+    };
+    for wood in ["oak", "spruce", "birch", "jungle", "acacia", "dark_oak", "mangrove", "cherry"] {
+        let kind = match id.strip_prefix(wood).and_then(|s| s.strip_prefix('_')) {
+            Some(kind) => kind,
+            None => continue,
+        };
+        let planks = base_color_of_id(&format!("{wood}_planks"));
+        let bark = Some(match wood {
+            "birch" => 14,
+            "acacia" => 11,
+            "spruce" | "dark_oak" => 26,
+            "cherry" => 43,
+            _ => 34,
+        });
+        return match kind {
+            "log" => {
+                let vertical = block.attributes.get("axis").map(|a| a == "y").unwrap_or(true);
+                if stripped || vertical { planks } else { bark }
+            }
+            "wood" => if stripped { planks } else { bark },
+            "leaves" => if wood == "cherry" { Some(20) } else { Some(7) },
+            "sapling" | "propagule" => Some(7),
+            _ => {
+                if SHAPE_SUFFIXES.iter().any(|s| s.strip_prefix('_') == Some(kind))
+                    || kind.ends_with("sign") {
+                    planks
+                } else {
+                    None
+                }
+            }
+        };
+    }
+    return None;
+}
+
+/// Base color shown on map of a block, 0 means transparent on map. Unknown blocks are guessed by
+/// their id, and fallback to stone.
+pub fn map_base_color(block: &Block) -> u8 {
+    let id = block.id.strip_prefix("waxed_").unwrap_or(&block.id);
+    if let Some(color) = base_color_of_id(id) {
+        return color;
+    }
+    if let Some(color) = base_color_of_dyed(id) {
+        return color;
+    }
+    if let Some(color) = base_color_of_wood(id, block) {
+        return color;
+    }
+    for suffix in SHAPE_SUFFIXES {
+        let material = match id.strip_suffix(suffix) {
+            Some(m) => m,
+            None => continue,
+        };
+        for candidate in [material.to_string(), format!("{material}s"), format!("{material}_block"), format!("{material}_planks")] {
+            if let Some(color) = base_color_of_id(&candidate) {
+                return color;
+            }
+        }
+    }
+
+    if id.contains("deepslate") {
+        return 59;
+    }
+    if id.contains("glass") || id.ends_with("torch") || id.ends_with("button") || id.ends_with("skull")
+        || id.ends_with("head") || id.ends_with("coral_fan") {
+        return 0;
+    }
+    if id.ends_with("flower") || id.ends_with("tulip") || id.ends_with("sapling") || id.ends_with("leaves") {
+        return 7;
+    }
+    return 11;
+}
+
+#[test]
+fn test_map_base_color() {
+    let color = |id: &str| map_base_color(&Block::from_id(id).unwrap());
+    assert_eq!(color("minecraft:air"), 0);
+    assert_eq!(color("minecraft:glass"), 0);
+    assert_eq!(color("minecraft:grass_block[snowy=false]"), 1);
+    assert_eq!(color("minecraft:white_wool"), 8);
+    assert_eq!(color("minecraft:light_blue_concrete"), 17);
+    assert_eq!(color("minecraft:blue_concrete_powder"), 25);
+    assert_eq!(color("minecraft:light_gray_terracotta"), 44);
+    assert_eq!(color("minecraft:black_stained_glass"), 29);
+    assert_eq!(color("minecraft:oak_log[axis=y]"), 13);
+    assert_eq!(color("minecraft:oak_log[axis=x]"), 34);
+    assert_eq!(color("minecraft:dark_oak_stairs"), 26);
+    assert_eq!(color("minecraft:stripped_birch_wood"), 2);
+    assert_eq!(color("minecraft:stone_brick_stairs"), 11);
+    assert_eq!(color("minecraft:quartz_slab"), 14);
+    assert_eq!(color("minecraft:waxed_oxidized_cut_copper_slab"), 55);
+    assert_eq!(color("minecraft:deepslate_tile_wall"), 59);
+    assert_eq!(color("minecraft:water[level=0]"), 12);
+}
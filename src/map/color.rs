@@ -0,0 +1,140 @@
+/*
+mc_schem is a rust library to generate, load, manipulate and save minecraft schematic files.
+Copyright (C) 2024  joseph
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use strum::FromRepr;
+
+/// RGB of map base colors, indexed by base color id. Id 0 means no color(transparent).
+pub const MAP_BASE_COLORS: [[u8; 3]; 62] = [
+    [0, 0, 0],       // none
+    [127, 178, 56],  // grass
+    [247, 233, 163], // sand
+    [199, 199, 199], // wool
+    [255, 0, 0],     // fire
+    [160, 160, 255], // ice
+    [167, 167, 167], // metal
+    [0, 124, 0],     // plant
+    [255, 255, 255], // snow
+    [164, 168, 184], // clay
+    [151, 109, 77],  // dirt
+    [112, 112, 112], // stone
+    [64, 64, 255],   // water
+    [143, 119, 72],  // wood
+    [255, 252, 245], // quartz
+    [216, 127, 51],  // color_orange
+    [178, 76, 216],  // color_magenta
+    [102, 153, 216], // color_light_blue
+    [229, 229, 51],  // color_yellow
+    [127, 204, 25],  // color_light_green
+    [242, 127, 165], // color_pink
+    [76, 76, 76],    // color_gray
+    [153, 153, 153], // color_light_gray
+    [76, 127, 153],  // color_cyan
+    [127, 63, 178],  // color_purple
+    [51, 76, 178],   // color_blue
+    [102, 76, 51],   // color_brown
+    [102, 127, 51],  // color_green
+    [153, 51, 51],   // color_red
+    [25, 25, 25],    // color_black
+    [250, 238, 77],  // gold
+    [92, 219, 213],  // diamond
+    [74, 128, 255],  // lapis
+    [0, 217, 58],    // emerald
+    [129, 86, 49],   // podzol
+    [112, 2, 0],     // nether
+    [209, 177, 161], // terracotta_white
+    [159, 82, 36],   // terracotta_orange
+    [149, 87, 108],  // terracotta_magenta
+    [112, 108, 138], // terracotta_light_blue
+    [186, 133, 36],  // terracotta_yellow
+    [103, 117, 53],  // terracotta_light_green
+    [160, 77, 78],   // terracotta_pink
+    [57, 41, 35],    // terracotta_gray
+    [135, 107, 98],  // terracotta_light_gray
+    [87, 92, 92],    // terracotta_cyan
+    [122, 73, 88],   // terracotta_purple
+    [76, 62, 92],    // terracotta_blue
+    [76, 50, 35],    // terracotta_brown
+    [76, 82, 42],    // terracotta_green
+    [142, 60, 46],   // terracotta_red
+    [37, 22, 16],    // terracotta_black
+    [189, 48, 49],   // crimson_nylium
+    [148, 63, 97],   // crimson_stem
+    [92, 25, 29],    // crimson_hyphae
+    [22, 126, 134],  // warped_nylium
+    [58, 142, 140],  // warped_stem
+    [86, 44, 62],    // warped_hyphae
+    [20, 180, 133],  // warped_wart_block
+    [100, 100, 100], // deepslate
+    [216, 175, 147], // raw_iron
+    [127, 167, 150], // glow_lichen
+];
+
+/// Brightness of a map color, stored in the lowest 2 bits of color id
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromRepr)]
+pub enum MapColorShade {
+    Low = 0,
+    Normal = 1,
+    High = 2,
+    /// Never produced by exploring, only available for map arts made by editing
+    Lowest = 3,
+}
+
+impl MapColorShade {
+    /// Multiplier applied to base color, out of 255
+    pub fn multiplier(&self) -> u32 {
+        return match self {
+            MapColorShade::Low => 180,
+            MapColorShade::Normal => 220,
+            MapColorShade::High => 255,
+            MapColorShade::Lowest => 135,
+        };
+    }
+}
+
+/// RGB of a base color with shade. Returns `None` if base color is not known.
+pub fn map_color_rgb(base_color: u8, shade: MapColorShade) -> Option<[u8; 3]> {
+    let base = MAP_BASE_COLORS.get(base_color as usize)?;
+    let m = shade.multiplier();
+    return Some([(base[0] as u32 * m / 255) as u8,
+        (base[1] as u32 * m / 255) as u8,
+        (base[2] as u32 * m / 255) as u8]);
+}
+
+/// RGBA of a color id in map data. Base color 0 and unknown colors are fully transparent.
+pub fn map_color_rgba(color_id: u8) -> [u8; 4] {
+    let base = color_id / 4;
+    if base == 0 {
+        return [0, 0, 0, 0];
+    }
+    let shade = MapColorShade::from_repr(color_id % 4).unwrap();
+    return match map_color_rgb(base, shade) {
+        Some([r, g, b]) => [r, g, b, 255],
+        None => [0, 0, 0, 0],
+    };
+}
+
+#[test]
+fn test_map_colors() {
+    // grass with normal shade
+    assert_eq!(map_color_rgba(1 * 4 + 1), [109, 153, 48, 255]);
+    assert_eq!(map_color_rgba(8 * 4 + 2), [255, 255, 255, 255]);
+    assert_eq!(map_color_rgba(12 * 4 + 0), [45, 45, 180, 255]);
+    assert_eq!(map_color_rgba(2), [0, 0, 0, 0]);
+    assert_eq!(map_color_rgba(255), [0, 0, 0, 0]);
+}
@@ -0,0 +1,234 @@
+/*
+mc_schem is a rust library to generate, load, manipulate and save minecraft schematic files.
+Copyright (C) 2024  joseph
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use std::collections::{BTreeMap, HashMap};
+
+use fastnbt::Value;
+use flate2::read::GzDecoder;
+
+use crate::{unwrap_opt_tag, unwrap_tag};
+use crate::error::{Error, unwrap_opt_bool_or, unwrap_opt_i32, unwrap_opt_i8, unwrap_opt_or, unwrap_opt_string};
+use crate::image::RgbaImage;
+use crate::map::{MAP_SIZE, map_color_rgba, MapBanner, MapData, MapFrame};
+use crate::player::DimensionId;
+use crate::schem::id_of_nbt_tag;
+use crate::world::FilesRead;
+
+fn parse_marker_pos(nbt: &HashMap<String, Value>, tag_path: &str) -> Result<[i32; 3], Error> {
+    let path = format!("{tag_path}/Pos");
+    let pos = unwrap_opt_tag!(nbt.get("Pos"),Compound,HashMap::new(),path);
+    return Ok([unwrap_opt_i32(pos, "X", &path)?,
+        unwrap_opt_i32(pos, "Y", &path)?,
+        unwrap_opt_i32(pos, "Z", &path)?]);
+}
+
+impl MapBanner {
+    pub fn from_nbt(nbt: &HashMap<String, Value>, tag_path: &str) -> Result<MapBanner, Error> {
+        let name = match nbt.get("Name") {
+            Some(name) => Some(unwrap_tag!(name,String,"".to_string(),format!("{tag_path}/Name")).clone()),
+            None => None,
+        };
+        return Ok(MapBanner {
+            pos: parse_marker_pos(nbt, tag_path)?,
+            color: unwrap_opt_string(nbt, "Color", tag_path)?.clone(),
+            name,
+        });
+    }
+}
+
+impl MapFrame {
+    pub fn from_nbt(nbt: &HashMap<String, Value>, tag_path: &str) -> Result<MapFrame, Error> {
+        return Ok(MapFrame {
+            pos: parse_marker_pos(nbt, tag_path)?,
+            rotation: unwrap_opt_i32(nbt, "Rotation", tag_path)?,
+            entity_id: unwrap_opt_i32(nbt, "EntityId", tag_path)?,
+        });
+    }
+}
+
+/// Extract map id from filename like `map_12.dat`
+fn parse_map_filename(filename: &str) -> Option<i32> {
+    let id = filename.strip_prefix("map_")?.strip_suffix(".dat")?;
+    return id.parse::<i32>().ok();
+}
+
+impl MapData {
+    /// Parse the whole content of `map_<id>.dat`, including `DataVersion` and `data`
+    pub fn from_nbt(nbt: &HashMap<String, Value>, tag_path: &str) -> Result<MapData, Error> {
+        let data_version = unwrap_opt_or(nbt, "DataVersion", tag_path, 0, unwrap_opt_i32)?;
+        let data_path = format!("{tag_path}/data");
+        let data = unwrap_opt_tag!(nbt.get("data"),Compound,HashMap::new(),data_path);
+        let tag_path = &data_path;
+
+        let colors_path = format!("{tag_path}/colors");
+        let colors = unwrap_opt_tag!(data.get("colors"),ByteArray,fastnbt::ByteArray::new(vec![]),colors_path);
+        if colors.len() != MAP_SIZE * MAP_SIZE {
+            return Err(Error::InvalidValue {
+                tag_path: colors_path,
+                error: format!("Map colors should contain {} bytes, but found {}", MAP_SIZE * MAP_SIZE, colors.len()),
+            });
+        }
+
+        let mut banners = Vec::new();
+        if let Some(tag) = data.get("banners") {
+            let path = format!("{tag_path}/banners");
+            for (idx, banner) in unwrap_tag!(tag,List,vec![],path).iter().enumerate() {
+                let path = format!("{path}[{idx}]");
+                banners.push(MapBanner::from_nbt(unwrap_tag!(banner,Compound,HashMap::new(),path), &path)?);
+            }
+        }
+        let mut frames = Vec::new();
+        if let Some(tag) = data.get("frames") {
+            let path = format!("{tag_path}/frames");
+            for (idx, frame) in unwrap_tag!(tag,List,vec![],path).iter().enumerate() {
+                let path = format!("{path}[{idx}]");
+                frames.push(MapFrame::from_nbt(unwrap_tag!(frame,Compound,HashMap::new(),path), &path)?);
+            }
+        }
+
+        return Ok(MapData {
+            data_version,
+            scale: unwrap_opt_or(data, "scale", tag_path, 0, unwrap_opt_i8)?,
+            dimension: DimensionId::from_nbt(data, "dimension", tag_path)?,
+            center: [unwrap_opt_i32(data, "xCenter", tag_path)?, unwrap_opt_i32(data, "zCenter", tag_path)?],
+            locked: unwrap_opt_bool_or(data, "locked", tag_path, false)?,
+            tracking_position: unwrap_opt_bool_or(data, "trackingPosition", tag_path, true)?,
+            unlimited_tracking: unwrap_opt_bool_or(data, "unlimitedTracking", tag_path, false)?,
+            banners,
+            frames,
+            colors: colors.iter().map(|c| *c as u8).collect(),
+        });
+    }
+
+    /// Load `data/map_<id>.dat` from a world folder
+    pub fn from_files(files: &dyn FilesRead, id: i32) -> Result<MapData, Error> {
+        let filename = format!("data/map_{id}.dat");
+        let src = files.open_file(&filename)?;
+        let nbt: HashMap<String, Value> = match fastnbt::from_reader(GzDecoder::new(src)) {
+            Ok(nbt) => nbt,
+            Err(e) => return Err(Error::NBTReadError(e)),
+        };
+        return Self::from_nbt(&nbt, &format!("{}/{filename}", files.path()));
+    }
+
+    /// Ids of all maps in `data` folder of a world, in ascending order
+    pub fn map_ids(files: &dyn FilesRead) -> Vec<i32> {
+        let mut result: Vec<i32> = files.sub_directory("data").files().iter()
+            .filter_map(|info| parse_map_filename(&info.name))
+            .collect();
+        result.sort();
+        return result;
+    }
+
+    /// Load all maps in `data` folder of a world, indexed by map id
+    pub fn all_from_files(files: &dyn FilesRead) -> Result<BTreeMap<i32, MapData>, Error> {
+        let mut result = BTreeMap::new();
+        for id in Self::map_ids(files) {
+            result.insert(id, Self::from_files(files, id)?);
+        }
+        return Ok(result);
+    }
+
+    /// Number of blocks covered by a pixel in each horizontal direction
+    pub fn blocks_per_pixel(&self) -> i32 {
+        return 1 << self.scale;
+    }
+
+    /// Color id of pixel at `[x, z]`
+    pub fn color_at(&self, x: usize, z: usize) -> u8 {
+        return self.colors[z * MAP_SIZE + x];
+    }
+
+    /// The north-west corner of blocks covered by pixel at `[x, z]`
+    pub fn pixel_to_block(&self, x: usize, z: usize) -> [i32; 2] {
+        let bpp = self.blocks_per_pixel();
+        let half = MAP_SIZE as i32 / 2;
+        return [self.center[0] + (x as i32 - half) * bpp,
+            self.center[1] + (z as i32 - half) * bpp];
+    }
+
+    /// Render map to a 128x128 image. North is up, and pixels without color are transparent.
+    pub fn to_rgba_image(&self) -> RgbaImage {
+        let mut pixels = Vec::with_capacity(self.colors.len() * 4);
+        for color in &self.colors {
+            pixels.extend_from_slice(&map_color_rgba(*color));
+        }
+        return RgbaImage::from_raw(MAP_SIZE as u32, MAP_SIZE as u32, pixels).unwrap();
+    }
+
+    /// Render map and save as png
+    pub fn save_png(&self, filename: &str) -> Result<(), Error> {
+        return self.to_rgba_image().save_png(filename);
+    }
+}
+
+#[test]
+fn test_parse_map_data() {
+    use std::io::Write;
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use crate::world::FolderOnDisk;
+
+    let mut colors = vec![0i8; MAP_SIZE * MAP_SIZE];
+    colors[0] = (8 * 4 + 2) as i8;
+    colors[MAP_SIZE * MAP_SIZE - 1] = (12 * 4 + 1) as i8;
+    let mut banner_pos = HashMap::new();
+    banner_pos.insert("X".to_string(), Value::Int(10));
+    banner_pos.insert("Y".to_string(), Value::Int(64));
+    banner_pos.insert("Z".to_string(), Value::Int(-3));
+    let mut banner = HashMap::new();
+    banner.insert("Pos".to_string(), Value::Compound(banner_pos));
+    banner.insert("Color".to_string(), Value::String("red".to_string()));
+    let mut data = HashMap::new();
+    data.insert("scale".to_string(), Value::Byte(1));
+    data.insert("dimension".to_string(), Value::String("minecraft:the_nether".to_string()));
+    data.insert("xCenter".to_string(), Value::Int(64));
+    data.insert("zCenter".to_string(), Value::Int(-128));
+    data.insert("locked".to_string(), Value::Byte(1));
+    data.insert("banners".to_string(), Value::List(vec![Value::Compound(banner)]));
+    data.insert("frames".to_string(), Value::List(vec![]));
+    data.insert("colors".to_string(), Value::ByteArray(fastnbt::ByteArray::new(colors)));
+    let mut root = HashMap::new();
+    root.insert("DataVersion".to_string(), Value::Int(3578));
+    root.insert("data".to_string(), Value::Compound(data));
+
+    let dir = std::env::temp_dir().join(format!("mc_schem_test_map_{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("data")).unwrap();
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&fastnbt::to_bytes(&root).unwrap()).unwrap();
+    std::fs::write(dir.join("data/map_7.dat"), encoder.finish().unwrap()).unwrap();
+    std::fs::write(dir.join("data/idcounts.dat"), []).unwrap();
+
+    let files = FolderOnDisk::new(dir.to_str().unwrap());
+    assert_eq!(MapData::map_ids(&files), vec![7]);
+    let maps = MapData::all_from_files(&files).unwrap();
+    let map = &maps[&7];
+    assert_eq!(map.data_version, 3578);
+    assert_eq!(map.scale, 1);
+    assert_eq!(map.dimension.id(), "minecraft:the_nether");
+    assert_eq!(map.center, [64, -128]);
+    assert!(map.locked);
+    assert_eq!(map.banners, vec![MapBanner { pos: [10, 64, -3], color: "red".to_string(), name: None }]);
+    assert_eq!(map.pixel_to_block(0, 0), [-64, -256]);
+
+    let img = map.to_rgba_image();
+    assert_eq!(img.pixel(0, 0), [255, 255, 255, 255]);
+    assert_eq!(img.pixel(127, 127), [55, 55, 220, 255]);
+    assert_eq!(img.pixel(1, 0), [0, 0, 0, 0]);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
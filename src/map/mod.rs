@@ -0,0 +1,71 @@
+/*
+mc_schem is a rust library to generate, load, manipulate and save minecraft schematic files.
+Copyright (C) 2024  joseph
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use crate::player::DimensionId;
+
+mod color;
+mod map_data;
+mod block_color;
+mod render;
+
+pub use color::{MAP_BASE_COLORS, MapColorShade, map_color_rgb, map_color_rgba};
+pub use block_color::map_base_color;
+pub use render::render_top_down;
+
+/// Width and height of a map in pixels
+pub const MAP_SIZE: usize = 128;
+
+/// Content of a map item, stored in `data/map_<id>.dat`
+#[derive(Debug, Clone)]
+pub struct MapData {
+    pub data_version: i32,
+    /// A pixel covers `2^scale` x `2^scale` blocks, ranges from 0 to 4
+    pub scale: i8,
+    pub dimension: DimensionId,
+    /// Center of map in blocks, `[x, z]`
+    pub center: [i32; 2],
+    /// Locked maps are not updated when players explore
+    pub locked: bool,
+    /// Whether player and item frame markers are shown
+    pub tracking_position: bool,
+    /// Whether player markers are shown when players are far away
+    pub unlimited_tracking: bool,
+    pub banners: Vec<MapBanner>,
+    pub frames: Vec<MapFrame>,
+    /// Color ids of 128x128 pixels, indexed by `z * 128 + x`.
+    /// A color id is `base_color * 4 + shade`, see `map_color_rgba`
+    pub colors: Vec<u8>,
+}
+
+/// A banner marker on map
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MapBanner {
+    pub pos: [i32; 3],
+    /// Dye color of banner, like `white` and `light_blue`
+    pub color: String,
+    /// Json text of custom name
+    pub name: Option<String>,
+}
+
+/// A marker of an item frame holding this map
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MapFrame {
+    pub pos: [i32; 3],
+    pub rotation: i32,
+    pub entity_id: i32,
+}
@@ -0,0 +1,155 @@
+/*
+mc_schem is a rust library to generate, load, manipulate and save minecraft schematic files.
+Copyright (C) 2024  joseph
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use crate::block::Block;
+use crate::image::RgbaImage;
+use crate::map::{map_base_color, map_color_rgb, MapColorShade};
+use crate::region::WorldSlice;
+
+/// Base color of water, whose shade depends on depth rather than height
+const WATER_BASE_COLOR: u8 = 12;
+
+/// Whether the map shows water at this block. Waterlogged blocks are shown as water.
+fn is_water(block: &Block) -> bool {
+    if map_base_color(block) == WATER_BASE_COLOR {
+        return true;
+    }
+    return block.attributes.get("waterlogged").map(|v| v == "true").unwrap_or(false);
+}
+
+/// Shade of a water pixel, deeper water is darker, with a checkerboard pattern on boundaries.
+fn water_shade(depth: i32, x: i32, z: i32) -> MapColorShade {
+    let value = depth as f64 * 0.1 + ((x + z) & 1) as f64 * 0.2;
+    if value < 0.5 {
+        return MapColorShade::High;
+    }
+    if value > 0.9 {
+        return MapColorShade::Low;
+    }
+    return MapColorShade::Normal;
+}
+
+/// Shade of a non-water pixel by the height difference to its northern neighbor
+fn height_shade(height: i32, north_height: i32, x: i32, z: i32) -> MapColorShade {
+    let value = (height - north_height) as f64 + (((x + z) & 1) as f64 - 0.5) * 0.4;
+    if value > 0.6 {
+        return MapColorShade::High;
+    }
+    if value < -0.6 {
+        return MapColorShade::Low;
+    }
+    return MapColorShade::Normal;
+}
+
+/// Render a slice as a map with 1 block per pixel. Image x is slice x, and image y is slice z, so
+/// north is up.
+///
+/// For each column, the highest block with a map color is shown. Its shade depends on the height
+/// difference to the block on the north, or the depth if it's water. The northmost row has no
+/// northern neighbor inside the slice, so it's compared with itself and usually has normal shade.
+/// Columns without any colored block are transparent.
+pub fn render_top_down<S: WorldSlice + ?Sized>(slice: &S) -> RgbaImage {
+    let shape = slice.shape();
+    let mut image = RgbaImage::new(shape[0].max(0) as u32, shape[2].max(0) as u32);
+    for x in 0..shape[0] {
+        let mut north_height: Option<i32> = None;
+        for z in 0..shape[2] {
+            let mut surface = None;
+            for y in (0..shape[1]).rev() {
+                let block = slice.block_at([x, y, z]).unwrap();
+                let base_color = map_base_color(block);
+                if base_color != 0 {
+                    surface = Some((y, block, base_color));
+                    break;
+                }
+            }
+            let (height, block, base_color) = match surface {
+                Some(s) => s,
+                None => {
+                    north_height = Some(-1);
+                    continue;
+                }
+            };
+
+            let (base_color, shade) = if is_water(block) {
+                let mut depth = 1;
+                while height - depth >= 0 && is_water(slice.block_at([x, height - depth, z]).unwrap()) {
+                    depth += 1;
+                }
+                (WATER_BASE_COLOR, water_shade(depth, x, z))
+            } else {
+                (base_color, height_shade(height, north_height.unwrap_or(height), x, z))
+            };
+            north_height = Some(height);
+
+            let [r, g, b] = map_color_rgb(base_color, shade).unwrap();
+            image.set_pixel(x as u32, z as u32, [r, g, b, 255]);
+        }
+    }
+    return image;
+}
+
+#[test]
+fn test_render_top_down() {
+    use crate::mapart::{generate_mapart, MapArtBlock, MapArtMode, MapArtOption};
+    use crate::region::Region;
+
+    // map art round trip: every pixel is an exact map color
+    let blocks: Vec<MapArtBlock> = ["minecraft:white_wool", "minecraft:red_wool", "minecraft:blue_concrete"].iter()
+        .map(|id| {
+            let block = Block::from_id(id).unwrap();
+            MapArtBlock { base_color: map_base_color(&block), block }
+        }).collect();
+    let shades = [MapColorShade::Low, MapColorShade::Normal, MapColorShade::High];
+    let mut image = RgbaImage::new(5, 7);
+    for x in 0..5 {
+        for z in 0..7 {
+            let block = &blocks[(x * 3 + z * 7) as usize % blocks.len()];
+            let [r, g, b] = map_color_rgb(block.base_color, shades[(x + z * z) as usize % 3]).unwrap();
+            image.set_pixel(x, z, [r, g, b, 255]);
+        }
+    }
+    let option = MapArtOption { mode: MapArtMode::Staircase, ..MapArtOption::default() };
+    let schem = generate_mapart(&image, &blocks, &option).unwrap();
+    let rendered = render_top_down(&schem.regions[0]);
+    assert_eq!(rendered.width(), 5);
+    assert_eq!(rendered.height(), 8);
+    for x in 0..5 {
+        for z in 0..7 {
+            assert_eq!(rendered.pixel(x, z + 1), image.pixel(x, z), "Pixel ({x}, {z}) differs");
+        }
+    }
+
+    // water depth
+    let mut region = Region::with_shape([2, 12, 1]);
+    let water = Block::from_id("minecraft:water[level=0]").unwrap();
+    let stone = Block::from_id("minecraft:stone").unwrap();
+    region.set_block([0, 0, 0], &stone).unwrap();
+    region.set_block([1, 0, 0], &stone).unwrap();
+    region.set_block([0, 1, 0], &water).unwrap();
+    for y in 1..12 {
+        region.set_block([1, y, 0], &water).unwrap();
+    }
+    let rendered = render_top_down(&region);
+    let water_rgb = |shade| {
+        let [r, g, b] = map_color_rgb(WATER_BASE_COLOR, shade).unwrap();
+        [r, g, b, 255]
+    };
+    assert_eq!(rendered.pixel(0, 0), water_rgb(MapColorShade::High));
+    assert_eq!(rendered.pixel(1, 0), water_rgb(MapColorShade::Low));
+}
@@ -0,0 +1,341 @@
+/*
+mc_schem is a rust library to generate, load, manipulate and save minecraft schematic files.
+Copyright (C) 2024  joseph
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use strum::Display;
+use crate::block::Block;
+use crate::error::Error;
+use crate::image::RgbaImage;
+use crate::map::{MAP_BASE_COLORS, map_color_rgb, MapColorShade};
+use crate::region::Region;
+use crate::schem::Schematic;
+
+/// A block that can be used to build map art, and the map base color it shows on map
+#[derive(Debug, Clone)]
+pub struct MapArtBlock {
+    pub block: Block,
+    /// Index in `MAP_BASE_COLORS`, 0 is not allowed
+    pub base_color: u8,
+}
+
+/// How blocks are arranged vertically
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+pub enum MapArtMode {
+    /// All blocks are at the same height, so only the normal shade is available
+    Flat,
+    /// Blocks are stepped up and down along z, so that low, normal and high shades are available.
+    /// Each column(same x) is lowered independently so its lowest block is at the bottom.
+    Staircase,
+}
+
+/// Dithering applied before quantizing pixels to map colors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+pub enum Dithering {
+    None,
+    FloydSteinberg,
+    JarvisJudiceNinke,
+}
+
+/// How the difference between two colors is measured
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+pub enum ColorMetric {
+    /// Euclidean distance in sRGB
+    Rgb,
+    /// Weighted euclidean distance in sRGB, weights depends on mean of red channel
+    RedMean,
+    /// Euclidean distance in CIELAB(CIE76)
+    Lab,
+}
+
+/// Options to generate map art
+#[derive(Debug, Clone)]
+pub struct MapArtOption {
+    pub mode: MapArtMode,
+    pub dithering: Dithering,
+    pub color_metric: ColorMetric,
+    /// Block of the extra row on north side, which decides shade of the first row of pixels.
+    /// It's also used as support block.
+    pub base_block: Block,
+    /// Whether to place `base_block` under every colored block. Required for blocks affected by
+    /// gravity like sand and concrete powder.
+    pub support_blocks: bool,
+    /// Pixels with alpha lower than this value are transparent and left empty
+    pub alpha_threshold: u8,
+}
+
+impl Default for MapArtOption {
+    fn default() -> Self {
+        return MapArtOption {
+            mode: MapArtMode::Flat,
+            dithering: Dithering::None,
+            color_metric: ColorMetric::RedMean,
+            base_block: Block::from_id("minecraft:cobblestone").unwrap(),
+            support_blocks: false,
+            alpha_threshold: 128,
+        };
+    }
+}
+
+impl ColorMetric {
+    /// Convert a sRGB color to the space where distance is computed
+    fn convert(&self, rgb: [f32; 3]) -> [f32; 3] {
+        return match self {
+            ColorMetric::Rgb | ColorMetric::RedMean => rgb,
+            ColorMetric::Lab => srgb_to_lab(rgb),
+        };
+    }
+
+    /// Squared distance between 2 colors that are already converted by `convert`
+    fn distance(&self, a: &[f32; 3], b: &[f32; 3]) -> f32 {
+        let d = [a[0] - b[0], a[1] - b[1], a[2] - b[2]];
+        return match self {
+            ColorMetric::Rgb | ColorMetric::Lab => d[0] * d[0] + d[1] * d[1] + d[2] * d[2],
+            ColorMetric::RedMean => {
+                let r_mean = (a[0] + b[0]) / 2.0;
+                (2.0 + r_mean / 256.0) * d[0] * d[0] + 4.0 * d[1] * d[1] + (2.0 + (255.0 - r_mean) / 256.0) * d[2] * d[2]
+            }
+        };
+    }
+}
+
+fn srgb_to_lab(rgb: [f32; 3]) -> [f32; 3] {
+    let linear = rgb.map(|c| {
+        let c = c / 255.0;
+        if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+    });
+    // XYZ normalized by D65 white point
+    let x = (0.4124 * linear[0] + 0.3576 * linear[1] + 0.1805 * linear[2]) / 0.95047;
+    let y = 0.2126 * linear[0] + 0.7152 * linear[1] + 0.0722 * linear[2];
+    let z = (0.0193 * linear[0] + 0.1192 * linear[1] + 0.9505 * linear[2]) / 1.08883;
+    let f = |t: f32| {
+        if t > 0.008856 { t.cbrt() } else { 7.787 * t + 16.0 / 116.0 }
+    };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+    return [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)];
+}
+
+impl Dithering {
+    /// Error diffusion kernel, as `(dx, dz, weight)`
+    fn kernel(&self) -> &'static [(i32, i32, f32)] {
+        return match self {
+            Dithering::None => &[],
+            Dithering::FloydSteinberg => &[
+                (1, 0, 7.0 / 16.0),
+                (-1, 1, 3.0 / 16.0), (0, 1, 5.0 / 16.0), (1, 1, 1.0 / 16.0)],
+            Dithering::JarvisJudiceNinke => &[
+                (1, 0, 7.0 / 48.0), (2, 0, 5.0 / 48.0),
+                (-2, 1, 3.0 / 48.0), (-1, 1, 5.0 / 48.0), (0, 1, 7.0 / 48.0), (1, 1, 5.0 / 48.0), (2, 1, 3.0 / 48.0),
+                (-2, 2, 1.0 / 48.0), (-1, 2, 3.0 / 48.0), (0, 2, 5.0 / 48.0), (1, 2, 3.0 / 48.0), (2, 2, 1.0 / 48.0)],
+        };
+    }
+}
+
+struct Candidate {
+    block_idx: usize,
+    shade: MapColorShade,
+    rgb: [f32; 3],
+    converted: [f32; 3],
+}
+
+/// Choose block and shade for each pixel, indexed by `z * width + x`. Transparent pixels are `None`.
+fn quantize(image: &RgbaImage, blocks: &[MapArtBlock], option: &MapArtOption) -> Vec<Option<(usize, MapColorShade)>> {
+    let shades: &[MapColorShade] = match option.mode {
+        MapArtMode::Flat => &[MapColorShade::Normal],
+        MapArtMode::Staircase => &[MapColorShade::Low, MapColorShade::Normal, MapColorShade::High],
+    };
+    let mut candidates = Vec::with_capacity(blocks.len() * shades.len());
+    for (block_idx, block) in blocks.iter().enumerate() {
+        for shade in shades {
+            let rgb = map_color_rgb(block.base_color, *shade).unwrap().map(|c| c as f32);
+            candidates.push(Candidate { block_idx, shade: *shade, rgb, converted: option.color_metric.convert(rgb) });
+        }
+    }
+
+    let width = image.width() as usize;
+    let height = image.height() as usize;
+    let mut pixels: Vec<[f32; 3]> = image.pixels().chunks_exact(4)
+        .map(|p| [p[0] as f32, p[1] as f32, p[2] as f32])
+        .collect();
+    let kernel = option.dithering.kernel();
+    let mut result = Vec::with_capacity(width * height);
+    for z in 0..height {
+        for x in 0..width {
+            if image.pixel(x as u32, z as u32)[3] < option.alpha_threshold {
+                result.push(None);
+                continue;
+            }
+            let pixel = pixels[z * width + x].map(|c| c.clamp(0.0, 255.0));
+            let converted = option.color_metric.convert(pixel);
+            let mut best = &candidates[0];
+            let mut best_distance = f32::MAX;
+            for candidate in &candidates {
+                let distance = option.color_metric.distance(&converted, &candidate.converted);
+                if distance < best_distance {
+                    best = candidate;
+                    best_distance = distance;
+                }
+            }
+            result.push(Some((best.block_idx, best.shade)));
+
+            let error = [pixel[0] - best.rgb[0], pixel[1] - best.rgb[1], pixel[2] - best.rgb[2]];
+            for (dx, dz, weight) in kernel {
+                let (nx, nz) = (x as i32 + dx, z as i32 + dz);
+                if nx < 0 || nx >= width as i32 || nz >= height as i32 {
+                    continue;
+                }
+                let neighbor = &mut pixels[nz as usize * width + nx as usize];
+                for c in 0..3 {
+                    neighbor[c] += error[c] * weight;
+                }
+            }
+        }
+    }
+    return result;
+}
+
+/// Generate a map art schematic whose map rendering approximates `image`. Each pixel is a block,
+/// with x to the east and z to the south. An extra row of `base_block` is placed at z=0 on the
+/// north side, so the image starts at z=1.
+///
+/// Transparent pixels are left empty, in staircase mode the pixel to the south of them keeps the
+/// height as if they were normal shade, so its shade may differ in game.
+pub fn generate_mapart(image: &RgbaImage, blocks: &[MapArtBlock], option: &MapArtOption) -> Result<Schematic, Error> {
+    if blocks.is_empty() {
+        return Err(Error::NoBlockForMapArt);
+    }
+    for block in blocks {
+        if block.base_color == 0 || block.base_color as usize >= MAP_BASE_COLORS.len() {
+            return Err(Error::InvalidMapBaseColor { block: block.block.clone(), base_color: block.base_color });
+        }
+    }
+    let width = image.width() as usize;
+    let length = image.height() as usize;
+    let pixels = quantize(image, blocks, option);
+
+    // heights of every column, including the north row
+    let mut heights = vec![0i32; width * (length + 1)];
+    if option.mode == MapArtMode::Staircase {
+        for x in 0..width {
+            let mut min_height = 0;
+            for z in 1..=length {
+                let north = heights[(z - 1) * width + x];
+                let cur = match pixels[(z - 1) * width + x] {
+                    Some((_, MapColorShade::High)) => north + 1,
+                    Some((_, MapColorShade::Low)) => north - 1,
+                    _ => north,
+                };
+                heights[z * width + x] = cur;
+                min_height = min_height.min(cur);
+            }
+            for z in 0..=length {
+                heights[z * width + x] -= min_height;
+            }
+        }
+    }
+    let y_base = if option.support_blocks { 1 } else { 0 };
+    let max_height = heights.iter().copied().max().unwrap_or(0);
+
+    let mut region = Region::with_shape([width as i32, max_height + y_base + 1, length as i32 + 1]);
+    region.name = "MapArt".to_string();
+    let base_id = region.find_or_append_to_palette(&option.base_block);
+    let block_ids: Vec<u16> = blocks.iter().map(|b| region.find_or_append_to_palette(&b.block)).collect();
+    for z in 0..=length {
+        for x in 0..width {
+            let y = (heights[z * width + x] + y_base) as usize;
+            let block_id = if z == 0 {
+                base_id
+            } else {
+                match pixels[(z - 1) * width + x] {
+                    Some((block_idx, _)) => block_ids[block_idx],
+                    None => continue,
+                }
+            };
+            region.array_yzx[[y, z, x]] = block_id;
+            if option.support_blocks {
+                region.array_yzx[[y - 1, z, x]] = base_id;
+            }
+        }
+    }
+
+    let mut schem = Schematic::new();
+    schem.metadata.name = "MapArt".to_string();
+    schem.regions.push(region);
+    return Ok(schem);
+}
+
+#[test]
+fn test_generate_mapart() {
+    use crate::region::WorldSlice;
+    let white = Block::from_id("minecraft:white_wool").unwrap();
+    let black = Block::from_id("minecraft:black_wool").unwrap();
+    let blocks = vec![MapArtBlock { block: white.clone(), base_color: 8 },
+                      MapArtBlock { block: black.clone(), base_color: 29 }];
+
+    // flat, with support blocks
+    let mut image = RgbaImage::new(2, 2);
+    image.set_pixel(0, 0, [250, 250, 250, 255]);
+    image.set_pixel(1, 0, [10, 10, 10, 255]);
+    image.set_pixel(0, 1, [30, 20, 20, 255]);
+    let option = MapArtOption { support_blocks: true, ..MapArtOption::default() };
+    let schem = generate_mapart(&image, &blocks, &option).unwrap();
+    let region = &schem.regions[0];
+    assert_eq!(region.shape(), [2, 2, 3]);
+    assert_eq!(region.block_at([0, 1, 0]).unwrap(), &option.base_block);
+    assert_eq!(region.block_at([0, 1, 1]).unwrap(), &white);
+    assert_eq!(region.block_at([1, 1, 1]).unwrap(), &black);
+    assert_eq!(region.block_at([0, 1, 2]).unwrap(), &black);
+    assert_eq!(region.block_at([0, 0, 2]).unwrap(), &option.base_block);
+    // transparent pixel
+    assert!(region.block_at([1, 1, 2]).unwrap().is_air());
+    assert!(region.block_at([1, 0, 2]).unwrap().is_air());
+
+    // staircase: high, high, low
+    let mut image = RgbaImage::new(1, 3);
+    image.set_pixel(0, 0, [255, 255, 255, 255]);
+    image.set_pixel(0, 1, [255, 255, 255, 255]);
+    image.set_pixel(0, 2, [180, 180, 180, 255]);
+    let option = MapArtOption { mode: MapArtMode::Staircase, ..MapArtOption::default() };
+    let schem = generate_mapart(&image, &blocks[0..1], &option).unwrap();
+    let region = &schem.regions[0];
+    assert_eq!(region.shape(), [1, 3, 4]);
+    assert_eq!(region.block_at([0, 0, 0]).unwrap(), &option.base_block);
+    assert_eq!(region.block_at([0, 1, 1]).unwrap(), &white);
+    assert_eq!(region.block_at([0, 2, 2]).unwrap(), &white);
+    assert_eq!(region.block_at([0, 1, 3]).unwrap(), &white);
+
+    // gray is dithered to a mixture of black and white
+    let mut image = RgbaImage::new(8, 8);
+    for x in 0..8 {
+        for z in 0..8 {
+            image.set_pixel(x, z, [120, 120, 120, 255]);
+        }
+    }
+    for dithering in [Dithering::None, Dithering::FloydSteinberg, Dithering::JarvisJudiceNinke] {
+        let option = MapArtOption { dithering, color_metric: ColorMetric::Lab, ..MapArtOption::default() };
+        let schem = generate_mapart(&image, &blocks, &option).unwrap();
+        let whites = (0..8).flat_map(|x| (1..9).map(move |z| [x, 0, z]))
+            .filter(|pos| schem.regions[0].block_at(*pos).unwrap() == &white)
+            .count();
+        if dithering == Dithering::None {
+            assert!(whites == 0 || whites == 64);
+        } else {
+            assert!(whites > 8 && whites < 56, "{dithering}: {whites} white blocks");
+        }
+    }
+
+    assert!(generate_mapart(&image, &[], &MapArtOption::default()).is_err());
+}
@@ -0,0 +1,360 @@
+/*
+mc_schem is a rust library to generate, load, manipulate and save minecraft schematic files.
+Copyright (C) 2024  joseph
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use std::collections::BTreeMap;
+use fastnbt::Value;
+use crate::block::Block;
+use crate::region::{BlockEntity, Region};
+use crate::schem::Schematic;
+
+/// Number of slots in a shulker box
+pub const SHULKER_BOX_SLOTS: u64 = 27;
+
+/// Options to generate material list
+#[derive(Debug, Clone)]
+pub struct MaterialListOption {
+    /// Count items in containers, like chests and shulker boxes
+    pub include_containers: bool,
+}
+
+impl Default for MaterialListOption {
+    fn default() -> Self {
+        return Self {
+            include_containers: false,
+        };
+    }
+}
+
+/// Count of an item, splitted into shulker boxes, stacks and remaining items
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ItemBreakdown {
+    pub shulker_boxes: u64,
+    pub stacks: u64,
+    pub items: u64,
+}
+
+impl ItemBreakdown {
+    /// Split `count` items whose max stack size is `stack_size`
+    pub fn new(count: u64, stack_size: u64) -> ItemBreakdown {
+        let per_box = stack_size * SHULKER_BOX_SLOTS;
+        return ItemBreakdown {
+            shulker_boxes: count / per_box,
+            stacks: (count % per_box) / stack_size,
+            items: count % stack_size,
+        };
+    }
+}
+
+/// Max stack size of an item, the namespace is optional.
+pub fn stack_size(item_id: &str) -> u64 {
+    let id = item_id.strip_prefix("minecraft:").unwrap_or(item_id);
+    const SIZE_16: &[&str] = &["bucket", "snowball", "egg", "ender_pearl", "honey_bottle",
+        "armor_stand", "written_book"];
+    const SUFFIX_16: &[&str] = &["_sign", "_banner"];
+    const SIZE_1: &[&str] = &["minecart", "shulker_box", "totem_of_undying", "potion",
+        "splash_potion", "lingering_potion", "cake", "saddle", "elytra", "trident", "bow",
+        "crossbow", "shield", "fishing_rod", "writable_book", "enchanted_book", "debug_stick",
+        "flint_and_steel", "shears", "spyglass", "brush", "goat_horn", "bundle", "suspicious_stew",
+        "carrot_on_a_stick", "warped_fungus_on_a_stick", "mace"];
+    const SUFFIX_1: &[&str] = &["_bucket", "_bed", "_boat", "_raft", "_minecart",
+        "_shulker_box", "_sword", "_pickaxe", "_axe", "_shovel", "_hoe", "_helmet", "_chestplate",
+        "_leggings", "_boots", "_horse_armor", "_banner_pattern", "_soup"];
+
+    if SIZE_16.contains(&id) || SUFFIX_16.iter().any(|s| id.ends_with(s)) {
+        return 16;
+    }
+    if SIZE_1.contains(&id) || id.starts_with("music_disc_") || SUFFIX_1.iter().any(|s| id.ends_with(s)) {
+        return 1;
+    }
+    return 64;
+}
+
+/// Blocks that can't be obtained as items
+const NO_ITEM_BLOCKS: &[&str] = &["air", "cave_air", "void_air", "structure_void",
+    "piston_head", "moving_piston", "fire", "soul_fire", "nether_portal", "end_portal",
+    "end_gateway", "bubble_column", "frosted_ice"];
+
+/// Blocks placed by a different item
+const BLOCK_TO_ITEM: &[(&str, &str)] = &[
+    ("redstone_wire", "redstone"),
+    ("tripwire", "string"),
+    ("wall_torch", "torch"),
+    ("soul_wall_torch", "soul_torch"),
+    ("redstone_wall_torch", "redstone_torch"),
+    ("wheat", "wheat_seeds"),
+    ("carrots", "carrot"),
+    ("potatoes", "potato"),
+    ("beetroots", "beetroot_seeds"),
+    ("melon_stem", "melon_seeds"),
+    ("attached_melon_stem", "melon_seeds"),
+    ("pumpkin_stem", "pumpkin_seeds"),
+    ("attached_pumpkin_stem", "pumpkin_seeds"),
+    ("cocoa", "cocoa_beans"),
+    ("sweet_berry_bush", "sweet_berries"),
+    ("cave_vines", "glow_berries"),
+    ("cave_vines_plant", "glow_berries"),
+    ("torchflower_crop", "torchflower_seeds"),
+    ("pitcher_crop", "pitcher_pod"),
+    ("kelp_plant", "kelp"),
+    ("bamboo_sapling", "bamboo"),
+    ("weeping_vines_plant", "weeping_vines"),
+    ("twisting_vines_plant", "twisting_vines"),
+    ("big_dripleaf_stem", "big_dripleaf"),
+    ("tall_seagrass", "seagrass"),
+    ("farmland", "dirt"),
+    ("dirt_path", "dirt"),
+    ("grass_path", "dirt"),
+    ("water_cauldron", "cauldron"),
+    ("lava_cauldron", "cauldron"),
+    ("powder_snow_cauldron", "cauldron"),
+    ("potted_azalea_bush", "azalea"),
+    ("potted_flowering_azalea_bush", "flowering_azalea"),
+];
+
+/// Blocks with 2 parts, only one of the parts is counted
+const TWO_PART_BLOCKS: &[(&str, &str, &str)] = &[
+    ("sunflower", "half", "lower"),
+    ("lilac", "half", "lower"),
+    ("rose_bush", "half", "lower"),
+    ("peony", "half", "lower"),
+    ("tall_grass", "half", "lower"),
+    ("large_fern", "half", "lower"),
+    ("tall_seagrass", "half", "lower"),
+    ("pitcher_plant", "half", "lower"),
+    ("small_dripleaf", "half", "lower"),
+];
+
+/// Properties that stores how many items are placed in one block
+const MULTI_ITEM_PROPERTIES: &[(&str, &str)] = &[
+    ("sea_pickle", "pickles"),
+    ("turtle_egg", "eggs"),
+    ("pink_petals", "flower_amount"),
+    ("snow", "layers"),
+];
+
+/// Items required to place `block`, like litematica's material list. The result is empty if the
+/// block can't be obtained as item, or it's a part of another block that has been counted.
+pub fn items_of_block(block: &Block) -> Vec<(String, u64)> {
+    let namespace = if block.namespace.is_empty() { "minecraft" } else { block.namespace.as_str() };
+    let id = block.id.as_str();
+    let attr = |key: &str| block.attributes.get(key).map(|s| s.as_str());
+    let item = |id: &str| format!("{namespace}:{id}");
+
+    if namespace != "minecraft" {
+        return vec![(item(id), 1)];
+    }
+    if NO_ITEM_BLOCKS.contains(&id) {
+        return vec![];
+    }
+    if id == "water" || id == "lava" {
+        return if attr("level").unwrap_or("0") == "0" {
+            vec![(item(&format!("{id}_bucket")), 1)]
+        } else { vec![] };
+    }
+    for (blk, key, value) in TWO_PART_BLOCKS {
+        if *blk == id && attr(key).unwrap_or(value) != *value {
+            return vec![];
+        }
+    }
+    if id.ends_with("_door") && attr("half").unwrap_or("lower") != "lower" {
+        return vec![];
+    }
+    if id.ends_with("_bed") && attr("part").unwrap_or("foot") != "foot" {
+        return vec![];
+    }
+    if let Some((_, to)) = BLOCK_TO_ITEM.iter().find(|(from, _)| *from == id) {
+        return vec![(item(to), 1)];
+    }
+    if id.ends_with("_slab") && attr("type") == Some("double") {
+        return vec![(item(id), 2)];
+    }
+    for (blk, key) in MULTI_ITEM_PROPERTIES {
+        if *blk == id {
+            let count = attr(key).and_then(|s| s.parse::<u64>().ok()).unwrap_or(1);
+            return vec![(item(id), count)];
+        }
+    }
+    if id == "candle" || id.ends_with("_candle") {
+        let count = attr("candles").and_then(|s| s.parse::<u64>().ok()).unwrap_or(1);
+        return vec![(item(id), count)];
+    }
+    if id.ends_with("candle_cake") {
+        let candle = id.strip_suffix("_cake").unwrap();
+        return vec![(item("cake"), 1), (item(candle), 1)];
+    }
+    if let Some(plant) = id.strip_prefix("potted_") {
+        return vec![(item("flower_pot"), 1), (item(plant), 1)];
+    }
+    for (wall, placed) in [("_wall_hanging_sign", "_hanging_sign"), ("_wall_sign", "_sign"),
+        ("_wall_banner", "_banner"), ("_wall_head", "_head"), ("_wall_skull", "_skull"),
+        ("_wall_fan", "_fan")] {
+        if let Some(prefix) = id.strip_suffix(wall) {
+            return vec![(item(&format!("{prefix}{placed}")), 1)];
+        }
+    }
+    return vec![(item(id), 1)];
+}
+
+/// Items stored in a container, nested containers are not expanded.
+pub fn items_in_block_entity(be: &BlockEntity) -> Vec<(String, u64)> {
+    let mut result = Vec::new();
+    let items = match be.tags.get("Items") {
+        Some(Value::List(items)) => items,
+        _ => return result,
+    };
+    for item in items {
+        let item = match item {
+            Value::Compound(c) => c,
+            _ => continue,
+        };
+        let id = match item.get("id") {
+            Some(Value::String(id)) => id.clone(),
+            _ => continue,
+        };
+        // "Count" before 1.20.5, "count" after
+        let count = match item.get("Count").or_else(|| item.get("count")) {
+            Some(Value::Byte(c)) => *c as i64,
+            Some(Value::Short(c)) => *c as i64,
+            Some(Value::Int(c)) => *c as i64,
+            _ => 1,
+        };
+        if count > 0 {
+            result.push((id, count as u64));
+        }
+    }
+    return result;
+}
+
+/// Items required to build a region or schematic, sorted by item id.
+#[derive(Debug, Clone, Default)]
+pub struct MaterialList {
+    /// Item id with namespace -> count
+    pub items: BTreeMap<String, u64>,
+}
+
+impl MaterialList {
+    pub fn new() -> MaterialList {
+        return MaterialList::default();
+    }
+
+    /// Add `count` items
+    pub fn add(&mut self, item: &str, count: u64) {
+        *self.items.entry(item.to_string()).or_insert(0) += count;
+    }
+
+    /// Merge another material list into this one
+    pub fn merge(&mut self, other: &MaterialList) {
+        for (item, count) in &other.items {
+            self.add(item, *count);
+        }
+    }
+
+    /// Count items of a region
+    pub fn from_region(region: &Region, option: &MaterialListOption) -> MaterialList {
+        let mut block_count = vec![0u64; region.palette.len()];
+        for idx in region.array_yzx.iter() {
+            if let Some(c) = block_count.get_mut(*idx as usize) {
+                *c += 1;
+            }
+        }
+        let mut result = MaterialList::new();
+        for (blk, count) in region.palette.iter().zip(block_count.iter()) {
+            if *count == 0 {
+                continue;
+            }
+            for (item, num) in items_of_block(blk) {
+                result.add(&item, num * count);
+            }
+        }
+        if option.include_containers {
+            for be in region.block_entities.values() {
+                for (item, num) in items_in_block_entity(be) {
+                    result.add(&item, num);
+                }
+            }
+        }
+        return result;
+    }
+
+    /// Count items of all regions in a schematic
+    pub fn from_schematic(schem: &Schematic, option: &MaterialListOption) -> MaterialList {
+        let mut result = MaterialList::new();
+        for reg in &schem.regions {
+            result.merge(&MaterialList::from_region(reg, option));
+        }
+        return result;
+    }
+
+    /// Total number of items
+    pub fn total(&self) -> u64 {
+        return self.items.values().sum();
+    }
+
+    /// Items sorted by count in descending order, items with same count are sorted by id.
+    pub fn sorted(&self) -> Vec<(&str, u64)> {
+        let mut result: Vec<(&str, u64)> = self.items.iter().map(|(k, v)| (k.as_str(), *v)).collect();
+        result.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        return result;
+    }
+
+    /// Export as csv, with columns `item,count,stack_size,shulker_boxes,stacks,items`
+    pub fn to_csv(&self) -> String {
+        let mut result = String::from("item,count,stack_size,shulker_boxes,stacks,items\n");
+        for (item, count) in self.sorted() {
+            let size = stack_size(item);
+            let bd = ItemBreakdown::new(count, size);
+            result.push_str(&format!("{item},{count},{size},{},{},{}\n", bd.shulker_boxes, bd.stacks, bd.items));
+        }
+        return result;
+    }
+
+    /// Export as a json array of objects, with the same fields as csv
+    pub fn to_json(&self) -> String {
+        let mut entries = Vec::with_capacity(self.items.len());
+        for (item, count) in self.sorted() {
+            let size = stack_size(item);
+            let bd = ItemBreakdown::new(count, size);
+            entries.push(format!("  {{\"item\": \"{}\", \"count\": {count}, \"stack_size\": {size}, \"shulker_boxes\": {}, \"stacks\": {}, \"items\": {}}}",
+                                 escape_json(item), bd.shulker_boxes, bd.stacks, bd.items));
+        }
+        if entries.is_empty() {
+            return "[]\n".to_string();
+        }
+        return format!("[\n{}\n]\n", entries.join(",\n"));
+    }
+}
+
+fn escape_json(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            c if (c as u32) < 0x20 => result.push_str(&format!("\\u{:04x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+    return result;
+}
+
+impl Schematic {
+    /// Generate material list of this schematic
+    pub fn material_list(&self, option: &MaterialListOption) -> MaterialList {
+        return MaterialList::from_schematic(self, option);
+    }
+}
@@ -16,6 +16,8 @@ You should have received a copy of the GNU General Public License
 along with this program.  If not, see <https://www.gnu.org/licenses/>.
 */
 
+use std::collections::HashMap;
+use std::sync::OnceLock;
 use strum::Display;
 use crate::block::Block;
 use crate::region::BlockEntity;
@@ -1234,4 +1236,445 @@ impl Block {
 
         return Ok(None);
     }
-}
\ No newline at end of file
+}
+
+/// Convert a color name in 1.13+ to 1.12-. Returns `None` if `color` is not a dye color.
+pub fn color_to_old(color: &str) -> Option<&'static str> {
+    let lut = [("white", "white"),
+        ("orange", "orange"),
+        ("magenta", "magenta"),
+        ("light_blue", "light_blue"),
+        ("yellow", "yellow"),
+        ("lime", "lime"),
+        ("pink", "pink"),
+        ("gray", "gray"),
+        ("light_gray", "silver"),
+        ("cyan", "cyan"),
+        ("purple", "purple"),
+        ("blue", "blue"),
+        ("brown", "brown"),
+        ("green", "green"),
+        ("red", "red"),
+        ("black", "black")];
+    for (new, old) in lut {
+        if new == color {
+            return Some(old);
+        }
+    }
+    return None;
+}
+
+/// Id in 1.13+, id in 1.12- and properties in 1.12-
+type FlattenedBlockId = (&'static str, &'static str, &'static [(&'static str, &'static str)]);
+
+/// Blocks that are renamed in the flattening(1.13), and their properties in 1.12-. The first
+/// element is the id in 1.13+, the second is the id in 1.12-.
+const FLATTENED_BLOCK_IDS: &[FlattenedBlockId] = &[
+    ("cave_air", "air", &[]),
+    ("void_air", "air", &[]),
+    ("stone", "stone", &[("variant", "stone")]),
+    ("granite", "stone", &[("variant", "granite")]),
+    ("polished_granite", "stone", &[("variant", "smooth_granite")]),
+    ("diorite", "stone", &[("variant", "diorite")]),
+    ("polished_diorite", "stone", &[("variant", "smooth_diorite")]),
+    ("andesite", "stone", &[("variant", "andesite")]),
+    ("polished_andesite", "stone", &[("variant", "smooth_andesite")]),
+    ("grass_block", "grass", &[]),
+    ("dirt", "dirt", &[("variant", "dirt")]),
+    ("coarse_dirt", "dirt", &[("variant", "coarse_dirt")]),
+    ("podzol", "dirt", &[("variant", "podzol")]),
+    ("sand", "sand", &[("variant", "sand")]),
+    ("red_sand", "sand", &[("variant", "red_sand")]),
+    ("sponge", "sponge", &[("wet", "false")]),
+    ("wet_sponge", "sponge", &[("wet", "true")]),
+    ("sandstone", "sandstone", &[("type", "sandstone")]),
+    ("chiseled_sandstone", "sandstone", &[("type", "chiseled_sandstone")]),
+    ("cut_sandstone", "sandstone", &[("type", "smooth_sandstone")]),
+    ("red_sandstone", "red_sandstone", &[("type", "red_sandstone")]),
+    ("chiseled_red_sandstone", "red_sandstone", &[("type", "chiseled_red_sandstone")]),
+    ("cut_red_sandstone", "red_sandstone", &[("type", "smooth_red_sandstone")]),
+    ("note_block", "noteblock", &[]),
+    ("powered_rail", "golden_rail", &[]),
+    ("cobweb", "web", &[]),
+    ("grass", "tallgrass", &[("type", "tall_grass")]),
+    ("short_grass", "tallgrass", &[("type", "tall_grass")]),
+    ("fern", "tallgrass", &[("type", "fern")]),
+    ("dead_bush", "deadbush", &[]),
+    ("moving_piston", "piston_extension", &[]),
+    ("dandelion", "yellow_flower", &[("type", "dandelion")]),
+    ("poppy", "red_flower", &[("type", "poppy")]),
+    ("blue_orchid", "red_flower", &[("type", "blue_orchid")]),
+    ("allium", "red_flower", &[("type", "allium")]),
+    ("azure_bluet", "red_flower", &[("type", "houstonia")]),
+    ("red_tulip", "red_flower", &[("type", "red_tulip")]),
+    ("orange_tulip", "red_flower", &[("type", "orange_tulip")]),
+    ("white_tulip", "red_flower", &[("type", "white_tulip")]),
+    ("pink_tulip", "red_flower", &[("type", "pink_tulip")]),
+    ("oxeye_daisy", "red_flower", &[("type", "oxeye_daisy")]),
+    ("bricks", "brick_block", &[]),
+    ("smooth_stone", "double_stone_slab", &[("variant", "stone"), ("seamless", "true")]),
+    ("smooth_sandstone", "double_stone_slab", &[("variant", "sandstone"), ("seamless", "true")]),
+    ("smooth_quartz", "double_stone_slab", &[("variant", "quartz"), ("seamless", "true")]),
+    ("smooth_red_sandstone", "double_stone_slab2", &[("variant", "red_sandstone"), ("seamless", "true")]),
+    ("spawner", "mob_spawner", &[]),
+    ("cobblestone_stairs", "stone_stairs", &[]),
+    ("sign", "standing_sign", &[]),
+    ("oak_sign", "standing_sign", &[]),
+    ("oak_wall_sign", "wall_sign", &[]),
+    ("oak_door", "wooden_door", &[]),
+    ("oak_pressure_plate", "wooden_pressure_plate", &[]),
+    ("oak_button", "wooden_button", &[]),
+    ("snow", "snow_layer", &[]),
+    ("snow_block", "snow", &[]),
+    ("sugar_cane", "reeds", &[]),
+    ("oak_fence", "fence", &[]),
+    ("oak_fence_gate", "fence_gate", &[]),
+    ("oak_trapdoor", "trapdoor", &[]),
+    ("carved_pumpkin", "pumpkin", &[]),
+    ("jack_o_lantern", "lit_pumpkin", &[]),
+    ("nether_portal", "portal", &[]),
+    ("infested_stone", "monster_egg", &[("variant", "stone")]),
+    ("infested_cobblestone", "monster_egg", &[("variant", "cobblestone")]),
+    ("infested_stone_bricks", "monster_egg", &[("variant", "stone_brick")]),
+    ("infested_mossy_stone_bricks", "monster_egg", &[("variant", "mossy_brick")]),
+    ("infested_cracked_stone_bricks", "monster_egg", &[("variant", "cracked_brick")]),
+    ("infested_chiseled_stone_bricks", "monster_egg", &[("variant", "chiseled_brick")]),
+    ("stone_bricks", "stonebrick", &[("variant", "stonebrick")]),
+    ("mossy_stone_bricks", "stonebrick", &[("variant", "mossy_stonebrick")]),
+    ("cracked_stone_bricks", "stonebrick", &[("variant", "cracked_stonebrick")]),
+    ("chiseled_stone_bricks", "stonebrick", &[("variant", "chiseled_stonebrick")]),
+    ("melon", "melon_block", &[]),
+    ("attached_pumpkin_stem", "pumpkin_stem", &[]),
+    ("attached_melon_stem", "melon_stem", &[]),
+    ("lily_pad", "waterlily", &[]),
+    ("nether_bricks", "nether_brick", &[]),
+    ("end_stone_bricks", "end_bricks", &[]),
+    ("cobblestone_wall", "cobblestone_wall", &[("variant", "cobblestone")]),
+    ("mossy_cobblestone_wall", "cobblestone_wall", &[("variant", "mossy_cobblestone")]),
+    ("nether_quartz_ore", "quartz_ore", &[]),
+    ("quartz_block", "quartz_block", &[("variant", "default")]),
+    ("chiseled_quartz_block", "quartz_block", &[("variant", "chiseled")]),
+    ("slime_block", "slime", &[]),
+    ("prismarine", "prismarine", &[("variant", "prismarine")]),
+    ("prismarine_bricks", "prismarine", &[("variant", "prismarine_bricks")]),
+    ("dark_prismarine", "prismarine", &[("variant", "dark_prismarine")]),
+    ("terracotta", "hardened_clay", &[]),
+    ("sunflower", "double_plant", &[("variant", "sunflower")]),
+    ("lilac", "double_plant", &[("variant", "syringa")]),
+    ("tall_grass", "double_plant", &[("variant", "double_grass")]),
+    ("large_fern", "double_plant", &[("variant", "fern")]),
+    ("rose_bush", "double_plant", &[("variant", "rose")]),
+    ("peony", "double_plant", &[("variant", "paeonia")]),
+    ("dirt_path", "grass_path", &[]),
+    ("magma_block", "magma", &[]),
+    ("red_nether_bricks", "red_nether_brick", &[]),
+    ("flower_pot", "flower_pot", &[("contents", "empty")]),
+    ("skeleton_skull", "skull", &[("facing", "up")]),
+    ("wither_skeleton_skull", "skull", &[("facing", "up")]),
+    ("zombie_head", "skull", &[("facing", "up")]),
+    ("player_head", "skull", &[("facing", "up")]),
+    ("creeper_head", "skull", &[("facing", "up")]),
+    ("dragon_head", "skull", &[("facing", "up")]),
+    ("skeleton_wall_skull", "skull", &[]),
+    ("wither_skeleton_wall_skull", "skull", &[]),
+    ("zombie_wall_head", "skull", &[]),
+    ("player_wall_head", "skull", &[]),
+    ("creeper_wall_head", "skull", &[]),
+    ("dragon_wall_head", "skull", &[]),
+    ("anvil", "anvil", &[("damage", "0")]),
+    ("chipped_anvil", "anvil", &[("damage", "1")]),
+    ("damaged_anvil", "anvil", &[("damage", "2")]),
+];
+
+/// Stone slabs in 1.13+, and their variants in 1.12-
+const FLATTENED_STONE_SLABS: &[(&str, &str, &str)] = &[
+    ("stone_slab", "stone_slab", "stone"),
+    ("smooth_stone_slab", "stone_slab", "stone"),
+    ("sandstone_slab", "stone_slab", "sandstone"),
+    ("petrified_oak_slab", "stone_slab", "wood_old"),
+    ("cobblestone_slab", "stone_slab", "cobblestone"),
+    ("brick_slab", "stone_slab", "brick"),
+    ("stone_brick_slab", "stone_slab", "stone_brick"),
+    ("nether_brick_slab", "stone_slab", "nether_brick"),
+    ("quartz_slab", "stone_slab", "quartz"),
+    ("red_sandstone_slab", "stone_slab2", "red_sandstone"),
+    ("purpur_slab", "purpur_slab", "default"),
+];
+
+/// Properties that decide what the block is, instead of the state of the block. A number id can
+/// never be matched if such properties are different.
+const IDENTITY_PROPERTIES: [&str; 5] = ["variant", "color", "type", "contents", "damage"];
+
+type OldBlockLut = HashMap<String, Vec<(u8, u8, Block)>>;
+
+/// Look-up table from 1.12- string id to all valid number id and damage values
+fn old_block_lut() -> &'static OldBlockLut {
+    static LUT: OnceLock<OldBlockLut> = OnceLock::new();
+    return LUT.get_or_init(|| {
+        let mut lut: OldBlockLut = HashMap::new();
+        let mut damage_list = Vec::with_capacity(16);
+        for id in 0..=255u8 {
+            get_valid_damage_values(id, &mut damage_list);
+            for damage in &damage_list {
+                if let Ok(blk) = Block::from_old(id, *damage, DataVersion::Java_1_12_2) {
+                    lut.entry(blk.id.clone()).or_default().push((id, *damage, blk));
+                }
+            }
+        }
+        lut
+    });
+}
+
+impl Block {
+    /// Convert a block in 1.13+ to its string id and properties in 1.12-. Blocks that are already
+    /// in 1.12- format are returned as they are, so it's safe to call this function on any block.
+    /// Returns `None` if the block has no equivalent before the flattening.
+    pub fn to_pre_flattening(&self) -> Option<Block> {
+        if !self.namespace.is_empty() && self.namespace != "minecraft" {
+            return None;
+        }
+        let mut result = self.clone();
+        result.namespace = "minecraft".to_string();
+        if self.is_pre_flattening() {
+            return Some(result);
+        }
+        let id = self.id.as_str();
+
+        for (new, old, props) in FLATTENED_BLOCK_IDS {
+            if *new != id {
+                continue;
+            }
+            result.id = old.to_string();
+            for (key, val) in *props {
+                result.set_property(key, *val);
+            }
+            return Some(result);
+        }
+
+        // stone slabs
+        for (new, old, variant) in FLATTENED_STONE_SLABS {
+            if *new != id {
+                continue;
+            }
+            Self::slab_type_to_old(&mut result, old, variant);
+            return Some(result);
+        }
+
+        if old_block_lut().contains_key(id) {
+            // lit blocks are split into 2 ids in 1.12-, but other blocks can be kept
+            match (id, self.attributes.get("lit").map(|s| s.as_str())) {
+                ("furnace", Some("true")) => result.id = "lit_furnace".to_string(),
+                ("redstone_ore", Some("true")) => result.id = "lit_redstone_ore".to_string(),
+                ("redstone_lamp", Some("true")) => result.id = "lit_redstone_lamp".to_string(),
+                ("redstone_torch", Some("false")) => result.id = "unlit_redstone_torch".to_string(),
+                _ => {}
+            }
+            if ["torch", "redstone_torch"].contains(&id) && !self.attributes.contains_key("facing") {
+                result.set_property("facing", "up");
+            }
+            return Some(result);
+        }
+
+        match id {
+            "wall_torch" => {
+                result.id = "torch".to_string();
+                return Some(result);
+            }
+            "redstone_wall_torch" => {
+                let lit = self.attributes.get("lit").map(|s| s.as_str()) != Some("false");
+                result.id = if lit { "redstone_torch" } else { "unlit_redstone_torch" }.to_string();
+                return Some(result);
+            }
+            "repeater" | "comparator" => {
+                let powered = self.attributes.get("powered").map(|s| s.as_str()) == Some("true");
+                result.id = format!("{}_{id}", if powered { "powered" } else { "unpowered" });
+                return Some(result);
+            }
+            "daylight_detector" => {
+                if self.attributes.get("inverted").map(|s| s.as_str()) == Some("true") {
+                    result.id = "daylight_detector_inverted".to_string();
+                }
+                return Some(result);
+            }
+            "quartz_pillar" => {
+                let axis = self.attributes.get("axis").map(|s| s.as_str()).unwrap_or("y");
+                result.id = "quartz_block".to_string();
+                result.set_property("variant", &format!("lines_{axis}"));
+                return Some(result);
+            }
+            "mushroom_stem" => {
+                result.id = "brown_mushroom_block".to_string();
+                result.attributes.clear();
+                result.set_property("variant", "stem");
+                return Some(result);
+            }
+            "water" | "lava" => return Some(result),
+            _ => {}
+        }
+        if id.starts_with("potted_") {
+            result.id = "flower_pot".to_string();
+            return Some(result);
+        }
+
+        // blocks with color
+        for (suffix, old_id, color_as_prefix) in [
+            ("_wool", "wool", false),
+            ("_stained_glass", "stained_glass", false),
+            ("_stained_glass_pane", "stained_glass_pane", false),
+            ("_terracotta", "stained_hardened_clay", false),
+            ("_carpet", "carpet", false),
+            ("_concrete", "concrete", false),
+            ("_concrete_powder", "concrete_powder", false),
+            ("_bed", "bed", false),
+            ("_banner", "standing_banner", false),
+            ("_wall_banner", "wall_banner", false),
+            ("_shulker_box", "shulker_box", true),
+            ("_glazed_terracotta", "glazed_terracotta", true)] {
+            let color = match id.strip_suffix(suffix) {
+                Some(c) => c,
+                None => continue,
+            };
+            let old_color = match color_to_old(color) {
+                Some(c) => c,
+                None => continue,
+            };
+            if color_as_prefix {
+                result.id = format!("{old_color}_{old_id}");
+            } else {
+                result.id = old_id.to_string();
+                if !["bed", "standing_banner", "wall_banner"].contains(&old_id) {
+                    result.set_property("color", old_color);
+                }
+            }
+            return Some(result);
+        }
+
+        // blocks with wood types
+        for wood in ["oak", "spruce", "birch", "jungle", "acacia", "dark_oak"] {
+            let suffix = match id.strip_prefix(wood).and_then(|s| s.strip_prefix('_')) {
+                Some(s) => s,
+                None => continue,
+            };
+            let is_old_wood = ["oak", "spruce", "birch", "jungle"].contains(&wood);
+            match suffix {
+                "planks" => {
+                    result.id = "planks".to_string();
+                    result.set_property("variant", wood);
+                }
+                "sapling" => {
+                    result.id = "sapling".to_string();
+                    result.set_property("type", wood);
+                }
+                "log" | "wood" => {
+                    result.id = if is_old_wood { "log" } else { "log2" }.to_string();
+                    result.set_property("variant", wood);
+                    if suffix == "wood" {
+                        result.set_property("axis", "none");
+                    }
+                }
+                "leaves" => {
+                    result.id = if is_old_wood { "leaves" } else { "leaves2" }.to_string();
+                    let persistent = self.attributes.get("persistent").map(|s| s.as_str()) == Some("true");
+                    result.set_property("variant", wood);
+                    result.set_property("decayable", &!persistent);
+                    result.set_property("check_decay", &false);
+                }
+                "slab" => {
+                    Self::slab_type_to_old(&mut result, "wooden_slab", wood);
+                }
+                "fence" | "fence_gate" | "door" => {
+                    result.id = format!("{wood}_{suffix}");
+                }
+                _ => return None,
+            }
+            return Some(result);
+        }
+
+        return None;
+    }
+
+    /// Returns true if the block looks like a block in 1.12-, which means that its id exists in
+    /// 1.12- and it has no properties that don't exist in 1.12-. Some ids(like `grass` and `snow`)
+    /// exist both in 1.12- and 1.13+ but refer to different blocks, this function tells them apart
+    /// by properties.
+    pub fn is_pre_flattening(&self) -> bool {
+        let candidates = match old_block_lut().get(&self.id) {
+            Some(c) => c,
+            None => return false,
+        };
+        for (_, _, candidate) in candidates {
+            if self.attributes.is_empty() != candidate.attributes.is_empty() {
+                continue;
+            }
+            let mut all_keys_exist = true;
+            for key in self.attributes.keys() {
+                if !candidate.attributes.contains_key(key) {
+                    all_keys_exist = false;
+                    break;
+                }
+            }
+            if all_keys_exist {
+                return true;
+            }
+        }
+        return false;
+    }
+
+    fn slab_type_to_old(block: &mut Block, single_id: &str, variant: &str) {
+        let slab_type = block.attributes.remove("type").unwrap_or("bottom".to_string());
+        block.attributes.remove("waterlogged");
+        block.set_property("variant", variant);
+        if slab_type == "double" {
+            block.id = match single_id {
+                "stone_slab" => "double_stone_slab",
+                "stone_slab2" => "double_stone_slab2",
+                "wooden_slab" => "double_wooden_slab",
+                _ => "purpur_double_slab",
+            }.to_string();
+        } else {
+            block.id = single_id.to_string();
+            block.set_property("half", &slab_type);
+        }
+    }
+
+    /// Convert block to number id and damage value in 1.12-. Both 1.12- blocks(like those loaded
+    /// from `.schematic`) and 1.13+ blocks can be converted. Properties that are not stored in
+    /// damage value(like `shape` of stairs) are ignored. Returns `None` if the block has no
+    /// equivalent before the flattening.
+    pub fn to_old(&self) -> Option<(u8, u8)> {
+        let old_block = self.to_pre_flattening()?;
+        let candidates = old_block_lut().get(&old_block.id)?;
+
+        let mut best: Option<(u8, u8)> = None;
+        let mut best_score = (0, i32::MIN);
+        for (id, damage, candidate) in candidates {
+            if *candidate == old_block {
+                return Some((*id, *damage));
+            }
+            let mut matched = 0;
+            let mut mismatched = 0;
+            let mut is_other_block = false;
+            for (key, val) in &candidate.attributes {
+                if let Some(v) = old_block.attributes.get(key) {
+                    if v == val {
+                        matched += 1;
+                    } else if IDENTITY_PROPERTIES.contains(&key.as_str()) {
+                        is_other_block = true;
+                        break;
+                    } else {
+                        mismatched += 1;
+                    }
+                }
+            }
+            if is_other_block {
+                continue;
+            }
+            let score = (matched, -mismatched);
+            if best.is_none() || score > best_score {
+                best = Some((*id, *damage));
+                best_score = score;
+            }
+        }
+        return best;
+    }
+}
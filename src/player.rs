@@ -1,8 +1,12 @@
 use std::any::Any;
 use std::collections::{BTreeMap, HashMap};
 use std::fmt::{Debug, Formatter};
+use fastnbt::Value;
 use strum::FromRepr;
+use crate::error::{Error, unwrap_opt_bool_or, unwrap_opt_f32, unwrap_opt_f64, unwrap_opt_i16, unwrap_opt_i32, unwrap_opt_i8, unwrap_opt_or, unwrap_opt_string};
 use crate::item::{Inventory, Item};
+use crate::{unwrap_opt_tag, unwrap_tag};
+use crate::schem::id_of_nbt_tag;
 
 #[derive(Debug, Clone)]
 pub struct DimensionId(String);
@@ -35,11 +39,25 @@ pub enum AttributeOperation {
     Multiply = 2,
 }
 
+impl AttributeOperation {
+    /// Parse operation names used since 1.20.5
+    pub fn from_name(name: &str) -> Option<AttributeOperation> {
+        return match name {
+            "add_value" => Some(AttributeOperation::Add),
+            "add_multiplied_base" => Some(AttributeOperation::MultiplyBase),
+            "add_multiplied_total" => Some(AttributeOperation::Multiply),
+            _ => None,
+        };
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct AttributeModifier {
+    /// Name of modifier, or the namespaced id since 1.21
     pub name: String,
     pub amount: f64,
     pub operation: AttributeOperation,
+    /// All zero since 1.21, as modifiers are identified by id
     pub uuid: [i32; 4],
 }
 
@@ -176,7 +194,7 @@ pub struct PotionEffect {
     pub hidden_effects: Vec<PotionEffect>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct PotionEffectFactorCalculationData {
     pub effect_changed_timestamp: i32,
     pub factor_current: f32,
@@ -460,4 +478,378 @@ impl Clone for EntityBox {
     fn clone(&self) -> Self {
         return EntityBox(self.0.clone_as_entity());
     }
-}
\ No newline at end of file
+}
+
+fn parse_uuid(nbt: &HashMap<String, Value>, key: &str, tag_path: &str) -> Result<[i32; 4], Error> {
+    let tag_path = format!("{tag_path}/{key}");
+    let arr = unwrap_opt_tag!(nbt.get(key),IntArray,fastnbt::IntArray::new(vec![]),tag_path);
+    if arr.len() != 4 {
+        return Err(Error::InvalidValue {
+            tag_path,
+            error: format!("UUID should contains 4 ints, but found {}", arr.len()),
+        });
+    }
+    return Ok([arr[0], arr[1], arr[2], arr[3]]);
+}
+
+/// Parse a list of doubles or floats like `Pos`, `Motion` and `Rotation`
+fn parse_f64_list<const N: usize>(nbt: &HashMap<String, Value>, key: &str, tag_path: &str) -> Result<[f64; N], Error> {
+    let tag_path = format!("{tag_path}/{key}");
+    let list = unwrap_opt_tag!(nbt.get(key),List,vec![],tag_path);
+    if list.len() != N {
+        return Err(Error::InvalidValue {
+            tag_path,
+            error: format!("Expected {N} elements, but found {}", list.len()),
+        });
+    }
+    let mut result = [0.0; N];
+    for (idx, val) in list.iter().enumerate() {
+        result[idx] = match val {
+            Value::Double(d) => *d,
+            Value::Float(f) => *f as f64,
+            _ => return Err(Error::TagTypeMismatch {
+                tag_path: format!("{tag_path}[{idx}]"),
+                expected_type: id_of_nbt_tag(&Value::Double(0.0)),
+                found_type: id_of_nbt_tag(val),
+            }),
+        };
+    }
+    return Ok(result);
+}
+
+impl DimensionId {
+    /// Parse dimension id stored at `key`. Before 1.16, dimension is stored as a number(-1 for
+    /// nether, 0 for overworld and 1 for the end). Missing key means overworld.
+    pub fn from_nbt(nbt: &HashMap<String, Value>, key: &str, tag_path: &str) -> Result<DimensionId, Error> {
+        let number = match nbt.get(key) {
+            None => return Ok(DimensionId::default()),
+            Some(Value::Int(id)) => Some(*id),
+            Some(Value::Byte(id)) => Some(*id as i32),
+            _ => None,
+        };
+        if let Some(number) = number {
+            return Ok(match number {
+                -1 => DimensionId::nether(),
+                1 => DimensionId::the_end(),
+                _ => DimensionId::overworld(),
+            });
+        }
+        return Ok(DimensionId(unwrap_opt_string(nbt, key, tag_path)?.clone()));
+    }
+
+    /// Namespaced id of dimension, like `minecraft:overworld`
+    pub fn id(&self) -> &str {
+        return &self.0;
+    }
+}
+
+impl EntityFields {
+    /// Parse common fields of all entities
+    pub fn from_nbt(nbt: &HashMap<String, Value>, tag_path: &str) -> Result<EntityFields, Error> {
+        let mut result = EntityFields {
+            air: unwrap_opt_or(nbt, "Air", tag_path, 300, unwrap_opt_i16)?,
+            ..EntityFields::default()
+        };
+        if nbt.contains_key("CustomName") {
+            result.custom_name = Some(unwrap_opt_string(nbt, "CustomName", tag_path)?.clone());
+        }
+        result.custom_name_visible = unwrap_opt_bool_or(nbt, "CustomNameVisible", tag_path, false)?;
+        result.fall_distance = unwrap_opt_or(nbt, "FallDistance", tag_path, 0.0, unwrap_opt_f32)?;
+        result.fire = unwrap_opt_or(nbt, "Fire", tag_path, -20, unwrap_opt_i16)?;
+        result.glowing = unwrap_opt_bool_or(nbt, "Glowing", tag_path, false)?;
+        result.has_visual_fire = unwrap_opt_bool_or(nbt, "HasVisualFire", tag_path, false)?;
+        result.invulnerable = unwrap_opt_bool_or(nbt, "Invulnerable", tag_path, false)?;
+        if nbt.contains_key("Motion") {
+            result.motion = parse_f64_list(nbt, "Motion", tag_path)?;
+        }
+        result.no_gravity = unwrap_opt_bool_or(nbt, "NoGravity", tag_path, false)?;
+        result.on_ground = unwrap_opt_bool_or(nbt, "OnGround", tag_path, false)?;
+        // Passengers are not parsed, since only players are implemented
+        result.portal_cool_down = unwrap_opt_or(nbt, "PortalCooldown", tag_path, 0, unwrap_opt_i32)?;
+        result.pos = parse_f64_list(nbt, "Pos", tag_path)?;
+        if nbt.contains_key("Rotation") {
+            let rot: [f64; 2] = parse_f64_list(nbt, "Rotation", tag_path)?;
+            result.rotation = [rot[0] as f32, rot[1] as f32];
+        }
+        result.silent = unwrap_opt_bool_or(nbt, "Silent", tag_path, false)?;
+        if let Some(tags) = nbt.get("Tags") {
+            let tags_path = format!("{tag_path}/Tags");
+            let tags = unwrap_tag!(tags,List,vec![],tags_path);
+            for (idx, tag) in tags.iter().enumerate() {
+                let tag = unwrap_tag!(tag,String,"".to_string(),format!("{tags_path}[{idx}]"));
+                result.tags.insert(tag.clone(), ScoreboardTag { score: 0, locked: false });
+            }
+        }
+        result.ticks_frozen = unwrap_opt_or(nbt, "TicksFrozen", tag_path, 0, unwrap_opt_i32)?;
+        if nbt.contains_key("UUID") {
+            result.uuid = parse_uuid(nbt, "UUID", tag_path)?;
+        }
+        return Ok(result);
+    }
+}
+
+impl PotionEffect {
+    pub fn from_nbt(nbt: &HashMap<String, Value>, tag_path: &str) -> Result<PotionEffect, Error> {
+        let mut hidden_effects = Vec::new();
+        if let Some(hidden) = nbt.get("hidden_effect") {
+            let hidden_path = format!("{tag_path}/hidden_effect");
+            let hidden = unwrap_tag!(hidden,Compound,HashMap::new(),hidden_path);
+            hidden_effects.push(PotionEffect::from_nbt(hidden, &hidden_path)?);
+        }
+        let mut factor_calculation_data = PotionEffectFactorCalculationData::default();
+        if let Some(factor) = nbt.get("factor_calculation_data") {
+            let factor_path = format!("{tag_path}/factor_calculation_data");
+            let factor = unwrap_tag!(factor,Compound,HashMap::new(),factor_path);
+            factor_calculation_data = PotionEffectFactorCalculationData {
+                effect_changed_timestamp: unwrap_opt_or(factor, "effect_changed_timestamp", &factor_path, 0, unwrap_opt_i32)?,
+                factor_current: unwrap_opt_or(factor, "factor_current", &factor_path, 0.0, unwrap_opt_f32)?,
+                factor_previous_frame: unwrap_opt_or(factor, "factor_previous_frame", &factor_path, 0.0, unwrap_opt_f32)?,
+                factor_start: unwrap_opt_or(factor, "factor_start", &factor_path, 0.0, unwrap_opt_f32)?,
+                factor_target: unwrap_opt_or(factor, "factor_target", &factor_path, 0.0, unwrap_opt_f32)?,
+                had_effect_last_tick: unwrap_opt_bool_or(factor, "had_effect_last_tick", &factor_path, false)?,
+                padding_duration: unwrap_opt_or(factor, "padding_duration", &factor_path, 0, unwrap_opt_i32)?,
+            };
+        }
+
+        return Ok(PotionEffect {
+            ambient: unwrap_opt_bool_or(nbt, "ambient", tag_path, false)?,
+            amplifier: unwrap_opt_or(nbt, "amplifier", tag_path, 0, unwrap_opt_i8)?,
+            duration: unwrap_opt_or(nbt, "duration", tag_path, 0, unwrap_opt_i32)?,
+            id: unwrap_opt_string(nbt, "id", tag_path)?.clone(),
+            show_icon: unwrap_opt_bool_or(nbt, "show_icon", tag_path, true)?,
+            show_particles: unwrap_opt_bool_or(nbt, "show_particles", tag_path, true)?,
+            factor_calculation_data,
+            hidden_effects,
+        });
+    }
+}
+
+impl AttributeModifier {
+    /// Parse modifier before 1.20.5
+    fn from_legacy_nbt(nbt: &HashMap<String, Value>, tag_path: &str) -> Result<AttributeModifier, Error> {
+        let operation = unwrap_opt_i32(nbt, "Operation", tag_path)?;
+        let operation = match AttributeOperation::from_repr(operation) {
+            Some(op) => op,
+            None => return Err(Error::InvalidValue {
+                tag_path: format!("{tag_path}/Operation"),
+                error: format!("Invalid attribute operation {operation}"),
+            }),
+        };
+        return Ok(AttributeModifier {
+            name: unwrap_opt_string(nbt, "Name", tag_path)?.clone(),
+            amount: unwrap_opt_f64(nbt, "Amount", tag_path)?,
+            operation,
+            uuid: parse_uuid(nbt, "UUID", tag_path)?,
+        });
+    }
+
+    /// Parse modifier since 1.20.5. Modifiers have `uuid` and `name` in 1.20.5, and a namespaced
+    /// `id` since 1.21.
+    fn from_nbt(nbt: &HashMap<String, Value>, tag_path: &str) -> Result<AttributeModifier, Error> {
+        let operation = unwrap_opt_string(nbt, "operation", tag_path)?;
+        let operation = match AttributeOperation::from_name(operation) {
+            Some(op) => op,
+            None => return Err(Error::InvalidValue {
+                tag_path: format!("{tag_path}/operation"),
+                error: format!("Invalid attribute operation {operation}"),
+            }),
+        };
+        let (name, uuid) = if nbt.contains_key("id") {
+            (unwrap_opt_string(nbt, "id", tag_path)?.clone(), [0; 4])
+        } else {
+            (unwrap_opt_string(nbt, "name", tag_path)?.clone(), parse_uuid(nbt, "uuid", tag_path)?)
+        };
+        return Ok(AttributeModifier {
+            name,
+            amount: unwrap_opt_f64(nbt, "amount", tag_path)?,
+            operation,
+            uuid,
+        });
+    }
+}
+
+impl EntityAttribute {
+    /// Parse attribute of any version, keys are in lower case since 1.20.5
+    pub fn from_nbt(nbt: &HashMap<String, Value>, tag_path: &str) -> Result<EntityAttribute, Error> {
+        let legacy = nbt.contains_key("Base");
+        let base = unwrap_opt_f64(nbt, if legacy { "Base" } else { "base" }, tag_path)?;
+        let mut modifiers = Vec::new();
+        let modifiers_key = if legacy { "Modifiers" } else { "modifiers" };
+        if let Some(list) = nbt.get(modifiers_key) {
+            let list_path = format!("{tag_path}/{modifiers_key}");
+            let list = unwrap_tag!(list,List,vec![],list_path);
+            modifiers.reserve(list.len());
+            for (idx, modifier) in list.iter().enumerate() {
+                let path = format!("{list_path}[{idx}]");
+                let modifier = unwrap_tag!(modifier,Compound,HashMap::new(),path);
+                modifiers.push(if legacy {
+                    AttributeModifier::from_legacy_nbt(modifier, &path)?
+                } else {
+                    AttributeModifier::from_nbt(modifier, &path)?
+                });
+            }
+        }
+        return Ok(EntityAttribute { base, modifiers });
+    }
+}
+
+impl MobFields {
+    /// Parse common fields of mobs
+    pub fn from_nbt(nbt: &HashMap<String, Value>, tag_path: &str) -> Result<MobFields, Error> {
+        let mut result = MobFields {
+            absorption_amount: unwrap_opt_or(nbt, "AbsorptionAmount", tag_path, 0.0, unwrap_opt_f32)?,
+            ..MobFields::default()
+        };
+        // Named as ActiveEffects before 1.20.2
+        for key in ["active_effects", "ActiveEffects"] {
+            if let Some(effects) = nbt.get(key) {
+                let effects_path = format!("{tag_path}/{key}");
+                let effects = unwrap_tag!(effects,List,vec![],effects_path);
+                for (idx, effect) in effects.iter().enumerate() {
+                    let path = format!("{effects_path}[{idx}]");
+                    let effect = unwrap_tag!(effect,Compound,HashMap::new(),path);
+                    result.active_effects.push(PotionEffect::from_nbt(effect, &path)?);
+                }
+            }
+        }
+        // Named as Attributes before 1.20.5, and the name of attribute is renamed from Name to id
+        for (key, name_key) in [("attributes", "id"), ("Attributes", "Name")] {
+            if let Some(attributes) = nbt.get(key) {
+                let attributes_path = format!("{tag_path}/{key}");
+                let attributes = unwrap_tag!(attributes,List,vec![],attributes_path);
+                for (idx, attr) in attributes.iter().enumerate() {
+                    let path = format!("{attributes_path}[{idx}]");
+                    let attr = unwrap_tag!(attr,Compound,HashMap::new(),path);
+                    let name = unwrap_opt_string(attr, name_key, &path)?.clone();
+                    result.attributes.insert(name, EntityAttribute::from_nbt(attr, &path)?);
+                }
+            }
+        }
+        // Memories in brain are not parsed
+        result.death_time = unwrap_opt_or(nbt, "DeathTime", tag_path, 0, unwrap_opt_i16)?;
+        result.fall_flying = unwrap_opt_bool_or(nbt, "FallFlying", tag_path, false)?;
+        result.hurt_by_time_stamp = unwrap_opt_or(nbt, "HurtByTimestamp", tag_path, 0, unwrap_opt_i32)?;
+        result.hurt_time = unwrap_opt_or(nbt, "HurtTime", tag_path, 0, unwrap_opt_i16)?;
+        if nbt.contains_key("SleepingX") {
+            result.sleeping_pos = [unwrap_opt_i32(nbt, "SleepingX", tag_path)?,
+                unwrap_opt_i32(nbt, "SleepingY", tag_path)?,
+                unwrap_opt_i32(nbt, "SleepingZ", tag_path)?];
+        }
+        return Ok(result);
+    }
+}
+
+fn parse_game_type(nbt: &HashMap<String, Value>, key: &str, tag_path: &str) -> Result<PlayerGameType, Error> {
+    let val = unwrap_opt_or(nbt, key, tag_path, 0, unwrap_opt_i32)?;
+    return match PlayerGameType::from_repr(val) {
+        Some(gt) => Ok(gt),
+        // -1 means no previous game type
+        None if val == -1 => Ok(PlayerGameType::Survival),
+        None => Err(Error::InvalidValue {
+            tag_path: format!("{tag_path}/{key}"),
+            error: format!("Invalid game type {val}"),
+        }),
+    };
+}
+
+impl PlayerFields {
+    /// Parse fields of players
+    pub fn from_nbt(nbt: &HashMap<String, Value>, tag_path: &str) -> Result<PlayerFields, Error> {
+        let mut result = PlayerFields::default();
+        if let Some(abilities) = nbt.get("abilities") {
+            let path = format!("{tag_path}/abilities");
+            let abilities = unwrap_tag!(abilities,Compound,HashMap::new(),path);
+            let default = PlayerAbilities::default();
+            result.abilities = PlayerAbilities {
+                flying: unwrap_opt_bool_or(abilities, "flying", &path, default.flying)?,
+                instant_build: unwrap_opt_bool_or(abilities, "instabuild", &path, default.instant_build)?,
+                invulnerable: unwrap_opt_bool_or(abilities, "invulnerable", &path, default.invulnerable)?,
+                may_build: unwrap_opt_bool_or(abilities, "mayBuild", &path, default.may_build)?,
+                may_fly: unwrap_opt_bool_or(abilities, "mayfly", &path, default.may_fly)?,
+                walk_speed: unwrap_opt_or(abilities, "walkSpeed", &path, default.walk_speed, unwrap_opt_f32)?,
+                fly_speed: unwrap_opt_or(abilities, "flySpeed", &path, default.fly_speed, unwrap_opt_f32)?,
+            };
+        }
+        result.dimension = DimensionId::from_nbt(nbt, "Dimension", tag_path)?;
+        if let Some(items) = nbt.get("EnderItems") {
+            let path = format!("{tag_path}/EnderItems");
+            result.ender_items = Inventory::from_nbt(unwrap_tag!(items,List,vec![],path), &path)?;
+        }
+        if let Some(items) = nbt.get("Inventory") {
+            let path = format!("{tag_path}/Inventory");
+            result.inventory = Inventory::from_nbt(unwrap_tag!(items,List,vec![],path), &path)?;
+        }
+        if let Some(pos) = nbt.get("enteredNetherPosition") {
+            let path = format!("{tag_path}/enteredNetherPosition");
+            let pos = unwrap_tag!(pos,Compound,HashMap::new(),path);
+            result.entered_nether_position = Some([unwrap_opt_f64(pos, "x", &path)?,
+                unwrap_opt_f64(pos, "y", &path)?,
+                unwrap_opt_f64(pos, "z", &path)?]);
+        }
+        result.food_exhaust_level = unwrap_opt_or(nbt, "foodExhaustionLevel", tag_path, 0.0, unwrap_opt_f32)?;
+        result.food_saturation_level = unwrap_opt_or(nbt, "foodSaturationLevel", tag_path, 5.0, unwrap_opt_f32)?;
+        result.food_level = unwrap_opt_or(nbt, "foodLevel", tag_path, 20, unwrap_opt_i32)?;
+        result.food_tick_timer = unwrap_opt_or(nbt, "foodTickTimer", tag_path, 0, unwrap_opt_i32)?;
+        if let Some(location) = nbt.get("LastDeathLocation") {
+            let path = format!("{tag_path}/LastDeathLocation");
+            let location = unwrap_tag!(location,Compound,HashMap::new(),path);
+            let dim = DimensionId::from_nbt(location, "dimension", &path)?;
+            let pos_path = format!("{path}/pos");
+            let pos = unwrap_opt_tag!(location.get("pos"),IntArray,fastnbt::IntArray::new(vec![]),pos_path);
+            if pos.len() != 3 {
+                return Err(Error::InvalidValue {
+                    tag_path: pos_path,
+                    error: format!("Position should contains 3 ints, but found {}", pos.len()),
+                });
+            }
+            result.last_death_location = (dim, [pos[0], pos[1], pos[2]]);
+        }
+        result.game_type = parse_game_type(nbt, "playerGameType", tag_path)?;
+        result.previous_game_type = parse_game_type(nbt, "previousPlayerGameType", tag_path)?;
+        if let Some(vehicle) = nbt.get("RootVehicle") {
+            let path = format!("{tag_path}/RootVehicle");
+            let vehicle = unwrap_tag!(vehicle,Compound,HashMap::new(),path);
+            result.root_vehicle = Some(parse_uuid(vehicle, "Attach", &path)?);
+        }
+        result.score = unwrap_opt_or(nbt, "Score", tag_path, 0, unwrap_opt_i32)?;
+        result.seen_credits = unwrap_opt_bool_or(nbt, "seenCredits", tag_path, false)?;
+        result.selected_item_slot = unwrap_opt_or(nbt, "SelectedItemSlot", tag_path, 0, unwrap_opt_i32)?;
+        result.sleep_timer = unwrap_opt_or(nbt, "SleepTimer", tag_path, 0, unwrap_opt_i16)?;
+        // Entities on shoulders are not parsed, since only players are implemented
+        result.spawn_angle = unwrap_opt_or(nbt, "SpawnAngle", tag_path, 0.0, unwrap_opt_f32)?;
+        result.spawn_dimension = DimensionId::from_nbt(nbt, "SpawnDimension", tag_path)?;
+        result.spawn_forced = unwrap_opt_bool_or(nbt, "SpawnForced", tag_path, false)?;
+        if nbt.contains_key("SpawnX") {
+            result.spawn_pos = Some([unwrap_opt_i32(nbt, "SpawnX", tag_path)?,
+                unwrap_opt_i32(nbt, "SpawnY", tag_path)?,
+                unwrap_opt_i32(nbt, "SpawnZ", tag_path)?]);
+        }
+        if let Some(tracker) = nbt.get("warden_spawn_tracker") {
+            let path = format!("{tag_path}/warden_spawn_tracker");
+            let tracker = unwrap_tag!(tracker,Compound,HashMap::new(),path);
+            result.warden_spawn_tracker = WardenSpawnTracker {
+                cooldown_ticks: unwrap_opt_or(tracker, "cooldown_ticks", &path, 0, unwrap_opt_i32)?,
+                ticks_since_last_warning: unwrap_opt_or(tracker, "ticks_since_last_warning", &path, 0, unwrap_opt_i32)?,
+                warning_level: unwrap_opt_or(tracker, "warning_level", &path, 0, unwrap_opt_i32)?,
+            };
+        }
+        result.xp_info = XpInfo {
+            level: unwrap_opt_or(nbt, "XpLevel", tag_path, 0, unwrap_opt_i32)?,
+            xp_p: unwrap_opt_or(nbt, "XpP", tag_path, 0.0, unwrap_opt_f32)?,
+            speed: unwrap_opt_or(nbt, "XpSeed", tag_path, 0, unwrap_opt_i32)?,
+            total: unwrap_opt_or(nbt, "XpTotal", tag_path, 0, unwrap_opt_i32)?,
+        };
+        return Ok(result);
+    }
+}
+
+impl Player {
+    /// Parse a player from nbt, like `Player` in `level.dat` or files in `playerdata`
+    pub fn from_nbt(nbt: &HashMap<String, Value>, tag_path: &str) -> Result<Player, Error> {
+        return Ok(Player {
+            entity_fields: EntityFields::from_nbt(nbt, tag_path)?,
+            mob_fields: MobFields::from_nbt(nbt, tag_path)?,
+            player_fields: PlayerFields::from_nbt(nbt, tag_path)?,
+        });
+    }
+}
@@ -17,7 +17,7 @@ along with this program.  If not, see <https://www.gnu.org/licenses/>.
 */
 
 use std::collections::HashMap;
-use ndarray::{Array3};
+use ndarray::{Array3, s};
 use crate::block::Block;
 use crate::error::Error;
 
@@ -531,6 +531,85 @@ impl Region {
         return Ok(());
     }
 
+    /// Merge identical blocks in palette, which may appear after blocks in palette are modified
+    /// (like renaming). The first occurrence of each block is kept.
+    pub fn merge_duplicated_blocks(&mut self) {
+        let mut first_index: HashMap<&Block, u16> = HashMap::new();
+        let mut id_map: Vec<u16> = Vec::with_capacity(self.palette.len());
+        let mut unique: Vec<usize> = Vec::new();
+        for (idx, blk) in self.palette.iter().enumerate() {
+            let new_idx = *first_index.entry(blk).or_insert_with(|| {
+                unique.push(idx);
+                return (unique.len() - 1) as u16;
+            });
+            id_map.push(new_idx);
+        }
+        if unique.len() == self.palette.len() {
+            return;
+        }
+        self.palette = unique.iter().map(|idx| self.palette[*idx].clone()).collect();
+        for blkid in &mut self.array_yzx {
+            *blkid = id_map[*blkid as usize];
+        }
+    }
+
+    /// Returns the min and max relative position(inclusive) of blocks for which `keep` returns
+    /// true, or `None` if there's no such block.
+    pub fn bounding_box(&self, keep: impl Fn(&Block) -> bool) -> Option<([i32; 3], [i32; 3])> {
+        let keep_lut: Vec<bool> = self.palette.iter().map(&keep).collect();
+        let mut min = [i32::MAX; 3];
+        let mut max = [i32::MIN; 3];
+        for ((y, z, x), idx) in self.array_yzx.indexed_iter() {
+            if !keep_lut.get(*idx as usize).copied().unwrap_or(true) {
+                continue;
+            }
+            let pos = [x as i32, y as i32, z as i32];
+            for dim in 0..3 {
+                min[dim] = min[dim].min(pos[dim]);
+                max[dim] = max[dim].max(pos[dim]);
+            }
+        }
+        if min[0] > max[0] {
+            return None;
+        }
+        return Some((min, max));
+    }
+
+    /// Shrink the region to the bounding box of blocks for which `keep` returns true, like non-air
+    /// blocks. Block entities, pending ticks and entities (by their block position) out of the box
+    /// are removed, the rest are shifted and `offset` is adjusted so that everything stays at the
+    /// same global position. If no block is kept, the region is not changed and `Ok(false)` is
+    /// returned.
+    pub fn trim(&mut self, keep: impl Fn(&Block) -> bool) -> Result<bool, Error> {
+        let (min, max) = match self.bounding_box(keep) {
+            Some(bb) => bb,
+            None => return Ok(false),
+        };
+        let [x0, y0, z0] = min.map(|v| v as usize);
+        let [x1, y1, z1] = max.map(|v| v as usize + 1);
+        self.array_yzx = self.array_yzx.slice(s![y0..y1, z0..z1, x0..x1]).to_owned();
+
+        let inside = |pos: &[i32; 3]| (0..3).all(|dim| pos[dim] >= min[dim] && pos[dim] <= max[dim]);
+        let shift = |pos: &[i32; 3]| [pos[0] - min[0], pos[1] - min[1], pos[2] - min[2]];
+        self.block_entities = self.block_entities.drain()
+            .filter(|(pos, _)| inside(pos))
+            .map(|(pos, be)| (shift(&pos), be))
+            .collect();
+        self.pending_ticks = self.pending_ticks.drain()
+            .filter(|(pos, _)| inside(pos))
+            .map(|(pos, ticks)| (shift(&pos), ticks))
+            .collect();
+        self.entities.retain(|entity| inside(&entity.block_pos));
+        for entity in &mut self.entities {
+            entity.pos_shift([-min[0], -min[1], -min[2]]);
+        }
+        for (offset, min) in self.offset.iter_mut().zip(min) {
+            *offset += min;
+        }
+        self.shrink_palette()?;
+        return Ok(true);
+    }
+
     /// Find the block index of a block in palette
     pub fn find_in_palette(&self, block: &Block) -> Option<u16> {
         for (idx, blk) in self.palette.iter().enumerate() {
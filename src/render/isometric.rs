@@ -0,0 +1,207 @@
+/*
+mc_schem is a rust library to generate, load, manipulate and save minecraft schematic files.
+Copyright (C) 2024  joseph
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use std::collections::HashMap;
+use crate::block::Block;
+use crate::image::RgbaImage;
+use crate::region::WorldSlice;
+use crate::render::{IsometricOption, ViewDirection};
+use crate::schem::Schematic;
+
+/// Brightness of top, right(+u) and left(+w) faces, out of 255
+const FACE_BRIGHTNESS: [u32; 3] = [255, 166, 204];
+
+/// Blocks in rotated coordinates `[u, y, w]`, where the viewer stands at +u, +y and +w.
+struct RotatedVolume {
+    shape: [i32; 3],
+    colors: Vec<Option<[u8; 4]>>,
+}
+
+impl RotatedVolume {
+    fn new<'a>(shape_xyz: [i32; 3], view: ViewDirection, option: &IsometricOption,
+               block_at: impl Fn([i32; 3]) -> Option<&'a Block>) -> RotatedVolume {
+        let [sx, sy, sz] = shape_xyz.map(|s| s.max(0));
+        let shape = match view {
+            ViewDirection::SouthEast | ViewDirection::NorthWest => [sx, sy, sz],
+            ViewDirection::SouthWest | ViewDirection::NorthEast => [sz, sy, sx],
+        };
+        // blocks are cached by address, since they are references to palettes
+        let mut cache: HashMap<*const Block, Option<[u8; 4]>> = HashMap::new();
+        let mut colors = Vec::with_capacity((shape[0] * shape[1] * shape[2]) as usize);
+        for u in 0..shape[0] {
+            for y in 0..shape[1] {
+                for w in 0..shape[2] {
+                    let (x, z) = match view {
+                        ViewDirection::SouthEast => (u, w),
+                        ViewDirection::SouthWest => (sx - 1 - w, u),
+                        ViewDirection::NorthWest => (sx - 1 - u, sz - 1 - w),
+                        ViewDirection::NorthEast => (w, sz - 1 - u),
+                    };
+                    let color = match block_at([x, y, z]) {
+                        Some(block) => *cache.entry(block as *const Block)
+                            .or_insert_with(|| option.colors.color_of(block)),
+                        None => None,
+                    };
+                    colors.push(color);
+                }
+            }
+        }
+        return RotatedVolume { shape, colors };
+    }
+
+    fn color_at(&self, u: i32, y: i32, w: i32) -> Option<[u8; 4]> {
+        if u < 0 || y < 0 || w < 0 || u >= self.shape[0] || y >= self.shape[1] || w >= self.shape[2] {
+            return None;
+        }
+        return self.colors[((u * self.shape[1] + y) * self.shape[2] + w) as usize];
+    }
+
+    fn is_opaque(&self, u: i32, y: i32, w: i32) -> bool {
+        return self.color_at(u, y, w).map(|c| c[3] == 255).unwrap_or(false);
+    }
+}
+
+/// Fill the parallelogram spanned by `e1` and `e2` from `origin`, using pixel centers
+fn fill_parallelogram(image: &mut RgbaImage, origin: [i64; 2], e1: [i64; 2], e2: [i64; 2], color: [u8; 4]) {
+    let corners = [origin, [origin[0] + e1[0], origin[1] + e1[1]], [origin[0] + e2[0], origin[1] + e2[1]],
+        [origin[0] + e1[0] + e2[0], origin[1] + e1[1] + e2[1]]];
+    let min_x = corners.iter().map(|c| c[0]).min().unwrap().max(0);
+    let max_x = corners.iter().map(|c| c[0]).max().unwrap().min(image.width() as i64);
+    let min_y = corners.iter().map(|c| c[1]).min().unwrap().max(0);
+    let max_y = corners.iter().map(|c| c[1]).max().unwrap().min(image.height() as i64);
+    // work in doubled coordinates, so that pixel centers are integers
+    let det = e1[0] * e2[1] - e1[1] * e2[0];
+    for py in min_y..max_y {
+        for px in min_x..max_x {
+            let dx = 2 * (px - origin[0]) + 1;
+            let dy = 2 * (py - origin[1]) + 1;
+            let a = (dx * e2[1] - dy * e2[0]) * det.signum();
+            let b = (e1[0] * dy - e1[1] * dx) * det.signum();
+            let limit = 2 * det.abs();
+            if a >= 0 && a < limit && b >= 0 && b < limit {
+                image.blend_pixel(px as u32, py as u32, color);
+            }
+        }
+    }
+}
+
+fn shade(color: [u8; 4], brightness: u32) -> [u8; 4] {
+    return [(color[0] as u32 * brightness / 255) as u8,
+        (color[1] as u32 * brightness / 255) as u8,
+        (color[2] as u32 * brightness / 255) as u8,
+        color[3]];
+}
+
+fn render_volume(volume: &RotatedVolume, option: &IsometricOption) -> RgbaImage {
+    let s = option.scale.max(1) as i64;
+    let [su, sy, sw] = volume.shape.map(|v| v as i64);
+    let mut image = RgbaImage::new(((su + sw) * 2 * s) as u32, ((su + sw) * s + sy * 2 * s) as u32);
+    for py in 0..image.height() {
+        for px in 0..image.width() {
+            image.set_pixel(px, py, option.background);
+        }
+    }
+    let offset = [sw * 2 * s, sy * 2 * s];
+    let project = |u: i64, y: i64, w: i64| [(u - w) * 2 * s + offset[0], (u + w) * s - y * 2 * s + offset[1]];
+    let axis_u = [2 * s, s];
+    let axis_w = [-2 * s, s];
+    let axis_y = [0, -2 * s];
+
+    // painter's algorithm, blocks closer to viewer are drawn later
+    for depth in 0..(su + sy + sw) {
+        for u in 0..su.min(depth + 1) {
+            for y in 0..sy.min(depth - u + 1) {
+                let w = depth - u - y;
+                if w >= sw {
+                    continue;
+                }
+                let (ui, yi, wi) = (u as i32, y as i32, w as i32);
+                let color = match volume.color_at(ui, yi, wi) {
+                    Some(c) => c,
+                    None => continue,
+                };
+                if !volume.is_opaque(ui, yi + 1, wi) {
+                    fill_parallelogram(&mut image, project(u, y + 1, w), axis_u, axis_w, shade(color, FACE_BRIGHTNESS[0]));
+                }
+                if !volume.is_opaque(ui + 1, yi, wi) {
+                    fill_parallelogram(&mut image, project(u + 1, y, w), axis_y, axis_w, shade(color, FACE_BRIGHTNESS[1]));
+                }
+                if !volume.is_opaque(ui, yi, wi + 1) {
+                    fill_parallelogram(&mut image, project(u, y, w + 1), axis_u, axis_y, shade(color, FACE_BRIGHTNESS[2]));
+                }
+            }
+        }
+    }
+    return image;
+}
+
+/// Render a slice as flat-shaded isometric cubes. Blocks are colored by `option.colors`, top faces
+/// are the brightest and faces hidden by opaque neighbors are skipped.
+pub fn render_isometric<S: WorldSlice + ?Sized>(slice: &S, option: &IsometricOption) -> RgbaImage {
+    let volume = RotatedVolume::new(slice.shape(), option.view, option, |pos| slice.block_at(pos));
+    return render_volume(&volume, option);
+}
+
+impl Schematic {
+    /// Render all regions as flat-shaded isometric cubes, see `render::render_isometric`. If
+    /// regions overlap, the first region wins.
+    pub fn render_isometric(&self, option: &IsometricOption) -> RgbaImage {
+        let volume = RotatedVolume::new(self.shape(), option.view, option, |pos| self.first_block_at(pos));
+        return render_volume(&volume, option);
+    }
+}
+
+#[test]
+fn test_render_isometric() {
+    use crate::region::Region;
+    use crate::render::BlockColorTable;
+
+    let mut region = Region::with_shape([2, 1, 1]);
+    region.set_block([0, 0, 0], &Block::from_id("minecraft:stone").unwrap()).unwrap();
+    let mut colors = BlockColorTable::default();
+    colors.custom.insert("minecraft:stone".to_string(), [200, 100, 50, 255]);
+    let mut option = IsometricOption { scale: 2, background: [1, 2, 3, 255], colors, ..IsometricOption::default() };
+
+    let image = render_isometric(&region, &option);
+    assert_eq!((image.width(), image.height()), (12, 10));
+    // the stone is at the left, with its top face centered at (4, 2)
+    assert_eq!(image.pixel(4, 2), [200, 100, 50, 255]);
+    assert_eq!(image.pixel(5, 6), shade([200, 100, 50, 255], FACE_BRIGHTNESS[1]));
+    assert_eq!(image.pixel(2, 6), shade([200, 100, 50, 255], FACE_BRIGHTNESS[2]));
+    assert_eq!(image.pixel(8, 4), [1, 2, 3, 255]);
+    assert_eq!(image.pixel(0, 0), [1, 2, 3, 255]);
+
+    // viewed from north west, the stone is at the right
+    option.view = ViewDirection::NorthWest;
+    let image = render_isometric(&region, &option);
+    assert_eq!(image.pixel(8, 4), [200, 100, 50, 255]);
+    assert_eq!(image.pixel(4, 2), [1, 2, 3, 255]);
+
+    // rotating by 90 degrees swaps width and length
+    let mut schem = Schematic::new();
+    schem.regions.push(Region::with_shape([3, 2, 1]));
+    schem.regions[0].fill_with(&Block::from_id("minecraft:white_wool").unwrap());
+    option.view = ViewDirection::SouthWest;
+    let image = schem.render_isometric(&option);
+    assert_eq!((image.width(), image.height()), (16, 16));
+
+    // scale 0 is treated as 1
+    option.scale = 0;
+    let image = schem.render_isometric(&option);
+    assert_eq!((image.width(), image.height()), (8, 8));
+}
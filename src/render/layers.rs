@@ -0,0 +1,301 @@
+/*
+mc_schem is a rust library to generate, load, manipulate and save minecraft schematic files.
+Copyright (C) 2024  joseph
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use std::collections::HashMap;
+use std::path::Path;
+use crate::block::Block;
+use crate::error::Error;
+use crate::image::RgbaImage;
+use crate::render::{write_file, Layer, LayerGuide, LayerGuideOption};
+use crate::schem::Schematic;
+
+const SYMBOLS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+const GRID_COLOR: [u8; 4] = [128, 128, 128, 255];
+
+fn namespaced_id(block: &Block) -> String {
+    let namespace = if block.namespace.is_empty() { "minecraft" } else { &block.namespace };
+    return format!("{namespace}:{}", block.id);
+}
+
+fn sort_counts(counts: &HashMap<usize, u64>) -> Vec<(usize, u64)> {
+    let mut result: Vec<(usize, u64)> = counts.iter().map(|(idx, count)| (*idx, *count)).collect();
+    result.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    return result;
+}
+
+fn escape_html(text: &str) -> String {
+    return text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;");
+}
+
+fn base64_encode(bytes: &[u8]) -> String {
+    const TABLE: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut result = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = (chunk[0] as u32) << 16 | (*chunk.get(1).unwrap_or(&0) as u32) << 8 | *chunk.get(2).unwrap_or(&0) as u32;
+        for idx in 0..4 {
+            if idx <= chunk.len() {
+                result.push(TABLE[(n >> (18 - 6 * idx) & 0x3F) as usize] as char);
+            } else {
+                result.push('=');
+            }
+        }
+    }
+    return result;
+}
+
+impl Schematic {
+    /// Split schematic into y layers for building guides. Blocks are looked up by
+    /// `first_block_at`, so the first region wins if regions overlap.
+    pub fn layer_guide(&self, option: &LayerGuideOption) -> LayerGuide {
+        let shape = self.shape();
+        let (palette, _) = self.full_palette();
+        let palette_index: HashMap<&Block, usize> = palette.iter().enumerate()
+            .map(|(idx, (block, _))| (*block, idx))
+            .collect();
+        // legend index of every full palette entry, assigned when first seen
+        let mut palette_to_legend: Vec<Option<usize>> = vec![None; palette.len()];
+        let mut legend_index: HashMap<String, usize> = HashMap::new();
+        let mut legend = Vec::new();
+        let mut legend_colors = Vec::new();
+
+        let mut layers = Vec::with_capacity(shape[1].max(0) as usize);
+        for y in 0..shape[1] {
+            let mut cells = Vec::with_capacity((shape[0] * shape[2]).max(0) as usize);
+            let mut counts: HashMap<usize, u64> = HashMap::new();
+            for z in 0..shape[2] {
+                for x in 0..shape[0] {
+                    let block = match self.first_block_at([x, y, z]) {
+                        Some(b) if !b.is_air() && !b.is_structure_void() => b,
+                        _ => {
+                            cells.push(None);
+                            continue;
+                        }
+                    };
+                    let palette_idx = palette_index[block];
+                    let legend_idx = match palette_to_legend[palette_idx] {
+                        Some(idx) => idx,
+                        None => {
+                            let id = namespaced_id(block);
+                            let idx = match legend_index.get(&id) {
+                                Some(idx) => *idx,
+                                None => {
+                                    legend_index.insert(id.clone(), legend.len());
+                                    legend.push(id);
+                                    legend_colors.push(option.colors.color_of(block).unwrap_or(option.colors.fallback));
+                                    legend.len() - 1
+                                }
+                            };
+                            palette_to_legend[palette_idx] = Some(idx);
+                            idx
+                        }
+                    };
+                    cells.push(Some(legend_idx));
+                    *counts.entry(legend_idx).or_insert(0) += 1;
+                }
+            }
+            if option.skip_empty_layers && counts.is_empty() {
+                continue;
+            }
+            layers.push(Layer { y, cells, counts: sort_counts(&counts) });
+        }
+        return LayerGuide { shape, legend, legend_colors, layers };
+    }
+}
+
+impl LayerGuide {
+    /// Character representing a legend entry in text grids. Air and structure void are `.`
+    pub fn symbol(legend_idx: usize) -> char {
+        if legend_idx < SYMBOLS.len() {
+            return SYMBOLS[legend_idx] as char;
+        }
+        return char::from_u32(0xC0 + (legend_idx - SYMBOLS.len()) as u32).unwrap_or('?');
+    }
+
+    /// Count of every legend entry in all layers, sorted by count descending
+    pub fn total_counts(&self) -> Vec<(usize, u64)> {
+        let mut counts: HashMap<usize, u64> = HashMap::new();
+        for layer in &self.layers {
+            for (idx, count) in &layer.counts {
+                *counts.entry(*idx).or_insert(0) += count;
+            }
+        }
+        return sort_counts(&counts);
+    }
+
+    /// Render a layer from top, north is up. Empty cells are transparent, and cells are separated
+    /// by grid lines if blocks are at least 4 pixels wide.
+    pub fn layer_image(&self, layer: &Layer, option: &LayerGuideOption) -> RgbaImage {
+        let ppb = option.pixels_per_block.max(1);
+        let grid = ppb >= 4;
+        let mut image = RgbaImage::new(self.shape[0].max(0) as u32 * ppb, self.shape[2].max(0) as u32 * ppb);
+        for z in 0..self.shape[2].max(0) as u32 {
+            for x in 0..self.shape[0].max(0) as u32 {
+                let cell = layer.cells[(z * self.shape[0] as u32 + x) as usize];
+                for dy in 0..ppb {
+                    for dx in 0..ppb {
+                        let on_grid = grid && (dx == 0 || dy == 0 || dx == ppb - 1 || dy == ppb - 1);
+                        let color = if on_grid {
+                            GRID_COLOR
+                        } else {
+                            match cell {
+                                Some(idx) => self.legend_colors[idx],
+                                None => continue,
+                            }
+                        };
+                        image.set_pixel(x * ppb + dx, z * ppb + dy, color);
+                    }
+                }
+            }
+        }
+        return image;
+    }
+
+    /// A text grid of a layer with legend and counts. Rows are z from north to south.
+    pub fn layer_text(&self, layer: &Layer) -> String {
+        let mut result = format!("Layer y = {}\n", layer.y);
+        for row in layer.cells.chunks(self.shape[0].max(1) as usize) {
+            for cell in row {
+                result.push(match cell {
+                    Some(idx) => Self::symbol(*idx),
+                    None => '.',
+                });
+            }
+            result.push('\n');
+        }
+        result.push('\n');
+        for (idx, count) in &layer.counts {
+            result.push_str(&format!("{} {} x {count}\n", Self::symbol(*idx), self.legend[*idx]));
+        }
+        return result;
+    }
+
+    /// Write `layer_<y>.png` and `layer_<y>.txt` for every layer and `materials.txt` for all
+    /// layers into `dir`, which is created if not exist.
+    pub fn save_to_directory(&self, dir: impl AsRef<Path>, option: &LayerGuideOption) -> Result<(), Error> {
+        let dir = dir.as_ref();
+        if let Err(e) = std::fs::create_dir_all(dir) {
+            return Err(Error::FileCreateError(e));
+        }
+        for layer in &self.layers {
+            write_file(&dir.join(format!("layer_{}.png", layer.y)), &self.layer_image(layer, option).to_png_bytes())?;
+            write_file(&dir.join(format!("layer_{}.txt", layer.y)), self.layer_text(layer).as_bytes())?;
+        }
+        let mut materials = String::new();
+        for (idx, count) in self.total_counts() {
+            materials.push_str(&format!("{} {} x {count}\n", Self::symbol(idx), self.legend[idx]));
+        }
+        return write_file(&dir.join("materials.txt"), materials.as_bytes());
+    }
+
+    fn html_count_table(&self, counts: &[(usize, u64)]) -> String {
+        let mut result = String::from("<table>\n<tr><th>Color</th><th>Symbol</th><th>Block</th><th>Count</th></tr>\n");
+        for (idx, count) in counts {
+            let [r, g, b, a] = self.legend_colors[*idx];
+            result.push_str(&format!(
+                "<tr><td><span class=\"swatch\" style=\"background: rgba({r}, {g}, {b}, {:.3})\"></span></td><td>{}</td><td>{}</td><td>{count}</td></tr>\n",
+                a as f64 / 255.0, escape_html(&Self::symbol(*idx).to_string()), escape_html(&self.legend[*idx])));
+        }
+        result.push_str("</table>\n");
+        return result;
+    }
+
+    /// Export all layers as a single html file, images are embedded as data urls.
+    pub fn to_html(&self, title: &str, option: &LayerGuideOption) -> String {
+        let mut html = format!(r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<style>
+body {{ font-family: sans-serif; }}
+img {{ image-rendering: pixelated; border: 1px solid #888; }}
+table {{ border-collapse: collapse; margin: 8px 0; }}
+td, th {{ border: 1px solid #ccc; padding: 2px 8px; }}
+.swatch {{ display: inline-block; width: 16px; height: 16px; border: 1px solid #444; }}
+</style>
+</head>
+<body>
+<h1>{title}</h1>
+<p>Size: {} x {} x {}, {} layers</p>
+<h2>Materials</h2>
+"#, self.shape[0], self.shape[1], self.shape[2], self.layers.len(), title = escape_html(title));
+        html.push_str(&self.html_count_table(&self.total_counts()));
+        for layer in &self.layers {
+            let png = self.layer_image(layer, option).to_png_bytes();
+            html.push_str(&format!("<h2 id=\"layer_{y}\">Layer y = {y}</h2>\n<img alt=\"Layer {y}\" src=\"data:image/png;base64,{}\">\n",
+                                   base64_encode(&png), y = layer.y));
+            html.push_str(&self.html_count_table(&layer.counts));
+        }
+        html.push_str("</body>\n</html>\n");
+        return html;
+    }
+
+    /// Export all layers as a single html file, see `to_html`.
+    pub fn save_html(&self, filename: impl AsRef<Path>, title: &str, option: &LayerGuideOption) -> Result<(), Error> {
+        return write_file(filename.as_ref(), self.to_html(title, option).as_bytes());
+    }
+}
+
+#[test]
+fn test_layer_guide() {
+    use crate::region::Region;
+
+    let stone = Block::from_id("minecraft:stone").unwrap();
+    let mut schem = Schematic::new();
+    schem.regions.push(Region::with_shape([3, 3, 2]));
+    let region = &mut schem.regions[0];
+    for x in 0..3 {
+        region.set_block([x, 0, 0], &stone).unwrap();
+    }
+    region.set_block([1, 0, 1], &Block::from_id("minecraft:oak_stairs[facing=east]").unwrap()).unwrap();
+    region.set_block([2, 0, 1], &Block::from_id("minecraft:oak_stairs[facing=west]").unwrap()).unwrap();
+    region.set_block([0, 2, 1], &stone).unwrap();
+
+    let option = LayerGuideOption { pixels_per_block: 4, ..LayerGuideOption::default() };
+    let guide = schem.layer_guide(&option);
+    assert_eq!(guide.legend, vec!["minecraft:stone".to_string(), "minecraft:oak_stairs".to_string()]);
+    assert_eq!(guide.layers.iter().map(|l| l.y).collect::<Vec<_>>(), vec![0, 2]);
+    assert_eq!(guide.layers[0].counts, vec![(0, 3), (1, 2)]);
+    assert_eq!(guide.total_counts(), vec![(0, 4), (1, 2)]);
+    assert_eq!(guide.layer_text(&guide.layers[0]),
+               "Layer y = 0\nAAA\n.BB\n\nA minecraft:stone x 3\nB minecraft:oak_stairs x 2\n");
+
+    let image = guide.layer_image(&guide.layers[1], &option);
+    assert_eq!((image.width(), image.height()), (12, 8));
+    assert_eq!(image.pixel(0, 4), GRID_COLOR);
+    assert_eq!(image.pixel(1, 5), guide.legend_colors[0]);
+    assert_eq!(image.pixel(5, 5), [0, 0, 0, 0]);
+
+    let html = guide.to_html("Test <guide>", &option);
+    assert!(html.contains("<h1>Test &lt;guide&gt;</h1>"));
+    assert!(html.contains("<h2 id=\"layer_2\">"));
+    assert!(html.contains("data:image/png;base64,iVBORw0KGgo"));
+
+    let dir = std::env::temp_dir().join(format!("mc_schem_test_layers_{}", std::process::id()));
+    guide.save_to_directory(&dir, &option).unwrap();
+    assert_eq!(std::fs::read_to_string(dir.join("layer_2.txt")).unwrap(),
+               "Layer y = 2\n...\nA..\n\nA minecraft:stone x 1\n");
+    assert!(dir.join("layer_0.png").exists());
+    assert!(!dir.join("layer_1.png").exists());
+    assert!(dir.join("materials.txt").exists());
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(base64_encode(b"Man"), "TWFu");
+    assert_eq!(base64_encode(b"Ma"), "TWE=");
+    assert_eq!(base64_encode(b"M"), "TQ==");
+}
@@ -0,0 +1,455 @@
+/*
+mc_schem is a rust library to generate, load, manipulate and save minecraft schematic files.
+Copyright (C) 2024  joseph
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use std::collections::HashMap;
+use std::path::Path;
+use crate::block::Block;
+use crate::error::Error;
+use crate::region::Region;
+use crate::render::{write_file, BlockColorTable, Mesh, MeshMaterial, MeshQuad};
+use crate::schem::Schematic;
+
+/// Last words of block ids that are not full cubes, faces next to them are never culled
+const NON_FULL_SUFFIXES: [&str; 28] = ["slab", "stairs", "fence", "gate", "wall", "pane", "door", "trapdoor",
+    "carpet", "torch", "button", "plate", "sign", "rail", "lantern", "chain", "bars", "rod", "flower",
+    "sapling", "banner", "bed", "candle", "cake", "head", "skull", "snow", "pot"];
+
+/// Full cubes whose last word is in `NON_FULL_SUFFIXES`
+const FULL_BLOCKS: [&str; 2] = ["sea_lantern", "jack_o_lantern"];
+
+/// Normals in the order of vertex normals in obj files
+const NORMALS: [[i32; 3]; 6] = [[1, 0, 0], [-1, 0, 0], [0, 1, 0], [0, -1, 0], [0, 0, 1], [0, 0, -1]];
+
+/// Whether neighbor faces of this block are hidden. Plants on map are not full blocks unless
+/// they are leaves.
+fn is_full_opaque(block: &Block, color: [u8; 4]) -> bool {
+    if color[3] != 255 {
+        return false;
+    }
+    if block.id == "grass_block" || block.id == "snow_block" || block.id.ends_with("leaves") {
+        return true;
+    }
+    let last_word = block.id.rsplit('_').next().unwrap_or(&block.id);
+    if block.id.starts_with("potted_")
+        || (NON_FULL_SUFFIXES.contains(&last_word) && !FULL_BLOCKS.contains(&block.id.as_str())) {
+        return false;
+    }
+    return crate::map::map_base_color(block) != 7;
+}
+
+/// Keep only letters, digits and underscores, so that names are valid in obj and mtl files
+fn sanitize_name(name: &str) -> String {
+    return name.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect();
+}
+
+fn srgb_to_linear(c: u8) -> f64 {
+    let c = c as f64 / 255.0;
+    return if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) };
+}
+
+impl Region {
+    /// Build a mesh of all visible blocks, every block is treated as a full cube. Faces between
+    /// opaque full blocks and faces between the same blocks are culled, then coplanar faces of the
+    /// same material are merged greedily. Positions are relative to the region.
+    pub fn to_mesh(&self, colors: &BlockColorTable) -> Mesh {
+        let mut mesh = Mesh::default();
+        let mut material_of: Vec<Option<usize>> = Vec::with_capacity(self.palette.len());
+        let mut full_opaque: Vec<bool> = Vec::with_capacity(self.palette.len());
+        for block in &self.palette {
+            match colors.color_of(block) {
+                Some(color) => {
+                    material_of.push(Some(mesh.add_material(&block.full_id(), color)));
+                    full_opaque.push(is_full_opaque(block, color));
+                }
+                None => {
+                    material_of.push(None);
+                    full_opaque.push(false);
+                }
+            }
+        }
+
+        let shape_yzx = self.array_yzx.shape();
+        let shape = [shape_yzx[2] as i32, shape_yzx[0] as i32, shape_yzx[1] as i32];
+        let id_at = |pos: [i32; 3]| -> Option<u16> {
+            if (0..3).any(|dim| pos[dim] < 0 || pos[dim] >= shape[dim]) {
+                return None;
+            }
+            return Some(self.array_yzx[[pos[1] as usize, pos[2] as usize, pos[0] as usize]]);
+        };
+
+        for d in 0..3 {
+            // (d, u, v) is cyclic, so corners along u then v are counter-clockwise seen from +d
+            let u = (d + 1) % 3;
+            let v = (d + 2) % 3;
+            let (size_u, size_v) = (shape[u] as usize, shape[v] as usize);
+            let mut mask: Vec<Option<usize>> = vec![None; size_u * size_v];
+            for dir in [1, -1] {
+                for k in 0..shape[d] {
+                    for i in 0..size_u {
+                        for j in 0..size_v {
+                            let mut pos = [0; 3];
+                            pos[d] = k;
+                            pos[u] = i as i32;
+                            pos[v] = j as i32;
+                            let id = id_at(pos).unwrap();
+                            pos[d] += dir;
+                            let visible = match id_at(pos) {
+                                Some(neighbor) => neighbor != id && !full_opaque[neighbor as usize],
+                                None => true,
+                            };
+                            mask[i * size_v + j] = if visible { material_of[id as usize] } else { None };
+                        }
+                    }
+
+                    for i in 0..size_u {
+                        for j in 0..size_v {
+                            let material = match mask[i * size_v + j] {
+                                Some(m) => m,
+                                None => continue,
+                            };
+                            let mut h = 1;
+                            while j + h < size_v && mask[i * size_v + j + h] == Some(material) {
+                                h += 1;
+                            }
+                            let mut w = 1;
+                            while i + w < size_u && (j..j + h).all(|jj| mask[(i + w) * size_v + jj] == Some(material)) {
+                                w += 1;
+                            }
+                            for ii in i..i + w {
+                                for jj in j..j + h {
+                                    mask[ii * size_v + jj] = None;
+                                }
+                            }
+
+                            let mut base = [0; 3];
+                            base[d] = if dir > 0 { k + 1 } else { k };
+                            base[u] = i as i32;
+                            base[v] = j as i32;
+                            let mut c1 = base;
+                            c1[u] += w as i32;
+                            let mut c2 = c1;
+                            c2[v] += h as i32;
+                            let mut c3 = base;
+                            c3[v] += h as i32;
+                            let mut normal = [0; 3];
+                            normal[d] = dir;
+                            let corners = if dir > 0 { [base, c1, c2, c3] } else { [base, c3, c2, c1] };
+                            mesh.quads.push(MeshQuad { material, corners, normal });
+                        }
+                    }
+                }
+            }
+        }
+        mesh.remove_unused_materials();
+        return mesh;
+    }
+}
+
+impl Schematic {
+    /// Build a mesh of all regions, see `Region::to_mesh`. Faces between regions are not culled.
+    pub fn to_mesh(&self, colors: &BlockColorTable) -> Mesh {
+        let mut mesh = Mesh::default();
+        for region in &self.regions {
+            mesh.append(&region.to_mesh(colors), region.offset);
+        }
+        return mesh;
+    }
+}
+
+impl Mesh {
+    /// Add a material named after `name`, returns its index. A suffix is appended if the
+    /// sanitized name is used by another material.
+    pub fn add_material(&mut self, name: &str, color: [u8; 4]) -> usize {
+        let base = sanitize_name(name);
+        let mut name = base.clone();
+        let mut suffix = 1;
+        while self.materials.iter().any(|m| m.name == name) {
+            name = format!("{base}_{suffix}");
+            suffix += 1;
+        }
+        self.materials.push(MeshMaterial { name, color });
+        return self.materials.len() - 1;
+    }
+
+    /// Append quads of `other` moved by `offset`. Materials with the same name and color are shared.
+    pub fn append(&mut self, other: &Mesh, offset: [i32; 3]) {
+        let lut: Vec<usize> = other.materials.iter().map(|m| {
+            match self.materials.iter().position(|cur| cur == m) {
+                Some(idx) => idx,
+                None => self.add_material(&m.name, m.color),
+            }
+        }).collect();
+        for quad in &other.quads {
+            let corners = quad.corners.map(|c| [c[0] + offset[0], c[1] + offset[1], c[2] + offset[2]]);
+            self.quads.push(MeshQuad { material: lut[quad.material], corners, normal: quad.normal });
+        }
+    }
+
+    /// Remove materials that no quad uses
+    pub fn remove_unused_materials(&mut self) {
+        let mut used = vec![false; self.materials.len()];
+        for quad in &self.quads {
+            used[quad.material] = true;
+        }
+        let mut lut = vec![0; self.materials.len()];
+        let mut materials = Vec::new();
+        for (idx, material) in self.materials.drain(..).enumerate() {
+            if used[idx] {
+                lut[idx] = materials.len();
+                materials.push(material);
+            }
+        }
+        self.materials = materials;
+        for quad in &mut self.quads {
+            quad.material = lut[quad.material];
+        }
+    }
+
+    /// Quads of every material, indexed by material
+    fn quads_by_material(&self) -> Vec<Vec<&MeshQuad>> {
+        let mut result = vec![Vec::new(); self.materials.len()];
+        for quad in &self.quads {
+            result[quad.material].push(quad);
+        }
+        return result;
+    }
+
+    /// Export as wavefront obj and mtl text, `mtl_filename` is referred by `mtllib` in obj.
+    pub fn to_obj(&self, mtl_filename: &str) -> (String, String) {
+        let mut obj = format!("# Generated by mc_schem\nmtllib {mtl_filename}\n");
+        let mut vertex_index: HashMap<[i32; 3], usize> = HashMap::new();
+        let mut faces = String::new();
+        for (material, quads) in self.materials.iter().zip(self.quads_by_material()) {
+            if quads.is_empty() {
+                continue;
+            }
+            faces.push_str(&format!("usemtl {}\n", material.name));
+            for quad in quads {
+                let normal = NORMALS.iter().position(|n| *n == quad.normal).unwrap() + 1;
+                faces.push('f');
+                for corner in &quad.corners {
+                    let idx = match vertex_index.get(corner) {
+                        Some(idx) => *idx,
+                        None => {
+                            obj.push_str(&format!("v {} {} {}\n", corner[0], corner[1], corner[2]));
+                            vertex_index.insert(*corner, vertex_index.len() + 1);
+                            vertex_index.len()
+                        }
+                    };
+                    faces.push_str(&format!(" {idx}//{normal}"));
+                }
+                faces.push('\n');
+            }
+        }
+        for n in NORMALS {
+            obj.push_str(&format!("vn {} {} {}\n", n[0], n[1], n[2]));
+        }
+        obj.push_str(&faces);
+
+        let mut mtl = String::from("# Generated by mc_schem\n");
+        for material in &self.materials {
+            let [r, g, b, a] = material.color;
+            mtl.push_str(&format!("newmtl {}\nKa 0 0 0\nKd {:.4} {:.4} {:.4}\nd {:.4}\nillum 1\n\n", material.name,
+                                  r as f64 / 255.0, g as f64 / 255.0, b as f64 / 255.0, a as f64 / 255.0));
+        }
+        return (obj, mtl);
+    }
+
+    /// Save as obj, and the mtl file with the same name next to it
+    pub fn save_obj(&self, obj_filename: impl AsRef<Path>) -> Result<(), Error> {
+        let obj_path = obj_filename.as_ref();
+        let mtl_path = obj_path.with_extension("mtl");
+        let mtl_name = mtl_path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        let (obj, mtl) = self.to_obj(&mtl_name);
+        write_file(obj_path, obj.as_bytes())?;
+        return write_file(&mtl_path, mtl.as_bytes());
+    }
+
+    /// Export as binary gltf(glb). Each material is a primitive with its own vertices.
+    pub fn to_glb(&self) -> Vec<u8> {
+        let mut bin: Vec<u8> = Vec::new();
+        let mut buffer_views = Vec::new();
+        let mut accessors = Vec::new();
+        let mut primitives = Vec::new();
+        let mut materials = Vec::new();
+        let mut push_view = |bin: &mut Vec<u8>, data: &[u8], target: u32| -> usize {
+            buffer_views.push(format!("{{\"buffer\":0,\"byteOffset\":{},\"byteLength\":{},\"target\":{target}}}", bin.len(), data.len()));
+            bin.extend_from_slice(data);
+            return buffer_views.len() - 1;
+        };
+
+        for (material, quads) in self.materials.iter().zip(self.quads_by_material()) {
+            let [r, g, b, a] = material.color;
+            let alpha_mode = if a == 255 { "OPAQUE" } else { "BLEND" };
+            materials.push(format!("{{\"name\":\"{}\",\"pbrMetallicRoughness\":{{\"baseColorFactor\":[{:.5},{:.5},{:.5},{:.5}],\"metallicFactor\":0,\"roughnessFactor\":1}},\"alphaMode\":\"{alpha_mode}\"}}",
+                                   material.name, srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b), a as f64 / 255.0));
+            if quads.is_empty() {
+                continue;
+            }
+            let mut positions: Vec<u8> = Vec::with_capacity(quads.len() * 48);
+            let mut normals: Vec<u8> = Vec::with_capacity(quads.len() * 48);
+            let mut indices: Vec<u8> = Vec::with_capacity(quads.len() * 24);
+            let mut min = [i32::MAX; 3];
+            let mut max = [i32::MIN; 3];
+            for (quad_idx, quad) in quads.iter().enumerate() {
+                for corner in &quad.corners {
+                    for dim in 0..3 {
+                        positions.extend_from_slice(&(corner[dim] as f32).to_le_bytes());
+                        normals.extend_from_slice(&(quad.normal[dim] as f32).to_le_bytes());
+                        min[dim] = min[dim].min(corner[dim]);
+                        max[dim] = max[dim].max(corner[dim]);
+                    }
+                }
+                let first = quad_idx as u32 * 4;
+                for idx in [first, first + 1, first + 2, first, first + 2, first + 3] {
+                    indices.extend_from_slice(&idx.to_le_bytes());
+                }
+            }
+            let vertex_count = quads.len() * 4;
+            let position_view = push_view(&mut bin, &positions, 34962);
+            accessors.push(format!("{{\"bufferView\":{position_view},\"componentType\":5126,\"count\":{vertex_count},\"type\":\"VEC3\",\"min\":[{},{},{}],\"max\":[{},{},{}]}}",
+                                   min[0], min[1], min[2], max[0], max[1], max[2]));
+            let normal_view = push_view(&mut bin, &normals, 34962);
+            accessors.push(format!("{{\"bufferView\":{normal_view},\"componentType\":5126,\"count\":{vertex_count},\"type\":\"VEC3\"}}"));
+            let index_view = push_view(&mut bin, &indices, 34963);
+            accessors.push(format!("{{\"bufferView\":{index_view},\"componentType\":5125,\"count\":{},\"type\":\"SCALAR\"}}", quads.len() * 6));
+            let first_accessor = accessors.len() - 3;
+            primitives.push(format!("{{\"attributes\":{{\"POSITION\":{first_accessor},\"NORMAL\":{}}},\"indices\":{},\"material\":{}}}",
+                                    first_accessor + 1, first_accessor + 2, materials.len() - 1));
+        }
+
+        let mut json = String::from("{\"asset\":{\"version\":\"2.0\",\"generator\":\"mc_schem\"},\"scene\":0,");
+        if primitives.is_empty() {
+            json.push_str("\"scenes\":[{}]");
+        } else {
+            json.push_str(&format!("\"scenes\":[{{\"nodes\":[0]}}],\"nodes\":[{{\"mesh\":0}}],\"meshes\":[{{\"primitives\":[{}]}}],\"materials\":[{}],\"accessors\":[{}],\"bufferViews\":[{}],\"buffers\":[{{\"byteLength\":{}}}]",
+                                   primitives.join(","), materials.join(","), accessors.join(","), buffer_views.join(","), bin.len()));
+        }
+        json.push('}');
+
+        let mut json = json.into_bytes();
+        while json.len() % 4 != 0 {
+            json.push(b' ');
+        }
+        while bin.len() % 4 != 0 {
+            bin.push(0);
+        }
+        let total = 12 + 8 + json.len() + if bin.is_empty() { 0 } else { 8 + bin.len() };
+        let mut result = Vec::with_capacity(total);
+        result.extend_from_slice(b"glTF");
+        result.extend_from_slice(&2u32.to_le_bytes());
+        result.extend_from_slice(&(total as u32).to_le_bytes());
+        result.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        result.extend_from_slice(b"JSON");
+        result.extend_from_slice(&json);
+        if !bin.is_empty() {
+            result.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+            result.extend_from_slice(b"BIN\0");
+            result.extend_from_slice(&bin);
+        }
+        return result;
+    }
+
+    /// Save as binary gltf(glb)
+    pub fn save_glb(&self, filename: impl AsRef<Path>) -> Result<(), Error> {
+        return write_file(filename.as_ref(), &self.to_glb());
+    }
+}
+
+#[test]
+fn test_mesh() {
+    let stone = Block::from_id("minecraft:stone").unwrap();
+    let dirt = Block::from_id("minecraft:dirt").unwrap();
+    let glass = Block::from_id("minecraft:glass").unwrap();
+    let colors = BlockColorTable::default();
+
+    // a single cube
+    let mut region = Region::with_shape([1, 1, 1]);
+    region.fill_with(&stone);
+    let mesh = region.to_mesh(&colors);
+    assert_eq!(mesh.quads.len(), 6);
+    assert_eq!(mesh.materials.len(), 1);
+    assert_eq!(mesh.materials[0].name, "minecraft_stone");
+    for quad in &mesh.quads {
+        // corners are counter-clockwise seen from outside, so the cross product points outward
+        let [a, b, c, _] = quad.corners;
+        let e1 = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+        let e2 = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+        let cross = [e1[1] * e2[2] - e1[2] * e2[1], e1[2] * e2[0] - e1[0] * e2[2], e1[0] * e2[1] - e1[1] * e2[0]];
+        assert_eq!(cross, quad.normal);
+    }
+
+    // greedy meshing merges a 3x2x1 box into 6 quads
+    let mut region = Region::with_shape([3, 2, 1]);
+    region.fill_with(&stone);
+    assert_eq!(region.to_mesh(&colors).quads.len(), 6);
+
+    // different materials are not merged, and faces between opaque blocks are culled
+    let mut region = Region::with_shape([2, 1, 1]);
+    region.set_block([0, 0, 0], &stone).unwrap();
+    region.set_block([1, 0, 0], &dirt).unwrap();
+    let mesh = region.to_mesh(&colors);
+    assert_eq!(mesh.quads.len(), 10);
+    assert_eq!(mesh.materials.len(), 2);
+
+    // faces next to glass are kept, faces between glass are culled and the rest are merged
+    let mut region = Region::with_shape([3, 1, 1]);
+    region.set_block([0, 0, 0], &stone).unwrap();
+    region.set_block([1, 0, 0], &glass).unwrap();
+    region.set_block([2, 0, 0], &glass).unwrap();
+    let mesh = region.to_mesh(&colors);
+    assert_eq!(mesh.quads.len(), 6 + 5);
+
+    // only whole last words of ids mark blocks as not full
+    let opaque = [128, 128, 128, 255];
+    for id in ["bedrock", "sea_lantern", "jack_o_lantern", "chain_command_block", "stone"] {
+        assert!(is_full_opaque(&Block::from_id(id).unwrap(), opaque), "{id}");
+    }
+    for id in ["oak_slab", "lantern", "chain", "end_rod", "cobblestone_wall", "red_bed", "oak_trapdoor",
+        "stone_pressure_plate", "potted_poppy"] {
+        assert!(!is_full_opaque(&Block::from_id(id).unwrap(), opaque), "{id}");
+    }
+
+    let (obj, mtl) = mesh.to_obj("test.mtl");
+    assert!(obj.starts_with("# Generated by mc_schem\nmtllib test.mtl\n"));
+    assert_eq!(obj.lines().filter(|l| l.starts_with("f ")).count(), 11);
+    assert_eq!(obj.lines().filter(|l| l.starts_with("v ")).count(), 12);
+    assert_eq!(obj.lines().filter(|l| l.starts_with("usemtl ")).count(), 2);
+    assert!(mtl.contains("newmtl minecraft_glass\n"));
+
+    let glb = mesh.to_glb();
+    assert_eq!(&glb[0..4], b"glTF");
+    assert_eq!(u32::from_le_bytes([glb[8], glb[9], glb[10], glb[11]]) as usize, glb.len());
+    let json_len = u32::from_le_bytes([glb[12], glb[13], glb[14], glb[15]]) as usize;
+    assert_eq!(&glb[16..20], b"JSON");
+    let json = std::str::from_utf8(&glb[20..20 + json_len]).unwrap();
+    assert!(json.contains("\"alphaMode\":\"BLEND\""));
+    let bin_len = u32::from_le_bytes([glb[20 + json_len], glb[21 + json_len], glb[22 + json_len], glb[23 + json_len]]) as usize;
+    assert_eq!(&glb[24 + json_len..28 + json_len], b"BIN\0");
+    // 11 quads, 4 vertices with position and normal each, and 6 indices
+    assert_eq!(bin_len, 11 * (4 * 24 + 6 * 4));
+
+    let mut schem = Schematic::new();
+    schem.regions.push(region.clone());
+    region.offset = [3, 0, 0];
+    schem.regions.push(region);
+    let mesh = schem.to_mesh(&colors);
+    assert_eq!(mesh.materials.len(), 2);
+    assert_eq!(mesh.quads.len(), 22);
+    assert!(mesh.quads.iter().any(|q| q.corners.iter().any(|c| c[0] == 6)));
+}
@@ -0,0 +1,257 @@
+/*
+mc_schem is a rust library to generate, load, manipulate and save minecraft schematic files.
+Copyright (C) 2024  joseph
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use strum::Display;
+use crate::block::Block;
+use crate::error::Error;
+use crate::map::{map_base_color, map_color_rgb, MapColorShade};
+
+mod isometric;
+mod layers;
+mod mesh;
+
+pub use isometric::render_isometric;
+
+/// Colors of blocks used by renderers. By default colors are taken from map colors, so that no
+/// texture pack is required.
+#[derive(Debug, Clone)]
+pub struct BlockColorTable {
+    /// Colors by block id with namespace but without properties, like `minecraft:stone`. These
+    /// colors take priority over map colors.
+    pub custom: HashMap<String, [u8; 4]>,
+    /// Whether to use map colors for blocks not in `custom`. If false, `fallback` is used.
+    pub use_map_colors: bool,
+    /// Color of blocks that are not in `custom` and have no map color
+    pub fallback: [u8; 4],
+}
+
+impl Default for BlockColorTable {
+    fn default() -> Self {
+        return BlockColorTable {
+            custom: HashMap::new(),
+            use_map_colors: true,
+            fallback: [128, 128, 128, 255],
+        };
+    }
+}
+
+/// Parse color like `#RRGGBB` or `#RRGGBBAA`, `#` is optional
+pub fn parse_hex_color(text: &str) -> Option<[u8; 4]> {
+    let text = text.trim();
+    let text = text.strip_prefix('#').unwrap_or(text);
+    if (text.len() != 6 && text.len() != 8) || !text.is_ascii() {
+        return None;
+    }
+    let mut result = [255u8; 4];
+    for idx in 0..text.len() / 2 {
+        result[idx] = u8::from_str_radix(&text[idx * 2..idx * 2 + 2], 16).ok()?;
+    }
+    return Some(result);
+}
+
+/// Create `path` and write `content` into it, shared by the file exporters
+fn write_file(path: &Path, content: &[u8]) -> Result<(), Error> {
+    let mut file = match File::create(path) {
+        Ok(f) => f,
+        Err(e) => return Err(Error::FileCreateError(e)),
+    };
+    if let Err(e) = file.write_all(content) {
+        return Err(Error::IOWriteError(e));
+    }
+    return Ok(());
+}
+
+impl BlockColorTable {
+    /// Parse custom colors from csv text, each line is `block_id,color` like
+    /// `minecraft:stone,#707070`. Empty lines and lines starting with `#` are ignored.
+    pub fn from_csv(text: &str) -> Result<BlockColorTable, Error> {
+        let mut result = BlockColorTable::default();
+        for (line_idx, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (id, color) = match line.split_once(',') {
+                Some(pair) => pair,
+                None => return Err(Error::InvalidBlockColorTable {
+                    line: line_idx + 1,
+                    detail: format!("Expected \"block_id,color\", but found \"{line}\""),
+                }),
+            };
+            let color = match parse_hex_color(color) {
+                Some(c) => c,
+                None => return Err(Error::InvalidBlockColorTable {
+                    line: line_idx + 1,
+                    detail: format!("Invalid color \"{}\"", color.trim()),
+                }),
+            };
+            let id = id.trim();
+            let id = if id.contains(':') { id.to_string() } else { format!("minecraft:{id}") };
+            result.custom.insert(id, color);
+        }
+        return Ok(result);
+    }
+
+    /// Color of a block, `None` means the block is invisible, like air and structure void.
+    pub fn color_of(&self, block: &Block) -> Option<[u8; 4]> {
+        if block.is_air() || block.is_structure_void() {
+            return None;
+        }
+        let namespace = if block.namespace.is_empty() { "minecraft" } else { &block.namespace };
+        if let Some(color) = self.custom.get(&format!("{namespace}:{}", block.id)) {
+            return if color[3] == 0 { None } else { Some(*color) };
+        }
+        if !self.use_map_colors {
+            return Some(self.fallback);
+        }
+        let base_color = map_base_color(block);
+        if base_color != 0 {
+            let [r, g, b] = map_color_rgb(base_color, MapColorShade::High).unwrap();
+            let alpha = if block.id.contains("glass") { 160 } else { 255 };
+            return Some([r, g, b, alpha]);
+        }
+        // blocks like glass are transparent on map, but visible in renders
+        if block.id.contains("glass") {
+            return Some([200, 220, 255, 96]);
+        }
+        return None;
+    }
+}
+
+/// The corner where viewer stands, renders are rotated accordingly
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+pub enum ViewDirection {
+    SouthEast,
+    SouthWest,
+    NorthWest,
+    NorthEast,
+}
+
+/// Options to render isometric images
+#[derive(Debug, Clone)]
+pub struct IsometricOption {
+    pub view: ViewDirection,
+    /// A block is rendered as `4 * scale` pixels wide, 0 is treated as 1
+    pub scale: u32,
+    /// Color of pixels without any block
+    pub background: [u8; 4],
+    pub colors: BlockColorTable,
+}
+
+impl Default for IsometricOption {
+    fn default() -> Self {
+        return IsometricOption {
+            view: ViewDirection::SouthEast,
+            scale: 4,
+            background: [0, 0, 0, 0],
+            colors: BlockColorTable::default(),
+        };
+    }
+}
+
+/// Top-down view of every y layer of a schematic, for building layer by layer
+#[derive(Debug, Clone)]
+pub struct LayerGuide {
+    /// Shape of schematic in x, y, z
+    pub shape: [i32; 3],
+    /// Block ids with namespace but without properties, in the order of first appearance from
+    /// the bottom layer. Blocks that only differ in properties share one entry.
+    pub legend: Vec<String>,
+    /// Color of each legend entry
+    pub legend_colors: Vec<[u8; 4]>,
+    /// Layers from bottom to top
+    pub layers: Vec<Layer>,
+}
+
+/// A y layer in `LayerGuide`
+#[derive(Debug, Clone)]
+pub struct Layer {
+    pub y: i32,
+    /// Legend index of every block, indexed by `z * shape[0] + x`. Air and structure void are `None`
+    pub cells: Vec<Option<usize>>,
+    /// Count of every legend entry in this layer as `(legend index, count)`, sorted by count descending
+    pub counts: Vec<(usize, u64)>,
+}
+
+/// Options to generate and export layer guides
+#[derive(Debug, Clone)]
+pub struct LayerGuideOption {
+    /// Width and height of a block in layer images
+    pub pixels_per_block: u32,
+    pub colors: BlockColorTable,
+    /// Whether to skip layers without any block
+    pub skip_empty_layers: bool,
+}
+
+impl Default for LayerGuideOption {
+    fn default() -> Self {
+        return LayerGuideOption {
+            pixels_per_block: 16,
+            colors: BlockColorTable::default(),
+            skip_empty_layers: true,
+        };
+    }
+}
+
+/// A mesh of axis-aligned quads, 1 unit per block, with y up
+#[derive(Debug, Clone, Default)]
+pub struct Mesh {
+    pub materials: Vec<MeshMaterial>,
+    pub quads: Vec<MeshQuad>,
+}
+
+/// A flat colored material, made for a palette entry
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeshMaterial {
+    /// Unique name containing only letters, digits and underscores
+    pub name: String,
+    pub color: [u8; 4],
+}
+
+/// A rectangle facing one of 6 directions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MeshQuad {
+    /// Index in `Mesh::materials`
+    pub material: usize,
+    /// Corners in xyz, counter-clockwise when viewed from outside
+    pub corners: [[i32; 3]; 4],
+    /// Outward normal, a unit vector along x, y or z
+    pub normal: [i32; 3],
+}
+
+#[test]
+fn test_block_color_table() {
+    assert_eq!(parse_hex_color("#102030"), Some([16, 32, 48, 255]));
+    assert_eq!(parse_hex_color("10203040"), Some([16, 32, 48, 64]));
+    assert_eq!(parse_hex_color("#1020"), None);
+    assert_eq!(parse_hex_color("#10203g"), None);
+
+    let table = BlockColorTable::from_csv("# comment\nminecraft:stone,#FF0000\n\ndirt, #00FF0080\n").unwrap();
+    let color = |id: &str| table.color_of(&Block::from_id(id).unwrap());
+    assert_eq!(color("minecraft:stone"), Some([255, 0, 0, 255]));
+    assert_eq!(color("dirt"), Some([0, 255, 0, 128]));
+    assert_eq!(color("minecraft:white_wool"), Some([255, 255, 255, 255]));
+    assert_eq!(color("minecraft:air"), None);
+    assert_eq!(color("minecraft:torch"), None);
+    assert!(BlockColorTable::from_csv("minecraft:stone").is_err());
+    assert!(BlockColorTable::from_csv("minecraft:stone,red").is_err());
+}
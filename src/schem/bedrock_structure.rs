@@ -0,0 +1,728 @@
+/*
+mc_schem is a rust library to generate, load, manipulate and save minecraft schematic files.
+Copyright (C) 2024  joseph
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use fastnbt::{ByteArray, IntArray, LongArray, Value};
+use crate::bedrock_block::BedrockBlock;
+use crate::block::Block;
+use crate::error::Error;
+use crate::region::{BlockEntity, Entity, Region, WorldSlice};
+use crate::schem::{BedrockStructureLoadOption, BedrockStructureMetaData, BedrockStructureSaveOption, id_of_nbt_tag, MetaDataIR, Schematic};
+use crate::{unwrap_opt_tag, unwrap_tag};
+
+// Bedrock edition uses little-endian nbt without compression, which is not supported by fastnbt.
+// Tags are read into `fastnbt::Value` so that they can be processed like java nbt.
+
+const MAX_NBT_DEPTH: usize = 512;
+
+fn read_exact<const N: usize>(src: &mut dyn Read) -> Result<[u8; N], Error> {
+    let mut buf = [0u8; N];
+    if let Err(e) = src.read_exact(&mut buf) {
+        return Err(Error::IOReadError(e));
+    }
+    return Ok(buf);
+}
+
+fn read_le_len(src: &mut dyn Read, tag_path: &str) -> Result<usize, Error> {
+    let len = i32::from_le_bytes(read_exact::<4>(src)?);
+    if len < 0 {
+        return Err(Error::InvalidValue {
+            tag_path: tag_path.to_string(),
+            error: format!("Length of array or list should be non-negative, but found {len}"),
+        });
+    }
+    return Ok(len as usize);
+}
+
+// Read `len` bytes without allocating `len` bytes ahead, since `len` comes from untrusted input
+fn read_le_bytes(src: &mut dyn Read, len: usize) -> Result<Vec<u8>, Error> {
+    let mut buf = Vec::with_capacity(len.min(65536));
+    if let Err(e) = src.take(len as u64).read_to_end(&mut buf) {
+        return Err(Error::IOReadError(e));
+    }
+    if buf.len() != len {
+        return Err(Error::IOReadError(std::io::Error::from(std::io::ErrorKind::UnexpectedEof)));
+    }
+    return Ok(buf);
+}
+
+fn read_le_string(src: &mut dyn Read, tag_path: &str) -> Result<String, Error> {
+    let len = u16::from_le_bytes(read_exact::<2>(src)?) as usize;
+    let buf = read_le_bytes(src, len)?;
+    return match String::from_utf8(buf) {
+        Ok(s) => Ok(s),
+        Err(e) => Err(Error::InvalidValue {
+            tag_path: tag_path.to_string(),
+            error: format!("Invalid utf-8 string: {e}"),
+        }),
+    };
+}
+
+fn read_le_payload(src: &mut dyn Read, tag_id: u8, tag_path: &str, depth: usize) -> Result<Value, Error> {
+    if depth > MAX_NBT_DEPTH {
+        return Err(Error::InvalidValue {
+            tag_path: tag_path.to_string(),
+            error: format!("Nbt is nested more than {MAX_NBT_DEPTH} levels"),
+        });
+    }
+    let value = match tag_id {
+        1 => Value::Byte(i8::from_le_bytes(read_exact::<1>(src)?)),
+        2 => Value::Short(i16::from_le_bytes(read_exact::<2>(src)?)),
+        3 => Value::Int(i32::from_le_bytes(read_exact::<4>(src)?)),
+        4 => Value::Long(i64::from_le_bytes(read_exact::<8>(src)?)),
+        5 => Value::Float(f32::from_le_bytes(read_exact::<4>(src)?)),
+        6 => Value::Double(f64::from_le_bytes(read_exact::<8>(src)?)),
+        7 => {
+            let len = read_le_len(src, tag_path)?;
+            let buf = read_le_bytes(src, len)?;
+            Value::ByteArray(ByteArray::new(buf.into_iter().map(|b| b as i8).collect()))
+        }
+        8 => Value::String(read_le_string(src, tag_path)?),
+        9 => {
+            let element_id = read_exact::<1>(src)?[0];
+            let len = read_le_len(src, tag_path)?;
+            let mut list = Vec::with_capacity(len.min(65536));
+            for idx in 0..len {
+                list.push(read_le_payload(src, element_id, &format!("{tag_path}[{idx}]"), depth + 1)?);
+            }
+            Value::List(list)
+        }
+        10 => Value::Compound(read_le_compound(src, tag_path, depth + 1)?),
+        11 => {
+            let len = read_le_len(src, tag_path)?;
+            let mut arr = Vec::with_capacity(len.min(65536));
+            for _ in 0..len {
+                arr.push(i32::from_le_bytes(read_exact::<4>(src)?));
+            }
+            Value::IntArray(IntArray::new(arr))
+        }
+        12 => {
+            let len = read_le_len(src, tag_path)?;
+            let mut arr = Vec::with_capacity(len.min(65536));
+            for _ in 0..len {
+                arr.push(i64::from_le_bytes(read_exact::<8>(src)?));
+            }
+            Value::LongArray(LongArray::new(arr))
+        }
+        _ => return Err(Error::InvalidValue {
+            tag_path: tag_path.to_string(),
+            error: format!("Invalid nbt tag type {tag_id}"),
+        }),
+    };
+    return Ok(value);
+}
+
+fn read_le_compound(src: &mut dyn Read, tag_path: &str, depth: usize) -> Result<HashMap<String, Value>, Error> {
+    let mut result = HashMap::new();
+    loop {
+        let tag_id = read_exact::<1>(src)?[0];
+        if tag_id == 0 {
+            break;
+        }
+        let key = read_le_string(src, tag_path)?;
+        let value = read_le_payload(src, tag_id, &format!("{tag_path}/{key}"), depth)?;
+        result.insert(key, value);
+    }
+    return Ok(result);
+}
+
+/// Read little-endian nbt used by bedrock edition. The root tag must be a compound.
+pub fn read_le_nbt(src: &mut dyn Read) -> Result<HashMap<String, Value>, Error> {
+    let tag_id = read_exact::<1>(src)?[0];
+    if tag_id != 10 {
+        return Err(Error::TagTypeMismatch {
+            tag_path: "".to_string(),
+            expected_type: 10,
+            found_type: tag_id,
+        });
+    }
+    let _root_name = read_le_string(src, "")?;
+    return read_le_compound(src, "", 0);
+}
+
+fn write_all(dest: &mut dyn Write, bytes: &[u8]) -> Result<(), Error> {
+    if let Err(e) = dest.write_all(bytes) {
+        return Err(Error::NBTWriteError(e.into()));
+    }
+    return Ok(());
+}
+
+fn write_le_string(dest: &mut dyn Write, s: &str) -> Result<(), Error> {
+    if s.len() > u16::MAX as usize {
+        return Err(Error::StringTooLongInNBT { length: s.len(), max_length: u16::MAX as usize });
+    }
+    write_all(dest, &(s.len() as u16).to_le_bytes())?;
+    return write_all(dest, s.as_bytes());
+}
+
+fn write_le_payload(dest: &mut dyn Write, value: &Value) -> Result<(), Error> {
+    match value {
+        Value::Byte(v) => write_all(dest, &v.to_le_bytes())?,
+        Value::Short(v) => write_all(dest, &v.to_le_bytes())?,
+        Value::Int(v) => write_all(dest, &v.to_le_bytes())?,
+        Value::Long(v) => write_all(dest, &v.to_le_bytes())?,
+        Value::Float(v) => write_all(dest, &v.to_le_bytes())?,
+        Value::Double(v) => write_all(dest, &v.to_le_bytes())?,
+        Value::ByteArray(arr) => {
+            write_all(dest, &(arr.len() as i32).to_le_bytes())?;
+            let bytes: Vec<u8> = arr.iter().map(|b| *b as u8).collect();
+            write_all(dest, &bytes)?;
+        }
+        Value::String(s) => write_le_string(dest, s)?,
+        Value::List(list) => {
+            let element_id = match list.first() {
+                Some(first) => id_of_nbt_tag(first),
+                None => 0,
+            };
+            write_all(dest, &[element_id])?;
+            write_all(dest, &(list.len() as i32).to_le_bytes())?;
+            for element in list {
+                write_le_payload(dest, element)?;
+            }
+        }
+        Value::Compound(compound) => write_le_compound(dest, compound)?,
+        Value::IntArray(arr) => {
+            write_all(dest, &(arr.len() as i32).to_le_bytes())?;
+            for v in arr.iter() {
+                write_all(dest, &v.to_le_bytes())?;
+            }
+        }
+        Value::LongArray(arr) => {
+            write_all(dest, &(arr.len() as i32).to_le_bytes())?;
+            for v in arr.iter() {
+                write_all(dest, &v.to_le_bytes())?;
+            }
+        }
+    }
+    return Ok(());
+}
+
+fn write_le_compound(dest: &mut dyn Write, compound: &HashMap<String, Value>) -> Result<(), Error> {
+    for (key, value) in compound {
+        write_all(dest, &[id_of_nbt_tag(value)])?;
+        write_le_string(dest, key)?;
+        write_le_payload(dest, value)?;
+    }
+    return write_all(dest, &[0]);
+}
+
+/// Write little-endian nbt used by bedrock edition, the root tag has empty name.
+pub fn write_le_nbt(dest: &mut dyn Write, nbt: &HashMap<String, Value>) -> Result<(), Error> {
+    write_all(dest, &[10])?;
+    write_le_string(dest, "")?;
+    return write_le_compound(dest, nbt);
+}
+
+/// Java block entity ids whose bedrock id is not the camel case of java id
+const BEDROCK_BLOCK_ENTITY_IDS: &[(&str, &str)] = &[
+    ("trapped_chest", "Chest"),
+    ("enchanting_table", "EnchantTable"),
+    ("piston", "PistonArm"),
+    ("jigsaw", "JigsawBlock"),
+];
+
+/// Bedrock block entity ids have no namespace and start with an upper case letter, like `Chest`
+fn is_java_block_entity_id(id: &str) -> bool {
+    return id.contains(':') || id.starts_with(|c: char| c.is_ascii_lowercase());
+}
+
+/// Convert java block entity id like `minecraft:blast_furnace` to bedrock id like `BlastFurnace`
+fn bedrock_block_entity_id(java_id: &str) -> String {
+    let id = java_id.strip_prefix("minecraft:").unwrap_or(java_id);
+    if let Some((_, bedrock)) = BEDROCK_BLOCK_ENTITY_IDS.iter().find(|(java, _)| *java == id) {
+        return bedrock.to_string();
+    }
+    return id.split('_').map(|word| {
+        let mut chars = word.chars();
+        return match chars.next() {
+            Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+            None => String::new(),
+        };
+    }).collect();
+}
+
+/// Convert a java item stack to bedrock, components and tags of the item are dropped.
+fn java_item_to_bedrock(item: &HashMap<String, Value>) -> HashMap<String, Value> {
+    let name = match item.get("id") {
+        Some(Value::String(id)) if id.contains(':') => id.clone(),
+        Some(Value::String(id)) => format!("minecraft:{id}"),
+        _ => "minecraft:air".to_string(),
+    };
+    // Count is a byte before 1.20.5, and count is an int since 1.20.5
+    let count = match item.get("Count").or_else(|| item.get("count")) {
+        Some(Value::Byte(c)) => *c as i32,
+        Some(Value::Int(c)) => *c,
+        _ => 1,
+    };
+    let mut result = HashMap::from([
+        ("Name".to_string(), Value::String(name)),
+        ("Count".to_string(), Value::Byte(count.clamp(0, i8::MAX as i32) as i8)),
+        ("Damage".to_string(), Value::Short(0)),
+        ("WasPickedUp".to_string(), Value::Byte(0)),
+    ]);
+    if let Some(slot) = item.get("Slot") {
+        result.insert("Slot".to_string(), slot.clone());
+    }
+    return result;
+}
+
+/// Convert java items in `Items` to bedrock, items already in bedrock format are kept.
+fn java_items_to_bedrock(tags: &mut HashMap<String, Value>) {
+    if let Some(Value::List(items)) = tags.get_mut("Items") {
+        for item in items.iter_mut() {
+            if let Value::Compound(compound) = item {
+                if compound.contains_key("id") && !compound.contains_key("Name") {
+                    *compound = java_item_to_bedrock(compound);
+                }
+            }
+        }
+    }
+}
+
+/// Translate id and items of a java block entity, bedrock block entities are kept as is.
+fn block_entity_to_bedrock(mut tags: HashMap<String, Value>) -> HashMap<String, Value> {
+    let bedrock_id = match tags.get("id") {
+        Some(Value::String(id)) if is_java_block_entity_id(id) => bedrock_block_entity_id(id),
+        _ => return tags,
+    };
+    tags.insert("id".to_string(), Value::String(bedrock_id));
+    java_items_to_bedrock(&mut tags);
+    return tags;
+}
+
+/// Translate id and items of a java entity, bedrock entities(with `identifier`) are kept as is.
+fn entity_to_bedrock(mut tags: HashMap<String, Value>) -> HashMap<String, Value> {
+    if tags.contains_key("identifier") {
+        return tags;
+    }
+    if let Some(Value::String(id)) = tags.remove("id") {
+        let identifier = if id.contains(':') { id } else { format!("minecraft:{id}") };
+        tags.insert("identifier".to_string(), Value::String(identifier));
+        java_items_to_bedrock(&mut tags);
+    }
+    return tags;
+}
+
+fn parse_i32_list(nbt: &HashMap<String, Value>, key: &str, tag_path: &str) -> Result<[i32; 3], Error> {
+    let list = unwrap_opt_tag!(nbt.get(key),List,vec![],&*format!("{tag_path}/{key}"));
+    if list.len() != 3 {
+        return Err(Error::InvalidValue {
+            tag_path: format!("{tag_path}/{key}"),
+            error: format!("Expected a list with 3 elements, but found {}", list.len()),
+        });
+    }
+    let mut result = [0; 3];
+    for dim in 0..3 {
+        result[dim] = *unwrap_tag!(&list[dim],Int,0,&*format!("{tag_path}/{key}[{dim}]"));
+    }
+    return Ok(result);
+}
+
+fn parse_entity(mut nbt: HashMap<String, Value>, tag_path: &str, origin: [i32; 3]) -> Result<Entity, Error> {
+    let mut entity = Entity::new();
+    let pos = unwrap_opt_tag!(nbt.get("Pos"),List,vec![],&*format!("{tag_path}/Pos"));
+    if pos.len() != 3 {
+        return Err(Error::InvalidValue {
+            tag_path: format!("{tag_path}/Pos"),
+            error: format!("Pos field for an entity should contain 3 floats, but found {}", pos.len()),
+        });
+    }
+    for dim in 0..3 {
+        let p = *unwrap_tag!(&pos[dim],Float,0.0,&*format!("{tag_path}/Pos[{dim}]"));
+        entity.position[dim] = p as f64 - origin[dim] as f64;
+        entity.block_pos[dim] = entity.position[dim].floor() as i32;
+    }
+    nbt.remove("Pos");
+    entity.tags = nbt;
+    return Ok(entity);
+}
+
+impl MetaDataIR {
+    pub fn from_bedrock_structure(src: &BedrockStructureMetaData) -> MetaDataIR {
+        let mut result = MetaDataIR::default();
+        result.schem_origin = Some(src.structure_world_origin);
+        return result;
+    }
+}
+
+impl Schematic {
+    /// Load bedrock `.mcstructure` from file
+    pub fn from_bedrock_structure_file(filename: &str, option: &BedrockStructureLoadOption) -> Result<(Schematic, BedrockStructureMetaData), Error> {
+        let file = match File::open(filename) {
+            Ok(f) => f,
+            Err(e) => return Err(Error::FileOpenError(e)),
+        };
+        let mut reader = BufReader::new(file);
+        return Self::from_bedrock_structure_reader(&mut reader, option);
+    }
+
+    /// Load bedrock `.mcstructure` from reader
+    pub fn from_bedrock_structure_reader(src: &mut dyn Read, option: &BedrockStructureLoadOption) -> Result<(Schematic, BedrockStructureMetaData), Error> {
+        let nbt = read_le_nbt(src)?;
+        return Self::from_bedrock_structure_nbt(nbt, option);
+    }
+
+    /// Load bedrock `.mcstructure` from nbt
+    pub fn from_bedrock_structure_nbt(mut nbt: HashMap<String, Value>, option: &BedrockStructureLoadOption) -> Result<(Schematic, BedrockStructureMetaData), Error> {
+        let format_version = *unwrap_opt_tag!(nbt.get("format_version"),Int,0,"/format_version");
+        let size = parse_i32_list(&nbt, "size", "")?;
+        for (dim, s) in size.iter().enumerate() {
+            if *s < 0 {
+                return Err(Error::InvalidValue {
+                    tag_path: format!("/size[{dim}]"),
+                    error: format!("Expected non-negative value, but found {}", s),
+                });
+            }
+        }
+        let md = BedrockStructureMetaData {
+            format_version,
+            structure_world_origin: parse_i32_list(&nbt, "structure_world_origin", "")?,
+        };
+
+        let mut structure = unwrap_opt_tag!(nbt.remove("structure"),Compound,HashMap::new(),"/structure");
+
+        // parse palette
+        let mut palette_nbt;
+        {
+            let palette = unwrap_opt_tag!(structure.remove("palette"),Compound,HashMap::new(),"/structure/palette");
+            palette_nbt = match palette.into_iter().find(|(k, _)| k == "default") {
+                Some((_, Value::Compound(p))) => p,
+                Some((_, other)) => return Err(Error::TagTypeMismatch {
+                    tag_path: "/structure/palette/default".to_string(),
+                    expected_type: 10,
+                    found_type: id_of_nbt_tag(&other),
+                }),
+                None => return Err(Error::TagMissing("/structure/palette/default".to_string())),
+            };
+        }
+        let mut bedrock_palette = Vec::new();
+        {
+            let palette_list = unwrap_opt_tag!(palette_nbt.get("block_palette"),List,vec![],"/structure/palette/default/block_palette");
+            bedrock_palette.reserve(palette_list.len());
+            for (idx, blk) in palette_list.iter().enumerate() {
+                let tag_path = format!("/structure/palette/default/block_palette[{idx}]");
+                let blk = unwrap_tag!(blk,Compound,HashMap::new(),&*tag_path);
+                bedrock_palette.push(BedrockBlock::from_nbt(blk, &tag_path)?);
+            }
+        }
+
+        let mut region = Region::with_shape(size);
+        region.palette.clear();
+        for blk in &bedrock_palette {
+            region.palette.push(Block::from_bedrock(blk));
+        }
+        let background_idx = region.find_or_append_to_palette(&option.background_block.to_block());
+        if region.palette.len() >= 65536 {
+            return Err(Error::PaletteTooLong(region.palette.len()));
+        }
+
+        // parse blocks
+        {
+            let layers = unwrap_opt_tag!(structure.get("block_indices"),List,vec![],"/structure/block_indices");
+            if layers.is_empty() {
+                return Err(Error::TagMissing("/structure/block_indices[0]".to_string()));
+            }
+            let volume = size[0] as usize * size[1] as usize * size[2] as usize;
+            let mut layer_indices: Vec<&[Value]> = Vec::with_capacity(2);
+            for (layer_idx, layer) in layers.iter().enumerate().take(2) {
+                let tag_path = format!("/structure/block_indices[{layer_idx}]");
+                let layer = unwrap_tag!(layer,List,vec![],&*tag_path);
+                if layer.len() != volume {
+                    return Err(Error::BlockDataIncomplete {
+                        tag_path,
+                        index: layer.len(),
+                        detail: format!("Expected {volume} block indices, but found {}", layer.len()),
+                    });
+                }
+                layer_indices.push(layer);
+            }
+
+            let mut waterlogged_lut: HashMap<u16, u16> = HashMap::new();
+            let mut counter = 0;
+            for x in 0..size[0] {
+                for y in 0..size[1] {
+                    for z in 0..size[2] {
+                        let tag_path = format!("/structure/block_indices[0][{counter}]");
+                        let index = *unwrap_tag!(&layer_indices[0][counter],Int,0,&*tag_path);
+                        if index < -1 || index >= bedrock_palette.len() as i32 {
+                            return Err(Error::BlockIndexOutOfRange {
+                                tag_path,
+                                index,
+                                range: [-1, bedrock_palette.len() as i32],
+                            });
+                        }
+                        let mut block_idx = if index < 0 { background_idx } else { index as u16 };
+
+                        // the second layer is used for waterlogged blocks
+                        if let Some(layer) = layer_indices.get(1) {
+                            let tag_path = format!("/structure/block_indices[1][{counter}]");
+                            let index2 = *unwrap_tag!(&layer[counter],Int,0,&*tag_path);
+                            if index2 >= bedrock_palette.len() as i32 {
+                                return Err(Error::BlockIndexOutOfRange {
+                                    tag_path,
+                                    index: index2,
+                                    range: [-1, bedrock_palette.len() as i32],
+                                });
+                            }
+                            let is_water = index2 >= 0 && bedrock_palette[index2 as usize].is_water();
+                            if is_water && index >= 0 && !bedrock_palette[index as usize].is_water()
+                                && region.palette[block_idx as usize].is_waterloggable() {
+                                block_idx = match waterlogged_lut.get(&block_idx) {
+                                    Some(idx) => *idx,
+                                    None => {
+                                        let mut blk = region.palette[block_idx as usize].clone();
+                                        blk.set_property("waterlogged", "true");
+                                        let new_idx = region.find_or_append_to_palette(&blk);
+                                        waterlogged_lut.insert(block_idx, new_idx);
+                                        new_idx
+                                    }
+                                };
+                            }
+                        }
+
+                        region.array_yzx[[y as usize, z as usize, x as usize]] = block_idx;
+                        counter += 1;
+                    }
+                }
+            }
+            if region.palette.len() >= 65536 {
+                return Err(Error::PaletteTooLong(region.palette.len()));
+            }
+        }
+
+        // parse block entities
+        if let Some(Value::Compound(pos_data)) = palette_nbt.remove("block_position_data") {
+            for (key, data) in pos_data {
+                let tag_path = format!("/structure/palette/default/block_position_data/{key}");
+                let index: i64 = match key.parse() {
+                    Ok(i) => i,
+                    Err(_) => return Err(Error::InvalidValue {
+                        tag_path,
+                        error: format!("Expected block index as key, but found \"{key}\""),
+                    }),
+                };
+                let yz = size[1] as i64 * size[2] as i64;
+                if index < 0 || index >= yz * size[0] as i64 {
+                    return Err(Error::InvalidValue {
+                        tag_path,
+                        error: format!("Block index {index} is out of range"),
+                    });
+                }
+                let pos = [(index / yz) as i32, ((index / size[2] as i64) % size[1] as i64) as i32, (index % size[2] as i64) as i32];
+                let mut data = unwrap_tag!(data,Compound,HashMap::new(),&*tag_path);
+                let mut be_nbt = match data.remove("block_entity_data") {
+                    Some(Value::Compound(be)) => be,
+                    Some(other) => return Err(Error::TagTypeMismatch {
+                        tag_path: format!("{tag_path}/block_entity_data"),
+                        expected_type: 10,
+                        found_type: id_of_nbt_tag(&other),
+                    }),
+                    None => continue,
+                };
+                be_nbt.remove("x");
+                be_nbt.remove("y");
+                be_nbt.remove("z");
+                region.block_entities.insert(pos, BlockEntity { tags: be_nbt });
+            }
+        }
+
+        // parse entities
+        {
+            let entities = unwrap_opt_tag!(structure.remove("entities"),List,vec![],"/structure/entities");
+            for (idx, entity) in entities.into_iter().enumerate() {
+                let tag_path = format!("/structure/entities[{idx}]");
+                let entity = unwrap_tag!(entity,Compound,HashMap::new(),&*tag_path);
+                region.entities.push(parse_entity(entity, &tag_path, md.structure_world_origin)?);
+            }
+        }
+
+        region.shrink_palette()?;
+
+        let mut schem = Schematic::new();
+        schem.metadata = MetaDataIR::from_bedrock_structure(&md);
+        schem.regions.push(region);
+        return Ok((schem, md));
+    }
+
+    /// Get metadata of bedrock `.mcstructure`
+    pub fn metadata_bedrock_structure(&self) -> BedrockStructureMetaData {
+        return BedrockStructureMetaData {
+            structure_world_origin: self.metadata.schem_origin.unwrap_or([0, 0, 0]),
+            ..BedrockStructureMetaData::default()
+        };
+    }
+
+    /// Save schematic as bedrock `.mcstructure` to nbt. Java block entities and entities are
+    /// translated only partially: their ids and items in `Items` are converted, while components
+    /// and tags of items are dropped, and other fields (like sign text or entity attributes) are
+    /// kept in java format, which bedrock may fail to read. Tags that are already in bedrock
+    /// format are kept as is.
+    pub fn to_nbt_bedrock_structure(&self, option: &BedrockStructureSaveOption) -> Result<HashMap<String, Value>, Error> {
+        let md = self.metadata_bedrock_structure();
+        let shape = self.shape();
+        for dim in 0..3 {
+            if shape[dim] < 0 {
+                return Err(Error::NegativeSize { size: shape, region_name: "all regions".to_string() });
+            }
+        }
+        let origin = md.structure_world_origin;
+
+        let (full_palette, luts_of_block_idx) = self.full_palette();
+        let mut bedrock_palette: Vec<BedrockBlock> = Vec::with_capacity(full_palette.len() + 1);
+        // index of every java block in bedrock palette, or -1 for structure void
+        let mut palette_lut: Vec<i32> = Vec::with_capacity(full_palette.len());
+        for (blk, _) in &full_palette {
+            if blk.is_structure_void() {
+                palette_lut.push(-1);
+                continue;
+            }
+            let bedrock = blk.to_bedrock();
+            let idx = match bedrock_palette.iter().position(|b| *b == bedrock) {
+                Some(idx) => idx,
+                None => {
+                    bedrock_palette.push(bedrock);
+                    bedrock_palette.len() - 1
+                }
+            };
+            palette_lut.push(idx as i32);
+        }
+        let mut water_idx: Option<i32> = None;
+        let background_idx = if option.background_block.to_block().is_structure_void() {
+            -1
+        } else {
+            let bedrock = option.background_block.to_block().to_bedrock();
+            match bedrock_palette.iter().position(|b| *b == bedrock) {
+                Some(idx) => idx as i32,
+                None => {
+                    bedrock_palette.push(bedrock);
+                    bedrock_palette.len() as i32 - 1
+                }
+            }
+        };
+
+        let volume = self.volume() as usize;
+        let mut layer0: Vec<Value> = Vec::with_capacity(volume);
+        let mut layer1: Vec<Value> = Vec::with_capacity(volume);
+        let mut block_position_data: HashMap<String, Value> = HashMap::new();
+        for x in 0..shape[0] {
+            for y in 0..shape[1] {
+                for z in 0..shape[2] {
+                    let g_pos = [x, y, z];
+                    let mut info = None;
+                    for (reg_idx, reg) in self.regions.iter().enumerate() {
+                        if let Some(blk_info) = reg.block_info_at(reg.global_pos_to_relative_pos(g_pos)) {
+                            info = Some((reg_idx, blk_info));
+                            break;
+                        }
+                    }
+
+                    let (reg_idx, (local_idx, blk, be, _)) = match info {
+                        Some(i) => i,
+                        None => {
+                            layer0.push(Value::Int(background_idx));
+                            layer1.push(Value::Int(-1));
+                            continue;
+                        }
+                    };
+                    let g_idx = luts_of_block_idx[reg_idx][local_idx as usize];
+                    layer0.push(Value::Int(palette_lut[g_idx]));
+
+                    let waterlogged = blk.attributes.get("waterlogged").map(|w| w == "true").unwrap_or(false);
+                    if waterlogged {
+                        let idx = match water_idx {
+                            Some(idx) => idx,
+                            None => {
+                                bedrock_palette.push(BedrockBlock::water());
+                                water_idx = Some(bedrock_palette.len() as i32 - 1);
+                                bedrock_palette.len() as i32 - 1
+                            }
+                        };
+                        layer1.push(Value::Int(idx));
+                    } else {
+                        layer1.push(Value::Int(-1));
+                    }
+
+                    if let Some(be) = be {
+                        let mut be_nbt = block_entity_to_bedrock(be.tags.clone());
+                        be_nbt.insert("x".to_string(), Value::Int(x + origin[0]));
+                        be_nbt.insert("y".to_string(), Value::Int(y + origin[1]));
+                        be_nbt.insert("z".to_string(), Value::Int(z + origin[2]));
+                        let data = HashMap::from([("block_entity_data".to_string(), Value::Compound(be_nbt))]);
+                        block_position_data.insert((layer0.len() - 1).to_string(), Value::Compound(data));
+                    }
+                }
+            }
+        }
+
+        let mut entities = Vec::new();
+        for reg in &self.regions {
+            for entity in &reg.entities {
+                let mut nbt = entity_to_bedrock(entity.tags.clone());
+                let mut pos = Vec::with_capacity(3);
+                for (dim, o) in origin.iter().enumerate() {
+                    pos.push(Value::Float((entity.position[dim] + reg.offset[dim] as f64 + *o as f64) as f32));
+                }
+                nbt.insert("Pos".to_string(), Value::List(pos));
+                entities.push(Value::Compound(nbt));
+            }
+        }
+
+        let mut default_palette = HashMap::new();
+        {
+            let block_palette: Vec<Value> = bedrock_palette.iter()
+                .map(|b| Value::Compound(b.to_nbt(option.block_version))).collect();
+            default_palette.insert("block_palette".to_string(), Value::List(block_palette));
+            default_palette.insert("block_position_data".to_string(), Value::Compound(block_position_data));
+        }
+
+        let mut structure = HashMap::new();
+        structure.insert("block_indices".to_string(), Value::List(vec![Value::List(layer0), Value::List(layer1)]));
+        structure.insert("entities".to_string(), Value::List(entities));
+        structure.insert("palette".to_string(), Value::Compound(HashMap::from([("default".to_string(), Value::Compound(default_palette))])));
+
+        let mut nbt = HashMap::new();
+        nbt.insert("format_version".to_string(), Value::Int(md.format_version));
+        nbt.insert("size".to_string(), Value::List(shape.iter().map(|s| Value::Int(*s)).collect()));
+        nbt.insert("structure".to_string(), Value::Compound(structure));
+        nbt.insert("structure_world_origin".to_string(), Value::List(origin.iter().map(|s| Value::Int(*s)).collect()));
+        return Ok(nbt);
+    }
+
+    /// Save schematic to writer as bedrock `.mcstructure`
+    pub fn save_bedrock_structure_writer(&self, dest: &mut dyn Write, option: &BedrockStructureSaveOption) -> Result<(), Error> {
+        let nbt = self.to_nbt_bedrock_structure(option)?;
+        return write_le_nbt(dest, &nbt);
+    }
+
+    /// Save schematic to file as bedrock `.mcstructure`
+    pub fn save_bedrock_structure_file(&self, filename: &str, option: &BedrockStructureSaveOption) -> Result<(), Error> {
+        let nbt = self.to_nbt_bedrock_structure(option)?;
+        let file = match File::create(filename) {
+            Ok(f) => f,
+            Err(e) => return Err(Error::FileCreateError(e)),
+        };
+        let mut writer = BufWriter::new(file);
+        write_le_nbt(&mut writer, &nbt)?;
+        if let Err(e) = writer.flush() {
+            return Err(Error::NBTWriteError(e.into()));
+        }
+        return Ok(());
+    }
+}
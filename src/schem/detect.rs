@@ -0,0 +1,139 @@
+/*
+mc_schem is a rust library to generate, load, manipulate and save minecraft schematic files.
+Copyright (C) 2024  joseph
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::Read;
+use fastnbt::Value;
+use flate2::read::GzDecoder;
+use crate::error::Error;
+use crate::schem::{bedrock_structure, BedrockStructureLoadOption, LitematicaLoadOption, RawMetaData, Schematic, VanillaStructureLoadOption, WorldEdit12LoadOption, WorldEdit13LoadOption};
+use crate::SchemFormat;
+
+/// Decompress `bytes` if it's gzipped, otherwise return it as is.
+fn decompress(bytes: &[u8]) -> Option<Cow<'_, [u8]>> {
+    if bytes.len() >= 2 && bytes[0] == 0x1f && bytes[1] == 0x8b {
+        let mut decompressed = Vec::new();
+        if GzDecoder::new(bytes).read_to_end(&mut decompressed).is_err() {
+            return None;
+        }
+        return Some(Cow::Owned(decompressed));
+    }
+    return Some(Cow::Borrowed(bytes));
+}
+
+/// Guess format by root tags of big endian nbt
+fn format_of_java_nbt(nbt: &HashMap<String, Value>) -> Option<SchemFormat> {
+    if nbt.contains_key("Regions") && nbt.contains_key("MinecraftDataVersion") {
+        return Some(SchemFormat::Litematica);
+    }
+    if let Some(Value::Compound(schem)) = nbt.get("Schematic") {
+        if schem.contains_key("Version") {
+            return Some(SchemFormat::WorldEdit13);
+        }
+    }
+    if nbt.contains_key("Materials") && nbt.contains_key("Blocks") {
+        return Some(SchemFormat::WorldEdit12);
+    }
+    if nbt.contains_key("Version") && (nbt.contains_key("BlockData") || nbt.contains_key("Palette")) {
+        return Some(SchemFormat::WorldEdit13);
+    }
+    if nbt.contains_key("size") && (nbt.contains_key("palette") || nbt.contains_key("palettes"))
+        && nbt.contains_key("blocks") {
+        return Some(SchemFormat::VanillaStructure);
+    }
+    return None;
+}
+
+/// Guess format by root tags of little endian nbt
+fn format_of_bedrock_nbt(nbt: &HashMap<String, Value>) -> Option<SchemFormat> {
+    if nbt.contains_key("format_version") && nbt.contains_key("structure") && nbt.contains_key("size") {
+        return Some(SchemFormat::BedrockStructure);
+    }
+    return None;
+}
+
+/// Detect format and parse the nbt at the same time
+fn detect_and_parse(bytes: &[u8]) -> Option<(SchemFormat, HashMap<String, Value>)> {
+    let bytes = decompress(bytes)?;
+    // all schematic formats have a compound as root tag
+    if bytes.first() != Some(&10) {
+        return None;
+    }
+    if let Ok(nbt) = fastnbt::from_bytes::<HashMap<String, Value>>(&bytes) {
+        if let Some(format) = format_of_java_nbt(&nbt) {
+            return Some((format, nbt));
+        }
+    }
+    if let Ok(nbt) = bedrock_structure::read_le_nbt(&mut bytes.as_ref()) {
+        if let Some(format) = format_of_bedrock_nbt(&nbt) {
+            return Some((format, nbt));
+        }
+    }
+    return None;
+}
+
+impl SchemFormat {
+    /// Detect schematic format from file content. Gzipped and uncompressed nbt are both
+    /// accepted, and the format is decided by tags in root compound. Returns `None` if the content
+    /// is not a known schematic.
+    pub fn detect(bytes: &[u8]) -> Option<SchemFormat> {
+        return detect_and_parse(bytes).map(|(format, _)| format);
+    }
+}
+
+impl Schematic {
+    /// Load schematic from bytes, the format is detected by content with default options.
+    pub fn from_bytes_auto(bytes: &[u8]) -> Result<(Schematic, RawMetaData), Error> {
+        let (format, nbt) = match detect_and_parse(bytes) {
+            Some(res) => res,
+            None => return Err(Error::UnrecognisedFormat),
+        };
+        return match format {
+            SchemFormat::Litematica => {
+                let (schem, raw) = Self::from_litematica_nbt(nbt, &LitematicaLoadOption::default())?;
+                Ok((schem, RawMetaData::Litematica(raw)))
+            }
+            SchemFormat::VanillaStructure => {
+                let (schem, raw) = Self::from_vanilla_structure_nbt(nbt, &VanillaStructureLoadOption::default())?;
+                Ok((schem, RawMetaData::VanillaStructure(raw)))
+            }
+            SchemFormat::WorldEdit13 => {
+                let (schem, raw) = Self::from_world_edit_13_nbt(nbt, &WorldEdit13LoadOption::default())?;
+                Ok((schem, RawMetaData::WE13(raw)))
+            }
+            SchemFormat::WorldEdit12 => {
+                let (schem, raw, ..) = Self::from_world_edit_12_nbt(nbt, &WorldEdit12LoadOption::default())?;
+                Ok((schem, RawMetaData::WE12(raw)))
+            }
+            SchemFormat::BedrockStructure => {
+                let (schem, raw) = Self::from_bedrock_structure_nbt(nbt, &BedrockStructureLoadOption::default())?;
+                Ok((schem, RawMetaData::BedrockStructure(raw)))
+            }
+        };
+    }
+
+    /// Load schematic from a reader, the format is detected by content with default options.
+    pub fn from_reader_auto(src: &mut dyn Read) -> Result<(Schematic, RawMetaData), Error> {
+        let mut bytes = Vec::new();
+        if let Err(e) = src.read_to_end(&mut bytes) {
+            return Err(Error::IOReadError(e));
+        }
+        return Self::from_bytes_auto(&bytes);
+    }
+}
@@ -0,0 +1,682 @@
+/*
+mc_schem is a rust library to generate, load, manipulate and save minecraft schematic files.
+Copyright (C) 2024  joseph
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use std::collections::HashMap;
+use fastnbt::Value;
+use crate::block::Block;
+use crate::error::Error;
+use crate::region::Region;
+use crate::schem::{DataVersion, Schematic};
+use crate::schem::upgrade::migrations;
+
+/// Blocks introduced after 1.13, with the data version of the first release containing them.
+/// Wood families are matched by prefix in `block_introduced_version`.
+const BLOCK_REGISTRY: &[(&str, i32)] = &[
+    // 1.14
+    ("spruce_sign", 1952), ("birch_sign", 1952), ("jungle_sign", 1952),
+    ("acacia_sign", 1952), ("dark_oak_sign", 1952),
+    ("spruce_wall_sign", 1952), ("birch_wall_sign", 1952), ("jungle_wall_sign", 1952),
+    ("acacia_wall_sign", 1952), ("dark_oak_wall_sign", 1952),
+    ("stone_slab", 1952), ("stone_stairs", 1952),
+    ("granite_stairs", 1952), ("granite_slab", 1952), ("granite_wall", 1952),
+    ("polished_granite_stairs", 1952), ("polished_granite_slab", 1952),
+    ("diorite_stairs", 1952), ("diorite_slab", 1952), ("diorite_wall", 1952),
+    ("polished_diorite_stairs", 1952), ("polished_diorite_slab", 1952),
+    ("andesite_stairs", 1952), ("andesite_slab", 1952), ("andesite_wall", 1952),
+    ("polished_andesite_stairs", 1952), ("polished_andesite_slab", 1952),
+    ("mossy_stone_brick_stairs", 1952), ("mossy_stone_brick_slab", 1952), ("mossy_stone_brick_wall", 1952),
+    ("mossy_cobblestone_stairs", 1952), ("mossy_cobblestone_slab", 1952),
+    ("smooth_sandstone_stairs", 1952), ("smooth_sandstone_slab", 1952),
+    ("smooth_red_sandstone_stairs", 1952), ("smooth_red_sandstone_slab", 1952),
+    ("smooth_quartz_stairs", 1952), ("smooth_quartz_slab", 1952),
+    ("red_nether_brick_stairs", 1952), ("red_nether_brick_slab", 1952), ("red_nether_brick_wall", 1952),
+    ("end_stone_brick_stairs", 1952), ("end_stone_brick_slab", 1952), ("end_stone_brick_wall", 1952),
+    ("cut_sandstone_slab", 1952), ("cut_red_sandstone_slab", 1952),
+    ("brick_wall", 1952), ("prismarine_wall", 1952), ("red_sandstone_wall", 1952),
+    ("stone_brick_wall", 1952), ("nether_brick_wall", 1952), ("sandstone_wall", 1952),
+    ("cornflower", 1952), ("lily_of_the_valley", 1952), ("wither_rose", 1952),
+    ("potted_cornflower", 1952), ("potted_lily_of_the_valley", 1952), ("potted_wither_rose", 1952),
+    ("sweet_berry_bush", 1952), ("scaffolding", 1952), ("loom", 1952), ("barrel", 1952),
+    ("smoker", 1952), ("blast_furnace", 1952), ("cartography_table", 1952),
+    ("fletching_table", 1952), ("grindstone", 1952), ("lectern", 1952),
+    ("smithing_table", 1952), ("stonecutter", 1952), ("bell", 1952), ("lantern", 1952),
+    ("campfire", 1952), ("bamboo", 1952), ("bamboo_sapling", 1952), ("potted_bamboo", 1952),
+    ("composter", 1952), ("jigsaw", 1952),
+    // 1.15
+    ("bee_nest", 2225), ("beehive", 2225), ("honey_block", 2225), ("honeycomb_block", 2225),
+    // 1.16
+    ("nether_sprouts", 2566), ("weeping_vines", 2566), ("weeping_vines_plant", 2566),
+    ("twisting_vines", 2566), ("twisting_vines_plant", 2566), ("shroomlight", 2566),
+    ("soul_fire", 2566), ("soul_soil", 2566), ("soul_torch", 2566), ("soul_wall_torch", 2566),
+    ("soul_lantern", 2566), ("soul_campfire", 2566), ("basalt", 2566), ("polished_basalt", 2566),
+    ("blackstone", 2566), ("blackstone_stairs", 2566), ("blackstone_slab", 2566), ("blackstone_wall", 2566),
+    ("polished_blackstone", 2566), ("polished_blackstone_stairs", 2566), ("polished_blackstone_slab", 2566),
+    ("polished_blackstone_wall", 2566), ("polished_blackstone_button", 2566),
+    ("polished_blackstone_pressure_plate", 2566), ("polished_blackstone_bricks", 2566),
+    ("cracked_polished_blackstone_bricks", 2566), ("chiseled_polished_blackstone", 2566),
+    ("polished_blackstone_brick_stairs", 2566), ("polished_blackstone_brick_slab", 2566),
+    ("polished_blackstone_brick_wall", 2566), ("gilded_blackstone", 2566),
+    ("ancient_debris", 2566), ("netherite_block", 2566), ("crying_obsidian", 2566),
+    ("respawn_anchor", 2566), ("lodestone", 2566), ("target", 2566), ("nether_gold_ore", 2566),
+    ("quartz_bricks", 2566), ("chain", 2566), ("cracked_nether_bricks", 2566),
+    ("chiseled_nether_bricks", 2566),
+    // 1.17
+    ("deepslate", 2724), ("cobbled_deepslate", 2724), ("polished_deepslate", 2724),
+    ("deepslate_bricks", 2724), ("cracked_deepslate_bricks", 2724), ("deepslate_tiles", 2724),
+    ("cracked_deepslate_tiles", 2724), ("chiseled_deepslate", 2724),
+    ("cobbled_deepslate_stairs", 2724), ("cobbled_deepslate_slab", 2724), ("cobbled_deepslate_wall", 2724),
+    ("polished_deepslate_stairs", 2724), ("polished_deepslate_slab", 2724), ("polished_deepslate_wall", 2724),
+    ("deepslate_brick_stairs", 2724), ("deepslate_brick_slab", 2724), ("deepslate_brick_wall", 2724),
+    ("deepslate_tile_stairs", 2724), ("deepslate_tile_slab", 2724), ("deepslate_tile_wall", 2724),
+    ("deepslate_coal_ore", 2724), ("deepslate_iron_ore", 2724), ("deepslate_copper_ore", 2724),
+    ("deepslate_gold_ore", 2724), ("deepslate_redstone_ore", 2724), ("deepslate_emerald_ore", 2724),
+    ("deepslate_lapis_ore", 2724), ("deepslate_diamond_ore", 2724),
+    ("copper_ore", 2724), ("raw_iron_block", 2724), ("raw_copper_block", 2724), ("raw_gold_block", 2724),
+    ("amethyst_block", 2724), ("budding_amethyst", 2724), ("amethyst_cluster", 2724),
+    ("large_amethyst_bud", 2724), ("medium_amethyst_bud", 2724), ("small_amethyst_bud", 2724),
+    ("calcite", 2724), ("tuff", 2724), ("tinted_glass", 2724), ("smooth_basalt", 2724),
+    ("dripstone_block", 2724), ("pointed_dripstone", 2724), ("azalea", 2724),
+    ("flowering_azalea", 2724), ("azalea_leaves", 2724), ("flowering_azalea_leaves", 2724),
+    ("potted_azalea_bush", 2724), ("potted_flowering_azalea_bush", 2724),
+    ("moss_block", 2724), ("moss_carpet", 2724), ("glow_lichen", 2724), ("big_dripleaf", 2724),
+    ("big_dripleaf_stem", 2724), ("small_dripleaf", 2724), ("spore_blossom", 2724),
+    ("cave_vines", 2724), ("cave_vines_plant", 2724), ("hanging_roots", 2724),
+    ("rooted_dirt", 2724), ("powder_snow", 2724), ("sculk_sensor", 2724), ("lightning_rod", 2724),
+    ("infested_deepslate", 2724),
+    ("light", 2724), ("lava_cauldron", 2724), ("powder_snow_cauldron", 2724),
+    ("copper_block", 2724), ("cut_copper", 2724), ("cut_copper_stairs", 2724),
+    ("cut_copper_slab", 2724), ("exposed_copper", 2724), ("exposed_cut_copper", 2724),
+    ("exposed_cut_copper_stairs", 2724), ("exposed_cut_copper_slab", 2724),
+    ("weathered_copper", 2724), ("weathered_cut_copper", 2724),
+    ("weathered_cut_copper_stairs", 2724), ("weathered_cut_copper_slab", 2724),
+    ("oxidized_copper", 2724), ("oxidized_cut_copper", 2724), ("oxidized_cut_copper_stairs", 2724),
+    ("oxidized_cut_copper_slab", 2724),
+    ("waxed_copper_block", 2724), ("waxed_cut_copper", 2724), ("waxed_cut_copper_stairs", 2724),
+    ("waxed_cut_copper_slab", 2724), ("waxed_exposed_copper", 2724),
+    ("waxed_exposed_cut_copper", 2724), ("waxed_exposed_cut_copper_stairs", 2724),
+    ("waxed_exposed_cut_copper_slab", 2724), ("waxed_weathered_copper", 2724),
+    ("waxed_weathered_cut_copper", 2724), ("waxed_weathered_cut_copper_stairs", 2724),
+    ("waxed_weathered_cut_copper_slab", 2724), ("waxed_oxidized_copper", 2724),
+    ("waxed_oxidized_cut_copper", 2724), ("waxed_oxidized_cut_copper_stairs", 2724),
+    ("waxed_oxidized_cut_copper_slab", 2724),
+    // 1.19
+    ("mud", 3105), ("packed_mud", 3105), ("mud_bricks", 3105), ("mud_brick_stairs", 3105),
+    ("mud_brick_slab", 3105), ("mud_brick_wall", 3105), ("sculk", 3105), ("sculk_vein", 3105),
+    ("sculk_catalyst", 3105), ("sculk_shrieker", 3105), ("frogspawn", 3105),
+    ("ochre_froglight", 3105), ("verdant_froglight", 3105), ("pearlescent_froglight", 3105),
+    ("reinforced_deepslate", 3105), ("muddy_mangrove_roots", 3105),
+    // 1.20
+    ("chiseled_bookshelf", 3463), ("decorated_pot", 3463), ("suspicious_sand", 3463),
+    ("suspicious_gravel", 3463), ("torchflower", 3463), ("torchflower_crop", 3463),
+    ("potted_torchflower", 3463), ("pitcher_plant", 3463), ("pitcher_crop", 3463),
+    ("pink_petals", 3463), ("sniffer_egg", 3463), ("calibrated_sculk_sensor", 3463),
+    ("piglin_head", 3463), ("piglin_wall_head", 3463),
+    ("bamboo_planks", 3463), ("bamboo_mosaic", 3463), ("bamboo_mosaic_stairs", 3463),
+    ("bamboo_mosaic_slab", 3463), ("bamboo_block", 3463), ("stripped_bamboo_block", 3463),
+    ("bamboo_stairs", 3463), ("bamboo_slab", 3463), ("bamboo_fence", 3463),
+    ("bamboo_fence_gate", 3463), ("bamboo_door", 3463), ("bamboo_trapdoor", 3463),
+    ("bamboo_button", 3463), ("bamboo_pressure_plate", 3463), ("bamboo_sign", 3463),
+    ("bamboo_wall_sign", 3463),
+    // 1.21
+    ("tuff_stairs", 3953), ("tuff_slab", 3953), ("tuff_wall", 3953), ("polished_tuff", 3953),
+    ("polished_tuff_stairs", 3953), ("polished_tuff_slab", 3953), ("polished_tuff_wall", 3953),
+    ("chiseled_tuff", 3953), ("tuff_bricks", 3953), ("tuff_brick_stairs", 3953),
+    ("tuff_brick_slab", 3953), ("tuff_brick_wall", 3953), ("chiseled_tuff_bricks", 3953),
+    ("crafter", 3953), ("trial_spawner", 3953), ("vault", 3953), ("heavy_core", 3953),
+    ("chiseled_copper", 3953), ("copper_grate", 3953), ("copper_bulb", 3953), ("copper_door", 3953),
+    ("copper_trapdoor", 3953), ("exposed_chiseled_copper", 3953), ("exposed_copper_grate", 3953),
+    ("exposed_copper_bulb", 3953), ("exposed_copper_door", 3953), ("exposed_copper_trapdoor", 3953),
+    ("weathered_chiseled_copper", 3953), ("weathered_copper_grate", 3953),
+    ("weathered_copper_bulb", 3953), ("weathered_copper_door", 3953),
+    ("weathered_copper_trapdoor", 3953), ("oxidized_chiseled_copper", 3953),
+    ("oxidized_copper_grate", 3953), ("oxidized_copper_bulb", 3953), ("oxidized_copper_door", 3953),
+    ("oxidized_copper_trapdoor", 3953),
+    ("waxed_chiseled_copper", 3953), ("waxed_copper_grate", 3953), ("waxed_copper_bulb", 3953),
+    ("waxed_copper_door", 3953), ("waxed_copper_trapdoor", 3953),
+    ("waxed_exposed_chiseled_copper", 3953), ("waxed_exposed_copper_grate", 3953),
+    ("waxed_exposed_copper_bulb", 3953), ("waxed_exposed_copper_door", 3953),
+    ("waxed_exposed_copper_trapdoor", 3953), ("waxed_weathered_chiseled_copper", 3953),
+    ("waxed_weathered_copper_grate", 3953), ("waxed_weathered_copper_bulb", 3953),
+    ("waxed_weathered_copper_door", 3953), ("waxed_weathered_copper_trapdoor", 3953),
+    ("waxed_oxidized_chiseled_copper", 3953), ("waxed_oxidized_copper_grate", 3953),
+    ("waxed_oxidized_copper_bulb", 3953), ("waxed_oxidized_copper_door", 3953),
+    ("waxed_oxidized_copper_trapdoor", 3953),
+];
+
+/// Wood families introduced after 1.13. Every block whose id contains `{wood}_` as prefix (or
+/// after `stripped_`/`potted_`) belongs to the family. Bamboo blocks are listed in
+/// `BLOCK_REGISTRY` instead, since `bamboo_sapling` and `potted_bamboo` exist since 1.14.
+const WOOD_FAMILIES: &[(&str, i32)] = &[
+    ("crimson", 2566),
+    ("warped", 2566),
+    ("mangrove", 3105),
+    ("cherry", 3463),
+];
+
+/// Builtin fallbacks of single blocks. Properties are kept if both ids end with the same word,
+/// like `_stairs` or `_slab`.
+const BUILTIN_FALLBACKS: &[(&str, &str)] = &[
+    ("stone_slab", "smooth_stone_slab"),
+    ("cornflower", "blue_orchid"),
+    ("lily_of_the_valley", "oxeye_daisy"),
+    ("wither_rose", "poppy"),
+    ("potted_cornflower", "potted_blue_orchid"),
+    ("potted_lily_of_the_valley", "potted_oxeye_daisy"),
+    ("potted_wither_rose", "potted_poppy"),
+    ("honey_block", "slime_block"),
+    ("honeycomb_block", "orange_concrete"),
+    ("soul_soil", "soul_sand"),
+    ("soul_torch", "torch"),
+    ("soul_wall_torch", "wall_torch"),
+    ("soul_lantern", "lantern"),
+    ("soul_campfire", "campfire"),
+    ("shroomlight", "glowstone"),
+    ("crying_obsidian", "obsidian"),
+    ("netherite_block", "diamond_block"),
+    ("ancient_debris", "netherrack"),
+    ("nether_gold_ore", "nether_quartz_ore"),
+    ("quartz_bricks", "quartz_block"),
+    ("chain", "iron_bars"),
+    ("cracked_nether_bricks", "nether_bricks"),
+    ("chiseled_nether_bricks", "nether_bricks"),
+    ("basalt", "stone"),
+    ("polished_basalt", "smooth_stone"),
+    ("blackstone", "cobblestone"),
+    ("polished_blackstone", "stone"),
+    ("polished_blackstone_bricks", "stone_bricks"),
+    ("cracked_polished_blackstone_bricks", "cracked_stone_bricks"),
+    ("chiseled_polished_blackstone", "chiseled_stone_bricks"),
+    ("gilded_blackstone", "gold_ore"),
+    ("crimson_nylium", "netherrack"),
+    ("warped_nylium", "netherrack"),
+    ("warped_wart_block", "nether_wart_block"),
+    ("deepslate", "stone"),
+    ("cobbled_deepslate", "cobblestone"),
+    ("polished_deepslate", "polished_andesite"),
+    ("deepslate_bricks", "stone_bricks"),
+    ("cracked_deepslate_bricks", "cracked_stone_bricks"),
+    ("deepslate_tiles", "stone_bricks"),
+    ("cracked_deepslate_tiles", "cracked_stone_bricks"),
+    ("chiseled_deepslate", "chiseled_stone_bricks"),
+    ("infested_deepslate", "infested_stone"),
+    ("copper_ore", "iron_ore"),
+    ("raw_iron_block", "iron_block"),
+    ("raw_gold_block", "gold_block"),
+    ("raw_copper_block", "iron_block"),
+    ("calcite", "diorite"),
+    ("tuff", "andesite"),
+    ("smooth_basalt", "stone"),
+    ("tinted_glass", "black_stained_glass"),
+    ("amethyst_block", "purpur_block"),
+    ("dripstone_block", "granite"),
+    ("moss_block", "green_wool"),
+    ("moss_carpet", "green_carpet"),
+    ("rooted_dirt", "coarse_dirt"),
+    ("powder_snow", "snow_block"),
+    ("lava_cauldron", "cauldron"),
+    ("powder_snow_cauldron", "cauldron"),
+    ("azalea_leaves", "oak_leaves"),
+    ("flowering_azalea_leaves", "oak_leaves"),
+    ("mud", "coarse_dirt"),
+    ("packed_mud", "dirt"),
+    ("mud_bricks", "bricks"),
+    ("reinforced_deepslate", "obsidian"),
+    ("muddy_mangrove_roots", "coarse_dirt"),
+    ("ochre_froglight", "glowstone"),
+    ("verdant_froglight", "glowstone"),
+    ("pearlescent_froglight", "glowstone"),
+    ("sculk", "black_wool"),
+    ("chiseled_bookshelf", "bookshelf"),
+    ("suspicious_sand", "sand"),
+    ("suspicious_gravel", "gravel"),
+    ("torchflower", "dandelion"),
+    ("potted_torchflower", "potted_dandelion"),
+    ("bamboo_planks", "oak_planks"),
+    ("bamboo_stairs", "oak_stairs"),
+    ("bamboo_slab", "oak_slab"),
+    ("bamboo_fence", "oak_fence"),
+    ("bamboo_fence_gate", "oak_fence_gate"),
+    ("bamboo_door", "oak_door"),
+    ("bamboo_trapdoor", "oak_trapdoor"),
+    ("bamboo_button", "oak_button"),
+    ("bamboo_pressure_plate", "oak_pressure_plate"),
+    ("bamboo_sign", "oak_sign"),
+    ("bamboo_wall_sign", "oak_wall_sign"),
+    ("bamboo_block", "oak_log"),
+    ("stripped_bamboo_block", "stripped_oak_log"),
+    ("bamboo_mosaic", "oak_planks"),
+    ("bamboo_mosaic_slab", "oak_slab"),
+    ("bamboo_mosaic_stairs", "oak_stairs"),
+    ("polished_tuff", "polished_andesite"),
+    ("chiseled_tuff", "chiseled_stone_bricks"),
+    ("tuff_bricks", "stone_bricks"),
+    ("chiseled_tuff_bricks", "chiseled_stone_bricks"),
+    ("heavy_core", "iron_block"),
+];
+
+/// Words of wood blocks that exist for oak, used to map a new wood family to oak.
+const WOOD_SUFFIXES: &[&str] = &[
+    "planks", "log", "wood", "leaves", "sapling", "door", "trapdoor", "fence", "fence_gate",
+    "sign", "wall_sign", "slab", "stairs", "button", "pressure_plate",
+];
+
+/// Entities introduced after 1.13, with the data version of the first release containing them.
+/// Renamed entities are handled by migrations instead.
+const ENTITY_REGISTRY: &[(&str, i32)] = &[
+    // 1.14
+    ("cat", 1952), ("fox", 1952), ("panda", 1952), ("pillager", 1952), ("ravager", 1952),
+    ("trader_llama", 1952), ("wandering_trader", 1952),
+    // 1.15
+    ("bee", 2225),
+    // 1.16
+    ("hoglin", 2566), ("piglin", 2566), ("strider", 2566), ("zoglin", 2566), ("piglin_brute", 2578),
+    // 1.17
+    ("axolotl", 2724), ("glow_squid", 2724), ("goat", 2724), ("glow_item_frame", 2724),
+    ("marker", 2724),
+    // 1.19
+    ("allay", 3105), ("frog", 3105), ("tadpole", 3105), ("warden", 3105), ("chest_boat", 3105),
+    ("block_display", 3337), ("item_display", 3337), ("text_display", 3337), ("interaction", 3337),
+    // 1.20
+    ("camel", 3463), ("sniffer", 3463),
+    // 1.21
+    ("armadillo", 3837), ("breeze", 3953), ("bogged", 3953), ("wind_charge", 3953),
+    ("breeze_wind_charge", 3953), ("ominous_item_spawner", 3953),
+];
+
+/// Block entity types whose tags can be kept when a block of the first type is replaced by a
+/// block of the second type. Only the id of block entity is changed.
+const COMPATIBLE_BLOCK_ENTITIES: &[(&str, &str)] = &[
+    ("hanging_sign", "sign"),
+    ("trapped_chest", "chest"),
+    ("blast_furnace", "furnace"),
+    ("smoker", "furnace"),
+];
+
+/// Blocks with block entity whose type can't be told from the suffix of id, with the type of
+/// their block entity (without namespace).
+const BLOCK_ENTITY_BLOCKS: &[(&str, &str)] = &[
+    ("chest", "chest"), ("trapped_chest", "trapped_chest"), ("ender_chest", "ender_chest"),
+    ("furnace", "furnace"), ("blast_furnace", "blast_furnace"), ("smoker", "smoker"),
+    ("barrel", "barrel"), ("beacon", "beacon"), ("bell", "bell"), ("brewing_stand", "brewing_stand"),
+    ("conduit", "conduit"), ("comparator", "comparator"), ("daylight_detector", "daylight_detector"),
+    ("dispenser", "dispenser"), ("dropper", "dropper"), ("enchanting_table", "enchanting_table"),
+    ("end_gateway", "end_gateway"), ("end_portal", "end_portal"), ("hopper", "hopper"),
+    ("jigsaw", "jigsaw"), ("jukebox", "jukebox"), ("lectern", "lectern"),
+    ("structure_block", "structure_block"), ("sculk_sensor", "sculk_sensor"),
+    ("calibrated_sculk_sensor", "calibrated_sculk_sensor"), ("sculk_catalyst", "sculk_catalyst"),
+    ("sculk_shrieker", "sculk_shrieker"), ("chiseled_bookshelf", "chiseled_bookshelf"),
+    ("decorated_pot", "decorated_pot"), ("crafter", "crafter"), ("trial_spawner", "trial_spawner"),
+    ("vault", "vault"), ("campfire", "campfire"), ("soul_campfire", "campfire"),
+    ("command_block", "command_block"), ("chain_command_block", "command_block"),
+    ("repeating_command_block", "command_block"), ("spawner", "mob_spawner"),
+    ("moving_piston", "piston"), ("bee_nest", "beehive"), ("beehive", "beehive"),
+    ("suspicious_sand", "brushable_block"), ("suspicious_gravel", "brushable_block"),
+];
+
+/// Returns the block entity type (without namespace) of block `id`, or `None` if the block has no
+/// block entity.
+fn block_entity_type(id: &str) -> Option<&str> {
+    if id.ends_with("_hanging_sign") {
+        return Some("hanging_sign");
+    }
+    if id.ends_with("_sign") {
+        return Some("sign");
+    }
+    if id.ends_with("shulker_box") {
+        return Some("shulker_box");
+    }
+    if id.ends_with("_bed") {
+        return Some("bed");
+    }
+    if id.ends_with("_banner") {
+        return Some("banner");
+    }
+    if (id.ends_with("_skull") || id.ends_with("_head")) && id != "piston_head" {
+        return Some("skull");
+    }
+    return BLOCK_ENTITY_BLOCKS.iter().find(|(block, _)| *block == id).map(|(_, be)| *be);
+}
+
+/// Returns the data version of the first release containing block `id`(without namespace), or
+/// `None` if the block exists in 1.13, or it's unknown.
+pub fn block_introduced_version(id: &str) -> Option<i32> {
+    if let Some((_, version)) = BLOCK_REGISTRY.iter().find(|(name, _)| *name == id) {
+        return Some(*version);
+    }
+    if id.ends_with("_hanging_sign") {
+        return Some(DataVersion::Java_1_20 as i32);
+    }
+    if id == "candle" || id.ends_with("_candle") || id.ends_with("candle_cake") {
+        return Some(DataVersion::Java_1_17 as i32);
+    }
+    let stripped = id.strip_prefix("stripped_")
+        .or_else(|| id.strip_prefix("potted_"))
+        .unwrap_or(id);
+    for (wood, version) in WOOD_FAMILIES {
+        if stripped.starts_with(wood) && stripped[wood.len()..].starts_with('_') {
+            return Some(*version);
+        }
+    }
+    return None;
+}
+
+/// How to handle blocks that doesn't exist in the target version
+#[derive(Debug, Clone)]
+pub struct DowngradePolicy {
+    /// Fallbacks specified by user, the key is block id without namespace, like `cherry_planks`.
+    /// User fallbacks are checked before builtin ones.
+    pub fallbacks: HashMap<String, Block>,
+    /// Whether to use builtin fallbacks
+    pub use_builtin_fallbacks: bool,
+    /// The block to use if no fallback is found. If it's `None`, downgrading fails instead.
+    pub default_block: Option<Block>,
+}
+
+impl Default for DowngradePolicy {
+    fn default() -> Self {
+        return Self {
+            fallbacks: HashMap::new(),
+            use_builtin_fallbacks: true,
+            default_block: Some(Block::air()),
+        };
+    }
+}
+
+/// A block in palette that was replaced in downgrading
+#[derive(Debug, Clone)]
+pub struct BlockSubstitution {
+    /// Name of the region
+    pub region_name: String,
+    /// The block before downgrading
+    pub original: Block,
+    /// The block after downgrading, before reverting renames
+    pub replacement: Block,
+    /// Number of blocks replaced
+    pub count: u64,
+}
+
+/// Every substitution made in downgrading
+#[derive(Debug, Clone, Default)]
+pub struct DowngradeReport {
+    pub substitutions: Vec<BlockSubstitution>,
+    /// Number of block entities removed because their blocks are replaced by blocks with another
+    /// kind of block entity, or without block entity
+    pub removed_block_entities: u64,
+    /// Number of entities removed because they don't exist in the target version
+    pub removed_entities: u64,
+}
+
+fn last_word(id: &str) -> &str {
+    return match id.rfind('_') {
+        Some(idx) => &id[idx + 1..],
+        None => id,
+    };
+}
+
+fn builtin_fallback(block: &Block) -> Option<Block> {
+    let id = block.id.as_str();
+    let mut fallback_id: Option<String> = None;
+    if let Some((_, to)) = BUILTIN_FALLBACKS.iter().find(|(from, _)| *from == id) {
+        fallback_id = Some(to.to_string());
+    } else if let Some(ore) = id.strip_prefix("deepslate_") {
+        if ore.ends_with("_ore") {
+            fallback_id = Some(ore.to_string());
+        }
+    } else if id.ends_with("_wall_hanging_sign") {
+        fallback_id = Some("oak_wall_sign".to_string());
+    } else if id.ends_with("_hanging_sign") {
+        fallback_id = Some("oak_sign".to_string());
+    }
+
+    if fallback_id.is_none() {
+        let (prefix, rest) = match id.strip_prefix("stripped_") {
+            Some(rest) => ("stripped_", rest),
+            None => ("", id),
+        };
+        for (wood, _) in WOOD_FAMILIES {
+            let suffix = match rest.strip_prefix(wood).and_then(|s| s.strip_prefix('_')) {
+                Some(s) => s,
+                None => continue,
+            };
+            let suffix = match suffix {
+                "stem" => "log",
+                "hyphae" => "wood",
+                "propagule" => "sapling",
+                s => s,
+            };
+            if WOOD_SUFFIXES.contains(&suffix) {
+                fallback_id = Some(format!("{prefix}oak_{suffix}"));
+            }
+            break;
+        }
+    }
+
+    if fallback_id.is_none() {
+        for shape in ["stairs", "slab", "wall", "button", "pressure_plate"] {
+            if id.ends_with(&format!("_{shape}")) {
+                fallback_id = Some(match shape {
+                    "stairs" => "stone_brick_stairs",
+                    "slab" => "stone_brick_slab",
+                    "wall" => "cobblestone_wall",
+                    "button" => "stone_button",
+                    _ => "stone_pressure_plate",
+                }.to_string());
+                break;
+            }
+        }
+    }
+
+    let fallback_id = fallback_id?;
+    let mut result = Block::empty_block();
+    result.namespace = block.namespace.clone();
+    if last_word(&fallback_id) == last_word(id) {
+        result.attributes = block.attributes.clone();
+        if id.ends_with("_hanging_sign") {
+            result.attributes.remove("attached");
+        }
+    }
+    result.id = fallback_id;
+    return Some(result);
+}
+
+/// Blocks introduced after `current` are not checked, they must be blocks with the same id in
+/// older versions, like `stone_slab` in 1.13.
+fn exists_in(block: &Block, current: i32, target: i32) -> bool {
+    if !block.namespace.is_empty() && block.namespace != "minecraft" {
+        return true;
+    }
+    return match block_introduced_version(&block.id) {
+        Some(version) => version <= target || version > current,
+        None => true,
+    };
+}
+
+/// Find a replacement of `block` that exists in `target`.
+fn find_fallback(block: &Block, current: i32, target: i32, policy: &DowngradePolicy) -> Option<Block> {
+    let mut candidate = block.clone();
+    // fallbacks can be chained, like deepslate_copper_ore -> copper_ore -> iron_ore
+    for _ in 0..8 {
+        if exists_in(&candidate, current, target) {
+            return Some(candidate);
+        }
+        let mut next = policy.fallbacks.get(&candidate.id).cloned();
+        if next.is_none() && policy.use_builtin_fallbacks {
+            next = builtin_fallback(&candidate);
+        }
+        candidate = next?;
+    }
+    return None;
+}
+
+fn downgrade_region(region: &mut Region, current: i32, target: i32, policy: &DowngradePolicy,
+                    report: &mut DowngradeReport) -> Result<(), Error> {
+    let before = region.entities.len();
+    region.entities.retain(|entity| {
+        let id = match entity.tags.get("id") {
+            Some(Value::String(id)) => id.strip_prefix("minecraft:").unwrap_or(id),
+            _ => return true,
+        };
+        return match ENTITY_REGISTRY.iter().find(|(name, _)| *name == id) {
+            Some((_, version)) => *version <= target || *version > current,
+            None => true,
+        };
+    });
+    report.removed_entities += (before - region.entities.len()) as u64;
+
+    let mut replaced = vec![false; region.palette.len()];
+    let mut original_be_types: Vec<Option<String>> = vec![None; region.palette.len()];
+    for (idx, blk) in region.palette.iter_mut().enumerate() {
+        if exists_in(blk, current, target) {
+            continue;
+        }
+        let replacement = match find_fallback(blk, current, target, policy) {
+            Some(r) => r,
+            None => match &policy.default_block {
+                Some(default) => default.clone(),
+                None => return Err(Error::InvalidTargetVersion {
+                    current,
+                    target,
+                    reason: format!("block {} in region {} doesn't exist in target version, and has no fallback", blk.full_id(), region.name),
+                }),
+            },
+        };
+        report.substitutions.push(BlockSubstitution {
+            region_name: region.name.clone(),
+            original: blk.clone(),
+            replacement: replacement.clone(),
+            count: 0,
+        });
+        original_be_types[idx] = block_entity_type(&blk.id).map(|t| t.to_string());
+        *blk = replacement;
+        replaced[idx] = true;
+    }
+    if !replaced.contains(&true) {
+        return Ok(());
+    }
+
+    let first_sub = report.substitutions.len() - replaced.iter().filter(|r| **r).count();
+    let mut counter = vec![0u64; region.palette.len()];
+    for idx in region.array_yzx.iter() {
+        counter[*idx as usize] += 1;
+    }
+    let mut sub_idx = first_sub;
+    for (idx, r) in replaced.iter().enumerate() {
+        if *r {
+            report.substitutions[sub_idx].count = counter[idx];
+            sub_idx += 1;
+        }
+    }
+
+    // block entities of replaced blocks are kept only if the new block has the same kind of block
+    // entity, or a compatible one
+    let palette = &region.palette;
+    let array = &region.array_yzx;
+    let before = region.block_entities.len();
+    region.block_entities.retain(|pos, be| {
+        let idx = array[[pos[1] as usize, pos[2] as usize, pos[0] as usize]] as usize;
+        if !replaced[idx] {
+            return true;
+        }
+        let (original, new) = match (&original_be_types[idx], block_entity_type(&palette[idx].id)) {
+            (Some(original), Some(new)) => (original, new),
+            _ => return false,
+        };
+        if original == new {
+            return true;
+        }
+        if COMPATIBLE_BLOCK_ENTITIES.contains(&(original.as_str(), new)) {
+            be.tags.insert("id".to_string(), Value::String(format!("minecraft:{new}")));
+            return true;
+        }
+        return false;
+    });
+    report.removed_block_entities += (before - region.block_entities.len()) as u64;
+    return Ok(());
+}
+
+impl Schematic {
+    /// Downgrade blocks, block entities and entities from `self.metadata.mc_data_version` to
+    /// `target`. Blocks introduced after `target` are replaced according to `policy`, entities
+    /// introduced after `target` are removed, then migrations are reverted step by step. Every
+    /// replaced block is listed in the returned report. Format versions in metadata (like
+    /// litematica version) are reset to match `target`.
+    pub fn downgrade_to(&mut self, target: DataVersion, policy: &DowngradePolicy) -> Result<DowngradeReport, Error> {
+        let current = self.metadata.mc_data_version;
+        let target = target as i32;
+        if target < DataVersion::Java_1_13 as i32 {
+            return Err(Error::UnsupportedVersion { data_version_i32: target });
+        }
+        if target > current {
+            return Err(Error::InvalidTargetVersion {
+                current,
+                target,
+                reason: "target version is higher than current version, use upgrade_to instead".to_string(),
+            });
+        }
+
+        let mut report = DowngradeReport::default();
+        for reg in &mut self.regions {
+            downgrade_region(reg, current, target, policy, &mut report)?;
+        }
+
+        for migration in migrations().iter().rev() {
+            if migration.data_version > current || migration.data_version <= target {
+                continue;
+            }
+            for reg in &mut self.regions {
+                if let Some(revert) = migration.revert_block {
+                    for blk in &mut reg.palette {
+                        revert(blk);
+                    }
+                }
+                if let Some(revert) = migration.revert_block_entity {
+                    for be in reg.block_entities.values_mut() {
+                        revert(be);
+                    }
+                }
+                if let Some(revert) = migration.revert_entity {
+                    for entity in &mut reg.entities {
+                        revert(entity);
+                    }
+                }
+            }
+        }
+        for reg in &mut self.regions {
+            reg.merge_duplicated_blocks();
+        }
+
+        self.metadata.set_data_version_i32(target);
+        return Ok(report);
+    }
+}
+
+#[test]
+fn test_block_entity_blocks_registered() {
+    // blocks with block entity that exist in 1.13
+    const EXISTING: &[&str] = &["chest", "trapped_chest", "ender_chest", "furnace", "beacon",
+        "brewing_stand", "conduit", "comparator", "daylight_detector", "dispenser", "dropper",
+        "enchanting_table", "end_gateway", "end_portal", "hopper", "jukebox", "structure_block",
+        "command_block", "chain_command_block", "repeating_command_block", "spawner", "moving_piston"];
+    for (id, _) in BLOCK_ENTITY_BLOCKS {
+        assert_eq!(block_introduced_version(id).is_some(), !EXISTING.contains(id), "{id}");
+    }
+    assert_eq!(block_introduced_version("bamboo_sapling"), Some(1952));
+    assert_eq!(block_introduced_version("potted_bamboo"), Some(1952));
+    assert_eq!(block_introduced_version("bamboo_planks"), Some(3463));
+    assert_eq!(block_introduced_version("tuff_brick_wall"), Some(3953));
+}
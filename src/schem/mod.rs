@@ -21,9 +21,13 @@ pub mod world_edit13;
 pub mod litematica;
 
 pub mod vanilla_structure;
+pub mod bedrock_structure;
 pub mod mc_version;
 pub mod common;
 pub mod schem_slice;
+pub mod upgrade;
+pub mod downgrade;
+pub mod detect;
 
 
 use std::cmp::max;
@@ -254,6 +258,24 @@ impl VanillaStructureMetaData {
     }
 }
 
+/// Metadata of bedrock structure
+#[derive(Debug, Clone)]
+#[allow(dead_code)]
+pub struct BedrockStructureMetaData {
+    pub format_version: i32,
+    /// Position of the structure in the world where it's saved
+    pub structure_world_origin: [i32; 3],
+}
+
+impl Default for BedrockStructureMetaData {
+    fn default() -> BedrockStructureMetaData {
+        return BedrockStructureMetaData {
+            format_version: 1,
+            structure_world_origin: [0, 0, 0],
+        };
+    }
+}
+
 /// Raw metadata of different formats
 #[derive(Debug)]
 pub enum RawMetaData {
@@ -261,6 +283,7 @@ pub enum RawMetaData {
     WE12(WE12MetaData),
     WE13(WE13MetaData),
     VanillaStructure(VanillaStructureMetaData),
+    BedrockStructure(BedrockStructureMetaData),
 }
 
 /// Intermediate representation via different metadata formats
@@ -305,6 +328,20 @@ impl MetaDataIR {
         return Self::from_data_version_i32(version as i32);
     }
 
+    /// Set data version, and reset litematica version, litematica sub version and world edit
+    /// schem version to the ones used by that minecraft version, so that converted schematics
+    /// won't be saved with format versions that the target version can't load.
+    pub fn set_data_version_i32(&mut self, version: i32) {
+        self.mc_data_version = version;
+        if let Some(lite_version) = LitematicaMetaData::data_version_to_lite_version(version) {
+            self.litematica_version = lite_version;
+            self.litematica_subversion = LitematicaMetaData::data_version_to_lite_subversion(version);
+        }
+        if let Ok(we13) = WE13MetaData::from_data_version_i32(version) {
+            self.schem_version = we13.version;
+        }
+    }
+
     pub fn from_data_version_i32(version: i32) -> Result<MetaDataIR, Error> {
         use std::time::{SystemTime, UNIX_EPOCH};
         let time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as i64;
@@ -494,6 +531,45 @@ impl Schematic {
         return result;
     }
 
+    /// Trim every region to the bounding box of blocks for which `keep` returns true, and move
+    /// regions so that the schematic starts at \[0, 0, 0\]. Regions without any kept block are
+    /// removed, unless all regions are empty.
+    pub fn trim(&mut self, keep: impl Fn(&Block) -> bool) -> Result<(), Error> {
+        let mut kept = Vec::with_capacity(self.regions.len());
+        for reg in &mut self.regions {
+            kept.push(reg.trim(&keep)?);
+        }
+        if kept.contains(&true) {
+            let mut idx = 0;
+            self.regions.retain(|_| {
+                idx += 1;
+                return kept[idx - 1];
+            });
+        }
+        if self.regions.is_empty() {
+            return Ok(());
+        }
+
+        let mut min_offset = [i32::MAX; 3];
+        for reg in &self.regions {
+            for (min, offset) in min_offset.iter_mut().zip(reg.offset) {
+                *min = (*min).min(offset);
+            }
+        }
+        for reg in &mut self.regions {
+            for (offset, min) in reg.offset.iter_mut().zip(min_offset) {
+                *offset -= min;
+            }
+        }
+        for dim in 0..3 {
+            self.metadata.schem_offset[dim] += min_offset[dim];
+            if let Some(we_offset) = &mut self.metadata.schem_we_offset {
+                we_offset[dim] += min_offset[dim];
+            }
+        }
+        return Ok(());
+    }
+
     /// The volume of whole schematic
     pub fn volume(&self) -> u64 {
         let mut result: u64 = 1;
@@ -575,12 +651,23 @@ impl Schematic {
             let (schem, raw, ..) = Self::from_world_edit_12_file(filename, &WorldEdit12LoadOption::default())?;
             return Ok((schem, RawMetaData::WE12(raw)));
         }
+        if filename.ends_with(".mcstructure") {
+            let (schem, raw) = Self::from_bedrock_structure_file(filename, &BedrockStructureLoadOption::default())?;
+            return Ok((schem, RawMetaData::BedrockStructure(raw)));
+        }
 
         let split = filename.split(".");
         let extension = split.last().unwrap_or_else(|| "");
 
-
-        return Err(Error::UnrecognisedExtension { extension: extension.to_string() });
+        // unknown extension, try detecting format by content
+        let bytes = match std::fs::read(filename) {
+            Ok(b) => b,
+            Err(e) => return Err(Error::FileOpenError(e)),
+        };
+        return match Self::from_bytes_auto(&bytes) {
+            Err(Error::UnrecognisedFormat) => Err(Error::UnrecognisedExtension { extension: extension.to_string() }),
+            other => other,
+        };
     }
 
     /// Save schematic to file.
@@ -594,6 +681,12 @@ impl Schematic {
         if filename.ends_with(".schem") {
             return self.save_world_edit_13_file(filename, &WorldEdit13SaveOption::default());
         }
+        if filename.ends_with(".schematic") {
+            return self.save_world_edit_12_file(filename, &WorldEdit12SaveOption::default());
+        }
+        if filename.ends_with(".mcstructure") {
+            return self.save_bedrock_structure_file(filename, &BedrockStructureSaveOption::default());
+        }
 
         let split = filename.split(".");
         let extension = split.last().unwrap_or_else(|| "");
@@ -850,3 +943,63 @@ impl Default for WorldEdit12LoadOption {
     }
 }
 
+
+/// Options to save world edit 1.12-
+#[derive(Debug)]
+pub struct WorldEdit12SaveOption {
+    /// Level of gzip compression, 0<= level <=9.
+    pub compress_level: Compression,
+    /// If the schematic contains multiple regions, some positions may not be covered by any region,
+    /// but `.schematic` can have only one region, so we must define a block for these positions.
+    /// Air by default.
+    pub background_block: CommonBlock,
+    /// Number id and damage of blocks that are not vanilla blocks in 1.12-, like blocks of forge mods.
+    /// Number ids can be up to 4095, ids greater than 255 are stored in `AddBlocks`. Blocks in this
+    /// map are searched before vanilla blocks.
+    pub custom_number_ids: HashMap<Block, (u16, u8)>,
+}
+
+impl Default for WorldEdit12SaveOption {
+    fn default() -> Self {
+        return WorldEdit12SaveOption {
+            compress_level: Compression::best(),
+            background_block: CommonBlock::Air,
+            custom_number_ids: HashMap::new(),
+        }
+    }
+}
+
+/// Options to load bedrock structure
+#[derive(Debug)]
+pub struct BedrockStructureLoadOption {
+    /// Block of positions whose block index is -1. Bedrock structure uses -1 for structure void.
+    pub background_block: CommonBlock,
+}
+
+impl Default for BedrockStructureLoadOption {
+    fn default() -> Self {
+        return BedrockStructureLoadOption {
+            background_block: CommonBlock::StructureVoid,
+        };
+    }
+}
+
+/// Options to save bedrock structure
+#[derive(Debug)]
+pub struct BedrockStructureSaveOption {
+    /// If the schematic contains multiple regions, some positions may not be covered by any region,
+    /// and these positions will be filled with this block. Structure void by default, which is
+    /// saved as -1.
+    pub background_block: CommonBlock,
+    /// Block version written to block palette, see `bedrock_block::BEDROCK_BLOCK_VERSION_1_21_0`
+    pub block_version: i32,
+}
+
+impl Default for BedrockStructureSaveOption {
+    fn default() -> Self {
+        return BedrockStructureSaveOption {
+            background_block: CommonBlock::StructureVoid,
+            block_version: crate::bedrock_block::BEDROCK_BLOCK_VERSION_1_21_0,
+        };
+    }
+}
@@ -0,0 +1,345 @@
+/*
+mc_schem is a rust library to generate, load, manipulate and save minecraft schematic files.
+Copyright (C) 2024  joseph
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use std::collections::HashMap;
+use fastnbt::Value;
+use crate::block::Block;
+use crate::error::Error;
+use crate::region::{BlockEntity, Entity};
+use crate::schem::{DataVersion, Schematic};
+
+/// A migration step, like a data fixer in minecraft. It's applied when upgrading from a data
+/// version lower than `data_version` to a data version no lower than `data_version`, and reverted
+/// when downgrading in the opposite direction.
+#[derive(Debug, Clone, Copy)]
+pub struct Migration {
+    /// The data version where this change happened
+    pub data_version: i32,
+    /// What this migration does
+    pub description: &'static str,
+    /// Rewrite a block in palette
+    pub fix_block: Option<fn(&mut Block)>,
+    /// Rewrite tags of a block entity
+    pub fix_block_entity: Option<fn(&mut BlockEntity)>,
+    /// Rewrite tags of an entity
+    pub fix_entity: Option<fn(&mut Entity)>,
+    /// Undo `fix_block`
+    pub revert_block: Option<fn(&mut Block)>,
+    /// Undo `fix_block_entity`
+    pub revert_block_entity: Option<fn(&mut BlockEntity)>,
+    /// Undo `fix_entity`
+    pub revert_entity: Option<fn(&mut Entity)>,
+}
+
+/// All migrations sorted by data version. Only changes after 1.13 are recorded, upgrading from
+/// 1.12 or lower is not supported.
+///
+/// This is not a complete data fixer. Only renamed or split blocks, the wall shape change, the
+/// zombified piglin rename and the sign text format are covered. Other changes between versions,
+/// like item stack formats in block entities or new default properties, are kept as they are.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        data_version: DataVersion::Snapshot_18w43a as i32,
+        description: "Rename sign to oak_sign and stone_slab to smooth_stone_slab",
+        fix_block: Some(fix_1901_block),
+        fix_block_entity: None,
+        fix_entity: None,
+        revert_block: Some(revert_1901_block),
+        revert_block_entity: None,
+        revert_entity: None,
+    },
+    Migration {
+        data_version: 2502,
+        description: "Wall connections become none/low/tall",
+        fix_block: Some(fix_2502_wall),
+        fix_block_entity: None,
+        fix_entity: None,
+        revert_block: Some(revert_2502_wall),
+        revert_block_entity: None,
+        revert_entity: None,
+    },
+    Migration {
+        data_version: 2509,
+        description: "Rename zombie_pigman to zombified_piglin",
+        fix_block: None,
+        fix_block_entity: None,
+        fix_entity: Some(fix_2509_entity),
+        revert_block: None,
+        revert_block_entity: None,
+        revert_entity: Some(revert_2509_entity),
+    },
+    Migration {
+        data_version: 2679,
+        description: "Split filled cauldron into water_cauldron",
+        fix_block: Some(fix_2679_cauldron),
+        fix_block_entity: None,
+        fix_entity: None,
+        revert_block: Some(revert_2679_cauldron),
+        revert_block_entity: None,
+        revert_entity: None,
+    },
+    Migration {
+        data_version: 2680,
+        description: "Rename grass_path to dirt_path",
+        fix_block: Some(fix_2680_block),
+        fix_block_entity: None,
+        fix_entity: None,
+        revert_block: Some(revert_2680_block),
+        revert_block_entity: None,
+        revert_entity: None,
+    },
+    Migration {
+        data_version: 3439,
+        description: "Signs have front and back text",
+        fix_block: None,
+        fix_block_entity: Some(fix_3439_sign),
+        fix_entity: None,
+        revert_block: None,
+        revert_block_entity: Some(revert_3439_sign),
+        revert_entity: None,
+    },
+    Migration {
+        data_version: 3692,
+        description: "Rename grass to short_grass",
+        fix_block: Some(fix_3692_block),
+        fix_block_entity: None,
+        fix_entity: None,
+        revert_block: Some(revert_3692_block),
+        revert_block_entity: None,
+        revert_entity: None,
+    },
+];
+
+/// Get all migrations sorted by data version. See `MIGRATIONS` for what is covered.
+pub fn migrations() -> &'static [Migration] {
+    return MIGRATIONS;
+}
+
+fn is_vanilla(block: &Block) -> bool {
+    return block.namespace.is_empty() || block.namespace == "minecraft";
+}
+
+fn rename_block(block: &mut Block, from: &str, to: &str) {
+    if is_vanilla(block) && block.id == from {
+        block.id = to.to_string();
+    }
+}
+
+fn fix_1901_block(block: &mut Block) {
+    rename_block(block, "sign", "oak_sign");
+    rename_block(block, "wall_sign", "oak_wall_sign");
+    rename_block(block, "stone_slab", "smooth_stone_slab");
+}
+
+fn revert_1901_block(block: &mut Block) {
+    rename_block(block, "oak_sign", "sign");
+    rename_block(block, "oak_wall_sign", "wall_sign");
+    rename_block(block, "smooth_stone_slab", "stone_slab");
+}
+
+fn fix_2502_wall(block: &mut Block) {
+    if !is_vanilla(block) || !block.id.ends_with("_wall") {
+        return;
+    }
+    for dir in ["north", "south", "east", "west"] {
+        if let Some(value) = block.attributes.get_mut(dir) {
+            if value == "true" {
+                *value = "low".to_string();
+            } else if value == "false" {
+                *value = "none".to_string();
+            }
+        }
+    }
+}
+
+fn revert_2502_wall(block: &mut Block) {
+    if !is_vanilla(block) || !block.id.ends_with("_wall") {
+        return;
+    }
+    for dir in ["north", "south", "east", "west"] {
+        if let Some(value) = block.attributes.get_mut(dir) {
+            if value == "low" || value == "tall" {
+                *value = "true".to_string();
+            } else if value == "none" {
+                *value = "false".to_string();
+            }
+        }
+    }
+}
+
+fn fix_2509_entity(entity: &mut Entity) {
+    if let Some(Value::String(id)) = entity.tags.get_mut("id") {
+        if id == "minecraft:zombie_pigman" {
+            *id = "minecraft:zombified_piglin".to_string();
+        }
+    }
+}
+
+fn revert_2509_entity(entity: &mut Entity) {
+    if let Some(Value::String(id)) = entity.tags.get_mut("id") {
+        if id == "minecraft:zombified_piglin" {
+            *id = "minecraft:zombie_pigman".to_string();
+        }
+    }
+}
+
+fn fix_2679_cauldron(block: &mut Block) {
+    if !is_vanilla(block) || block.id != "cauldron" {
+        return;
+    }
+    if let Some(level) = block.attributes.remove("level") {
+        if level != "0" {
+            block.id = "water_cauldron".to_string();
+            block.attributes.insert("level".to_string(), level);
+        }
+    }
+}
+
+fn revert_2679_cauldron(block: &mut Block) {
+    if !is_vanilla(block) {
+        return;
+    }
+    if block.id == "water_cauldron" {
+        block.id = "cauldron".to_string();
+    } else if block.id == "cauldron" {
+        block.attributes.insert("level".to_string(), "0".to_string());
+    }
+}
+
+fn fix_2680_block(block: &mut Block) {
+    rename_block(block, "grass_path", "dirt_path");
+}
+
+fn revert_2680_block(block: &mut Block) {
+    rename_block(block, "dirt_path", "grass_path");
+}
+
+fn fix_3439_sign(be: &mut BlockEntity) {
+    let tags = &mut be.tags;
+    if !tags.contains_key("Text1") || tags.contains_key("front_text") {
+        return;
+    }
+    let mut messages = Vec::with_capacity(4);
+    for idx in 1..=4 {
+        let text = match tags.remove(&format!("Text{idx}")) {
+            Some(Value::String(t)) => t,
+            _ => "\"\"".to_string(),
+        };
+        messages.push(Value::String(text));
+    }
+    let color = tags.remove("Color").unwrap_or(Value::String("black".to_string()));
+    let glowing = tags.remove("GlowingText").unwrap_or(Value::Byte(0));
+
+    let front = HashMap::from([
+        ("messages".to_string(), Value::List(messages)),
+        ("color".to_string(), color),
+        ("has_glowing_text".to_string(), glowing),
+    ]);
+    let back = HashMap::from([
+        ("messages".to_string(), Value::List(vec![Value::String("\"\"".to_string()); 4])),
+        ("color".to_string(), Value::String("black".to_string())),
+        ("has_glowing_text".to_string(), Value::Byte(0)),
+    ]);
+    tags.insert("front_text".to_string(), Value::Compound(front));
+    tags.insert("back_text".to_string(), Value::Compound(back));
+    tags.insert("is_waxed".to_string(), Value::Byte(0));
+}
+
+fn revert_3439_sign(be: &mut BlockEntity) {
+    let tags = &mut be.tags;
+    let front = match tags.remove("front_text") {
+        Some(Value::Compound(front)) => front,
+        _ => return,
+    };
+    tags.remove("back_text");
+    tags.remove("is_waxed");
+    let messages = match front.get("messages") {
+        Some(Value::List(messages)) => messages.clone(),
+        _ => Vec::new(),
+    };
+    for idx in 0..4 {
+        let text = match messages.get(idx) {
+            Some(Value::String(t)) => t.clone(),
+            _ => "\"\"".to_string(),
+        };
+        tags.insert(format!("Text{}", idx + 1), Value::String(text));
+    }
+    tags.insert("Color".to_string(), front.get("color").cloned().unwrap_or(Value::String("black".to_string())));
+    tags.insert("GlowingText".to_string(), front.get("has_glowing_text").cloned().unwrap_or(Value::Byte(0)));
+}
+
+fn fix_3692_block(block: &mut Block) {
+    rename_block(block, "grass", "short_grass");
+}
+
+fn revert_3692_block(block: &mut Block) {
+    rename_block(block, "short_grass", "grass");
+}
+
+impl Schematic {
+    /// Upgrade blocks, block entities and entities from `self.metadata.mc_data_version` to `target`
+    /// by applying migrations step by step, then set data version to `target`. Upgrading from
+    /// versions before 1.13 is not supported.
+    ///
+    /// Only the changes listed by [`migrations`] are converted, everything else is copied as it is,
+    /// so the result may still contain data that `target` doesn't understand. Blocks that become
+    /// identical after renaming are merged in palette.
+    pub fn upgrade_to(&mut self, target: DataVersion) -> Result<(), Error> {
+        let current = self.metadata.mc_data_version;
+        let target = target as i32;
+        if current < DataVersion::Java_1_13 as i32 {
+            return Err(Error::UnsupportedVersion { data_version_i32: current });
+        }
+        if target < current {
+            return Err(Error::InvalidTargetVersion {
+                current,
+                target,
+                reason: "target version is lower than current version, use downgrade_to instead".to_string(),
+            });
+        }
+
+        for migration in MIGRATIONS {
+            if migration.data_version <= current || migration.data_version > target {
+                continue;
+            }
+            for reg in &mut self.regions {
+                if let Some(fix) = migration.fix_block {
+                    for blk in &mut reg.palette {
+                        fix(blk);
+                    }
+                }
+                if let Some(fix) = migration.fix_block_entity {
+                    for be in reg.block_entities.values_mut() {
+                        fix(be);
+                    }
+                }
+                if let Some(fix) = migration.fix_entity {
+                    for entity in &mut reg.entities {
+                        fix(entity);
+                    }
+                }
+            }
+        }
+        for reg in &mut self.regions {
+            reg.merge_duplicated_blocks();
+        }
+
+        self.metadata.set_data_version_i32(target);
+        return Ok(());
+    }
+}
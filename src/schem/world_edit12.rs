@@ -20,13 +20,14 @@ use std::collections::HashMap;
 use std::fs::File;
 use std::mem;
 use fastnbt::Value;
+use flate2::GzBuilder;
 use flate2::read::GzDecoder;
 use ndarray::Array3;
 use crate::block::Block;
 use crate::error::Error;
 use crate::old_block::OldBlockParseError;
-use crate::region::{BlockEntity, Region};
-use crate::schem::{common, id_of_nbt_tag, MetaDataIR, Schematic, WE12MetaData, WorldEdit12LoadOption};
+use crate::region::{BlockEntity, Region, WorldSlice};
+use crate::schem::{common, id_of_nbt_tag, MetaDataIR, Schematic, WE12MetaData, WorldEdit12LoadOption, WorldEdit12SaveOption};
 use crate::{unwrap_opt_tag, unwrap_tag};
 
 
@@ -326,4 +327,188 @@ impl Region {
 
         return Ok((region, id_damage_array));
     }
-}
\ No newline at end of file
+}
+
+impl Schematic {
+    /// Get metadata of `.schematic`
+    pub fn metadata_world_edit_12(&self) -> Result<WE12MetaData, Error> {
+        let shape = self.shape();
+        for sz in shape {
+            if sz < 0 {
+                return Err(Error::NegativeSize { size: shape, region_name: "all regions".to_string() });
+            }
+            if sz > i16::MAX as i32 {
+                return Err(Error::SizeTooLarge {
+                    size: [shape[0] as u64, shape[1] as u64, shape[2] as u64],
+                    max_size: [i16::MAX as u64; 3],
+                });
+            }
+        }
+
+        let mut result = WE12MetaData::default();
+        result.materials = if self.metadata.schem_material.is_empty() {
+            "Alpha".to_string()
+        } else {
+            self.metadata.schem_material.clone()
+        };
+        result.we_offset = self.metadata.schem_offset;
+        result.we_origin = self.metadata.schem_origin.unwrap_or([0, 0, 0]);
+        [result.width, result.height, result.length] = [shape[0] as i16, shape[1] as i16, shape[2] as i16];
+        return Ok(result);
+    }
+
+    /// Convert a block to number id and damage, `pos` is only used to report error
+    fn block_to_number_id(block: &Block, pos: [i32; 3], option: &WorldEdit12SaveOption) -> Result<(u16, u8), Error> {
+        let (id, damage) = if let Some(id_damage) = option.custom_number_ids.get(block) {
+            *id_damage
+        } else if let Some((id, damage)) = block.to_old() {
+            (id as u16, damage)
+        } else {
+            return Err(Error::NoNumberIdForBlock { block: block.full_id(), pos });
+        };
+        if id >= 4096 || damage >= 16 {
+            return Err(Error::NumberIdTooLarge { block: block.full_id(), id, damage });
+        }
+        return Ok((id, damage));
+    }
+
+    /// Save `.schematic` to nbt
+    pub fn to_nbt_world_edit_12(&self, option: &WorldEdit12SaveOption) -> Result<HashMap<String, Value>, Error> {
+        let md = self.metadata_world_edit_12()?;
+        let shape = self.shape();
+        let mut root = HashMap::new();
+
+        // metadata
+        root.insert("Materials".to_string(), Value::String(md.materials.clone()));
+        root.insert("Width".to_string(), Value::Short(md.width));
+        root.insert("Height".to_string(), Value::Short(md.height));
+        root.insert("Length".to_string(), Value::Short(md.length));
+        for (dim, letter) in ['X', 'Y', 'Z'].iter().enumerate() {
+            root.insert(format!("WEOffset{letter}"), Value::Int(md.we_offset[dim]));
+            root.insert(format!("WEOrigin{letter}"), Value::Int(md.we_origin[dim]));
+        }
+
+        // blocks
+        {
+            let (full_palette, luts_of_block_idx) = self.full_palette();
+            // number ids are converted lazily, so that blocks in palette but not used won't cause error
+            let mut number_ids: Vec<Option<(u16, u8)>> = vec![None; full_palette.len()];
+            let background_block = option.background_block.to_block();
+
+            let volume = self.volume() as usize;
+            let mut blocks: Vec<i8> = Vec::with_capacity(volume);
+            let mut data: Vec<i8> = Vec::with_capacity(volume);
+            let mut add_blocks: Vec<i8> = vec![0; volume.div_ceil(2)];
+            let mut has_add_blocks = false;
+
+            for y in 0..shape[1] {
+                for z in 0..shape[2] {
+                    for x in 0..shape[0] {
+                        let pos = [x, y, z];
+                        let mut cur_block_gindex: Option<usize> = None;
+                        for (reg_idx, reg) in self.regions.iter().enumerate() {
+                            if let Some(cur_idx) = reg.block_index_at(reg.global_pos_to_relative_pos(pos)) {
+                                cur_block_gindex = Some(luts_of_block_idx[reg_idx][cur_idx as usize]);
+                                break;
+                            }
+                        }
+
+                        let (id, damage) = match cur_block_gindex {
+                            Some(gidx) => {
+                                if number_ids[gidx].is_none() {
+                                    number_ids[gidx] = Some(Self::block_to_number_id(full_palette[gidx].0, pos, option)?);
+                                }
+                                number_ids[gidx].unwrap()
+                            }
+                            None => Self::block_to_number_id(&background_block, pos, option)?,
+                        };
+
+                        let index = blocks.len();
+                        blocks.push((id & 0xFF) as u8 as i8);
+                        data.push(damage as i8);
+                        let add = ((id >> 8) & 0x0F) as u8;
+                        if add != 0 {
+                            has_add_blocks = true;
+                            let byte = &mut add_blocks[index >> 1];
+                            if index & 1 == 0 {
+                                *byte = ((*byte as u8 & 0xF0) | add) as i8;
+                            } else {
+                                *byte = ((*byte as u8 & 0x0F) | (add << 4)) as i8;
+                            }
+                        }
+                    }
+                }
+            }
+            root.insert("Blocks".to_string(), Value::ByteArray(fastnbt::ByteArray::new(blocks)));
+            root.insert("Data".to_string(), Value::ByteArray(fastnbt::ByteArray::new(data)));
+            if has_add_blocks {
+                root.insert("AddBlocks".to_string(), Value::ByteArray(fastnbt::ByteArray::new(add_blocks)));
+            }
+        }
+
+        // tile entities
+        {
+            let mut te_list = Vec::new();
+            for y in 0..shape[1] {
+                for z in 0..shape[2] {
+                    for x in 0..shape[0] {
+                        let be = match self.first_block_entity_at([x, y, z]) {
+                            Some(b) => b,
+                            None => continue,
+                        };
+                        let mut nbt = be.tags.clone();
+                        nbt.insert("x".to_string(), Value::Int(x));
+                        nbt.insert("y".to_string(), Value::Int(y));
+                        nbt.insert("z".to_string(), Value::Int(z));
+                        te_list.push(Value::Compound(nbt));
+                    }
+                }
+            }
+            root.insert("TileEntities".to_string(), Value::List(te_list));
+        }
+
+        // entities
+        {
+            let mut entity_list = Vec::new();
+            for reg in &self.regions {
+                for entity in &reg.entities {
+                    let mut e = entity.clone();
+                    e.pos_shift(reg.offset);
+                    let mut nbt = e.tags;
+                    nbt.insert("Pos".to_string(), Value::List(common::size_to_list(&e.position)));
+                    entity_list.push(Value::Compound(nbt));
+                }
+            }
+            root.insert("Entities".to_string(), Value::List(entity_list));
+        }
+
+        return Ok(root);
+    }
+
+    /// Save `.schematic` to writer
+    pub fn save_world_edit_12_writer(&self, dest: &mut dyn std::io::Write, option: &WorldEdit12SaveOption) -> Result<(), Error> {
+        let nbt = self.to_nbt_world_edit_12(option)?;
+        let mut encoder = GzBuilder::new()
+            .comment("Generated by mc_schem")
+            .write(dest, option.compress_level);
+
+        let res: Result<(), fastnbt::error::Error> = fastnbt::to_writer(&mut encoder, &nbt);
+        if let Err(e) = res {
+            return Err(Error::NBTWriteError(e));
+        }
+        if let Err(e) = encoder.finish() {
+            return Err(Error::NBTWriteError(e.into()));
+        }
+
+        return Ok(());
+    }
+
+    /// Save `.schematic` to file
+    pub fn save_world_edit_12_file(&self, filename: &str, option: &WorldEdit12SaveOption) -> Result<(), Error> {
+        let mut file = match File::create(filename) {
+            Ok(f) => f,
+            Err(e) => return Err(Error::FileCreateError(e)),
+        };
+        return self.save_world_edit_12_writer(&mut file, option);
+    }
+}
@@ -0,0 +1,550 @@
+/*
+mc_schem is a rust library to generate, load, manipulate and save minecraft schematic files.
+Copyright (C) 2024  joseph
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use std::collections::{BTreeMap, HashMap};
+use fastnbt::Value;
+use ndarray::Array3;
+use strum::Display;
+use crate::block::Block;
+use crate::region::{Region, WorldSlice};
+use crate::schem::Schematic;
+
+/// Axis in minecraft world
+#[repr(u8)]
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    X = 0,
+    Y = 1,
+    Z = 2,
+}
+
+/// Rotation around an axis. The rotation is clockwise when looking from the positive direction of
+/// the axis to the origin, so rotating around y axis is the same as rotating structures in vanilla.
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq)]
+pub enum Rotation {
+    None,
+    Clockwise90,
+    Clockwise180,
+    Counterclockwise90,
+}
+
+impl Rotation {
+    /// Convert clockwise degrees to rotation, returns `None` if degrees is not a multiple of 90.
+    pub fn from_degrees(degrees: i32) -> Option<Rotation> {
+        if degrees % 90 != 0 {
+            return None;
+        }
+        return Some(match degrees.rem_euclid(360) {
+            0 => Rotation::None,
+            90 => Rotation::Clockwise90,
+            180 => Rotation::Clockwise180,
+            _ => Rotation::Counterclockwise90,
+        });
+    }
+
+    /// Clockwise degrees of the rotation, in range [0, 360)
+    pub fn degrees(&self) -> i32 {
+        return match self {
+            Rotation::None => 0,
+            Rotation::Clockwise90 => 90,
+            Rotation::Clockwise180 => 180,
+            Rotation::Counterclockwise90 => 270,
+        };
+    }
+
+    fn quarter_turns(&self) -> usize {
+        return (self.degrees() / 90) as usize;
+    }
+}
+
+/// A rotation or mirroring in 3d space, where `new_pos[i] = sign[i] * old_pos[perm[i]]`
+#[derive(Debug, Clone, Copy)]
+struct Transform {
+    perm: [usize; 3],
+    sign: [i32; 3],
+}
+
+const DIRECTIONS: [(&str, [i32; 3]); 6] = [
+    ("down", [0, -1, 0]),
+    ("up", [0, 1, 0]),
+    ("north", [0, 0, -1]),
+    ("south", [0, 0, 1]),
+    ("west", [-1, 0, 0]),
+    ("east", [1, 0, 0]),
+];
+
+const HORIZONTAL_KEYS: [&str; 4] = ["north", "south", "east", "west"];
+
+impl Transform {
+    fn identity() -> Transform {
+        return Transform { perm: [0, 1, 2], sign: [1, 1, 1] };
+    }
+
+    /// Rotate 90 degrees clockwise around `axis`
+    fn quarter_turn(axis: Axis) -> Transform {
+        return match axis {
+            // up -> north
+            Axis::X => Transform { perm: [0, 2, 1], sign: [1, 1, -1] },
+            // north -> east
+            Axis::Y => Transform { perm: [2, 1, 0], sign: [-1, 1, 1] },
+            // up -> east
+            Axis::Z => Transform { perm: [1, 0, 2], sign: [1, -1, 1] },
+        };
+    }
+
+    fn rotation(axis: Axis, rotation: Rotation) -> Transform {
+        let mut result = Self::identity();
+        for _ in 0..rotation.quarter_turns() {
+            result = Self::quarter_turn(axis).after(&result);
+        }
+        return result;
+    }
+
+    fn mirror(axis: Axis) -> Transform {
+        let mut result = Self::identity();
+        result.sign[axis as usize] = -1;
+        return result;
+    }
+
+    /// Returns a transform that applies `first` and then `self`
+    fn after(&self, first: &Transform) -> Transform {
+        let mut result = Self::identity();
+        for dim in 0..3 {
+            result.perm[dim] = first.perm[self.perm[dim]];
+            result.sign[dim] = self.sign[dim] * first.sign[self.perm[dim]];
+        }
+        return result;
+    }
+
+    fn apply_vec(&self, v: [i32; 3]) -> [i32; 3] {
+        let mut result = [0; 3];
+        for dim in 0..3 {
+            result[dim] = self.sign[dim] * v[self.perm[dim]];
+        }
+        return result;
+    }
+
+    /// Transform a block position in a box with size `old_shape`
+    fn apply_pos(&self, pos: [i32; 3], old_shape: [i32; 3]) -> [i32; 3] {
+        let new_shape = self.apply_shape(old_shape);
+        let mut result = [0; 3];
+        for dim in 0..3 {
+            let p = pos[self.perm[dim]];
+            result[dim] = if self.sign[dim] > 0 { p } else { new_shape[dim] - 1 - p };
+        }
+        return result;
+    }
+
+    /// Transform a position in float in a box with size `old_shape`
+    fn apply_pos_f64(&self, pos: [f64; 3], old_shape: [i32; 3]) -> [f64; 3] {
+        let new_shape = self.apply_shape(old_shape);
+        let mut result = [0.0; 3];
+        for dim in 0..3 {
+            let p = pos[self.perm[dim]];
+            result[dim] = if self.sign[dim] > 0 { p } else { new_shape[dim] as f64 - p };
+        }
+        return result;
+    }
+
+    fn apply_shape(&self, shape: [i32; 3]) -> [i32; 3] {
+        return [shape[self.perm[0]], shape[self.perm[1]], shape[self.perm[2]]];
+    }
+
+    fn apply_direction(&self, dir: &str) -> Option<&'static str> {
+        let vec = DIRECTIONS.iter().find(|(name, _)| *name == dir)?.1;
+        let new_vec = self.apply_vec(vec);
+        return DIRECTIONS.iter().find(|(_, v)| *v == new_vec).map(|(name, _)| *name);
+    }
+
+    fn is_horizontal(dir: &str) -> bool {
+        return HORIZONTAL_KEYS.contains(&dir);
+    }
+
+    /// Whether y axis is still y axis after transform
+    fn keeps_y_axis(&self) -> bool {
+        return self.perm[1] == 1;
+    }
+
+    /// Whether up and down are swapped
+    fn flips_y(&self) -> bool {
+        return self.keeps_y_axis() && self.sign[1] < 0;
+    }
+
+    /// Whether the horizontal plane is mirrored, so that left and right are swapped
+    fn mirrors_horizontally(&self) -> bool {
+        if !self.keeps_y_axis() {
+            return false;
+        }
+        let swapped = if self.perm[0] == 0 { 1 } else { -1 };
+        return self.sign[0] * self.sign[2] * swapped < 0;
+    }
+
+    /// Transform yaw in degrees, 0 is south and 90 is west. Yaw is unchanged if y axis is changed.
+    fn apply_yaw(&self, yaw: f64) -> f64 {
+        if !self.keeps_y_axis() {
+            return yaw;
+        }
+        let rad = yaw.to_radians();
+        // vector of (x, z)
+        let (x, z) = (-rad.sin(), rad.cos());
+        let mut v = [0.0, 0.0, 0.0];
+        v[0] = x;
+        v[2] = z;
+        let new_x = self.sign[0] as f64 * v[self.perm[0]];
+        let new_z = self.sign[2] as f64 * v[self.perm[2]];
+        let new_yaw = (-new_x).atan2(new_z).to_degrees();
+        return new_yaw.rem_euclid(360.0);
+    }
+}
+
+fn swap_value(value: &str, a: &str, b: &str) -> String {
+    if value == a {
+        return b.to_string();
+    }
+    if value == b {
+        return a.to_string();
+    }
+    return value.to_string();
+}
+
+/// Transform rail shape like `north_south`, `ascending_east` and `south_west`
+fn transform_rail_shape(shape: &str, t: &Transform) -> Option<String> {
+    if let Some(dir) = shape.strip_prefix("ascending_") {
+        let new_dir = t.apply_direction(dir)?;
+        if !Transform::is_horizontal(new_dir) {
+            return None;
+        }
+        return Some(format!("ascending_{new_dir}"));
+    }
+    let (a, b) = shape.split_once('_')?;
+    let mut dirs = [t.apply_direction(a)?, t.apply_direction(b)?];
+    if !dirs.iter().all(|d| Transform::is_horizontal(d)) {
+        return None;
+    }
+    if dirs.contains(&"north") && dirs.contains(&"south") {
+        return Some("north_south".to_string());
+    }
+    if dirs.contains(&"east") && dirs.contains(&"west") {
+        return Some("east_west".to_string());
+    }
+    if dirs[0] == "east" || dirs[0] == "west" {
+        dirs.swap(0, 1);
+    }
+    return Some(format!("{}_{}", dirs[0], dirs[1]));
+}
+
+impl Block {
+    fn transformed(&self, t: &Transform) -> Block {
+        let mut result = self.clone();
+        let attrs = &self.attributes;
+        let new_attrs = &mut result.attributes;
+
+        // connections to neighbors, like fences, walls, vines and mushroom blocks
+        {
+            let has_horizontal_keys = HORIZONTAL_KEYS.iter().any(|k| attrs.contains_key(*k));
+            let six_way = has_horizontal_keys && attrs.contains_key("down");
+            let keys: Vec<&str> = if six_way {
+                DIRECTIONS.iter().map(|(name, _)| *name).collect()
+            } else if has_horizontal_keys {
+                HORIZONTAL_KEYS.to_vec()
+            } else {
+                vec![]
+            };
+            let mut moved: BTreeMap<String, String> = BTreeMap::new();
+            let mut valid = true;
+            for key in &keys {
+                if let Some(value) = attrs.get(*key) {
+                    match t.apply_direction(key) {
+                        Some(new_key) if six_way || Transform::is_horizontal(new_key) => {
+                            moved.insert(new_key.to_string(), value.clone());
+                        }
+                        _ => valid = false,
+                    }
+                }
+            }
+            if valid {
+                for key in &keys {
+                    new_attrs.remove(*key);
+                }
+                new_attrs.extend(moved);
+            }
+        }
+
+        for (key, value) in attrs {
+            let new_value = match key.as_str() {
+                "facing" | "vertical_direction" => {
+                    let new_dir = t.apply_direction(value);
+                    match new_dir {
+                        // blocks attached to floor or ceiling, like buttons and levers, can only face horizontally
+                        Some(d) if attrs.contains_key("face") && !Transform::is_horizontal(d) => None,
+                        Some(d) => Some(d.to_string()),
+                        None => None,
+                    }
+                }
+                "axis" => {
+                    let old_axis = ["x", "y", "z"].iter().position(|a| a == value);
+                    match old_axis {
+                        Some(old) => {
+                            let new = t.perm.iter().position(|p| *p == old).unwrap();
+                            Some(["x", "y", "z"][new].to_string())
+                        }
+                        None => None,
+                    }
+                }
+                "rotation" => {
+                    match value.parse::<i32>() {
+                        Ok(r) if t.keeps_y_axis() => {
+                            let yaw = t.apply_yaw(r as f64 * 22.5);
+                            Some(((yaw / 22.5).round() as i32).rem_euclid(16).to_string())
+                        }
+                        _ => None,
+                    }
+                }
+                "orientation" => {
+                    // jigsaw and crafter, like `north_up`
+                    match value.split_once('_') {
+                        Some((a, b)) => match (t.apply_direction(a), t.apply_direction(b)) {
+                            (Some(a), Some(b)) => Some(format!("{a}_{b}")),
+                            _ => None,
+                        },
+                        None => None,
+                    }
+                }
+                "shape" => {
+                    if value.contains("left") || value.contains("right") || value == "straight" {
+                        // stairs
+                        if t.mirrors_horizontally() {
+                            let (prefix, side) = value.split_once('_').unwrap_or((value, ""));
+                            Some(format!("{prefix}_{}", swap_value(side, "left", "right")).trim_end_matches('_').to_string())
+                        } else {
+                            None
+                        }
+                    } else {
+                        transform_rail_shape(value, t)
+                    }
+                }
+                "hinge" if t.mirrors_horizontally() => Some(swap_value(value, "left", "right")),
+                "type" => {
+                    // chests and slabs
+                    let mut v = value.clone();
+                    if t.mirrors_horizontally() {
+                        v = swap_value(&v, "left", "right");
+                    }
+                    if t.flips_y() {
+                        v = swap_value(&v, "top", "bottom");
+                    }
+                    Some(v)
+                }
+                "half" if t.flips_y() => Some(swap_value(&swap_value(value, "top", "bottom"), "upper", "lower")),
+                "face" if t.flips_y() => Some(swap_value(value, "floor", "ceiling")),
+                "attachment" if t.flips_y() => Some(swap_value(value, "floor", "ceiling")),
+                _ => None,
+            };
+            if let Some(new_value) = new_value {
+                new_attrs.insert(key.clone(), new_value);
+            }
+        }
+        return result;
+    }
+
+    /// Rotate the block around y axis, properties like `facing`, `axis`, `rotation`, `shape` and
+    /// connections to neighbors are rewritten.
+    pub fn rotated_y(&self, rotation: Rotation) -> Block {
+        return self.transformed(&Transform::rotation(Axis::Y, rotation));
+    }
+
+    /// Rotate the block around x axis. Blocks that can only face horizontally may become invalid,
+    /// because their `facing` can be `up` or `down` after rotation.
+    pub fn rotated_x(&self, rotation: Rotation) -> Block {
+        return self.transformed(&Transform::rotation(Axis::X, rotation));
+    }
+
+    /// Rotate the block around z axis. Blocks that can only face horizontally may become invalid,
+    /// because their `facing` can be `up` or `down` after rotation.
+    pub fn rotated_z(&self, rotation: Rotation) -> Block {
+        return self.transformed(&Transform::rotation(Axis::Z, rotation));
+    }
+
+    /// Mirror the block along `axis`, for example, mirroring along x axis swaps east and west.
+    pub fn mirrored(&self, axis: Axis) -> Block {
+        return self.transformed(&Transform::mirror(axis));
+    }
+}
+
+/// Transform tags of entity that store direction or position
+fn transform_entity_tags(tags: &mut HashMap<String, Value>, t: &Transform, old_shape: [i32; 3]) {
+    if let Some(Value::List(rotation)) = tags.get_mut("Rotation") {
+        if let Some(Value::Float(yaw)) = rotation.get_mut(0) {
+            *yaw = t.apply_yaw(*yaw as f64) as f32;
+        }
+        if t.flips_y() {
+            if let Some(Value::Float(pitch)) = rotation.get_mut(1) {
+                *pitch = -*pitch;
+            }
+        }
+    }
+
+    // hanging entities like paintings and item frames
+    {
+        let tile_keys = ["TileX", "TileY", "TileZ"];
+        let mut tile = [0; 3];
+        let mut has_tile = true;
+        for dim in 0..3 {
+            match tags.get(tile_keys[dim]) {
+                Some(Value::Int(v)) => tile[dim] = *v,
+                _ => has_tile = false,
+            }
+        }
+        if has_tile {
+            let new_tile = t.apply_pos(tile, old_shape);
+            for dim in 0..3 {
+                tags.insert(tile_keys[dim].to_string(), Value::Int(new_tile[dim]));
+            }
+        }
+    }
+
+    let is_painting = match tags.get("id") {
+        Some(Value::String(id)) => id.ends_with("painting"),
+        _ => false,
+    };
+    for key in ["Facing", "facing"] {
+        if let Some(Value::Byte(facing)) = tags.get_mut(key) {
+            // paintings use horizontal index(south, west, north, east), item frames use 3d index
+            let horizontal = ["south", "west", "north", "east"];
+            let old_dir = if is_painting {
+                horizontal.get(*facing as usize).copied()
+            } else {
+                DIRECTIONS.get(*facing as usize).map(|(name, _)| *name)
+            };
+            let new_dir = old_dir.and_then(|d| t.apply_direction(d));
+            if let Some(new_dir) = new_dir {
+                let new_idx = if is_painting {
+                    horizontal.iter().position(|d| *d == new_dir)
+                } else {
+                    DIRECTIONS.iter().position(|(name, _)| *name == new_dir)
+                };
+                if let Some(new_idx) = new_idx {
+                    *facing = new_idx as i8;
+                }
+            }
+        }
+    }
+}
+
+impl Region {
+    fn transform(&mut self, t: &Transform) {
+        let old_shape = self.shape();
+        let new_shape = t.apply_shape(old_shape);
+
+        let mut new_array: Array3<u16> = Array3::zeros([new_shape[1] as usize, new_shape[2] as usize, new_shape[0] as usize]);
+        for x in 0..old_shape[0] {
+            for y in 0..old_shape[1] {
+                for z in 0..old_shape[2] {
+                    let new_pos = t.apply_pos([x, y, z], old_shape);
+                    new_array[[new_pos[1] as usize, new_pos[2] as usize, new_pos[0] as usize]]
+                        = self.array_yzx[[y as usize, z as usize, x as usize]];
+                }
+            }
+        }
+        self.array_yzx = new_array;
+
+        for blk in &mut self.palette {
+            *blk = blk.transformed(t);
+        }
+
+        self.block_entities = self.block_entities.drain()
+            .map(|(pos, be)| (t.apply_pos(pos, old_shape), be)).collect();
+        self.pending_ticks = self.pending_ticks.drain()
+            .map(|(pos, ticks)| (t.apply_pos(pos, old_shape), ticks)).collect();
+
+        for entity in &mut self.entities {
+            entity.position = t.apply_pos_f64(entity.position, old_shape);
+            entity.block_pos = t.apply_pos(entity.block_pos, old_shape);
+            if let Some(Value::List(pos)) = entity.tags.get_mut("Pos") {
+                if pos.len() == 3 && pos.iter().all(|p| matches!(p, Value::Double(_))) {
+                    for (p, coord) in pos.iter_mut().zip(entity.position) {
+                        *p = Value::Double(coord);
+                    }
+                }
+            }
+            transform_entity_tags(&mut entity.tags, t, old_shape);
+        }
+    }
+
+    /// Rotate the region around y axis, including blocks, block entities, pending ticks and
+    /// entities. Offset of the region is not changed.
+    pub fn rotate_y(&mut self, rotation: Rotation) {
+        self.transform(&Transform::rotation(Axis::Y, rotation));
+    }
+
+    /// Rotate the region around x axis. See `Block::rotated_x` for limitations on blocks.
+    pub fn rotate_x(&mut self, rotation: Rotation) {
+        self.transform(&Transform::rotation(Axis::X, rotation));
+    }
+
+    /// Rotate the region around z axis. See `Block::rotated_z` for limitations on blocks.
+    pub fn rotate_z(&mut self, rotation: Rotation) {
+        self.transform(&Transform::rotation(Axis::Z, rotation));
+    }
+
+    /// Mirror the region along `axis`. Offset of the region is not changed.
+    pub fn mirror(&mut self, axis: Axis) {
+        self.transform(&Transform::mirror(axis));
+    }
+}
+
+impl Schematic {
+    fn transform(&mut self, t: &Transform) {
+        let shape = self.shape();
+        for reg in &mut self.regions {
+            let reg_shape = reg.shape();
+            let mut new_offset = [0; 3];
+            for (dim, offset) in new_offset.iter_mut().enumerate() {
+                let src = t.perm[dim];
+                *offset = if t.sign[dim] > 0 {
+                    reg.offset[src]
+                } else {
+                    shape[src] - reg.offset[src] - reg_shape[src]
+                };
+            }
+            reg.transform(t);
+            reg.offset = new_offset;
+        }
+    }
+
+    /// Rotate all regions around y axis, regions are moved so that the schematic still starts at
+    /// \[0, 0, 0\].
+    pub fn rotate_y(&mut self, rotation: Rotation) {
+        self.transform(&Transform::rotation(Axis::Y, rotation));
+    }
+
+    /// Rotate all regions around x axis. See `Block::rotated_x` for limitations on blocks.
+    pub fn rotate_x(&mut self, rotation: Rotation) {
+        self.transform(&Transform::rotation(Axis::X, rotation));
+    }
+
+    /// Rotate all regions around z axis. See `Block::rotated_z` for limitations on blocks.
+    pub fn rotate_z(&mut self, rotation: Rotation) {
+        self.transform(&Transform::rotation(Axis::Z, rotation));
+    }
+
+    /// Mirror all regions along `axis`
+    pub fn mirror(&mut self, axis: Axis) {
+        self.transform(&Transform::mirror(axis));
+    }
+}
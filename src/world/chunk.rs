@@ -8,11 +8,12 @@ use math::round::{ceil, floor};
 
 use crate::{unwrap_opt_tag, unwrap_tag};
 use crate::biome::Biome;
+use crate::block::Block;
 use crate::error::Error;
 use crate::region::{Light, PendingTick, PendingTickInfo, WorldSlice};
 use crate::schem::common;
 use crate::schem::common::ceil_up_to;
-use crate::schem::id_of_nbt_tag;
+use crate::schem::{id_of_nbt_tag, DataVersion};
 use crate::world::{Chunk, ChunkPos, ChunkRefAbsolutePos, ChunkRefRelativePos, ChunkStatus, NBTWithSource, SubChunk};
 
 impl Display for ChunkStatus {
@@ -102,17 +103,48 @@ impl Chunk {
             entities: vec![],
             block_entities: HashMap::new(),
             pending_ticks: HashMap::new(),
+            data_version: 0,
+            heightmaps: BTreeMap::new(),
+            structure_starts: BTreeMap::new(),
+            structure_references: BTreeMap::new(),
+            post_processing: vec![],
+            blending_data: None,
+            carving_masks: BTreeMap::new(),
+            unknown_number_id_blocks: BTreeMap::new(),
             file_region: "NoFile".to_string(),
             file_entities: "NoFile".to_string(),
         };
     }
 
+    /// Create a fully generated chunk with air in `y_range`, which should be aligned to 16.
+    pub fn with_y_range(y_range: Range<i32>) -> Chunk {
+        let mut chunk = Chunk::new();
+        chunk.status = ChunkStatus::Full;
+        chunk.is_light_on = false;
+        for y in y_range.start.div_euclid(16)..y_range.end.div_euclid(16) {
+            chunk.sub_chunks.insert(y as i8, SubChunk::filled_with(&Block::air(), Biome::plains));
+        }
+        return chunk;
+    }
+
     pub fn height(&self) -> i32 {
         debug_assert!(self.missing_sub_chunks().is_empty());
         return self.sub_chunks.len() as i32 * 16;
     }
 
+    /// Parse a chunk of any version. Blocks in chunks before 1.13 are NOT flattened, they are kept
+    /// in 1.12 string ids with properties (like `minecraft:stone[variant=granite]`), so a world
+    /// with both old and new chunks has pre-flattening and flattened ids mixed. Check
+    /// `data_version` of chunk to tell them apart.
     pub fn from_nbt(region_nbt_data: NBTWithSource, entity_nbt_data: Option<NBTWithSource>, chunk_pos: &ChunkPos) -> Result<Chunk, Error> {
+        // chunks before 1.18 are wrapped in Level, and chunks before 1.9 have no data version
+        let data_version = match region_nbt_data.nbt.get("DataVersion") {
+            Some(Value::Int(dv)) => *dv,
+            _ => 0,
+        };
+        if data_version < DataVersion::Snapshot_21w43a as i32 {
+            return Self::from_legacy_nbt(region_nbt_data, entity_nbt_data, chunk_pos, data_version);
+        }
         let region_source_filename = region_nbt_data.source;
         let mut region_nbt = region_nbt_data.nbt;
 
@@ -163,83 +195,24 @@ impl Chunk {
             }
         }
 
-        let pos_lb = chunk_pos.block_pos_lower_bound();
-        let pos_ub = chunk_pos.block_pos_upper_bound();
+        result.data_version = data_version;
+        result.parse_metadata(&mut region_nbt, &path_in_saves, chunk_pos, false, false)?;
         // block entities
         {
             let be_list_tag = format!("{path_in_saves}/block_entities");
-            let mut be_list = unwrap_opt_tag!(region_nbt.remove("block_entities"),List,vec![],be_list_tag);
-
-            let pos_lb = [pos_lb[0], result.y_range().start, pos_lb[1]];
-            let pos_ub = [pos_ub[0], result.y_range().end - 1, pos_ub[1]];
-
-            result.block_entities.reserve(be_list.len());
-
-            for (idx, nbt) in be_list.iter_mut().enumerate() {
-                let mut temp = Value::Byte(0);
-                std::mem::swap(&mut temp, nbt);
-                let be_nbt_tag = format!("{path_in_saves}/block_entities/[{idx}]");
-                let be_nbt = unwrap_tag!(temp,Compound,HashMap::new(),be_nbt_tag);
-                let (pos, be) = common::parse_block_entity_nocheck(be_nbt, &be_nbt_tag, true)?;
-                if !common::check_pos_in_range(pos, pos_lb, pos_ub) {
-                    return Err(Error::BlockPosOutOfRange {
-                        tag_path: be_nbt_tag,
-                        pos,
-                        lower_bound: pos_lb,
-                        upper_bound: pos_ub,
-                    });
-                }
-                if result.block_entities.contains_key(&pos) {
-                    return Err(Error::MultipleBlockEntityInOnePos {
-                        pos,
-                        latter_tag_path: be_nbt_tag,
-                    });
-                }
-                result.block_entities.insert(pos, be);
-            }
+            let be_list = unwrap_opt_tag!(region_nbt.remove("block_entities"),List,vec![],be_list_tag);
+            result.parse_block_entities(be_list, &be_list_tag, chunk_pos)?;
         }
         // pending ticks
         {
-            //let mut tick_tag_record = HashMap::new();
             let tag_path_block_ticks = format!("{path_in_saves}/block_ticks");
             let tag_block_ticks = unwrap_opt_tag!(region_nbt.get("block_ticks"),List,vec![],tag_path_block_ticks);
 
-
             let tag_path_fluid_ticks = format!("{path_in_saves}/fluid_ticks");
             let tag_fluid_ticks = unwrap_opt_tag!(region_nbt.get("fluid_ticks"),List,vec![],tag_path_fluid_ticks);
-            result.pending_ticks.reserve(tag_block_ticks.len() + tag_fluid_ticks.len());
-            for (is_block, (list, tag_path)) in [(tag_block_ticks, tag_path_block_ticks),
-                (tag_fluid_ticks, tag_path_fluid_ticks)].iter().enumerate() {
-                let is_block = is_block == 0;
-                for (idx, nbt) in list.iter().enumerate() {
-                    let cur_tag_path = format!("{tag_path}[{idx}]");
-                    let nbt = unwrap_tag!(nbt,Compound,HashMap::new(),cur_tag_path);
-                    let (pos, tick) = parse_pending_tick(nbt, is_block, &cur_tag_path)?;
-
-                    //if result.pending_ticks.contains_key(&pos) {
-                    // let former = &result.pending_ticks[&pos];
-                    // // If the pending tick is repeated, ignore the latter
-                    // if &tick == former {
-                    //     continue;
-                    // }
-                    // debug_assert!(tick_tag_record.contains_key(&pos));
-                    // return Err(Error::MultiplePendingTickInOnePos {
-                    //     pos,
-                    //     former_tag_path: tick_tag_record.remove(&pos).unwrap(),
-                    //     latter_tag_path: cur_tag_path,
-                    // });
-                    //}
-                    if let Some(dst) = result.pending_ticks.get_mut(&pos) {
-                        dst.push(tick);
-                    } else {
-                        result.pending_ticks.insert(pos, vec![tick]);
-                    }
-                    //tick_tag_record.insert(pos, cur_tag_path);
-                }
-            }
+            result.parse_pending_ticks(tag_block_ticks, &tag_path_block_ticks, tag_fluid_ticks, &tag_path_fluid_ticks)?;
         }
 
-
         // entities
         if let Some(entity_nbt_data) = entity_nbt_data {
             let entity_source_file = entity_nbt_data.source;
@@ -249,33 +222,98 @@ impl Chunk {
             let entity_path = format!("{entity_source_file}/[{},{}]/Entities",
                                       chunk_pos.local_coordinate().x,
                                       chunk_pos.local_coordinate().z);
-            let mut entity_list = unwrap_opt_tag!(entity_nbt.remove("Entities"),List,vec![],entity_path);
-            result.entities.reserve(entity_list.len());
-            for (idx, entity) in entity_list.iter_mut().enumerate() {
-                let cur_path = format!("{entity_path}/[{idx}]");
-                let temp = unwrap_tag!(entity,Compound,HashMap::new(),cur_path);
-                let mut entity = HashMap::new();
-                std::mem::swap(&mut entity, temp);
-
-                let entity = common::parse_entity_litematica(entity, &cur_path)?;
-                // check for position
-                let entity_pos_xz = [entity.block_pos[0], entity.block_pos[2]];
-                for dim in 0..2 {
-                    if entity_pos_xz[dim] < pos_lb[dim] || entity_pos_xz[dim] > pos_ub[dim] {
-                        return Err(Error::BlockPosOutOfRange {
-                            tag_path: format!("{cur_path}/Pos"),
-                            pos: entity.block_pos,
-                            lower_bound: [pos_lb[0], i32::MIN, pos_lb[1]],
-                            upper_bound: [pos_ub[0], i32::MAX, pos_ub[1]],
-                        });
-                    }
-                }
+            let entity_list = unwrap_opt_tag!(entity_nbt.remove("Entities"),List,vec![],entity_path);
+            result.parse_entities(entity_list, &entity_path, chunk_pos)?;
+        }
 
-                result.entities.push(entity);
+        return Ok(result);
+    }
+
+    /// Parse block entities in absolute positions, which must be inside this chunk. Sub chunks
+    /// should be parsed before.
+    pub fn parse_block_entities(&mut self, mut be_list: Vec<Value>, be_list_tag: &str, chunk_pos: &ChunkPos) -> Result<(), Error> {
+        let pos_lb = chunk_pos.block_pos_lower_bound();
+        let pos_ub = chunk_pos.block_pos_upper_bound();
+        let pos_lb = [pos_lb[0], self.y_range().start, pos_lb[1]];
+        let pos_ub = [pos_ub[0], self.y_range().end - 1, pos_ub[1]];
+
+        self.block_entities.reserve(be_list.len());
+
+        for (idx, nbt) in be_list.iter_mut().enumerate() {
+            let mut temp = Value::Byte(0);
+            std::mem::swap(&mut temp, nbt);
+            let be_nbt_tag = format!("{be_list_tag}/[{idx}]");
+            let be_nbt = unwrap_tag!(temp,Compound,HashMap::new(),be_nbt_tag);
+            let (pos, be) = common::parse_block_entity_nocheck(be_nbt, &be_nbt_tag, true)?;
+            if !common::check_pos_in_range(pos, pos_lb, pos_ub) {
+                return Err(Error::BlockPosOutOfRange {
+                    tag_path: be_nbt_tag,
+                    pos,
+                    lower_bound: pos_lb,
+                    upper_bound: pos_ub,
+                });
+            }
+            if self.block_entities.contains_key(&pos) {
+                return Err(Error::MultipleBlockEntityInOnePos {
+                    pos,
+                    latter_tag_path: be_nbt_tag,
+                });
             }
+            self.block_entities.insert(pos, be);
         }
+        return Ok(());
+    }
 
-        return Ok(result);
+    /// Parse block ticks and fluid ticks in absolute positions
+    pub fn parse_pending_ticks(&mut self, block_ticks: &[Value], block_ticks_path: &str,
+                               fluid_ticks: &[Value], fluid_ticks_path: &str) -> Result<(), Error> {
+        self.pending_ticks.reserve(block_ticks.len() + fluid_ticks.len());
+        for (is_block, (list, tag_path)) in [(block_ticks, block_ticks_path),
+            (fluid_ticks, fluid_ticks_path)].iter().enumerate() {
+            let is_block = is_block == 0;
+            for (idx, nbt) in list.iter().enumerate() {
+                let cur_tag_path = format!("{tag_path}[{idx}]");
+                let nbt = unwrap_tag!(nbt,Compound,HashMap::new(),cur_tag_path);
+                let (pos, tick) = parse_pending_tick(nbt, is_block, &cur_tag_path)?;
+
+                if let Some(dst) = self.pending_ticks.get_mut(&pos) {
+                    dst.push(tick);
+                } else {
+                    self.pending_ticks.insert(pos, vec![tick]);
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    /// Parse entities, whose x and z must be inside this chunk
+    pub fn parse_entities(&mut self, mut entity_list: Vec<Value>, entity_path: &str, chunk_pos: &ChunkPos) -> Result<(), Error> {
+        let pos_lb = chunk_pos.block_pos_lower_bound();
+        let pos_ub = chunk_pos.block_pos_upper_bound();
+        self.entities.reserve(entity_list.len());
+        for (idx, entity) in entity_list.iter_mut().enumerate() {
+            let cur_path = format!("{entity_path}/[{idx}]");
+            let temp = unwrap_tag!(entity,Compound,HashMap::new(),cur_path);
+            let mut entity = HashMap::new();
+            std::mem::swap(&mut entity, temp);
+
+            let entity = common::parse_entity_litematica(entity, &cur_path)?;
+            // check for position
+            let entity_pos_xz = [entity.block_pos[0], entity.block_pos[2]];
+            for dim in 0..2 {
+                if entity_pos_xz[dim] < pos_lb[dim] || entity_pos_xz[dim] > pos_ub[dim] {
+                    return Err(Error::BlockPosOutOfRange {
+                        tag_path: format!("{cur_path}/Pos"),
+                        pos: entity.block_pos,
+                        lower_bound: [pos_lb[0], i32::MIN, pos_lb[1]],
+                        upper_bound: [pos_ub[0], i32::MAX, pos_ub[1]],
+                    });
+                }
+            }
+
+            self.entities.push(entity);
+        }
+        return Ok(());
     }
 
     fn missing_sub_chunks(&self) -> Vec<i8> {
@@ -342,6 +380,103 @@ impl Chunk {
         };
     }
 
+    /// Set block at absolute position `a_pos`, only the lowest 4 bits of x and z are used to find
+    /// the block. Returns error if y is outside the chunk. The block entity and pending ticks at
+    /// `a_pos` are removed, since they belong to the old block. Light is marked as incorrect and
+    /// heightmaps are cleared, so that the game will compute them again.
+    pub fn set_block(&mut self, a_pos: [i32; 3], block: &Block) -> Result<(), Error> {
+        let y_sect = a_pos[1].div_euclid(16);
+        if y_sect < i8::MIN as i32 || y_sect > i8::MAX as i32 {
+            return Err(Error::BlockPosOutOfDimension { pos: a_pos });
+        }
+        let sub_chunk = match self.sub_chunks.get_mut(&(y_sect as i8)) {
+            Some(s) => s,
+            None => return Err(Error::BlockPosOutOfDimension { pos: a_pos }),
+        };
+        let r_pos = [a_pos[0].rem_euclid(16), a_pos[1].rem_euclid(16), a_pos[2].rem_euclid(16)];
+        if let Err(e) = sub_chunk.set_block(r_pos, block) {
+            return Err(match e {
+                Error::BlockPosOutOfDimension { .. } => Error::BlockPosOutOfDimension { pos: a_pos },
+                e => e,
+            });
+        }
+        self.block_entities.remove(&a_pos);
+        self.pending_ticks.remove(&a_pos);
+        self.is_light_on = false;
+        self.heightmaps.clear();
+        return Ok(());
+    }
+
+    /// Save chunk to nbt. The first value is the nbt in region file, and the second is the nbt in
+    /// entities file, which is `None` if there is no entity.
+    pub fn to_nbt(&self, chunk_pos: &ChunkPos, data_version: i32) -> (HashMap<String, Value>, Option<HashMap<String, Value>>) {
+        let global_pos = chunk_pos.to_global_pos();
+        let mut nbt = HashMap::new();
+        nbt.insert("DataVersion".to_string(), Value::Int(data_version));
+        nbt.insert("xPos".to_string(), Value::Int(global_pos.x));
+        nbt.insert("zPos".to_string(), Value::Int(global_pos.z));
+        nbt.insert("yPos".to_string(), Value::Int(self.y_offset() / 16));
+        nbt.insert("Status".to_string(), Value::String(self.status.to_string()));
+        nbt.insert("LastUpdate".to_string(), Value::Long(self.last_update));
+        nbt.insert("InhabitedTime".to_string(), Value::Long(self.inhabited_time));
+        nbt.insert("isLightOn".to_string(), Value::Byte(self.is_light_on as i8));
+        // sections
+        {
+            let mut sections = Vec::with_capacity(self.sub_chunks.len());
+            for (y, sub_chunk) in &self.sub_chunks {
+                sections.push(Value::Compound(section_to_nbt(sub_chunk, *y)));
+            }
+            nbt.insert("sections".to_string(), Value::List(sections));
+        }
+        self.metadata_to_nbt(&mut nbt);
+        // block entities
+        {
+            let mut be_list = Vec::with_capacity(self.block_entities.len());
+            for (pos, be) in &self.block_entities {
+                let mut be_nbt = be.tags.clone();
+                be_nbt.insert("x".to_string(), Value::Int(pos[0]));
+                be_nbt.insert("y".to_string(), Value::Int(pos[1]));
+                be_nbt.insert("z".to_string(), Value::Int(pos[2]));
+                be_list.push(Value::Compound(be_nbt));
+            }
+            nbt.insert("block_entities".to_string(), Value::List(be_list));
+        }
+        // pending ticks
+        {
+            let mut block_ticks = Vec::new();
+            let mut fluid_ticks = Vec::new();
+            for (pos, ticks) in &self.pending_ticks {
+                for tick in ticks {
+                    let tick_nbt = Value::Compound(pending_tick_to_nbt(pos, tick));
+                    match tick.info {
+                        PendingTickInfo::Block { .. } => block_ticks.push(tick_nbt),
+                        PendingTickInfo::Fluid { .. } => fluid_ticks.push(tick_nbt),
+                    }
+                }
+            }
+            nbt.insert("block_ticks".to_string(), Value::List(block_ticks));
+            nbt.insert("fluid_ticks".to_string(), Value::List(fluid_ticks));
+        }
+
+        if self.entities.is_empty() {
+            return (nbt, None);
+        }
+        // entities
+        let mut entity_nbt = HashMap::new();
+        entity_nbt.insert("DataVersion".to_string(), Value::Int(data_version));
+        entity_nbt.insert("Position".to_string(), Value::IntArray(fastnbt::IntArray::new(vec![global_pos.x, global_pos.z])));
+        let mut entity_list = Vec::with_capacity(self.entities.len());
+        for entity in &self.entities {
+            let mut tags = entity.tags.clone();
+            let pos = entity.position.iter().map(|p| Value::Double(*p)).collect();
+            tags.insert("Pos".to_string(), Value::List(pos));
+            entity_list.push(Value::Compound(tags));
+        }
+        entity_nbt.insert("Entities".to_string(), Value::List(entity_list));
+
+        return (nbt, Some(entity_nbt));
+    }
+
 }
 
 pub fn bits_per_block(block_types: usize, min_value: u8) -> u8 {
@@ -468,6 +603,51 @@ fn parse_biomes(reg: &mut SubChunk, sect: &HashMap<String, Value>, path: &str) -
     return Ok(());
 }
 
+/// Parse `SkyLight` and `BlockLight` of a section, missing light arrays are filled with 15
+pub fn parse_light(reg: &mut SubChunk, sect: &HashMap<String, Value>, path: &str) -> Result<(), Error> {
+    let sky_light = if let Some(s) = sect.get("SkyLight") {
+        let tag_path = format!("{path}/SkyLight");
+        let arr = unwrap_tag!(s,ByteArray,fastnbt::ByteArray::new(vec![]),tag_path).as_ref();
+        if arr.len() != 2048 {
+            return Err(Error::InvalidValue { tag_path, error: format!("The length should be 2048, but found {}", arr.len()) });
+        }
+        arr
+    } else {
+        &[]
+    };
+    let block_light = if let Some(s) = sect.get("BlockLight") {
+        let tag_path = format!("{path}/BlockLight");
+        let arr = unwrap_tag!(s,ByteArray,fastnbt::ByteArray::new(vec![]),tag_path).as_ref();
+        if arr.len() != 2048 {
+            return Err(Error::InvalidValue { tag_path, error: format!("The length should be 2048, but found {}", arr.len()) });
+        }
+        arr
+    } else {
+        &[]
+    };
+
+    for counter in 0..4096 {
+        let sl: u8 = if sky_light.is_empty() {
+            15
+        } else {
+            let b = u8::from_ne_bytes(sky_light[counter / 2].to_ne_bytes());
+            (b >> (4 * (counter % 2))) & 0xF
+        };
+        debug_assert!(sl <= 15);
+        let bl: u8 = if block_light.is_empty() {
+            15
+        } else {
+            let b = u8::from_ne_bytes(block_light[counter / 2].to_ne_bytes());
+            (b >> (4 * (counter % 2))) & 0xF
+        };
+        debug_assert!(bl <= 15);
+
+        let light = Light::new(sl, bl);
+        reg.sky_block_light_array[counter] = light;
+    }
+    return Ok(());
+}
+
 fn parse_section(sect: &HashMap<String, Value>, path: &str) -> Result<Option<(SubChunk, i8)>, Error> {
     let mut subchunk = SubChunk::new();
     // let reg = &mut subchunk.region;
@@ -491,53 +671,91 @@ fn parse_section(sect: &HashMap<String, Value>, path: &str) -> Result<Option<(Su
     parse_blocks(&mut subchunk, sect, path)?;
 
     // skylight and block light
-    {
-        let sky_light = if let Some(s) = sect.get("SkyLight") {
-            let tag_path = format!("{path}/SkyLight");
-            let arr = unwrap_tag!(s,ByteArray,fastnbt::ByteArray::new(vec![]),tag_path).as_ref();
-            if arr.len() != 2048 {
-                return Err(Error::InvalidValue { tag_path, error: format!("The length should be 2048, but found {}", arr.len()) });
-            }
-            arr
-        } else {
-            &[]
-        };
-        let block_light = if let Some(s) = sect.get("BlockLight") {
-            let tag_path = format!("{path}/BlockLight");
-            let arr = unwrap_tag!(s,ByteArray,fastnbt::ByteArray::new(vec![]),tag_path).as_ref();
-            if arr.len() != 2048 {
-                return Err(Error::InvalidValue { tag_path, error: format!("The length should be 2048, but found {}", arr.len()) });
+    parse_light(&mut subchunk, sect, path)?;
+
+    //biomes
+    parse_biomes(&mut subchunk, sect, path)?;
+
+    return Ok(Some((subchunk, y_pos)));
+}
+
+fn block_to_nbt(block: &Block) -> Value {
+    let mut nbt = block.to_nbt();
+    if block.namespace.is_empty() {
+        nbt.insert("Name".to_string(), Value::String(format!("minecraft:{}", block.id)));
+    }
+    return Value::Compound(nbt);
+}
+
+fn blocks_to_nbt(reg: &SubChunk) -> HashMap<String, Value> {
+    let mut block_states = HashMap::new();
+    let palette = reg.palette.iter().map(block_to_nbt).collect();
+    block_states.insert("palette".to_string(), Value::List(palette));
+    if reg.palette.len() > 1 {
+        let mut mbs = MultiBitSet::new(4096, bits_per_block(reg.palette.len(), 4));
+        for (idx, blk_id) in reg.block_id_array.iter().enumerate() {
+            mbs.set(idx, *blk_id as u64);
+        }
+        block_states.insert("data".to_string(), Value::LongArray(mbs.to_nbt_array()));
+    }
+    return block_states;
+}
+
+fn biomes_to_nbt(reg: &SubChunk) -> HashMap<String, Value> {
+    let mut biome_pal: Vec<Biome> = Vec::new();
+    let mut indices = [0u64; 64];
+    for (counter, biome) in reg.biome_array.iter().enumerate() {
+        let idx = match biome_pal.iter().position(|b| b == biome) {
+            Some(idx) => idx,
+            None => {
+                biome_pal.push(*biome);
+                biome_pal.len() - 1
             }
-            arr
-        } else {
-            &[]
         };
+        indices[counter] = idx as u64;
+    }
 
-        for counter in 0..4096 {
-            let sl: u8 = if sky_light.is_empty() {
-                15
-            } else {
-                let b = u8::from_ne_bytes(sky_light[counter / 2].to_ne_bytes());
-                (b >> (4 * (counter % 2))) & 0xF
-            };
-            debug_assert!(sl <= 15);
-            let bl: u8 = if block_light.is_empty() {
-                15
-            } else {
-                let b = u8::from_ne_bytes(block_light[counter / 2].to_ne_bytes());
-                (b >> (4 * (counter % 2))) & 0xF
-            };
-            debug_assert!(bl <= 15);
-
-            let light = Light::new(sl, bl);
-            subchunk.sky_block_light_array[counter] = light;
+    let mut biomes = HashMap::new();
+    let palette = biome_pal.iter().map(|b| Value::String(format!("minecraft:{b}"))).collect();
+    biomes.insert("palette".to_string(), Value::List(palette));
+    if biome_pal.len() > 1 {
+        let mut mbs = MultiBitSet::new(64, bits_per_block(biome_pal.len(), 1));
+        for (counter, idx) in indices.iter().enumerate() {
+            mbs.set(counter, *idx);
         }
+        biomes.insert("data".to_string(), Value::LongArray(mbs.to_nbt_array()));
     }
+    return biomes;
+}
 
-    //biomes
-    parse_biomes(&mut subchunk, sect, path)?;
+fn section_to_nbt(reg: &SubChunk, y: i8) -> HashMap<String, Value> {
+    let mut sect = HashMap::new();
+    sect.insert("Y".to_string(), Value::Byte(y));
+    sect.insert("block_states".to_string(), Value::Compound(blocks_to_nbt(reg)));
+    sect.insert("biomes".to_string(), Value::Compound(biomes_to_nbt(reg)));
+    // skylight and block light, 2 values in a byte, the lower 4 bits comes first
+    let mut sky_light = vec![0i8; 2048];
+    let mut block_light = vec![0i8; 2048];
+    for (counter, light) in reg.sky_block_light_array.iter().enumerate() {
+        let shift = 4 * (counter % 2);
+        sky_light[counter / 2] |= (light.sky_light() << shift) as i8;
+        block_light[counter / 2] |= (light.block_light() << shift) as i8;
+    }
+    sect.insert("SkyLight".to_string(), Value::ByteArray(fastnbt::ByteArray::new(sky_light)));
+    sect.insert("BlockLight".to_string(), Value::ByteArray(fastnbt::ByteArray::new(block_light)));
+    return sect;
+}
 
-    return Ok(Some((subchunk, y_pos)));
+fn pending_tick_to_nbt(pos: &[i32; 3], tick: &PendingTick) -> HashMap<String, Value> {
+    let mut nbt = common::size_to_compound(pos);
+    let id = match &tick.info {
+        PendingTickInfo::Block { id } => id,
+        PendingTickInfo::Fluid { id } => id,
+    };
+    nbt.insert("i".to_string(), Value::String(id.clone()));
+    nbt.insert("p".to_string(), Value::Int(tick.priority));
+    nbt.insert("t".to_string(), Value::Int(tick.time));
+    return nbt;
 }
 
 // MultiBitSet in chunk.rs and litematic.rs is different. MC doesn't allow to separate an element
@@ -611,6 +829,11 @@ impl MultiBitSet {
         return self.array.len();
     }
 
+    pub fn to_nbt_array(&self) -> fastnbt::LongArray {
+        let array = self.array.iter().map(|val| i64::from_be_bytes(val.to_be_bytes())).collect();
+        return fastnbt::LongArray::new(array);
+    }
+
     pub fn set_array_from_nbt(&mut self, i64_ne: &[i64]) {
         self.array.clear();
         self.array.reserve(i64_ne.len());
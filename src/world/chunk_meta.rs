@@ -0,0 +1,339 @@
+/*
+mc_schem is a rust library to generate, load, manipulate and save minecraft schematic files.
+Copyright (C) 2024  joseph
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use fastnbt::Value;
+
+use crate::{unwrap_opt_tag, unwrap_tag};
+use crate::error::Error;
+use crate::schem::id_of_nbt_tag;
+use crate::world::{Chunk, ChunkPos, Heightmap, StructureStart, XZCoordinate};
+use crate::world::chunk::bits_per_block;
+use crate::world::legacy_chunk::packed_element;
+
+impl Heightmap {
+    /// Bits of every height, heights are stored relative to the bottom of chunk
+    fn element_bits(y_range: &Range<i32>) -> u8 {
+        return bits_per_block((y_range.end - y_range.start + 1) as usize, 1);
+    }
+
+    /// Parse heightmap from packed longs. Before 20w17a(1.16), an element may be separated into 2
+    /// longs.
+    pub fn from_nbt(array: &[i64], y_range: &Range<i32>, spanning: bool, tag_path: &str) -> Result<Heightmap, Error> {
+        let bits = Self::element_bits(y_range);
+        let required_len = if spanning {
+            (256 * bits as usize).div_ceil(64)
+        } else {
+            let num_per_u64 = 64 / bits as usize;
+            256_usize.div_ceil(num_per_u64)
+        };
+        if array.len() != required_len {
+            return Err(Error::InvalidValue {
+                tag_path: tag_path.to_string(),
+                error: format!("A heightmap of {} blocks high requires {required_len} i64 elements, but found {}",
+                               y_range.end - y_range.start, array.len()),
+            });
+        }
+        let mut heights = [[0; 16]; 16];
+        for idx in 0..256 {
+            let height = packed_element(array, bits, idx, spanning) as i32;
+            heights[idx / 16][idx % 16] = y_range.start + height;
+        }
+        return Ok(Heightmap { heights });
+    }
+
+    pub fn to_nbt(&self, y_range: &Range<i32>) -> fastnbt::LongArray {
+        let bits = Self::element_bits(y_range) as usize;
+        let num_per_u64 = 64 / bits;
+        let mut array = vec![0u64; 256_usize.div_ceil(num_per_u64)];
+        for idx in 0..256 {
+            let height = (self.heights[idx / 16][idx % 16] - y_range.start) as u64;
+            array[idx / num_per_u64] |= height << ((idx % num_per_u64) * bits);
+        }
+        return fastnbt::LongArray::new(array.iter().map(|v| i64::from_ne_bytes(v.to_ne_bytes())).collect());
+    }
+
+    /// Height at relative position in chunk, only the lowest 4 bits of x and z are used
+    pub fn height_at(&self, x: i32, z: i32) -> i32 {
+        return self.heights[z.rem_euclid(16) as usize][x.rem_euclid(16) as usize];
+    }
+}
+
+impl StructureStart {
+    /// Parse a structure start, returns `None` if the id is `INVALID`, which means no structure.
+    pub fn from_nbt(nbt: HashMap<String, Value>, tag_path: &str) -> Result<Option<StructureStart>, Error> {
+        let id = unwrap_opt_tag!(nbt.get("id"),String,"".to_string(),format!("{tag_path}/id")).clone();
+        if id == "INVALID" {
+            return Ok(None);
+        }
+        let chunk_x = *unwrap_opt_tag!(nbt.get("ChunkX"),Int,0,format!("{tag_path}/ChunkX"));
+        let chunk_z = *unwrap_opt_tag!(nbt.get("ChunkZ"),Int,0,format!("{tag_path}/ChunkZ"));
+        let mut piece_boxes = Vec::new();
+        if let Some(children) = nbt.get("Children") {
+            let children = unwrap_tag!(children,List,vec![],format!("{tag_path}/Children"));
+            piece_boxes.reserve(children.len());
+            for (idx, child) in children.iter().enumerate() {
+                let path = format!("{tag_path}/Children[{idx}]");
+                let child = unwrap_tag!(child,Compound,HashMap::new(),path);
+                let bb_path = format!("{path}/BB");
+                let bb = unwrap_opt_tag!(child.get("BB"),IntArray,fastnbt::IntArray::new(vec![]),bb_path);
+                if bb.len() != 6 {
+                    return Err(Error::InvalidValue { tag_path: bb_path, error: format!("The length should be 6, but found {}", bb.len()) });
+                }
+                piece_boxes.push([bb[0], bb[1], bb[2], bb[3], bb[4], bb[5]]);
+            }
+        }
+        return Ok(Some(StructureStart {
+            id,
+            chunk_pos: ChunkPos::from_global_pos(&XZCoordinate { x: chunk_x, z: chunk_z }),
+            piece_boxes,
+            nbt,
+        }));
+    }
+
+    /// Bounding box of all pieces, `None` if there is no piece
+    pub fn bounding_box(&self) -> Option<[i32; 6]> {
+        let mut result = *self.piece_boxes.first()?;
+        for bb in &self.piece_boxes {
+            for dim in 0..3 {
+                result[dim] = result[dim].min(bb[dim]);
+                result[dim + 3] = result[dim + 3].max(bb[dim + 3]);
+            }
+        }
+        return Some(result);
+    }
+}
+
+fn chunk_pos_of_long(val: i64) -> ChunkPos {
+    let val = u64::from_ne_bytes(val.to_ne_bytes());
+    let x = (val & 0xFFFFFFFF) as u32 as i32;
+    let z = (val >> 32) as u32 as i32;
+    return ChunkPos::from_global_pos(&XZCoordinate { x, z });
+}
+
+fn chunk_pos_to_long(pos: &ChunkPos) -> i64 {
+    let global = pos.to_global_pos();
+    let val = (global.x as u32 as u64) | ((global.z as u32 as u64) << 32);
+    return i64::from_ne_bytes(val.to_ne_bytes());
+}
+
+impl Chunk {
+    /// Parse heightmaps, structures, post processing, blending data and carving masks. Sub chunks
+    /// should be parsed before. Tags of chunks before 1.18 are slightly different, which is
+    /// selected by `legacy`, and `spanning` marks if packed heightmaps are before 20w17a.
+    pub fn parse_metadata(&mut self, nbt: &mut HashMap<String, Value>, path: &str, chunk_pos: &ChunkPos,
+                          legacy: bool, spanning: bool) -> Result<(), Error> {
+        let y_range = self.y_range();
+        // heightmaps
+        if let Some(tag) = nbt.get("Heightmaps") {
+            let heightmaps = unwrap_tag!(tag,Compound,HashMap::new(),format!("{path}/Heightmaps"));
+            for (name, array) in heightmaps {
+                let tag_path = format!("{path}/Heightmaps/{name}");
+                let array = unwrap_tag!(array,LongArray,fastnbt::LongArray::new(vec![]),tag_path);
+                self.heightmaps.insert(name.clone(), Heightmap::from_nbt(array, &y_range, spanning, &tag_path)?);
+            }
+        }
+        // structures
+        let (structures_key, starts_key) = if legacy { ("Structures", "Starts") } else { ("structures", "starts") };
+        if let Some(tag) = nbt.get_mut(structures_key) {
+            let structures_path = format!("{path}/{structures_key}");
+            let structures = unwrap_tag!(tag,Compound,HashMap::new(),structures_path);
+            if let Some(starts) = structures.remove(starts_key) {
+                let starts_path = format!("{structures_path}/{starts_key}");
+                let starts = unwrap_tag!(starts,Compound,HashMap::new(),starts_path);
+                for (name, start) in starts {
+                    let tag_path = format!("{starts_path}/{name}");
+                    let start = unwrap_tag!(start,Compound,HashMap::new(),tag_path);
+                    if let Some(start) = StructureStart::from_nbt(start, &tag_path)? {
+                        self.structure_starts.insert(name, start);
+                    }
+                }
+            }
+            if let Some(references) = structures.get("References") {
+                let refs_path = format!("{structures_path}/References");
+                let references = unwrap_tag!(references,Compound,HashMap::new(),refs_path);
+                for (name, array) in references {
+                    let array = unwrap_tag!(array,LongArray,fastnbt::LongArray::new(vec![]),format!("{refs_path}/{name}"));
+                    if !array.is_empty() {
+                        self.structure_references.insert(name.clone(), array.iter().map(|v| chunk_pos_of_long(*v)).collect());
+                    }
+                }
+            }
+        }
+        // post processing
+        if let Some(tag) = nbt.get("PostProcessing") {
+            let pp_path = format!("{path}/PostProcessing");
+            let lists = unwrap_tag!(tag,List,vec![],pp_path);
+            let pos_lb = chunk_pos.block_pos_lower_bound();
+            for (sect_idx, list) in lists.iter().enumerate() {
+                let list_path = format!("{pp_path}[{sect_idx}]");
+                let list = unwrap_tag!(list,List,vec![],list_path);
+                for (idx, packed) in list.iter().enumerate() {
+                    let packed = *unwrap_tag!(packed,Short,0,format!("{list_path}[{idx}]")) as i32;
+                    self.post_processing.push([
+                        pos_lb[0] + (packed & 15),
+                        y_range.start + sect_idx as i32 * 16 + ((packed >> 4) & 15),
+                        pos_lb[1] + ((packed >> 8) & 15)]);
+                }
+            }
+        }
+        // blending data
+        if let Some(tag) = nbt.remove("blending_data") {
+            self.blending_data = Some(unwrap_tag!(tag,Compound,HashMap::new(),format!("{path}/blending_data")));
+        }
+        // carving masks, stored as bytes of java BitSet before 1.18
+        if let Some(tag) = nbt.get("CarvingMasks") {
+            let masks_path = format!("{path}/CarvingMasks");
+            let masks = unwrap_tag!(tag,Compound,HashMap::new(),masks_path);
+            for (name, mask) in masks {
+                let words: Vec<u64> = match mask {
+                    Value::LongArray(arr) => arr.iter().map(|v| u64::from_ne_bytes(v.to_ne_bytes())).collect(),
+                    Value::ByteArray(arr) => arr.chunks(8).map(|bytes| {
+                        let mut le = [0u8; 8];
+                        for (idx, b) in bytes.iter().enumerate() {
+                            le[idx] = u8::from_ne_bytes(b.to_ne_bytes());
+                        }
+                        u64::from_le_bytes(le)
+                    }).collect(),
+                    _ => return Err(Error::TagTypeMismatch {
+                        tag_path: format!("{masks_path}/{name}"),
+                        expected_type: id_of_nbt_tag(&Value::LongArray(fastnbt::LongArray::new(vec![]))),
+                        found_type: id_of_nbt_tag(mask),
+                    }),
+                };
+                self.carving_masks.insert(name.clone(), words);
+            }
+        }
+        return Ok(());
+    }
+
+    /// Write heightmaps, structures, post processing, blending data and carving masks in 1.18+
+    /// format
+    pub fn metadata_to_nbt(&self, nbt: &mut HashMap<String, Value>) {
+        let y_range = self.y_range();
+        if !self.heightmaps.is_empty() {
+            let heightmaps = self.heightmaps.iter()
+                .map(|(name, hm)| (name.clone(), Value::LongArray(hm.to_nbt(&y_range)))).collect();
+            nbt.insert("Heightmaps".to_string(), Value::Compound(heightmaps));
+        }
+        {
+            let starts = self.structure_starts.iter()
+                .map(|(name, start)| (name.clone(), Value::Compound(start.nbt.clone()))).collect();
+            let references = self.structure_references.iter()
+                .map(|(name, refs)| (name.clone(),
+                                     Value::LongArray(fastnbt::LongArray::new(refs.iter().map(chunk_pos_to_long).collect()))))
+                .collect();
+            let structures = HashMap::from([
+                ("starts".to_string(), Value::Compound(starts)),
+                ("References".to_string(), Value::Compound(references)),
+            ]);
+            nbt.insert("structures".to_string(), Value::Compound(structures));
+        }
+        if !self.post_processing.is_empty() {
+            let num_sections = ((y_range.end - y_range.start) / 16) as usize;
+            let mut lists = vec![Vec::new(); num_sections];
+            for pos in &self.post_processing {
+                let sect_idx = ((pos[1] - y_range.start) / 16) as usize;
+                let packed = (pos[0] & 15) | ((pos[1] - y_range.start) & 15) << 4 | (pos[2] & 15) << 8;
+                lists[sect_idx].push(Value::Short(packed as i16));
+            }
+            nbt.insert("PostProcessing".to_string(), Value::List(lists.into_iter().map(Value::List).collect()));
+        }
+        if let Some(blending_data) = &self.blending_data {
+            nbt.insert("blending_data".to_string(), Value::Compound(blending_data.clone()));
+        }
+        if !self.carving_masks.is_empty() {
+            let masks = self.carving_masks.iter()
+                .map(|(name, words)| (name.clone(), Value::LongArray(fastnbt::LongArray::new(
+                    words.iter().map(|v| i64::from_ne_bytes(v.to_ne_bytes())).collect()))))
+                .collect();
+            nbt.insert("CarvingMasks".to_string(), Value::Compound(masks));
+        }
+    }
+
+    /// Get heightmap by type, like `MOTION_BLOCKING`
+    pub fn heightmap(&self, kind: &str) -> Option<&Heightmap> {
+        return self.heightmaps.get(kind);
+    }
+
+    /// Returns if the block at absolute position is carved in carving step `step`, like `AIR`.
+    /// Only the lowest 4 bits of x and z are used.
+    pub fn is_carved(&self, step: &str, a_pos: [i32; 3]) -> bool {
+        let words = match self.carving_masks.get(step) {
+            Some(w) => w,
+            None => return false,
+        };
+        let y = a_pos[1] - self.y_range().start;
+        if y < 0 {
+            return false;
+        }
+        let bit = (a_pos[0].rem_euclid(16) | a_pos[2].rem_euclid(16) << 4 | y << 8) as usize;
+        return match words.get(bit / 64) {
+            Some(w) => (w >> (bit % 64)) & 1 != 0,
+            None => false,
+        };
+    }
+}
+
+#[test]
+fn test_chunk_metadata() {
+    let mut chunk = Chunk::with_y_range(-64..320);
+    let pos = ChunkPos::from_global_pos(&XZCoordinate { x: -2, z: 5 });
+    let mut heights = [[0; 16]; 16];
+    for z in 0..16 {
+        for x in 0..16 {
+            heights[z][x] = -64 + (x * 16 + z) as i32;
+        }
+    }
+    chunk.heightmaps.insert("WORLD_SURFACE".to_string(), Heightmap { heights });
+    chunk.structure_references.insert("minecraft:village_plains".to_string(),
+                                      vec![ChunkPos::from_global_pos(&XZCoordinate { x: -3, z: -7 })]);
+    chunk.post_processing.push([-17, 70, 93]);
+    chunk.carving_masks.insert("AIR".to_string(), vec![0; 16 * 16 * 384 / 64]);
+    chunk.carving_masks.get_mut("AIR").unwrap()[(1 | 2 << 4 | 3 << 8) / 64] |= 1 << ((1 | 2 << 4 | 3 << 8) % 64);
+
+    let mut nbt = HashMap::new();
+    chunk.metadata_to_nbt(&mut nbt);
+    let start = HashMap::from([
+        ("id".to_string(), Value::String("minecraft:village_plains".to_string())),
+        ("ChunkX".to_string(), Value::Int(-2)),
+        ("ChunkZ".to_string(), Value::Int(5)),
+        ("Children".to_string(), Value::List(vec![
+            Value::Compound(HashMap::from([("BB".to_string(), Value::IntArray(fastnbt::IntArray::new(vec![-32, 60, 80, -20, 70, 90])))])),
+            Value::Compound(HashMap::from([("BB".to_string(), Value::IntArray(fastnbt::IntArray::new(vec![-40, 64, 85, -30, 75, 88])))])),
+        ])),
+    ]);
+    if let Some(Value::Compound(structures)) = nbt.get_mut("structures") {
+        structures.insert("starts".to_string(), Value::Compound(HashMap::from([("minecraft:village_plains".to_string(), Value::Compound(start))])));
+    }
+
+    let mut parsed = Chunk::with_y_range(-64..320);
+    parsed.parse_metadata(&mut nbt, "", &pos, false, false).unwrap();
+    assert_eq!(parsed.heightmap("WORLD_SURFACE"), chunk.heightmap("WORLD_SURFACE"));
+    assert_eq!(parsed.heightmap("WORLD_SURFACE").unwrap().height_at(3, 2), -64 + 50);
+    assert_eq!(parsed.structure_references, chunk.structure_references);
+    assert_eq!(parsed.post_processing, chunk.post_processing);
+    assert!(parsed.is_carved("AIR", [1, -61, 2]));
+    assert!(!parsed.is_carved("AIR", [1, -60, 2]));
+    assert!(!parsed.is_carved("LIQUID", [1, -61, 2]));
+    let start = &parsed.structure_starts["minecraft:village_plains"];
+    assert_eq!(start.chunk_pos, pos);
+    assert_eq!(start.bounding_box(), Some([-40, 60, 80, -20, 75, 90]));
+}
@@ -6,13 +6,13 @@ use crate::world::{AbsolutePosIndexed, ChunkRefAbsolutePos, ChunkRefRelativePos,
 impl ChunkRefRelativePos<'_> {
     fn y_pos_to_section_number(&self, y_r: i32) -> i8 {
         let y_a = y_r + self.chunk.y_offset();
-        return (y_a / 16) as i8;
+        return y_a.div_euclid(16) as i8;
     }
 
     fn to_sub_chunk_r_pos(&self, r_pos: [i32; 3]) -> (i8, &SubChunk, [i32; 3]) {
         let y_sect_num = self.y_pos_to_section_number(r_pos[1]);
         let sect = &self.chunk.sub_chunks[&y_sect_num];
-        let y_offset = y_sect_num as i32 * 16;
+        let y_offset = y_sect_num as i32 * 16 - self.chunk.y_offset();
         debug_assert!(r_pos[1] - y_offset >= 0);
         let pos = [r_pos[0], r_pos[1] - y_offset, r_pos[2]];
         debug_assert!(sect.contains_coord(pos));
@@ -80,7 +80,7 @@ impl WorldSlice for ChunkRefRelativePos<'_> {
 
 impl<'s, 'chunk: 's> ChunkRefAbsolutePos<'chunk> {
     fn to_sub_chunk_r_pos(&'s self, a_pos: [i32; 3]) -> (i8, &'chunk SubChunk, [i32; 3]) {
-        let sect_number = (a_pos[1] / 16) as i8;
+        let sect_number = a_pos[1].div_euclid(16) as i8;
         debug_assert!(self.chunk.sub_chunks.contains_key(&sect_number));
         let sub_chunk: &'chunk SubChunk = self.chunk.sub_chunks.get(&sect_number).unwrap();
         let o = self.offset();
@@ -141,4 +141,35 @@ impl<'s, 'chunk: 's> AbsolutePosIndexed<'s, 'chunk> for ChunkRefAbsolutePos<'chu
             &[]
         };
     }
-}
\ No newline at end of file
+}
+#[test]
+fn test_chunk_ref_negative_coordinates() {
+    use crate::world::{Chunk, ChunkPos, Dimension, XZCoordinate};
+
+    let mut sub_chunk = SubChunk::new();
+    sub_chunk.palette = vec![Block::air(), Block::from_id("stone").unwrap()];
+    // x=1, y=2, z=3 in yzx order
+    sub_chunk.block_id_array[2 * 256 + 3 * 16 + 1] = 1;
+    assert_eq!(sub_chunk.block_at([1, 2, 3]).unwrap().id, "stone");
+    assert!(sub_chunk.block_at([3, 2, 1]).unwrap().is_air());
+
+    let mut chunk = Chunk::new();
+    chunk.sub_chunks.insert(-1, sub_chunk);
+    let mut upper = SubChunk::new();
+    upper.palette = vec![Block::air()];
+    chunk.sub_chunks.insert(0, upper);
+    assert_eq!(chunk.y_offset(), -16);
+
+    let chunk_pos = ChunkPos::from_global_pos(&XZCoordinate { x: -1, z: -1 });
+    let relative = chunk.as_relative_pos(&chunk_pos);
+    assert_eq!(relative.block_at([1, 2, 3]).unwrap().id, "stone");
+    assert!(relative.block_at([1, 18, 3]).unwrap().is_air());
+
+    let absolute = chunk.as_absolute_pos(&chunk_pos);
+    assert_eq!(absolute.block_at([-15, -14, -13]).unwrap().id, "stone");
+    assert!(absolute.block_at([-15, 2, -13]).unwrap().is_air());
+
+    let (pos, y) = Dimension::block_pos_to_chunk_pos([-15, -14, -13]);
+    assert_eq!(pos, chunk_pos);
+    assert_eq!(y, -1);
+}
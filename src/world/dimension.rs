@@ -1,5 +1,6 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::ops::Range;
+use std::path::Path;
 use std::sync::mpsc::{channel, Receiver};
 #[allow(unused_imports)]
 use std::time;
@@ -7,13 +8,15 @@ use fastnbt::Value;
 use flate2::read::GzDecoder;
 use crate::{Error, unwrap_opt_tag, unwrap_tag};
 #[allow(unused_imports)]
-use crate::world::{AbsolutePosIndexed, Chunk, ChunkPos, ChunkRefAbsolutePos, ChunkVariant, Dimension, FileInfo, FilesInMemory, FilesRead, mca, RefOrObject, XZCoordinate};
+use crate::world::{AbsolutePosIndexed, Chunk, ChunkPos, ChunkRefAbsolutePos, ChunkVariant, Dimension, FileInfo, FilesInMemory, FilesRead, MCASaveOption, mca, PasteOption, poi, RefOrObject, XZCoordinate};
 use rayon::prelude::*;
 use crate::block::Block;
+use crate::image::RgbaImage;
+use crate::map::render_top_down;
 use crate::error::unwrap_opt_i32;
 use crate::raid::{Raid, RaidList};
-use crate::region::{BlockEntity, HasOffset, PendingTick};
-use crate::schem::id_of_nbt_tag;
+use crate::region::{BlockEntity, HasOffset, PendingTick, Region, WorldSlice};
+use crate::schem::{id_of_nbt_tag, Schematic};
 
 impl<T> RefOrObject<'_, T> {
     pub fn to_ref(&self) -> &T {
@@ -129,17 +132,24 @@ impl Dimension {
                                                  parse_directly)?;
 
         let raids = parse_raids(&files.sub_directory("data"))?;
+        // poi files are parsed with chunks, or later by load_poi
+        let poi = if parse_directly {
+            poi::parse_poi_regions(&files.sub_directory("poi"))?
+        } else {
+            HashMap::new()
+        };
 
         return Ok(Dimension {
             chunks,
             y_range,
             raids,
+            poi,
         });
     }
 
     pub fn block_pos_to_chunk_pos(block_pos: [i32; 3]) -> (ChunkPos, i8) {
-        let cpos = ChunkPos::from_global_pos(&XZCoordinate { x: block_pos[0] / 16, z: block_pos[2] / 16 });
-        let y = block_pos[1] / 16;
+        let cpos = ChunkPos::from_global_pos(&XZCoordinate { x: block_pos[0].div_euclid(16), z: block_pos[2].div_euclid(16) });
+        let y = block_pos[1].div_euclid(16);
         return (cpos, y as i8);
     }
 
@@ -227,6 +237,234 @@ impl Dimension {
     }
 }
 
+impl Default for PasteOption {
+    fn default() -> Self {
+        return Self {
+            skip_air: false,
+            skip_structure_void: true,
+            invalidate_lighting: true,
+            regenerate_uuid: true,
+        };
+    }
+}
+
+impl Dimension {
+    /// Copy blocks, block entities, pending ticks and entities in the box from `min` to
+    /// `max`(both inclusive) to a new region, positions are converted to be relative to `min`.
+    /// Unparsed chunks are parsed temporarily, and blocks in missing chunks are air.
+    pub fn extract(&self, min: [i32; 3], max: [i32; 3]) -> Result<Region, Error> {
+        let lb = [min[0].min(max[0]), min[1].min(max[1]), min[2].min(max[2])];
+        let ub = [min[0].max(max[0]), min[1].max(max[1]), min[2].max(max[2])];
+        let mut region = Region::with_shape([ub[0] - lb[0] + 1, ub[1] - lb[1] + 1, ub[2] - lb[2] + 1]);
+        let inside = |pos: &[i32; 3]| (0..3).all(|dim| pos[dim] >= lb[dim] && pos[dim] <= ub[dim]);
+        let relative = |pos: &[i32; 3]| [pos[0] - lb[0], pos[1] - lb[1], pos[2] - lb[2]];
+
+        for chunk_x in lb[0].div_euclid(16)..=ub[0].div_euclid(16) {
+            for chunk_z in lb[2].div_euclid(16)..=ub[2].div_euclid(16) {
+                let chunk_pos = ChunkPos::from_global_pos(&XZCoordinate { x: chunk_x, z: chunk_z });
+                let chunk = match self.chunks.get(&chunk_pos) {
+                    Some(variant) => variant.check(&chunk_pos)?,
+                    None => continue,
+                };
+                let chunk = chunk.to_ref();
+                let chunk_lb = chunk_pos.block_pos_lower_bound();
+
+                for (sect_y, sub_chunk) in &chunk.sub_chunks {
+                    let sect_y = *sect_y as i32 * 16;
+                    if sect_y + 15 < lb[1] || sect_y > ub[1] {
+                        continue;
+                    }
+                    let lut: Vec<u16> = sub_chunk.palette.iter()
+                        .map(|blk| region.find_or_append_to_palette(blk))
+                        .collect();
+                    for y in sect_y.max(lb[1])..=(sect_y + 15).min(ub[1]) {
+                        for z in chunk_lb[1].max(lb[2])..=(chunk_lb[1] + 15).min(ub[2]) {
+                            for x in chunk_lb[0].max(lb[0])..=(chunk_lb[0] + 15).min(ub[0]) {
+                                let idx = sub_chunk.block_index_at([x - chunk_lb[0], y - sect_y, z - chunk_lb[1]]).unwrap();
+                                let r_pos = relative(&[x, y, z]);
+                                region.array_yzx[[r_pos[1] as usize, r_pos[2] as usize, r_pos[0] as usize]] = lut[idx as usize];
+                            }
+                        }
+                    }
+                }
+
+                for (pos, be) in &chunk.block_entities {
+                    if inside(pos) {
+                        region.block_entities.insert(relative(pos), be.clone());
+                    }
+                }
+                for (pos, ticks) in &chunk.pending_ticks {
+                    if inside(pos) {
+                        region.pending_ticks.insert(relative(pos), ticks.clone());
+                    }
+                }
+                for entity in &chunk.entities {
+                    if inside(&entity.block_pos) {
+                        let mut entity = entity.clone();
+                        entity.pos_shift([-lb[0], -lb[1], -lb[2]]);
+                        region.entities.push(entity);
+                    }
+                }
+            }
+        }
+        region.shrink_palette()?;
+        return Ok(region);
+    }
+
+    /// Render the box from `min` to `max`(both inclusive) as a map with 1 block per pixel, see
+    /// `map::render_top_down`.
+    pub fn render_top_down(&self, min: [i32; 3], max: [i32; 3]) -> Result<RgbaImage, Error> {
+        let region = self.extract(min, max)?;
+        return Ok(render_top_down(&region));
+    }
+
+    /// Set block at absolute position `a_pos`, the chunk is parsed in place if it's unparsed. The
+    /// block entity and pending ticks at `a_pos` are removed.
+    pub fn set_block(&mut self, a_pos: [i32; 3], block: &Block) -> Result<(), Error> {
+        let (chunk_pos, _) = Self::block_pos_to_chunk_pos(a_pos);
+        let chunk = match self.chunks.get_mut(&chunk_pos) {
+            Some(variant) => variant.parse_inplace(&chunk_pos)?,
+            None => return Err(Error::BlockPosOutOfDimension { pos: a_pos }),
+        };
+        return chunk.set_block(a_pos, block);
+    }
+
+    /// Save all chunks to `region` and `entities` directories under `path`. Parsed chunks are
+    /// serialized again with their own data version, while unparsed chunks are saved with their
+    /// original nbt. Parsed chunks before 21w43a(1.18) can't be saved, as only the 1.18+ layout
+    /// is written.
+    pub fn save_to_folder(&self, path: impl AsRef<Path>, option: &MCASaveOption) -> Result<(), Error> {
+        let path = path.as_ref();
+        return mca::write_multiple_regions(&self.chunks,
+                                           &path.join("region"),
+                                           &path.join("entities"),
+                                           None,
+                                           option);
+    }
+
+    /// Like `save_to_folder`, but only mca files containing any chunk in `chunks` are written,
+    /// other mca files in `path` are untouched.
+    pub fn save_chunks_to_folder(&self, path: impl AsRef<Path>, chunks: &HashSet<ChunkPos>, option: &MCASaveOption) -> Result<(), Error> {
+        let path = path.as_ref();
+        let files: HashSet<(i32, i32)> = chunks.iter()
+            .map(|pos| {
+                let file_coord = pos.file_coordinate();
+                (file_coord.x, file_coord.z)
+            })
+            .collect();
+        return mca::write_multiple_regions(&self.chunks,
+                                           &path.join("region"),
+                                           &path.join("entities"),
+                                           Some(&files),
+                                           option);
+    }
+
+    fn chunk_for_paste(&mut self, chunk_pos: &ChunkPos) -> Result<&mut Chunk, Error> {
+        if !self.chunks.contains_key(chunk_pos) {
+            let chunk = Chunk::with_y_range(self.y_range.clone());
+            self.chunks.insert(*chunk_pos, ChunkVariant::Parsed(Box::new(chunk)));
+        }
+        return self.chunks.get_mut(chunk_pos).unwrap().parse_inplace(chunk_pos);
+    }
+
+    /// Paste all regions of `schem` into this dimension, the origin of schematic is placed at
+    /// `origin`. Missing chunks are created only if any block or entity is written to them, and
+    /// existing block entities and pending ticks at overwritten blocks are removed. All regions are
+    /// checked before anything is changed, so the dimension is untouched if an error is returned.
+    /// Returns positions of all affected chunks, which can be passed to `save_chunks_to_folder`.
+    pub fn paste(&mut self, schem: &Schematic, origin: [i32; 3], option: &PasteOption) -> Result<HashSet<ChunkPos>, Error> {
+        let global_offset = |reg: &Region| [origin[0] + reg.offset[0], origin[1] + reg.offset[1], origin[2] + reg.offset[2]];
+        for reg in &schem.regions {
+            let shape = reg.shape();
+            let g_offset = global_offset(reg);
+            for a_y in [g_offset[1], g_offset[1] + shape[1] - 1] {
+                if shape[1] > 0 && !self.y_range.contains(&a_y) {
+                    return Err(Error::BlockPosOutOfDimension { pos: [g_offset[0], a_y, g_offset[2]] });
+                }
+            }
+            // parse existing chunks ahead, so that parsing errors are reported before any change
+            let (min, _) = Self::block_pos_to_chunk_pos(g_offset);
+            let (max, _) = Self::block_pos_to_chunk_pos([g_offset[0] + shape[0] - 1, 0, g_offset[2] + shape[2] - 1]);
+            let (min, max) = (min.to_global_pos(), max.to_global_pos());
+            for cx in min.x..=max.x {
+                for cz in min.z..=max.z {
+                    let chunk_pos = ChunkPos::from_global_pos(&XZCoordinate { x: cx, z: cz });
+                    if let Some(variant) = self.chunks.get_mut(&chunk_pos) {
+                        variant.parse_inplace(&chunk_pos)?;
+                    }
+                }
+            }
+        }
+
+        let mut affected = HashSet::new();
+        for reg in &schem.regions {
+            let shape = reg.shape();
+            let g_offset = global_offset(reg);
+            for x in 0..shape[0] {
+                for z in 0..shape[2] {
+                    let ys: Vec<i32> = (0..shape[1]).filter(|y| {
+                        let block = reg.block_at([x, *y, z]).unwrap();
+                        return !((option.skip_air && block.is_air()) || (option.skip_structure_void && block.is_structure_void()));
+                    }).collect();
+                    if ys.is_empty() {
+                        continue;
+                    }
+                    let (chunk_pos, _) = Self::block_pos_to_chunk_pos([g_offset[0] + x, 0, g_offset[2] + z]);
+                    let chunk = self.chunk_for_paste(&chunk_pos)?;
+                    let was_light_on = chunk.is_light_on;
+                    for y in ys {
+                        let r_pos = [x, y, z];
+                        let a_pos = [g_offset[0] + x, g_offset[1] + y, g_offset[2] + z];
+                        chunk.set_block(a_pos, reg.block_at(r_pos).unwrap())?;
+                        if let Some(be) = reg.block_entities.get(&r_pos) {
+                            chunk.block_entities.insert(a_pos, be.clone());
+                        }
+                        if let Some(ticks) = reg.pending_ticks.get(&r_pos) {
+                            chunk.pending_ticks.insert(a_pos, ticks.clone());
+                        }
+                    }
+                    // set_block always invalidates lighting
+                    chunk.is_light_on = was_light_on && !option.invalidate_lighting;
+                    affected.insert(chunk_pos);
+                }
+            }
+
+            for entity in &reg.entities {
+                let mut entity = entity.clone();
+                entity.pos_shift(g_offset);
+                if option.regenerate_uuid {
+                    regenerate_uuid(&mut entity.tags);
+                }
+                let (chunk_pos, _) = Self::block_pos_to_chunk_pos(entity.block_pos);
+                let chunk = self.chunk_for_paste(&chunk_pos)?;
+                chunk.entities.push(entity);
+                affected.insert(chunk_pos);
+            }
+        }
+        return Ok(affected);
+    }
+}
+
+/// Replace UUID of entity and its passengers with random ones, both the int array format(1.16+) and
+/// the pair of longs format are handled.
+fn regenerate_uuid(tags: &mut HashMap<String, Value>) {
+    if let Some(Value::IntArray(_)) = tags.get("UUID") {
+        let uuid: [i32; 4] = rand::random();
+        tags.insert("UUID".to_string(), Value::IntArray(fastnbt::IntArray::new(uuid.to_vec())));
+    }
+    if tags.contains_key("UUIDMost") || tags.contains_key("UUIDLeast") {
+        tags.insert("UUIDMost".to_string(), Value::Long(rand::random()));
+        tags.insert("UUIDLeast".to_string(), Value::Long(rand::random()));
+    }
+    if let Some(Value::List(passengers)) = tags.get_mut("Passengers") {
+        for passenger in passengers {
+            if let Value::Compound(passenger) = passenger {
+                regenerate_uuid(passenger);
+            }
+        }
+    }
+}
+
 impl HasOffset for Dimension {
     fn offset(&self) -> [i32; 3] {
         return [0, 0, 0];
@@ -373,6 +611,201 @@ fn test_load_dimension_mcc_block_entities() {
              parsed.duration_since(decompressed).unwrap().as_millis());
 }
 
+#[test]
+fn test_save_to_folder() {
+    use crate::world::FolderOnDisk;
+    let files = FilesInMemory::from_7z_file("test_files/world/00_1.20.2.7z", "").unwrap();
+    let mut dim = Dimension::from_files(&files, false, -64..320, 0).unwrap();
+    dim.parse_all(0).unwrap();
+
+    let diamond = Block::from_id("minecraft:diamond_block").unwrap();
+    dim.set_block([1, 100, -3], &diamond).unwrap();
+    assert!(dim.set_block([1, 400, -3], &diamond).is_err());
+    // the block entity of the replaced block is removed
+    let be_pos = dim.chunks.keys()
+        .filter_map(|pos| dim.get_chunk(pos))
+        .find_map(|chunk| chunk.block_entities.keys().next().copied());
+    if let Some(be_pos) = be_pos {
+        dim.set_block(be_pos, &diamond).unwrap();
+        let (chunk_pos, _) = Dimension::block_pos_to_chunk_pos(be_pos);
+        assert!(!dim.get_chunk(&chunk_pos).unwrap().block_entities.contains_key(&be_pos));
+    }
+
+    let dir = std::env::temp_dir().join("mc_schem_test_save_to_folder");
+    let _ = std::fs::remove_dir_all(&dir);
+    dim.save_to_folder(&dir, &MCASaveOption::default()).unwrap();
+
+    let saved = FolderOnDisk::new(dir.to_str().unwrap());
+    let chunks = mca::parse_multiple_regions(&saved.sub_directory("region"),
+                                             Some(&saved.sub_directory("entities")),
+                                             -64..320, 0, true).unwrap();
+    let loaded = Dimension { chunks, y_range: -64..320, raids: RaidList::default(), poi: HashMap::new() };
+    assert_eq!(loaded.chunks.len(), dim.chunks.len());
+    assert_eq!(loaded.block_at([1, 100, -3]), Some(&diamond));
+    for (pos, chunk) in &dim.chunks {
+        let chunk = chunk.check(pos).unwrap();
+        let chunk = chunk.to_ref();
+        let loaded_chunk = loaded.get_chunk(pos).unwrap();
+        assert_eq!(chunk.total_blocks(false), loaded_chunk.total_blocks(false));
+        assert_eq!(chunk.block_entities.len(), loaded_chunk.block_entities.len());
+        assert_eq!(chunk.entities.len(), loaded_chunk.entities.len());
+        assert_eq!(chunk.pending_ticks.len(), loaded_chunk.pending_ticks.len());
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_save_chunk_data_version() {
+    use crate::schem::DataVersion;
+    use crate::world::{FolderOnDisk, NBTWithSource};
+    let dir = std::env::temp_dir().join("mc_schem_test_save_chunk_data_version");
+    let _ = std::fs::remove_dir_all(&dir);
+    let option = MCASaveOption::default();
+    let stone = Block::from_id("minecraft:stone").unwrap();
+
+    // chunks saved by a newer version keep their data version, created chunks take the option
+    let newer_pos = ChunkPos::from_global_pos(&XZCoordinate { x: 0, z: 0 });
+    let created_pos = ChunkPos::from_global_pos(&XZCoordinate { x: 1, z: 0 });
+    let mut newer = Chunk::with_y_range(-64..320);
+    newer.data_version = 3953; // 1.21
+    newer.file_region = "r.0.0.mca".to_string();
+    let mut dim = Dimension {
+        chunks: HashMap::from([(newer_pos, ChunkVariant::Parsed(Box::new(newer))),
+            (created_pos, ChunkVariant::Parsed(Box::new(Chunk::with_y_range(-64..320))))]),
+        y_range: -64..320,
+        raids: RaidList::default(),
+        poi: HashMap::new(),
+    };
+    dim.set_block([1, 10, 1], &stone).unwrap();
+    dim.save_to_folder(&dir, &option).unwrap();
+    let saved = FolderOnDisk::new(dir.to_str().unwrap());
+    let chunks = mca::parse_multiple_regions(&saved.sub_directory("region"),
+                                             Some(&saved.sub_directory("entities")),
+                                             -64..320, 0, true).unwrap();
+    let loaded = Dimension { chunks, y_range: -64..320, raids: RaidList::default(), poi: HashMap::new() };
+    assert_eq!(loaded.get_chunk(&newer_pos).unwrap().data_version, 3953);
+    assert_eq!(loaded.get_chunk(&created_pos).unwrap().data_version, option.data_version);
+    assert_eq!(loaded.block_at([1, 10, 1]), Some(&stone));
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    // chunks before 1.18 can't be written in the current layout, including those before 1.9
+    for data_version in [Some(DataVersion::Java_1_12_2 as i32), None] {
+        let level = HashMap::from([
+            ("xPos".to_string(), Value::Int(0)),
+            ("zPos".to_string(), Value::Int(0)),
+            ("LastUpdate".to_string(), Value::Long(100)),
+            ("TerrainPopulated".to_string(), Value::Byte(1)),
+            ("Sections".to_string(), Value::List(vec![])),
+        ]);
+        let mut nbt = HashMap::from([("Level".to_string(), Value::Compound(level))]);
+        if let Some(dv) = data_version {
+            nbt.insert("DataVersion".to_string(), Value::Int(dv));
+        }
+        let old = Chunk::from_nbt(NBTWithSource { nbt, source: "r.0.0.mca" }, None, &newer_pos).unwrap();
+        let old_dim = Dimension {
+            chunks: HashMap::from([(newer_pos, ChunkVariant::Parsed(Box::new(old)))]),
+            y_range: 0..256,
+            raids: RaidList::default(),
+            poi: HashMap::new(),
+        };
+        match old_dim.save_to_folder(&dir, &option) {
+            Err(Error::UnsupportedChunkVersion { data_version: dv, .. }) => assert_eq!(dv, data_version.unwrap_or(0)),
+            other => panic!("Expected UnsupportedChunkVersion, but found {:?}", other),
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
+
+#[test]
+fn test_paste() {
+    let files = FilesInMemory::from_7z_file("test_files/world/00_1.20.2.7z", "").unwrap();
+    let mut dim = Dimension::from_files(&files, false, -64..320, 0).unwrap();
+    let num_chunks = dim.chunks.len();
+
+    let stone = Block::from_id("minecraft:stone").unwrap();
+    let chest = Block::from_id("minecraft:chest[facing=north]").unwrap();
+    let mut reg = Region::with_shape([3, 3, 3]);
+    reg.offset = [1, 0, 0];
+    reg.fill_with(&stone);
+    reg.set_block([1, 1, 1], &chest).unwrap();
+    reg.set_block([0, 2, 0], &Block::structure_void()).unwrap();
+    reg.set_block_entity_at([1, 1, 1], BlockEntity::new());
+    let mut schem = Schematic::new();
+    schem.regions.push(reg);
+
+    let origin = [10000, 70, -10001];
+    let affected = dim.paste(&schem, origin, &PasteOption::default()).unwrap();
+    // x in [10001, 10003], z in [-10001, -9999], crossing chunk border at z = -10000
+    assert_eq!(affected.len(), 2);
+    assert_eq!(dim.chunks.len(), num_chunks + 2);
+    assert_eq!(dim.block_at([10001, 70, -10001]), Some(&stone));
+    assert_eq!(dim.block_at([10002, 71, -10000]), Some(&chest));
+    assert!(dim.block_entity_at([10002, 71, -10000]).is_some());
+    assert!(dim.block_at([10001, 72, -10001]).unwrap().is_air());
+    assert!(dim.block_at([10000, 70, -10001]).unwrap().is_air());
+    for pos in &affected {
+        assert!(!dim.get_chunk(pos).unwrap().is_light_on);
+    }
+
+    assert!(dim.paste(&schem, [0, 319, 0], &PasteOption::default()).is_err());
+
+    // nothing is changed if any region is out of the dimension
+    let mut tall = Schematic::new();
+    let mut low = Region::with_shape([1, 1, 1]);
+    low.fill_with(&stone);
+    let mut high = low.clone();
+    high.offset = [0, 400, 0];
+    tall.regions.push(low);
+    tall.regions.push(high);
+    let num_chunks = dim.chunks.len();
+    assert!(dim.paste(&tall, [20000, 0, 20000], &PasteOption::default()).is_err());
+    assert_eq!(dim.chunks.len(), num_chunks);
+
+    // chunks are not created for columns that are skipped, and entities get new UUIDs
+    let mut reg = Region::with_shape([1, 1, 2]);
+    reg.fill_with(&stone);
+    reg.set_block([0, 0, 1], &Block::structure_void()).unwrap();
+    let mut entity = crate::region::Entity::new();
+    entity.tags.insert("UUID".to_string(), Value::IntArray(fastnbt::IntArray::new(vec![1, 2, 3, 4])));
+    reg.entities.push(entity);
+    let mut voids = Schematic::new();
+    voids.regions.push(reg);
+    let pasted = dim.paste(&voids, [30000, 70, 15], &PasteOption::default()).unwrap();
+    assert_eq!(pasted.len(), 1);
+    assert_eq!(dim.chunks.len(), num_chunks + 1);
+    let chunk = dim.get_chunk(pasted.iter().next().unwrap()).unwrap();
+    assert_ne!(chunk.entities[0].tags.get("UUID"), Some(&Value::IntArray(fastnbt::IntArray::new(vec![1, 2, 3, 4]))));
+
+    let dir = std::env::temp_dir().join("mc_schem_test_paste");
+    let _ = std::fs::remove_dir_all(&dir);
+    dim.save_chunks_to_folder(&dir, &affected, &MCASaveOption::default()).unwrap();
+    let region_files: Vec<_> = std::fs::read_dir(dir.join("region")).unwrap().collect();
+    assert_eq!(region_files.len(), 1);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_extract() {
+    let files = FilesInMemory::from_7z_file("test_files/world/00_1.20.2.7z", "").unwrap();
+    let mut dim = Dimension::from_files(&files, false, -64..320, 0).unwrap();
+    dim.parse_all(0).unwrap();
+
+    let min = [-20, -64, -20];
+    let max = [20, 10, 20];
+    let region = dim.extract(min, max).unwrap();
+    assert_eq!(region.shape(), [41, 75, 41]);
+    for x in min[0]..=max[0] {
+        for y in min[1]..=max[1] {
+            for z in min[2]..=max[2] {
+                let r_pos = [x - min[0], y - min[1], z - min[2]];
+                let expected = dim.block_at([x, y, z]).map(|b| b.clone()).unwrap_or(Block::air());
+                assert_eq!(region.block_at(r_pos), Some(&expected));
+            }
+        }
+    }
+    assert!(region.total_blocks(false) > 0);
+}
+
 #[test]
 fn test_raids() {
     let files = FilesInMemory::from_7z_file("test_files/world/03_raids-1.20.2.7z", "").unwrap();
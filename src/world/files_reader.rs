@@ -97,9 +97,10 @@ impl FilesRead for FolderOnDisk {
         let mut result = Vec::new();
         for entry in walkdir::WalkDir::new(&self.path) {
             if let Ok(entry) = entry {
-                let filename: &str;
-                if let Some(f) = entry.file_name().to_str() {
-                    filename = f;
+                // name relative to the folder, so that sub directories can filter by it
+                let filename: String;
+                if let Some(f) = entry.path().strip_prefix(&self.path).ok().and_then(|p| p.to_str()) {
+                    filename = f.replace('\\', "/");
                 } else {
                     continue;
                 }
@@ -109,8 +110,8 @@ impl FilesRead for FolderOnDisk {
                         continue;
                     }
                     let tmp = FileInfo {
-                        name: filename.to_string(),
-                        full_name: filename.to_string(),
+                        name: filename.clone(),
+                        full_name: filename,
                         size: metadata.len(),
                     };
                     result.push(tmp);
@@ -0,0 +1,556 @@
+/*
+mc_schem is a rust library to generate, load, manipulate and save minecraft schematic files.
+Copyright (C) 2024  joseph
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use std::collections::{BTreeMap, HashMap};
+
+use fastnbt::Value;
+
+use crate::{unwrap_opt_tag, unwrap_tag};
+use crate::biome::Biome;
+use crate::block::Block;
+use crate::error::Error;
+use crate::region::Light;
+use crate::schem::{common, DataVersion};
+use crate::schem::id_of_nbt_tag;
+use crate::world::{Chunk, ChunkPos, ChunkStatus, NBTWithSource, SubChunk};
+use crate::world::chunk::{bits_per_block, parse_light};
+
+/// Chunks before 1.18 always have 16 sections, from y=0 to y=255
+const LEGACY_SECTION_COUNT: i8 = 16;
+
+impl ChunkStatus {
+    /// Parse chunk status in 1.13 and 1.14-1.17. Statuses that were renamed or merged are
+    /// converted to the closest one.
+    pub fn from_legacy_str(str: &str) -> Option<ChunkStatus> {
+        if let Some(status) = Self::from_str(str) {
+            return Some(status);
+        }
+        let str = str.strip_prefix("minecraft:").unwrap_or(str);
+        let status = match str {
+            "base" => ChunkStatus::Noise,
+            "carved" | "liquid_carved" | "liquid_carvers" => ChunkStatus::Carvers,
+            "decorated" => ChunkStatus::Features,
+            "lighted" => ChunkStatus::Light,
+            "mobs_spawned" | "heightmaps" => ChunkStatus::Spawn,
+            "finalized" | "fullchunk" | "postprocessed" => ChunkStatus::Full,
+            _ => return None,
+        };
+        return Some(status);
+    }
+}
+
+fn nibble_at(array: &[i8], counter: usize) -> u8 {
+    let b = u8::from_ne_bytes(array[counter / 2].to_ne_bytes());
+    return (b >> (4 * (counter % 2))) & 0xF;
+}
+
+fn byte_array_with_len<'a>(sect: &'a HashMap<String, Value>, key: &str, len: usize, path: &str) -> Result<&'a [i8], Error> {
+    let tag_path = format!("{path}/{key}");
+    let arr = unwrap_opt_tag!(sect.get(key),ByteArray,fastnbt::ByteArray::new(vec![]),tag_path).as_ref();
+    if arr.len() != len {
+        return Err(Error::InvalidValue { tag_path, error: format!("The length should be {len}, but found {}", arr.len()) });
+    }
+    return Ok(arr);
+}
+
+/// Fill blocks of a section with number ids and damages in yzx order. Blocks that can't be
+/// converted are replaced by damage 0 or air, and counted in `unknown`.
+fn fill_number_id_blocks(reg: &mut SubChunk, id_damage: &[(u16, u8)], unknown: &mut BTreeMap<(u16, u8), u64>) {
+    debug_assert_eq!(id_damage.len(), 4096);
+    // index in palette of each id and damage
+    let mut index_of: HashMap<(u16, u8), (u16, bool)> = HashMap::new();
+    reg.palette.clear();
+    for (counter, key) in id_damage.iter().enumerate() {
+        let (index, converted) = *index_of.entry(*key).or_insert_with(|| {
+            let (id, damage) = *key;
+            let (block, converted) = if id > 255 {
+                (Block::air(), false)
+            } else if let Ok(b) = Block::from_old(id as u8, damage, DataVersion::Java_1_12_2) {
+                (b, true)
+            } else if let Ok(b) = Block::from_old(id as u8, 0, DataVersion::Java_1_12_2) {
+                (b, false)
+            } else {
+                (Block::air(), false)
+            };
+            let index = match reg.palette.iter().position(|blk| *blk == block) {
+                Some(idx) => idx,
+                None => {
+                    reg.palette.push(block);
+                    reg.palette.len() - 1
+                }
+            };
+            return (index as u16, converted);
+        });
+        if !converted {
+            *unknown.entry(*key).or_insert(0) += 1;
+        }
+        reg.block_id_array[counter] = index;
+    }
+}
+
+/// Parse `Blocks`, `Data` and `Add` of a section in anvil before 1.13
+fn parse_number_id_blocks(reg: &mut SubChunk, sect: &HashMap<String, Value>, path: &str,
+                          unknown: &mut BTreeMap<(u16, u8), u64>) -> Result<(), Error> {
+    let blocks = byte_array_with_len(sect, "Blocks", 4096, path)?;
+    let data = byte_array_with_len(sect, "Data", 2048, path)?;
+    let add = if sect.contains_key("Add") {
+        byte_array_with_len(sect, "Add", 2048, path)?
+    } else {
+        &[]
+    };
+
+    let mut id_damage = Vec::with_capacity(4096);
+    for (counter, block) in blocks.iter().enumerate() {
+        let mut id = u8::from_ne_bytes(block.to_ne_bytes()) as u16;
+        if !add.is_empty() {
+            id |= (nibble_at(add, counter) as u16) << 8;
+        }
+        id_damage.push((id, nibble_at(data, counter)));
+    }
+    fill_number_id_blocks(reg, &id_damage, unknown);
+    return Ok(());
+}
+
+/// Parse a McRegion chunk, whose `Blocks`, `Data`, `SkyLight` and `BlockLight` are 128 blocks high
+/// in xzy order, into 8 sections.
+fn parse_mcregion_sections(level: &HashMap<String, Value>, path: &str,
+                           unknown: &mut BTreeMap<(u16, u8), u64>) -> Result<Vec<SubChunk>, Error> {
+    const HEIGHT: usize = 128;
+    let blocks = byte_array_with_len(level, "Blocks", 16 * 16 * HEIGHT, path)?;
+    let data = byte_array_with_len(level, "Data", 16 * 16 * HEIGHT / 2, path)?;
+    let sky_light = if level.contains_key("SkyLight") {
+        byte_array_with_len(level, "SkyLight", 16 * 16 * HEIGHT / 2, path)?
+    } else {
+        &[]
+    };
+    let block_light = if level.contains_key("BlockLight") {
+        byte_array_with_len(level, "BlockLight", 16 * 16 * HEIGHT / 2, path)?
+    } else {
+        &[]
+    };
+    // index in McRegion arrays
+    let index_xzy = |x: usize, y: usize, z: usize| (x * 16 + z) * HEIGHT + y;
+
+    let mut result = Vec::with_capacity(HEIGHT / 16);
+    for sect_y in 0..(HEIGHT / 16) {
+        let mut sub_chunk = SubChunk::new();
+        let mut id_damage = Vec::with_capacity(4096);
+        for y in 0..16 {
+            for z in 0..16 {
+                for x in 0..16 {
+                    let src = index_xzy(x, sect_y * 16 + y, z);
+                    id_damage.push((u8::from_ne_bytes(blocks[src].to_ne_bytes()) as u16, nibble_at(data, src)));
+                    let sl = if sky_light.is_empty() { 15 } else { nibble_at(sky_light, src) };
+                    let bl = if block_light.is_empty() { 15 } else { nibble_at(block_light, src) };
+                    sub_chunk.sky_block_light_array[y * 256 + z * 16 + x] = Light::new(sl, bl);
+                }
+            }
+        }
+        fill_number_id_blocks(&mut sub_chunk, &id_damage, unknown);
+        result.push(sub_chunk);
+    }
+    return Ok(result);
+}
+
+/// Get the `idx`-th element of packed block states. Before 20w17a(1.16), an element may be
+/// separated into 2 longs, otherwise the remaining bits of a long are left unused.
+pub fn packed_element(array: &[i64], bits: u8, idx: usize, spanning: bool) -> u64 {
+    let bits = bits as usize;
+    let mask = (1u64 << bits) - 1;
+    if !spanning {
+        let num_per_u64 = 64 / bits;
+        let val = u64::from_ne_bytes(array[idx / num_per_u64].to_ne_bytes());
+        return (val >> ((idx % num_per_u64) * bits)) & mask;
+    }
+    let bit_beg = idx * bits;
+    let u64_idx = bit_beg / 64;
+    let offset = bit_beg % 64;
+    let mut val = u64::from_ne_bytes(array[u64_idx].to_ne_bytes()) >> offset;
+    if offset + bits > 64 {
+        val |= u64::from_ne_bytes(array[u64_idx + 1].to_ne_bytes()) << (64 - offset);
+    }
+    return val & mask;
+}
+
+/// Parse `Palette` and `BlockStates` of a section in 1.13-1.17
+fn parse_palette_blocks(reg: &mut SubChunk, sect: &HashMap<String, Value>, path: &str, spanning: bool) -> Result<(), Error> {
+    {
+        let palette = unwrap_opt_tag!(sect.get("Palette"),List,vec![],format!("{path}/Palette"));
+        let mut pal = Vec::with_capacity(palette.len());
+        for (idx, blk) in palette.iter().enumerate() {
+            let path = format!("{path}/Palette[{idx}]");
+            let blk = unwrap_tag!(blk,Compound,HashMap::new(),path);
+            pal.push(common::parse_block(blk, &path)?);
+        }
+        reg.palette = pal;
+    }
+    if reg.palette.is_empty() {
+        return Err(Error::PaletteIsEmpty { tag_path: format!("{path}/Palette") });
+    }
+    if reg.palette.len() > 65535 {
+        return Err(Error::PaletteTooLong(reg.palette.len()));
+    }
+
+    let path = format!("{path}/BlockStates");
+    let array_i64 = unwrap_opt_tag!(sect.get("BlockStates"),LongArray,fastnbt::LongArray::new(vec![]),path);
+    let bits = bits_per_block(reg.palette.len(), 4);
+    let required_len = if spanning {
+        4096 * bits as usize / 64
+    } else {
+        let num_per_u64 = 64 / bits as usize;
+        4096_usize.div_ceil(num_per_u64)
+    };
+    if array_i64.len() != required_len {
+        return Err(Error::InvalidValue {
+            tag_path: path,
+            error: format!("This subchunk has 4096 blocks of {} types, required {} i64 element to store them, but found {}",
+                           reg.palette.len(), required_len, array_i64.len()),
+        });
+    }
+
+    let block_id_max = reg.palette.len() - 1;
+    for idx in 0..4096 {
+        let blk_id = packed_element(array_i64, bits, idx, spanning) as usize;
+        if blk_id > block_id_max {
+            return Err(Error::BlockIndexOutOfRange {
+                tag_path: path,
+                index: blk_id as i32,
+                range: [0, block_id_max as i32],
+            });
+        }
+        reg.block_id_array[idx] = blk_id as u16;
+    }
+    return Ok(());
+}
+
+fn biome_of_old_id(id: i32, tag_path: &str) -> Result<Biome, Error> {
+    // -1 means the biome is not computed yet
+    if id == -1 {
+        return Ok(Biome::the_void);
+    }
+    return match Biome::from_old_id(id) {
+        Some(b) => Ok(b),
+        None => Err(Error::InvalidBiome { tag_path: tag_path.to_string(), biome: id.to_string() }),
+    };
+}
+
+/// Parse biomes of the whole chunk into 4x4x4 cells, 64 cells per section in the order of
+/// `SubChunk::biome_array`. Before 1.15 biomes are 2d (256 values in zx), since 1.15 they are 3d
+/// (1024 values). Missing biomes are filled with plains.
+fn parse_legacy_biomes(level: &HashMap<String, Value>, path: &str) -> Result<Vec<Biome>, Error> {
+    let num_cells = LEGACY_SECTION_COUNT as usize * 64;
+    let tag_path = format!("{path}/Biomes");
+    let ids: Vec<i32> = match level.get("Biomes") {
+        None => return Ok(vec![Biome::plains; num_cells]),
+        // biome ids are unsigned bytes before 1.13, except -1
+        Some(Value::ByteArray(arr)) => arr.iter()
+            .map(|b| if *b == -1 { -1 } else { u8::from_ne_bytes(b.to_ne_bytes()) as i32 }).collect(),
+        Some(tag) => unwrap_tag!(tag,IntArray,fastnbt::IntArray::new(vec![]),tag_path).to_vec(),
+    };
+
+    let mut biomes = Vec::with_capacity(num_cells);
+    if ids.len() == num_cells {
+        for (idx, id) in ids.iter().enumerate() {
+            biomes.push(biome_of_old_id(*id, &format!("{tag_path}[{idx}]"))?);
+        }
+        return Ok(biomes);
+    }
+    if ids.len() != 256 {
+        return Err(Error::InvalidValue {
+            tag_path,
+            error: format!("The length should be 256 or {num_cells}, but found {}", ids.len()),
+        });
+    }
+    // use the biome at the lower corner of each 4x4 column
+    let mut biomes_2d = [Biome::the_void; 16];
+    for z in 0..4 {
+        for x in 0..4 {
+            let idx = (z * 4) * 16 + x * 4;
+            biomes_2d[z * 4 + x] = biome_of_old_id(ids[idx], &format!("{tag_path}[{idx}]"))?;
+        }
+    }
+    for _y in 0..(num_cells / 16) {
+        biomes.extend_from_slice(&biomes_2d);
+    }
+    return Ok(biomes);
+}
+
+impl Chunk {
+    /// Parse chunk saved before 21w43a(1.18), whose data are stored in `Level` compound. Sections
+    /// are stored as `Blocks`, `Data` and `Add` before 1.13, and as `Palette` and `BlockStates`
+    /// in 1.13-1.17. McRegion chunks(Beta 1.3 to 1.1) have no sections but 128-high arrays in
+    /// `Level`. Missing sections are filled with air.
+    pub fn from_legacy_nbt(region_nbt_data: NBTWithSource, entity_nbt_data: Option<NBTWithSource>, chunk_pos: &ChunkPos,
+                           data_version: i32) -> Result<Chunk, Error> {
+        let region_source_filename = region_nbt_data.source;
+        let mut region_nbt = region_nbt_data.nbt;
+
+        let path_in_saves = format!("{region_source_filename}/[{},{}]/Level",
+                                    chunk_pos.local_coordinate().x,
+                                    chunk_pos.local_coordinate().z);
+        let mut level = std::mem::take(unwrap_opt_tag!(region_nbt.get_mut("Level"),Compound,HashMap::new(),path_in_saves));
+
+        let mut result = Chunk::new();
+        result.file_region = region_source_filename.to_string();
+        // chunk status
+        if data_version < DataVersion::Java_1_13 as i32 {
+            let populated = match level.get("TerrainPopulated") {
+                Some(tag) => *unwrap_tag!(tag,Byte,0,format!("{path_in_saves}/TerrainPopulated")) != 0,
+                None => false,
+            };
+            result.status = if populated { ChunkStatus::Full } else { ChunkStatus::Noise };
+        } else {
+            let str = unwrap_opt_tag!(level.get("Status"),String,"".to_string(),format!("{path_in_saves}/Status"));
+            result.status = match ChunkStatus::from_legacy_str(str) {
+                Some(s) => s,
+                None => return Err(Error::InvalidChunkStatus {
+                    tag_path: format!("{path_in_saves}/Status"),
+                    chunk_status: str.to_string(),
+                }),
+            };
+        }
+        result.last_update = *unwrap_opt_tag!(level.get("LastUpdate"),Long,0,format!("{path_in_saves}/LastUpdate"));
+        if let Some(tag) = level.get("InhabitedTime") {
+            result.inhabited_time = *unwrap_tag!(tag,Long,0,format!("{path_in_saves}/InhabitedTime"));
+        }
+        if let Some(tag) = level.get("isLightOn") {
+            result.is_light_on = *unwrap_tag!(tag,Byte,1,format!("{path_in_saves}/isLightOn")) != 0;
+        } else if let Some(tag) = level.get("LightPopulated") {
+            result.is_light_on = *unwrap_tag!(tag,Byte,1,format!("{path_in_saves}/LightPopulated")) != 0;
+        }
+
+        let spanning = data_version < DataVersion::Snapshot_20w17a as i32;
+        let biomes = parse_legacy_biomes(&level, &path_in_saves)?;
+        // sections
+        if !level.contains_key("Sections") && level.contains_key("Blocks") {
+            // McRegion chunks are 128 blocks high, the upper half is filled with air like converting to anvil
+            for (y, sub_chunk) in parse_mcregion_sections(&level, &path_in_saves, &mut result.unknown_number_id_blocks)?.into_iter().enumerate() {
+                result.sub_chunks.insert(y as i8, sub_chunk);
+            }
+        }
+        {
+            let sections = match level.get("Sections") {
+                Some(tag) => unwrap_tag!(tag,List,vec![],format!("{path_in_saves}/Sections")).as_slice(),
+                None => &[],
+            };
+            let block_key = if data_version < DataVersion::Java_1_13 as i32 { "Blocks" } else { "Palette" };
+            for (idx, nbt) in sections.iter().enumerate() {
+                let path = format!("{path_in_saves}/Sections[{idx}]");
+                let sect = unwrap_tag!(nbt,Compound,HashMap::new(),path);
+                let y = *unwrap_opt_tag!(sect.get("Y"),Byte,0,format!("{path}/Y"));
+                // sections out of world only contain light
+                if !(0..LEGACY_SECTION_COUNT).contains(&y) || !sect.contains_key(block_key) {
+                    continue;
+                }
+                let mut sub_chunk = SubChunk::new();
+                if data_version < DataVersion::Java_1_13 as i32 {
+                    parse_number_id_blocks(&mut sub_chunk, sect, &path, &mut result.unknown_number_id_blocks)?;
+                } else {
+                    parse_palette_blocks(&mut sub_chunk, sect, &path, spanning)?;
+                }
+                parse_light(&mut sub_chunk, sect, &path)?;
+                result.sub_chunks.insert(y, sub_chunk);
+            }
+            for y in 0..LEGACY_SECTION_COUNT {
+                let sub_chunk = result.sub_chunks.entry(y)
+                    .or_insert_with(|| SubChunk::filled_with(&Block::air(), Biome::plains));
+                let beg = y as usize * 64;
+                sub_chunk.biome_array.copy_from_slice(&biomes[beg..(beg + 64)]);
+            }
+        }
+        result.data_version = data_version;
+        result.parse_metadata(&mut level, &path_in_saves, chunk_pos, true, spanning)?;
+        // block entities
+        {
+            let be_list_tag = format!("{path_in_saves}/TileEntities");
+            let be_list = match level.remove("TileEntities") {
+                Some(tag) => unwrap_tag!(tag,List,vec![],be_list_tag),
+                None => vec![],
+            };
+            result.parse_block_entities(be_list, &be_list_tag, chunk_pos)?;
+        }
+        // pending ticks
+        {
+            let tag_path_block_ticks = format!("{path_in_saves}/TileTicks");
+            let tag_block_ticks = match level.get("TileTicks") {
+                Some(tag) => unwrap_tag!(tag,List,vec![],tag_path_block_ticks).as_slice(),
+                None => &[],
+            };
+            let tag_path_fluid_ticks = format!("{path_in_saves}/LiquidTicks");
+            let tag_fluid_ticks = match level.get("LiquidTicks") {
+                Some(tag) => unwrap_tag!(tag,List,vec![],tag_path_fluid_ticks).as_slice(),
+                None => &[],
+            };
+            result.parse_pending_ticks(tag_block_ticks, &tag_path_block_ticks, tag_fluid_ticks, &tag_path_fluid_ticks)?;
+        }
+        // entities, which are moved to entities files since 1.17
+        if let Some(tag) = level.remove("Entities") {
+            let entity_path = format!("{path_in_saves}/Entities");
+            let entity_list = unwrap_tag!(tag,List,vec![],entity_path);
+            result.parse_entities(entity_list, &entity_path, chunk_pos)?;
+        }
+        if let Some(entity_nbt_data) = entity_nbt_data {
+            let entity_source_file = entity_nbt_data.source;
+            let mut entity_nbt = entity_nbt_data.nbt;
+
+            result.file_entities = entity_source_file.to_string();
+            let entity_path = format!("{entity_source_file}/[{},{}]/Entities",
+                                      chunk_pos.local_coordinate().x,
+                                      chunk_pos.local_coordinate().z);
+            let entity_list = unwrap_opt_tag!(entity_nbt.remove("Entities"),List,vec![],entity_path);
+            result.parse_entities(entity_list, &entity_path, chunk_pos)?;
+        }
+
+        return Ok(result);
+    }
+}
+
+#[cfg(test)]
+fn legacy_chunk_nbt(data_version: Option<i32>, section: HashMap<String, Value>, extra: Vec<(&str, Value)>) -> HashMap<String, Value> {
+    let mut level = HashMap::from([
+        ("xPos".to_string(), Value::Int(0)),
+        ("zPos".to_string(), Value::Int(0)),
+        ("LastUpdate".to_string(), Value::Long(100)),
+        ("Sections".to_string(), Value::List(vec![Value::Compound(section)])),
+    ]);
+    for (key, val) in extra {
+        level.insert(key.to_string(), val);
+    }
+    let mut nbt = HashMap::from([("Level".to_string(), Value::Compound(level))]);
+    if let Some(dv) = data_version {
+        nbt.insert("DataVersion".to_string(), Value::Int(dv));
+    }
+    return nbt;
+}
+
+#[test]
+fn test_parse_pre_flattening_chunk() {
+    use crate::world::XZCoordinate;
+    let mut blocks = vec![0i8; 4096];
+    let mut data = vec![0i8; 2048];
+    // stone at (1, 16, 2), granite (stone:1) at (0, 16, 0)
+    blocks[2 * 16 + 1] = 1;
+    blocks[0] = 1;
+    data[0] = 1;
+    // stone with invalid damage 15 at (3, 16, 0), and a block with number id 257 at (4, 16, 0)
+    blocks[3] = 1;
+    data[1] = 0xF0u8 as i8;
+    blocks[4] = 1;
+    let mut add = vec![0i8; 2048];
+    add[2] = 1;
+    let section = HashMap::from([
+        ("Y".to_string(), Value::Byte(1)),
+        ("Blocks".to_string(), Value::ByteArray(fastnbt::ByteArray::new(blocks))),
+        ("Data".to_string(), Value::ByteArray(fastnbt::ByteArray::new(data))),
+        ("Add".to_string(), Value::ByteArray(fastnbt::ByteArray::new(add))),
+    ]);
+    let biomes = fastnbt::ByteArray::new(vec![1i8; 256]);
+    let nbt = legacy_chunk_nbt(Some(DataVersion::Java_1_12_2 as i32), section,
+                               vec![("TerrainPopulated", Value::Byte(1)), ("Biomes", Value::ByteArray(biomes))]);
+
+    let pos = ChunkPos::from_global_pos(&XZCoordinate { x: 0, z: 0 });
+    let chunk = Chunk::from_nbt(NBTWithSource { nbt, source: "r.0.0.mca" }, None, &pos).unwrap();
+    assert_eq!(chunk.status, ChunkStatus::Full);
+    assert_eq!(chunk.y_range(), 0..256);
+    let sub_chunk = &chunk.sub_chunks[&1];
+    // blocks are kept in 1.12 ids
+    assert_eq!(sub_chunk.palette[sub_chunk.block_id_array[2 * 16 + 1] as usize].full_id(), "minecraft:stone[variant=stone]");
+    assert_eq!(sub_chunk.palette[sub_chunk.block_id_array[0] as usize].full_id(), "minecraft:stone[variant=granite]");
+    assert_eq!(sub_chunk.palette[sub_chunk.block_id_array[3] as usize].full_id(), "minecraft:stone[variant=stone]");
+    assert!(sub_chunk.palette[sub_chunk.block_id_array[4] as usize].is_air());
+    assert_eq!(chunk.unknown_number_id_blocks, BTreeMap::from([((1, 15), 1), ((257, 0), 1)]));
+    assert!(chunk.sub_chunks[&0].palette[0].is_air());
+    assert_eq!(chunk.sub_chunks[&15].biome_array[0], Biome::plains);
+}
+
+#[test]
+fn test_parse_palette_chunk() {
+    use crate::world::XZCoordinate;
+    let palette: Vec<Value> = (0..17).map(|idx| {
+        let id = if idx == 0 { "minecraft:air".to_string() } else { format!("minecraft:wool_{idx}") };
+        Value::Compound(HashMap::from([("Name".to_string(), Value::String(id))]))
+    }).collect();
+    // 17 types of blocks take 5 bits
+    for (data_version, spanning) in [(DataVersion::Java_1_15 as i32, true), (DataVersion::Java_1_16 as i32, false)] {
+        let mut ids = [0u64; 4096];
+        for (idx, id) in ids.iter_mut().enumerate() {
+            *id = (idx % 17) as u64;
+        }
+        let num_u64 = if spanning { 4096 * 5 / 64 } else { (4096 + 11) / 12 };
+        let mut array = vec![0u64; num_u64];
+        for (idx, id) in ids.iter().enumerate() {
+            if spanning {
+                let bit = idx * 5;
+                array[bit / 64] |= id << (bit % 64);
+                if bit % 64 + 5 > 64 {
+                    array[bit / 64 + 1] |= id >> (64 - bit % 64);
+                }
+            } else {
+                array[idx / 12] |= id << ((idx % 12) * 5);
+            }
+        }
+        let array = array.iter().map(|v| i64::from_ne_bytes(v.to_ne_bytes())).collect();
+        let section = HashMap::from([
+            ("Y".to_string(), Value::Byte(0)),
+            ("Palette".to_string(), Value::List(palette.clone())),
+            ("BlockStates".to_string(), Value::LongArray(fastnbt::LongArray::new(array))),
+        ]);
+        let biomes = fastnbt::IntArray::new(vec![4; 1024]);
+        let nbt = legacy_chunk_nbt(Some(data_version), section,
+                                   vec![("Status", Value::String("full".to_string())), ("Biomes", Value::IntArray(biomes))]);
+        let pos = ChunkPos::from_global_pos(&XZCoordinate { x: 0, z: 0 });
+        let chunk = Chunk::from_nbt(NBTWithSource { nbt, source: "r.0.0.mca" }, None, &pos).unwrap();
+        let sub_chunk = &chunk.sub_chunks[&0];
+        for idx in 0..4096 {
+            assert_eq!(sub_chunk.block_id_array[idx] as u64, ids[idx]);
+        }
+        assert_eq!(sub_chunk.biome_array[0], Biome::forest);
+    }
+}
+
+#[test]
+fn test_parse_mcregion_chunk() {
+    use crate::world::XZCoordinate;
+    use crate::world::mca::parse_mcr_filename;
+    assert_eq!(parse_mcr_filename("r.-1.2.mcr"), Some(XZCoordinate { x: -1, z: 2 }));
+
+    let mut blocks = vec![0i8; 32768];
+    let mut data = vec![0i8; 16384];
+    // wool:14 (red) at (3, 70, 5)
+    let idx = (3 * 16 + 5) * 128 + 70;
+    blocks[idx] = 35;
+    data[idx / 2] = 14;
+    let level = HashMap::from([
+        ("xPos".to_string(), Value::Int(0)),
+        ("zPos".to_string(), Value::Int(0)),
+        ("LastUpdate".to_string(), Value::Long(100)),
+        ("TerrainPopulated".to_string(), Value::Byte(1)),
+        ("Blocks".to_string(), Value::ByteArray(fastnbt::ByteArray::new(blocks))),
+        ("Data".to_string(), Value::ByteArray(fastnbt::ByteArray::new(data))),
+    ]);
+    let nbt = HashMap::from([("Level".to_string(), Value::Compound(level))]);
+
+    let pos = ChunkPos::from_global_pos(&XZCoordinate { x: 0, z: 0 });
+    let chunk = Chunk::from_nbt(NBTWithSource { nbt, source: "r.0.0.mcr" }, None, &pos).unwrap();
+    assert_eq!(chunk.y_range(), 0..256);
+    let sub_chunk = &chunk.sub_chunks[&4];
+    let block = &sub_chunk.palette[sub_chunk.block_id_array[6 * 256 + 5 * 16 + 3] as usize];
+    assert_eq!(block.full_id(), "minecraft:wool[color=red]");
+    assert!(sub_chunk.palette[sub_chunk.block_id_array[6 * 256 + 3 * 16 + 5] as usize].is_air());
+    assert!(chunk.sub_chunks[&8].palette[0].is_air());
+}
@@ -0,0 +1,177 @@
+/*
+mc_schem is a rust library to generate, load, manipulate and save minecraft schematic files.
+Copyright (C) 2024  joseph
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use std::collections::{BTreeMap, HashMap};
+use fastnbt::Value;
+use flate2::read::GzDecoder;
+use crate::error::{Error, unwrap_opt_bool_or, unwrap_opt_f32, unwrap_opt_i32, unwrap_opt_i64, unwrap_opt_i8, unwrap_opt_or, unwrap_opt_string};
+use crate::player::{Player, PlayerGameType};
+use crate::{unwrap_opt_tag, unwrap_tag};
+use crate::schem::id_of_nbt_tag;
+use crate::world::{Difficulty, FilesRead, LevelData};
+
+fn parse_string_list(nbt: &HashMap<String, Value>, key: &str, tag_path: &str) -> Result<Vec<String>, Error> {
+    let mut result = Vec::new();
+    if let Some(list) = nbt.get(key) {
+        let list_path = format!("{tag_path}/{key}");
+        let list = unwrap_tag!(list,List,vec![],list_path);
+        result.reserve(list.len());
+        for (idx, val) in list.iter().enumerate() {
+            let val = unwrap_tag!(val,String,"".to_string(),format!("{list_path}[{idx}]"));
+            result.push(val.clone());
+        }
+    }
+    return Ok(result);
+}
+
+impl LevelData {
+    /// Parse the root compound of `level.dat`
+    pub fn from_nbt(nbt: &HashMap<String, Value>, tag_path: &str) -> Result<LevelData, Error> {
+        let tag_path = format!("{tag_path}/Data");
+        let data = unwrap_opt_tag!(nbt.get("Data"),Compound,HashMap::new(),tag_path);
+        let tag_path = tag_path.as_str();
+
+        let version_name = if let Some(version) = data.get("Version") {
+            let path = format!("{tag_path}/Version");
+            let version = unwrap_tag!(version,Compound,HashMap::new(),path);
+            Some(unwrap_opt_string(version, "Name", &path)?.clone())
+        } else {
+            None
+        };
+
+        // Since 1.16, seed is moved into WorldGenSettings
+        let seed = if let Some(settings) = data.get("WorldGenSettings") {
+            let path = format!("{tag_path}/WorldGenSettings");
+            let settings = unwrap_tag!(settings,Compound,HashMap::new(),path);
+            unwrap_opt_i64(settings, "seed", &path)?
+        } else {
+            unwrap_opt_or(data, "RandomSeed", tag_path, 0, unwrap_opt_i64)?
+        };
+
+        let game_type = unwrap_opt_or(data, "GameType", tag_path, 0, unwrap_opt_i32)?;
+        let game_type = match PlayerGameType::from_repr(game_type) {
+            Some(gt) => gt,
+            None => return Err(Error::InvalidValue {
+                tag_path: format!("{tag_path}/GameType"),
+                error: format!("Invalid game type {game_type}"),
+            }),
+        };
+        let difficulty = unwrap_opt_or(data, "Difficulty", tag_path, 2, unwrap_opt_i8)?;
+        let difficulty = match Difficulty::from_repr(difficulty) {
+            Some(d) => d,
+            None => return Err(Error::InvalidValue {
+                tag_path: format!("{tag_path}/Difficulty"),
+                error: format!("Invalid difficulty {difficulty}"),
+            }),
+        };
+
+        let mut game_rules = BTreeMap::new();
+        if let Some(rules) = data.get("GameRules") {
+            let path = format!("{tag_path}/GameRules");
+            let rules = unwrap_tag!(rules,Compound,HashMap::new(),path);
+            for (key, val) in rules {
+                let val = unwrap_tag!(val,String,"".to_string(),format!("{path}/{key}"));
+                game_rules.insert(key.clone(), val.clone());
+            }
+        }
+
+        let mut enabled_data_packs = Vec::new();
+        let mut disabled_data_packs = Vec::new();
+        if let Some(packs) = data.get("DataPacks") {
+            let path = format!("{tag_path}/DataPacks");
+            let packs = unwrap_tag!(packs,Compound,HashMap::new(),path);
+            enabled_data_packs = parse_string_list(packs, "Enabled", &path)?;
+            disabled_data_packs = parse_string_list(packs, "Disabled", &path)?;
+        }
+
+        let player = if let Some(player) = data.get("Player") {
+            let path = format!("{tag_path}/Player");
+            let player = unwrap_tag!(player,Compound,HashMap::new(),path);
+            Some(Player::from_nbt(player, &path)?)
+        } else {
+            None
+        };
+
+        return Ok(LevelData {
+            // DataVersion doesn't exist before 1.9
+            data_version: unwrap_opt_or(data, "DataVersion", tag_path, 0, unwrap_opt_i32)?,
+            version_name,
+            level_name: unwrap_opt_string(data, "LevelName", tag_path)?.clone(),
+            seed,
+            spawn_pos: [unwrap_opt_i32(data, "SpawnX", tag_path)?,
+                unwrap_opt_i32(data, "SpawnY", tag_path)?,
+                unwrap_opt_i32(data, "SpawnZ", tag_path)?],
+            spawn_angle: unwrap_opt_or(data, "SpawnAngle", tag_path, 0.0, unwrap_opt_f32)?,
+            game_type,
+            difficulty,
+            difficulty_locked: unwrap_opt_bool_or(data, "DifficultyLocked", tag_path, false)?,
+            hardcore: unwrap_opt_bool_or(data, "hardcore", tag_path, false)?,
+            allow_commands: unwrap_opt_bool_or(data, "allowCommands", tag_path, false)?,
+            time: unwrap_opt_or(data, "Time", tag_path, 0, unwrap_opt_i64)?,
+            day_time: unwrap_opt_or(data, "DayTime", tag_path, 0, unwrap_opt_i64)?,
+            last_played: unwrap_opt_or(data, "LastPlayed", tag_path, 0, unwrap_opt_i64)?,
+            raining: unwrap_opt_bool_or(data, "raining", tag_path, false)?,
+            rain_time: unwrap_opt_or(data, "rainTime", tag_path, 0, unwrap_opt_i32)?,
+            thundering: unwrap_opt_bool_or(data, "thundering", tag_path, false)?,
+            thunder_time: unwrap_opt_or(data, "thunderTime", tag_path, 0, unwrap_opt_i32)?,
+            clear_weather_time: unwrap_opt_or(data, "clearWeatherTime", tag_path, 0, unwrap_opt_i32)?,
+            game_rules,
+            enabled_data_packs,
+            disabled_data_packs,
+            server_brands: parse_string_list(data, "ServerBrands", tag_path)?,
+            was_modded: unwrap_opt_bool_or(data, "WasModded", tag_path, false)?,
+            player,
+        });
+    }
+
+    /// Parse `level.dat` in `files`, returns `None` if the file doesn't exist.
+    pub fn from_files(files: &dyn FilesRead) -> Result<Option<LevelData>, Error> {
+        if !files.files().iter().any(|info| info.name == "level.dat") {
+            return Ok(None);
+        }
+        let src = files.open_file("level.dat")?;
+        let nbt: HashMap<String, Value> = match fastnbt::from_reader(GzDecoder::new(src)) {
+            Ok(nbt) => nbt,
+            Err(e) => return Err(Error::NBTReadError(e)),
+        };
+        return Ok(Some(Self::from_nbt(&nbt, &format!("{}/level.dat", files.path()))?));
+    }
+}
+
+#[test]
+fn test_parse_level_data() {
+    use crate::world::FilesInMemory;
+    let files = FilesInMemory::from_7z_file("test_files/world/03_raids-1.20.2.7z", "").unwrap();
+    let ld = LevelData::from_files(&files).unwrap().unwrap();
+    assert_eq!(ld.data_version, 3578);
+    assert_eq!(ld.version_name.as_deref(), Some("1.20.2"));
+    assert_eq!(ld.seed, -5313044511093285108);
+    assert_eq!(ld.spawn_pos, [0, -60, 0]);
+    assert_eq!(ld.difficulty, Difficulty::Hard);
+    assert_eq!(ld.game_rules.get("keepInventory").map(|s| s.as_str()), Some("false"));
+    assert_eq!(ld.enabled_data_packs[0], "vanilla");
+
+    let player = ld.player.unwrap();
+    assert!((player.entity_fields.pos[0] + 289.2686341175251).abs() < 1e-9);
+    assert_eq!(player.entity_fields.uuid, [106533598, -1002224458, -1728602316, 1895033338]);
+    assert_eq!(player.mob_fields.active_effects.len(), 2);
+    assert_eq!(player.player_fields.dimension.id(), "minecraft:overworld");
+    assert_eq!(player.player_fields.inventory.0.len(), 3);
+    assert_eq!(player.player_fields.xp_info.level, 12);
+    assert!(player.player_fields.abilities.instant_build);
+}
@@ -0,0 +1,333 @@
+/*
+mc_schem is a rust library to generate, load, manipulate and save minecraft schematic files.
+Copyright (C) 2024  joseph
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! LZ4 compression used by region files since 1.20.5. Minecraft writes chunks with
+//! `LZ4BlockOutputStream` of lz4-java, so the data is a sequence of blocks, each has a header of
+//! magic, method, lengths and checksum, followed by raw or LZ4 compressed bytes. The stream ends
+//! with an empty block.
+
+use crate::error::Error;
+
+const MAGIC: &[u8; 8] = b"LZ4Block";
+const HEADER_BYTES: usize = MAGIC.len() + 1 + 4 + 4 + 4;
+const METHOD_RAW: u8 = 0x10;
+const METHOD_LZ4: u8 = 0x20;
+const BLOCK_SIZE: usize = 1 << 16;
+/// The largest block lz4-java writes, whose compression level is 15
+const MAX_BLOCK_SIZE: usize = 1 << (15 + 10);
+/// Every byte of LZ4 block produces at most 255 bytes
+const MAX_COMPRESSION_RATIO: usize = 255;
+/// Compression level written in token, computed from block size like lz4-java
+const COMPRESSION_LEVEL: u8 = 16 - 10;
+const CHECKSUM_SEED: u32 = 0x9747b28c;
+
+const MIN_MATCH: usize = 4;
+/// The last match must start at least 12 bytes before the end of block
+const MF_LIMIT: usize = 12;
+/// The last 5 bytes are always literals
+const LAST_LITERALS: usize = 5;
+
+fn read_u32_le(src: &[u8], idx: usize) -> u32 {
+    return u32::from_le_bytes([src[idx], src[idx + 1], src[idx + 2], src[idx + 3]]);
+}
+
+/// xxHash32 of `data`
+pub fn xxhash32(data: &[u8], seed: u32) -> u32 {
+    const P1: u32 = 2654435761;
+    const P2: u32 = 2246822519;
+    const P3: u32 = 3266489917;
+    const P4: u32 = 668265263;
+    const P5: u32 = 374761393;
+    let round = |acc: u32, input: u32| acc.wrapping_add(input.wrapping_mul(P2)).rotate_left(13).wrapping_mul(P1);
+
+    let mut idx = 0;
+    let mut h: u32;
+    if data.len() >= 16 {
+        let mut v = [seed.wrapping_add(P1).wrapping_add(P2), seed.wrapping_add(P2), seed, seed.wrapping_sub(P1)];
+        while idx + 16 <= data.len() {
+            for (lane, acc) in v.iter_mut().enumerate() {
+                *acc = round(*acc, read_u32_le(data, idx + lane * 4));
+            }
+            idx += 16;
+        }
+        h = v[0].rotate_left(1).wrapping_add(v[1].rotate_left(7))
+            .wrapping_add(v[2].rotate_left(12)).wrapping_add(v[3].rotate_left(18));
+    } else {
+        h = seed.wrapping_add(P5);
+    }
+    h = h.wrapping_add(data.len() as u32);
+    while idx + 4 <= data.len() {
+        h = h.wrapping_add(read_u32_le(data, idx).wrapping_mul(P3)).rotate_left(17).wrapping_mul(P4);
+        idx += 4;
+    }
+    while idx < data.len() {
+        h = h.wrapping_add((data[idx] as u32).wrapping_mul(P5)).rotate_left(11).wrapping_mul(P1);
+        idx += 1;
+    }
+    h ^= h >> 15;
+    h = h.wrapping_mul(P2);
+    h ^= h >> 13;
+    h = h.wrapping_mul(P3);
+    h ^= h >> 16;
+    return h;
+}
+
+fn checksum(data: &[u8]) -> u32 {
+    return xxhash32(data, CHECKSUM_SEED) & 0x0FFFFFFF;
+}
+
+fn decode_error(detail: String) -> Error {
+    return Error::LZ4DecompressError { detail };
+}
+
+/// Read the extended length after a 15 in token
+fn read_extended_len(src: &[u8], idx: &mut usize, len: &mut usize) -> Result<(), Error> {
+    loop {
+        let b = match src.get(*idx) {
+            Some(b) => *b,
+            None => return Err(decode_error("Length of literals or match is truncated".to_string())),
+        };
+        *idx += 1;
+        *len += b as usize;
+        if b != 255 {
+            return Ok(());
+        }
+    }
+}
+
+/// Decompress a raw LZ4 block whose decompressed size is `decompressed_len`. Returns error if
+/// `decompressed_len` can't be produced by `src`, so that it's safe to pass untrusted length.
+pub fn decompress_block(src: &[u8], decompressed_len: usize) -> Result<Vec<u8>, Error> {
+    if decompressed_len > src.len().saturating_mul(MAX_COMPRESSION_RATIO) {
+        return Err(decode_error(format!("{} bytes can't be decompressed to {decompressed_len} bytes", src.len())));
+    }
+    let mut dst: Vec<u8> = Vec::with_capacity(decompressed_len);
+    let mut idx = 0;
+    while idx < src.len() {
+        let token = src[idx];
+        idx += 1;
+        let mut literal_len = (token >> 4) as usize;
+        if literal_len == 15 {
+            read_extended_len(src, &mut idx, &mut literal_len)?;
+        }
+        if idx + literal_len > src.len() {
+            return Err(decode_error(format!("{literal_len} literals at {idx} exceed the end of block")));
+        }
+        dst.extend_from_slice(&src[idx..(idx + literal_len)]);
+        idx += literal_len;
+        // the last sequence has only literals
+        if idx >= src.len() {
+            break;
+        }
+
+        if idx + 2 > src.len() {
+            return Err(decode_error("Offset of match is truncated".to_string()));
+        }
+        let offset = u16::from_le_bytes([src[idx], src[idx + 1]]) as usize;
+        idx += 2;
+        if offset == 0 || offset > dst.len() {
+            return Err(decode_error(format!("Invalid offset {offset} when {} bytes are decompressed", dst.len())));
+        }
+        let mut match_len = (token & 0xF) as usize;
+        if match_len == 15 {
+            read_extended_len(src, &mut idx, &mut match_len)?;
+        }
+        match_len += MIN_MATCH;
+        // the match may overlap with itself, so copy byte by byte
+        let match_beg = dst.len() - offset;
+        for i in 0..match_len {
+            let b = dst[match_beg + i];
+            dst.push(b);
+        }
+        if dst.len() > decompressed_len {
+            break;
+        }
+    }
+    if dst.len() != decompressed_len {
+        return Err(decode_error(format!("Expected {decompressed_len} bytes after decompression, but found {}", dst.len())));
+    }
+    return Ok(dst);
+}
+
+fn write_extended_len(dst: &mut Vec<u8>, mut len: usize) {
+    while len >= 255 {
+        dst.push(255);
+        len -= 255;
+    }
+    dst.push(len as u8);
+}
+
+fn write_sequence(dst: &mut Vec<u8>, literals: &[u8], match_offset_len: Option<(usize, usize)>) {
+    let literal_nibble = literals.len().min(15) as u8;
+    let match_nibble = match match_offset_len {
+        Some((_, match_len)) => (match_len - MIN_MATCH).min(15) as u8,
+        None => 0,
+    };
+    dst.push((literal_nibble << 4) | match_nibble);
+    if literals.len() >= 15 {
+        write_extended_len(dst, literals.len() - 15);
+    }
+    dst.extend_from_slice(literals);
+    if let Some((offset, match_len)) = match_offset_len {
+        dst.extend_from_slice(&(offset as u16).to_le_bytes());
+        if match_len - MIN_MATCH >= 15 {
+            write_extended_len(dst, match_len - MIN_MATCH - 15);
+        }
+    }
+}
+
+/// Compress `src` into a raw LZ4 block with a greedy hash table matcher
+pub fn compress_block(src: &[u8]) -> Vec<u8> {
+    const HASH_BITS: u32 = 16;
+    let mut table = vec![usize::MAX; 1 << HASH_BITS];
+    let hash = |seq: u32| (seq.wrapping_mul(2654435761) >> (32 - HASH_BITS)) as usize;
+
+    let mut dst = Vec::with_capacity(src.len() / 2 + 16);
+    let mut anchor = 0;
+    let mut idx = 0;
+    while idx + MF_LIMIT <= src.len() {
+        let seq = read_u32_le(src, idx);
+        let h = hash(seq);
+        let candidate = table[h];
+        table[h] = idx;
+        if candidate == usize::MAX || idx - candidate > u16::MAX as usize || read_u32_le(src, candidate) != seq {
+            idx += 1;
+            continue;
+        }
+        let mut match_len = MIN_MATCH;
+        while idx + match_len < src.len() - LAST_LITERALS && src[candidate + match_len] == src[idx + match_len] {
+            match_len += 1;
+        }
+        write_sequence(&mut dst, &src[anchor..idx], Some((idx - candidate, match_len)));
+        idx += match_len;
+        anchor = idx;
+    }
+    write_sequence(&mut dst, &src[anchor..], None);
+    return dst;
+}
+
+fn write_block_header(dst: &mut Vec<u8>, method: u8, compressed_len: usize, original_len: usize, check: u32) {
+    dst.extend_from_slice(MAGIC);
+    dst.push(method | COMPRESSION_LEVEL);
+    dst.extend_from_slice(&(compressed_len as u32).to_le_bytes());
+    dst.extend_from_slice(&(original_len as u32).to_le_bytes());
+    dst.extend_from_slice(&check.to_le_bytes());
+}
+
+/// Compress `src` in the stream format of `LZ4BlockOutputStream`. Blocks that can't be compressed
+/// are stored as raw.
+pub fn compress_block_stream(src: &[u8]) -> Vec<u8> {
+    let mut dst = Vec::with_capacity(src.len() / 2 + HEADER_BYTES * 2);
+    for block in src.chunks(BLOCK_SIZE) {
+        let compressed = compress_block(block);
+        let check = checksum(block);
+        if compressed.len() < block.len() {
+            write_block_header(&mut dst, METHOD_LZ4, compressed.len(), block.len(), check);
+            dst.extend_from_slice(&compressed);
+        } else {
+            write_block_header(&mut dst, METHOD_RAW, block.len(), block.len(), check);
+            dst.extend_from_slice(block);
+        }
+    }
+    write_block_header(&mut dst, METHOD_RAW, 0, 0, 0);
+    return dst;
+}
+
+/// Decompress data in the stream format of `LZ4BlockOutputStream`, checksum of every block is
+/// verified.
+pub fn decompress_block_stream(src: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut dst = Vec::new();
+    let mut idx = 0;
+    loop {
+        if idx + HEADER_BYTES > src.len() {
+            return Err(decode_error(format!("Block header at {idx} is truncated")));
+        }
+        if &src[idx..(idx + MAGIC.len())] != MAGIC {
+            return Err(decode_error(format!("Invalid magic number of block at {idx}")));
+        }
+        let method = src[idx + MAGIC.len()] & 0xF0;
+        let compressed_len = read_u32_le(src, idx + 9) as usize;
+        let original_len = read_u32_le(src, idx + 13) as usize;
+        let check = read_u32_le(src, idx + 17);
+        idx += HEADER_BYTES;
+        if original_len == 0 && compressed_len == 0 {
+            return Ok(dst);
+        }
+        if original_len > MAX_BLOCK_SIZE {
+            return Err(decode_error(format!("Block size {original_len} at {idx} exceeds the maximum {MAX_BLOCK_SIZE}")));
+        }
+        if idx + compressed_len > src.len() {
+            return Err(decode_error(format!("Block of {compressed_len} bytes at {idx} is truncated")));
+        }
+        let data = &src[idx..(idx + compressed_len)];
+        idx += compressed_len;
+        let block = match method {
+            METHOD_RAW if compressed_len == original_len => data.to_vec(),
+            METHOD_LZ4 => decompress_block(data, original_len)?,
+            _ => return Err(decode_error(format!("Invalid method {method:#x} or length {compressed_len} -> {original_len}"))),
+        };
+        if checksum(&block) != check {
+            return Err(decode_error(format!("Checksum mismatch for block ending at {idx}")));
+        }
+        dst.extend_from_slice(&block);
+    }
+}
+
+#[test]
+fn test_xxhash32() {
+    assert_eq!(xxhash32(b"", 0), 0x02CC5D05);
+    assert_eq!(xxhash32(b"abc", 0), 0x32D153FF);
+    assert_eq!(xxhash32(b"Nobody inspects the spammish repetition", 0), 0xE2293B2F);
+}
+
+#[test]
+fn test_lz4_block_stream() {
+    let mut data = Vec::new();
+    for i in 0..200000u32 {
+        data.push((i % 251) as u8 ^ ((i / 1000) as u8));
+        if i % 7 == 0 {
+            data.extend_from_slice(b"minecraft:stone");
+        }
+    }
+    for src in [&data[..], &data[..10], &[]] {
+        let compressed = compress_block_stream(src);
+        assert_eq!(decompress_block_stream(&compressed).unwrap(), src);
+    }
+    assert!(compress_block_stream(&data).len() < data.len() / 2);
+
+    // literals only, then a match overlapping with itself
+    let block = [0x52, b'a', b'b', b'c', b'a', b'b', 0x02, 0x00, 0x10, b'z'];
+    assert_eq!(decompress_block(&block, 12).unwrap(), b"abcababababz");
+}
+
+#[test]
+fn test_lz4_java_stream() {
+    // 64 bytes of 'a' written by LZ4BlockOutputStream with the default block size
+    let stream: Vec<u8> = [
+        &b"LZ4Block"[..], &[0x26, 11, 0, 0, 0, 64, 0, 0, 0, 0x23, 0xf0, 0xf6, 0x0f],
+        &[0x1f, b'a', 0x01, 0x00, 39, 0x50, b'a', b'a', b'a', b'a', b'a'],
+        &b"LZ4Block"[..], &[0x16, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    ].concat();
+    assert_eq!(decompress_block_stream(&stream).unwrap(), [b'a'; 64]);
+
+    // lengths from corrupted headers are rejected before allocating
+    let mut huge = stream.clone();
+    huge[13..17].copy_from_slice(&(1u32 << 30).to_le_bytes());
+    assert!(decompress_block_stream(&huge).is_err());
+    assert!(decompress_block(&stream[21..32], 1 << 24).is_err());
+}
@@ -1,14 +1,22 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::Write;
 use std::ops::Range;
+use std::path::Path;
 use std::sync::Arc;
 use fastnbt::Value;
+use flate2::Compression;
 use flate2::read::{GzDecoder, ZlibDecoder};
+use flate2::write::{GzEncoder, ZlibEncoder};
+use rayon::prelude::*;
 use regex::Regex;
 use world::{XZCoordinate, ChunkPos};
 use crate::error::Error;
+use crate::schem::DataVersion;
 use crate::world;
-use crate::world::{ArcSlice, Chunk, ChunkVariant, Dimension, FileInfo, MCARawData, NBTWithSource, RefOrObject, UnparsedChunkData};
+use crate::world::lz4;
+use crate::world::{ArcSlice, Chunk, ChunkVariant, Dimension, FileInfo, MCACompressMethod, MCARawData, MCASaveOption, NBTWithSource, RefOrObject, UnparsedChunkData};
 use world::FilesRead;
 use crate::raid::RaidList;
 
@@ -20,8 +28,8 @@ impl Display for XZCoordinate {
     }
 }
 
-pub fn parse_mca_filename(filename: &str) -> Option<XZCoordinate> {
-    let reg = Regex::new(r"^r.(-*\d+).(-*\d+).mca$").unwrap();
+fn parse_region_filename(filename: &str, suffix: &str) -> Option<XZCoordinate> {
+    let reg = Regex::new(&format!(r"^r.(-*\d+).(-*\d+).{suffix}$")).unwrap();
     let cap = reg.captures(filename)?;
     debug_assert!(cap.iter().len() == 3);
 
@@ -33,6 +41,15 @@ pub fn parse_mca_filename(filename: &str) -> Option<XZCoordinate> {
     return Some(XZCoordinate { x, z });
 }
 
+pub fn parse_mca_filename(filename: &str) -> Option<XZCoordinate> {
+    return parse_region_filename(filename, "mca");
+}
+
+/// Parse filename of McRegion file, which is used from Beta 1.3 to 1.1
+pub fn parse_mcr_filename(filename: &str) -> Option<XZCoordinate> {
+    return parse_region_filename(filename, "mcr");
+}
+
 pub fn parse_mcc_filename(filename: &str) -> Option<ChunkPos> {
     let reg = Regex::new(r"^c.(-*\d+).(-*\d+).mcc$").unwrap();
     let cap = reg.captures(filename)?;
@@ -135,7 +152,7 @@ impl ChunkVariant {
     }
     pub fn parse_inplace(&mut self, chunk_pos: &ChunkPos) -> Result<&mut Chunk, Error> {
         if let ChunkVariant::Unparsed(raw) = self {
-            *self = ChunkVariant::Parsed(raw.parse(chunk_pos)?);
+            *self = ChunkVariant::Parsed(Box::new(raw.parse(chunk_pos)?));
         }
 
         match self {
@@ -167,6 +184,13 @@ impl MCARawData {
                 3 | 130 => {// no compress
                     parse_opt = fastnbt::from_reader(self.data.as_slice());
                 }
+                4 | 132 => {// lz4
+                    let bytes = lz4::decompress_block_stream(self.data.as_slice())?;
+                    parse_opt = fastnbt::from_bytes(&bytes);
+                }
+                127 | 255 => {// custom, the name of algorithm is stored before data
+                    return Err(Error::UnsupportedMCACustomCompression { algorithm: custom_compression_name(self.data.as_slice()) });
+                }
                 _ => { return Err(Error::InvalidMCACompressType { compress_label: self.compress_method }); }
             }
         }
@@ -181,6 +205,16 @@ impl MCARawData {
     }
 }
 
+/// Name of custom compression algorithm, which is a string with u16 length before compressed data
+fn custom_compression_name(data: &[u8]) -> String {
+    if data.len() < 2 {
+        return String::new();
+    }
+    let len = u16::from_be_bytes([data[0], data[1]]) as usize;
+    let end = (2 + len).min(data.len());
+    return String::from_utf8_lossy(&data[2..end]).to_string();
+}
+
 impl UnparsedChunkData {
     pub fn to_nbt(&self) -> Result<(NBTWithSource, Option<NBTWithSource>), Error> {
         let region_data = self.region_data.to_nbt()?;
@@ -213,12 +247,22 @@ fn get_compress_label(mca_data: &[u8]) -> (u8, usize) {
     return (compress_type, data_bytes);
 }
 
+/// Read all region files in `dir`. McRegion files(`.mcr`) share the same layout with anvil
+/// files(`.mca`), they are read only if there is no anvil file, because worlds converted to anvil
+/// keep the old McRegion files.
 pub fn parse_multiple_mca_files(dir: &dyn FilesRead) -> Result<HashMap<ChunkPos, MCARawData>, Error> {
     let files = dir.files();
     let mut mca_files = Vec::with_capacity(files.len());
-    for info in files {
+    for info in &files {
         if let Some(coord) = parse_mca_filename(&info.name) {
-            mca_files.push((info, coord));
+            mca_files.push((info.clone(), coord));
+        }
+    }
+    if mca_files.is_empty() {
+        for info in files {
+            if let Some(coord) = parse_mcr_filename(&info.name) {
+                mca_files.push((info, coord));
+            }
         }
     }
 
@@ -274,7 +318,7 @@ pub fn parse_multiple_regions(region_dir: &dyn FilesRead,
     }
 
     if parse_directly {
-        let mut temp = Dimension { chunks: result, y_range, raids: RaidList::default() };
+        let mut temp = Dimension { chunks: result, y_range, raids: RaidList::default(), poi: HashMap::new() };
         temp.parse_all(dimension_id)?;
         return Ok(temp.chunks);
     }
@@ -303,7 +347,7 @@ fn parse_mca_file(file_info: &FileInfo, file_coord: &XZCoordinate,
         for x in 0..32 {
             let local_pos = XZCoordinate { x, z };
             let pos = ChunkPos::from_local_pos(file_coord, &local_pos);
-            let unparsed = parse_mca_single_chunk(&pos, mca_bytes.clone(), region_dir)?;
+            let unparsed = parse_mca_single_chunk(&pos, mca_bytes.clone(), &file_info.name, region_dir)?;
             if let Some(raw) = unparsed {
                 result.insert(pos, raw);
             }
@@ -316,7 +360,10 @@ pub fn offset_in_mca_file(local_coord: &XZCoordinate<u32>) -> u32 {
     return 4 * ((local_coord.x & 31) + (local_coord.z & 31) * 32);
 }
 
-fn parse_mca_single_chunk(chunk_pos: &ChunkPos, mca_bytes: ArcSlice, region_dir: &dyn FilesRead) -> Result<Option<MCARawData>, Error> {
+/// `filename` is the name of the mca or mcr file in `region_dir`, which is recorded as the source
+/// of chunk.
+fn parse_mca_single_chunk(chunk_pos: &ChunkPos, mca_bytes: ArcSlice, filename: &str,
+                          region_dir: &dyn FilesRead) -> Result<Option<MCARawData>, Error> {
     let header: [u8; 4];
     let local_coord = chunk_pos.local_coordinate();
     {
@@ -362,7 +409,7 @@ fn parse_mca_single_chunk(chunk_pos: &ChunkPos, mca_bytes: ArcSlice, region_dir:
     //let range = ;
     let (compress_label, compressed_len) = get_compress_label(&mca_bytes[data_beg_idx..data_end_idx]);
 
-    if ![1, 128, 2, 129, 3, 130].contains(&compress_label) {
+    if ![1, 128, 2, 129, 3, 130, 4, 132, 127, 255].contains(&compress_label) {
         return Err(Error::InvalidMCACompressType { compress_label });
     }
 
@@ -388,6 +435,265 @@ fn parse_mca_single_chunk(chunk_pos: &ChunkPos, mca_bytes: ArcSlice, region_dir:
         time_stamp,
         compress_method: compress_label,
         data: mca_bytes.slice((data_beg_idx + 5)..(data_beg_idx + 5 + compressed_len)),
-        source_file: format!("{}/{}", region_dir.path(), chunk_pos.filename_mca()),
+        source_file: format!("{}/{}", region_dir.path(), filename),
     }));
-}
\ No newline at end of file
+}
+impl Default for MCASaveOption {
+    fn default() -> Self {
+        return Self {
+            compress_method: MCACompressMethod::Zlib,
+            compress_level: 6,
+            data_version: DataVersion::Java_1_20_4 as i32,
+        };
+    }
+}
+
+fn compress_nbt(nbt: &HashMap<String, Value>, option: &MCASaveOption) -> Result<Vec<u8>, Error> {
+    let bytes = match fastnbt::to_bytes(nbt) {
+        Ok(b) => b,
+        Err(e) => return Err(Error::NBTWriteError(e)),
+    };
+    let level = Compression::new(option.compress_level);
+    let res = match option.compress_method {
+        MCACompressMethod::GZip => {
+            let mut encoder = GzEncoder::new(Vec::new(), level);
+            encoder.write_all(&bytes).and_then(|_| encoder.finish())
+        }
+        MCACompressMethod::Zlib => {
+            let mut encoder = ZlibEncoder::new(Vec::new(), level);
+            encoder.write_all(&bytes).and_then(|_| encoder.finish())
+        }
+        MCACompressMethod::Uncompressed => Ok(bytes),
+        MCACompressMethod::LZ4 => Ok(lz4::compress_block_stream(&bytes)),
+    };
+    return match res {
+        Ok(compressed) => Ok(compressed),
+        Err(e) => Err(Error::IOWriteError(e)),
+    };
+}
+
+/// Region data and entity data of a chunk, compressed and ready to be written
+struct CompressedChunk {
+    chunk_pos: ChunkPos,
+    time_stamp: u32,
+    region_data: Vec<u8>,
+    entity_data: Option<Vec<u8>>,
+}
+
+fn compress_chunk(chunk_pos: &ChunkPos, variant: &ChunkVariant, option: &MCASaveOption) -> Result<CompressedChunk, Error> {
+    let (time_stamp, region_nbt, entity_nbt) = match variant {
+        ChunkVariant::Parsed(chunk) => {
+            // chunks created in memory have no data version and no file, while chunks loaded from
+            // files before 1.9 don't record data version
+            let data_version = if chunk.data_version == 0 && chunk.file_region == "NoFile" {
+                option.data_version
+            } else {
+                chunk.data_version
+            };
+            if data_version < DataVersion::Snapshot_21w43a as i32 {
+                let global_pos = chunk_pos.to_global_pos();
+                return Err(Error::UnsupportedChunkVersion {
+                    chunk_x: global_pos.x,
+                    chunk_z: global_pos.z,
+                    data_version,
+                });
+            }
+            let (region_nbt, entity_nbt) = chunk.to_nbt(chunk_pos, data_version);
+            (chunk.time_stamp, region_nbt, entity_nbt)
+        }
+        ChunkVariant::Unparsed(raw) => {
+            let (region_nbt, entity_nbt) = raw.to_nbt()?;
+            (raw.region_data.time_stamp, region_nbt.nbt, entity_nbt.map(|n| n.nbt))
+        }
+    };
+
+    let region_data = compress_nbt(&region_nbt, option)?;
+    let entity_data = if let Some(entity_nbt) = entity_nbt {
+        Some(compress_nbt(&entity_nbt, option)?)
+    } else {
+        None
+    };
+    return Ok(CompressedChunk {
+        chunk_pos: *chunk_pos,
+        time_stamp,
+        region_data,
+        entity_data,
+    });
+}
+
+/// Encode compressed chunks into a mca file. Chunks are allocated to sectors one by one, and
+/// chunks that take more than 255 sectors are stored in separated mcc files. Returns the bytes of
+/// mca file, and filenames and bytes of mcc files.
+pub fn encode_mca_file(chunks: &[(ChunkPos, u32, &[u8])], compress_method: MCACompressMethod)
+                       -> (Vec<u8>, Vec<(String, Vec<u8>)>) {
+    let mut mca_bytes = vec![0u8; 2 * SEGMENT_BYTES];
+    let mut mcc_files = Vec::new();
+
+    for (chunk_pos, time_stamp, data) in chunks {
+        let header_idx = offset_in_mca_file(&chunk_pos.local_coordinate()) as usize;
+        let offset_by_segment = (mca_bytes.len() / SEGMENT_BYTES) as u32;
+
+        let mut num_segments = (data.len() + 5).div_ceil(SEGMENT_BYTES);
+        if num_segments > 255 {
+            // Too large, store in mcc file and only keep compress label in mca
+            mca_bytes.extend_from_slice(&1u32.to_be_bytes());
+            mca_bytes.push(compress_method as u8 + 128);
+            mcc_files.push((chunk_pos.filename_mcc(), data.to_vec()));
+            num_segments = 1;
+        } else {
+            mca_bytes.extend_from_slice(&(data.len() as u32 + 1).to_be_bytes());
+            mca_bytes.push(compress_method as u8);
+            mca_bytes.extend_from_slice(data);
+        }
+        mca_bytes.resize((offset_by_segment as usize + num_segments) * SEGMENT_BYTES, 0);
+
+        let offset = offset_by_segment.to_be_bytes();
+        mca_bytes[header_idx..(header_idx + 4)].copy_from_slice(&[offset[1], offset[2], offset[3], num_segments as u8]);
+        let time_stamp_idx = header_idx + SEGMENT_BYTES;
+        mca_bytes[time_stamp_idx..(time_stamp_idx + 4)].copy_from_slice(&time_stamp.to_be_bytes());
+    }
+
+    return (mca_bytes, mcc_files);
+}
+
+fn write_file(dir: &Path, filename: &str, bytes: &[u8]) -> Result<(), Error> {
+    let mut file = match File::create(dir.join(filename)) {
+        Ok(f) => f,
+        Err(e) => return Err(Error::FileCreateError(e)),
+    };
+    if let Err(e) = file.write_all(bytes) {
+        return Err(Error::IOWriteError(e));
+    }
+    return Ok(());
+}
+
+/// Write chunks to mca files in `region_dir`, and entities in `entity_dir`. Existing mca files of
+/// the same region are overwritten, while other files are kept. If `only_files` is not `None`,
+/// only mca files whose file coordinate(x, z) is in it are written.
+pub fn write_multiple_regions(chunks: &HashMap<ChunkPos, ChunkVariant>,
+                              region_dir: &Path,
+                              entity_dir: &Path,
+                              only_files: Option<&HashSet<(i32, i32)>>,
+                              option: &MCASaveOption) -> Result<(), Error> {
+    for dir in [region_dir, entity_dir] {
+        if let Err(e) = std::fs::create_dir_all(dir) {
+            return Err(Error::FileCreateError(e));
+        }
+    }
+
+    let compressed: Vec<CompressedChunk> = chunks.par_iter()
+        .filter(|(pos, _)| {
+            let file_coord = pos.file_coordinate();
+            only_files.map_or(true, |files| files.contains(&(file_coord.x, file_coord.z)))
+        })
+        .map(|(pos, variant)| compress_chunk(pos, variant, option))
+        .collect::<Result<Vec<CompressedChunk>, Error>>()?;
+
+    let mut files: BTreeMap<(i32, i32), Vec<&CompressedChunk>> = BTreeMap::new();
+    for chunk in &compressed {
+        let file_coord = chunk.chunk_pos.file_coordinate();
+        files.entry((file_coord.x, file_coord.z)).or_default().push(chunk);
+    }
+
+    for (_, mut file_chunks) in files {
+        file_chunks.sort_by_key(|c| offset_in_mca_file(&c.chunk_pos.local_coordinate()));
+        let filename = file_chunks[0].chunk_pos.filename_mca();
+
+        let region_chunks: Vec<(ChunkPos, u32, &[u8])> = file_chunks.iter()
+            .map(|c| (c.chunk_pos, c.time_stamp, c.region_data.as_slice()))
+            .collect();
+        let (mca_bytes, mcc_files) = encode_mca_file(&region_chunks, option.compress_method);
+        write_file(region_dir, &filename, &mca_bytes)?;
+        for (mcc_name, mcc_bytes) in mcc_files {
+            write_file(region_dir, &mcc_name, &mcc_bytes)?;
+        }
+
+        let entity_chunks: Vec<(ChunkPos, u32, &[u8])> = file_chunks.iter()
+            .filter_map(|c| Some((c.chunk_pos, c.time_stamp, c.entity_data.as_ref()?.as_slice())))
+            .collect();
+        let (mca_bytes, mcc_files) = encode_mca_file(&entity_chunks, option.compress_method);
+        write_file(entity_dir, &filename, &mca_bytes)?;
+        for (mcc_name, mcc_bytes) in mcc_files {
+            write_file(entity_dir, &mcc_name, &mcc_bytes)?;
+        }
+    }
+
+    return Ok(());
+}
+
+#[test]
+fn test_encode_mca_file() {
+    let small = vec![7u8; 5000];
+    let large = vec![9u8; 256 * SEGMENT_BYTES];
+    let pos_small = ChunkPos::from_global_pos(&XZCoordinate { x: -1, z: 3 });
+    let pos_large = ChunkPos::from_global_pos(&XZCoordinate { x: 0, z: 3 });
+    let chunks = [(pos_small, 1, small.as_slice()), (pos_large, 2, large.as_slice())];
+    let (mca_bytes, mcc_files) = encode_mca_file(&chunks, MCACompressMethod::Zlib);
+
+    assert_eq!(mca_bytes.len() % SEGMENT_BYTES, 0);
+    assert_eq!(mcc_files.len(), 1);
+    assert_eq!(mcc_files[0].0, "c.0.3.mcc");
+    assert_eq!(mcc_files[0].1, large);
+
+    let header = |pos: &ChunkPos| {
+        let idx = offset_in_mca_file(&pos.local_coordinate()) as usize;
+        (u32::from_be_bytes([0, mca_bytes[idx], mca_bytes[idx + 1], mca_bytes[idx + 2]]) as usize, mca_bytes[idx + 3] as usize)
+    };
+    let (offset, num_segments) = header(&pos_small);
+    assert_eq!((offset, num_segments), (2, 2));
+    let (label, len) = get_compress_label(&mca_bytes[offset * SEGMENT_BYTES..]);
+    assert_eq!((label, len), (2, small.len() + 1));
+    assert_eq!(&mca_bytes[(offset * SEGMENT_BYTES + 5)..(offset * SEGMENT_BYTES + 5 + small.len())], small.as_slice());
+
+    let (offset, num_segments) = header(&pos_large);
+    assert_eq!((offset, num_segments), (4, 1));
+    assert_eq!(get_compress_label(&mca_bytes[offset * SEGMENT_BYTES..]).0, 130);
+}
+
+#[test]
+fn test_mcr_source_file() {
+    use crate::world::FolderOnDisk;
+    let dir = std::env::temp_dir().join("mc_schem_test_mcr_source_file");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let pos = ChunkPos::from_global_pos(&XZCoordinate { x: 1, z: 2 });
+    let data = vec![0u8; 100];
+    let (mcr_bytes, _) = encode_mca_file(&[(pos, 0, data.as_slice())], MCACompressMethod::Zlib);
+    std::fs::write(dir.join(pos.filename_mcr()), mcr_bytes).unwrap();
+
+    let folder = FolderOnDisk::new(dir.to_str().unwrap());
+    let chunks = parse_multiple_mca_files(&folder).unwrap();
+    assert!(chunks[&pos].source_file.ends_with("/r.0.0.mcr"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_compress_methods() {
+    let nbt = HashMap::from([
+        ("DataVersion".to_string(), Value::Int(3700)),
+        ("Status".to_string(), Value::String("minecraft:full".repeat(100))),
+    ]);
+    for method in [MCACompressMethod::GZip, MCACompressMethod::Zlib, MCACompressMethod::Uncompressed, MCACompressMethod::LZ4] {
+        let option = MCASaveOption { compress_method: method, ..MCASaveOption::default() };
+        let raw = MCARawData {
+            time_stamp: 0,
+            compress_method: method as u8,
+            data: ArcSlice::from(Arc::new(compress_nbt(&nbt, &option).unwrap())),
+            source_file: "r.0.0.mca".to_string(),
+        };
+        assert_eq!(raw.to_nbt().unwrap().nbt, nbt);
+    }
+
+    let mut custom = vec![0, 4];
+    custom.extend_from_slice(b"zstd");
+    let raw = MCARawData {
+        time_stamp: 0,
+        compress_method: 127,
+        data: ArcSlice::from(Arc::new(custom)),
+        source_file: "r.0.0.mca".to_string(),
+    };
+    match raw.to_nbt() {
+        Err(Error::UnsupportedMCACustomCompression { algorithm }) => assert_eq!(algorithm, "zstd"),
+        _ => panic!("Custom compression should be reported"),
+    }
+}
@@ -28,7 +28,9 @@ use crate::biome::Biome;
 use crate::block::Block;
 use crate::error::Error;
 use crate::raid::RaidList;
+use crate::player::{Player, PlayerGameType};
 use crate::region::{Light, PendingTick};
+use strum::FromRepr;
 
 pub mod mca;
 mod files_reader;
@@ -37,6 +39,11 @@ mod dimension;
 mod sub_chunk;
 mod chunk_ref;
 mod world;
+mod level_data;
+mod legacy_chunk;
+mod lz4;
+mod chunk_meta;
+mod poi;
 
 
 #[derive(Debug, Eq, Hash, PartialEq)]
@@ -74,12 +81,55 @@ pub struct Chunk {
     pub block_entities: HashMap<[i32; 3], BlockEntity>,
     pub pending_ticks: HashMap<[i32; 3], Vec<PendingTick>>,
 
+    /// Data version of the game that saved this chunk, 0 if it's not recorded(before 1.9)
+    pub data_version: i32,
+    /// Heightmaps indexed by type, like `MOTION_BLOCKING` and `WORLD_SURFACE`. They are cleared
+    /// when any block is changed, and the game will compute them again.
+    pub heightmaps: BTreeMap<String, Heightmap>,
+    /// Structures that start in this chunk, indexed by structure id
+    pub structure_starts: BTreeMap<String, StructureStart>,
+    /// Chunks where structures overlapping this chunk start, indexed by structure id
+    pub structure_references: BTreeMap<String, Vec<ChunkPos>>,
+    /// Absolute positions of blocks waiting for post processing, only in proto chunks
+    pub post_processing: Vec<[i32; 3]>,
+    /// Data to blend old chunks with newly generated terrain
+    pub blending_data: Option<HashMap<String, Value>>,
+    /// Bitsets of carved blocks, indexed by carving step like `AIR` and `LIQUID`. The bit index of
+    /// relative position (x, y, z) is `x | z << 4 | y << 8`, where y is relative to the bottom of
+    /// chunk. Only in proto chunks.
+    pub carving_masks: BTreeMap<String, Vec<u64>>,
+    /// Number ids and damages that can't be converted in chunks before 1.13, with the number of
+    /// such blocks. Invalid damage values are replaced by 0, and blocks with number id above 255
+    /// (which need the `Add` array) or unknown ids are replaced by air.
+    pub unknown_number_id_blocks: BTreeMap<(u16, u8), u64>,
+
     /// Related region file
     pub file_region: String,
     /// Related entities file
     pub file_entities: String,
 }
 
+/// Heightmap of a chunk
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heightmap {
+    /// Absolute y of the block above the highest matched block, indexed by `[z][x]`. It equals to
+    /// the bottom of chunk if no block is matched in the column.
+    pub heights: [[i32; 16]; 16],
+}
+
+/// A structure that starts in a chunk
+#[derive(Debug, Clone)]
+pub struct StructureStart {
+    /// Id of the structure, like `minecraft:village_plains`
+    pub id: String,
+    /// The chunk where the structure starts
+    pub chunk_pos: ChunkPos,
+    /// Bounding boxes of all pieces in `[x_min, y_min, z_min, x_max, y_max, z_max]`, inclusive
+    pub piece_boxes: Vec<[i32; 6]>,
+    /// All tags of the structure start
+    pub nbt: HashMap<String, Value>,
+}
+
 #[derive(Debug, Eq, Hash, PartialEq, Copy, Clone)]
 pub struct ChunkPos {
     global_x: i32,
@@ -131,7 +181,7 @@ pub struct UnparsedChunkData {
 
 #[derive(Debug, Clone)]
 pub enum ChunkVariant {
-    Parsed(Chunk),
+    Parsed(Box<Chunk>),
     Unparsed(UnparsedChunkData),
 }
 
@@ -141,11 +191,86 @@ pub struct Dimension {
     y_range: Range<i32>,
     #[allow(dead_code)]
     raids: RaidList,
+    /// Points of interest in `poi` folder, indexed by chunk and section y. It's empty until
+    /// `load_poi` is called, unless the dimension is loaded with `parse_directly`.
+    pub poi: HashMap<ChunkPos, BTreeMap<i8, PoiSection>>,
+}
+
+/// A point of interest, like a villager workstation, bed, bell, nether portal or lodestone
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoiRecord {
+    /// Type of poi, like `minecraft:home` and `minecraft:nether_portal`
+    pub poi_type: String,
+    /// Absolute position
+    pub pos: [i32; 3],
+    /// How many more villagers can claim this poi
+    pub free_tickets: i32,
+}
+
+/// Points of interest in a 16x16x16 section
+#[derive(Debug, Clone, Default)]
+pub struct PoiSection {
+    /// If false, the game will scan this section for poi again
+    pub valid: bool,
+    pub records: Vec<PoiRecord>,
 }
 
 #[derive(Debug, Clone)]
 pub struct World {
     pub dimensions: BTreeMap<i32, Dimension>,
+    /// Parsed from `level.dat`, `None` if the file doesn't exist
+    pub level_data: Option<LevelData>,
+    /// Players parsed from `playerdata/<uuid>.dat`, indexed by the uuid in filename. The
+    /// singleplayer player in `level.dat` is in `level_data` instead.
+    pub players: BTreeMap<String, Player>,
+    /// Player files that failed to be parsed and are skipped, indexed by filename
+    pub player_errors: BTreeMap<String, String>,
+}
+
+#[repr(i8)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, FromRepr)]
+pub enum Difficulty {
+    Peaceful = 0,
+    Easy = 1,
+    Normal = 2,
+    Hard = 3,
+}
+
+/// World-level metadata stored in `level.dat`
+#[derive(Debug, Clone)]
+pub struct LevelData {
+    /// Data version of the game that saved this world
+    pub data_version: i32,
+    /// Name of the game version, like `1.20.2`
+    pub version_name: Option<String>,
+    pub level_name: String,
+    pub seed: i64,
+    pub spawn_pos: [i32; 3],
+    pub spawn_angle: f32,
+    pub game_type: PlayerGameType,
+    pub difficulty: Difficulty,
+    pub difficulty_locked: bool,
+    pub hardcore: bool,
+    pub allow_commands: bool,
+    /// Game time in ticks
+    pub time: i64,
+    /// Time of day in ticks
+    pub day_time: i64,
+    /// Unix time in milliseconds
+    pub last_played: i64,
+    pub raining: bool,
+    pub rain_time: i32,
+    pub thundering: bool,
+    pub thunder_time: i32,
+    pub clear_weather_time: i32,
+    /// Game rules, values are stored as strings
+    pub game_rules: BTreeMap<String, String>,
+    pub enabled_data_packs: Vec<String>,
+    pub disabled_data_packs: Vec<String>,
+    pub server_brands: Vec<String>,
+    pub was_modded: bool,
+    /// The player in singleplayer world
+    pub player: Option<Player>,
 }
 
 #[derive(Debug, Clone)]
@@ -153,6 +278,43 @@ pub struct WorldLoadOption {
     parse_directly: bool,
 }
 
+/// Options to paste a schematic into a dimension
+#[derive(Debug, Clone)]
+pub struct PasteOption {
+    /// Don't overwrite blocks in world with air in schematic
+    pub skip_air: bool,
+    /// Don't overwrite blocks in world with structure void in schematic
+    pub skip_structure_void: bool,
+    /// Mark lighting of affected chunks as incorrect, so that the game will compute it again.
+    /// Heightmaps of affected chunks are always cleared, and the game will compute them again.
+    pub invalidate_lighting: bool,
+    /// Give pasted entities new random UUIDs. If false, entities keep UUIDs in the schematic, and
+    /// pasting the same schematic twice creates entities with duplicated UUIDs.
+    pub regenerate_uuid: bool,
+}
+
+/// Compress method of chunks in mca files
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[repr(u8)]
+pub enum MCACompressMethod {
+    GZip = 1,
+    Zlib = 2,
+    Uncompressed = 3,
+    /// Supported by minecraft since 1.20.5. `compress_level` is ignored.
+    LZ4 = 4,
+}
+
+#[derive(Debug, Clone)]
+pub struct MCASaveOption {
+    /// Compress method of every chunk
+    pub compress_method: MCACompressMethod,
+    /// Compress level, ranges from 0 to 9. Ignored if chunks are not compressed
+    pub compress_level: u32,
+    /// Data version written to chunks that are created in memory. Parsed chunks keep their own
+    /// data version, and unparsed chunks keep their original nbt
+    pub data_version: i32,
+}
+
 
 #[derive(Debug, Clone)]
 pub struct FileInfo {
@@ -0,0 +1,142 @@
+/*
+mc_schem is a rust library to generate, load, manipulate and save minecraft schematic files.
+Copyright (C) 2024  joseph
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use std::collections::{BTreeMap, HashMap};
+
+use fastnbt::Value;
+
+use crate::{unwrap_opt_tag, unwrap_tag};
+use crate::error::{Error, unwrap_opt_i32, unwrap_opt_string};
+use crate::schem::id_of_nbt_tag;
+use crate::world::{ChunkPos, Dimension, FilesRead, mca, PoiRecord, PoiSection};
+
+impl PoiRecord {
+    pub fn from_nbt(nbt: &HashMap<String, Value>, tag_path: &str) -> Result<PoiRecord, Error> {
+        let pos_path = format!("{tag_path}/pos");
+        let pos = unwrap_opt_tag!(nbt.get("pos"),IntArray,fastnbt::IntArray::new(vec![]),pos_path);
+        if pos.len() != 3 {
+            return Err(Error::InvalidValue { tag_path: pos_path, error: format!("The length should be 3, but found {}", pos.len()) });
+        }
+        return Ok(PoiRecord {
+            poi_type: unwrap_opt_string(nbt, "type", tag_path)?.clone(),
+            pos: [pos[0], pos[1], pos[2]],
+            free_tickets: unwrap_opt_i32(nbt, "free_tickets", tag_path)?,
+        });
+    }
+}
+
+impl PoiSection {
+    pub fn from_nbt(nbt: &HashMap<String, Value>, tag_path: &str) -> Result<PoiSection, Error> {
+        let valid = match nbt.get("Valid") {
+            Some(tag) => *unwrap_tag!(tag,Byte,0,format!("{tag_path}/Valid")) != 0,
+            None => false,
+        };
+        let records_path = format!("{tag_path}/Records");
+        let records_tag = unwrap_opt_tag!(nbt.get("Records"),List,vec![],records_path);
+        let mut records = Vec::with_capacity(records_tag.len());
+        for (idx, tag) in records_tag.iter().enumerate() {
+            let path = format!("{records_path}[{idx}]");
+            let tag = unwrap_tag!(tag,Compound,HashMap::new(),path);
+            records.push(PoiRecord::from_nbt(tag, &path)?);
+        }
+        return Ok(PoiSection { valid, records });
+    }
+}
+
+/// Parse poi sections of a chunk, indexed by section y
+fn parse_poi_chunk(nbt: &HashMap<String, Value>, tag_path: &str) -> Result<BTreeMap<i8, PoiSection>, Error> {
+    let sections_path = format!("{tag_path}/Sections");
+    let sections = unwrap_opt_tag!(nbt.get("Sections"),Compound,HashMap::new(),sections_path);
+    let mut result = BTreeMap::new();
+    for (y, tag) in sections {
+        let path = format!("{sections_path}/{y}");
+        let y = match y.parse::<i8>() {
+            Ok(y) => y,
+            Err(_) => return Err(Error::InvalidValue { tag_path: path, error: format!("\"{y}\" is not a valid section y") }),
+        };
+        let tag = unwrap_tag!(tag,Compound,HashMap::new(),path);
+        result.insert(y, PoiSection::from_nbt(tag, &path)?);
+    }
+    return Ok(result);
+}
+
+/// Parse all poi files(`r.x.z.mca`) in `poi_dir`. Chunks without any section are skipped.
+pub fn parse_poi_regions(poi_dir: &dyn FilesRead) -> Result<HashMap<ChunkPos, BTreeMap<i8, PoiSection>>, Error> {
+    let raw_data = mca::parse_multiple_mca_files(poi_dir)?;
+    let mut result = HashMap::with_capacity(raw_data.len());
+    for (chunk_pos, raw) in raw_data {
+        let nbt = raw.to_nbt()?;
+        let tag_path = format!("{}/[{},{}]", nbt.source,
+                               chunk_pos.local_coordinate().x,
+                               chunk_pos.local_coordinate().z);
+        let sections = parse_poi_chunk(&nbt.nbt, &tag_path)?;
+        if !sections.is_empty() {
+            result.insert(chunk_pos, sections);
+        }
+    }
+    return Ok(result);
+}
+
+impl Dimension {
+    /// Parse the `poi` folder in `files`, which should be the same folder passed to `from_files`.
+    /// Poi files are only parsed by `from_files` if `parse_directly` is true, call this to load
+    /// them later.
+    pub fn load_poi(&mut self, files: &dyn FilesRead) -> Result<(), Error> {
+        self.poi = parse_poi_regions(&files.sub_directory("poi"))?;
+        return Ok(());
+    }
+
+    /// Poi sections in a chunk, indexed by section y
+    pub fn poi_in_chunk(&self, chunk_pos: &ChunkPos) -> Option<&BTreeMap<i8, PoiSection>> {
+        return self.poi.get(chunk_pos);
+    }
+
+    /// All poi records in this dimension
+    pub fn poi_records(&self) -> impl Iterator<Item=&PoiRecord> {
+        return self.poi.values().flat_map(|sections| sections.values()).flat_map(|sect| sect.records.iter());
+    }
+
+    /// Poi records of a type, like `minecraft:home`. Namespace `minecraft` can be omitted.
+    pub fn poi_of_type<'a>(&'a self, poi_type: &'a str) -> impl Iterator<Item=&'a PoiRecord> {
+        let poi_type = poi_type.strip_prefix("minecraft:").unwrap_or(poi_type);
+        return self.poi_records().filter(move |record| {
+            record.poi_type.strip_prefix("minecraft:").unwrap_or(&record.poi_type) == poi_type
+        });
+    }
+}
+
+#[test]
+fn test_parse_poi() {
+    use crate::world::FilesInMemory;
+    let files = FilesInMemory::from_7z_file("test_files/world/03_raids-1.20.2.7z", "").unwrap();
+    let mut dim = Dimension::from_files(&files, false, -64..320, 0).unwrap();
+    assert!(dim.poi.is_empty());
+    dim.load_poi(&files).unwrap();
+    for (chunk_pos, sections) in &dim.poi {
+        let lb = chunk_pos.block_pos_lower_bound();
+        for (y, sect) in sections {
+            for record in &sect.records {
+                assert!(record.pos[0] >= lb[0] && record.pos[0] < lb[0] + 16);
+                assert!(record.pos[2] >= lb[1] && record.pos[2] < lb[1] + 16);
+                assert_eq!(record.pos[1].div_euclid(16), *y as i32);
+            }
+        }
+    }
+    assert!(dim.poi_of_type("home").count() > 0);
+    assert!(dim.poi_of_type("minecraft:meeting").count() > 0);
+}
@@ -1,6 +1,7 @@
 use ndarray::{ArrayView2, ArrayView3};
 use crate::biome::Biome;
 use crate::block::Block;
+use crate::error::Error;
 use crate::region::{BlockEntity, HasPalette, Light, PendingTick, WorldSlice};
 use crate::world::SubChunk;
 
@@ -19,6 +20,14 @@ impl SubChunk {
         return result;
     }
 
+    /// Create a sub chunk that all blocks are `block` and all biomes are `biome`
+    pub fn filled_with(block: &Block, biome: Biome) -> SubChunk {
+        let mut result = SubChunk::new();
+        result.palette.push(block.clone());
+        result.biome_array.fill(biome);
+        return result;
+    }
+
     pub fn block_id(&self) -> ArrayView3<u16> {
         return ArrayView3::from_shape([16, 16, 16], &self.block_id_array).unwrap();
     }
@@ -36,6 +45,27 @@ impl SubChunk {
     pub fn biome_at(&self, r_pos: [i32; 3]) -> Biome {
         return self.biome()[[(r_pos[2] / 2) as usize, (r_pos[0] / 2) as usize]];
     }
+
+    /// Set block at `r_pos`, the block is appended to palette if not found. Returns error if
+    /// `r_pos` is outside the sub chunk or the palette is full.
+    pub fn set_block(&mut self, r_pos: [i32; 3], block: &Block) -> Result<(), Error> {
+        if !self.contains_coord(r_pos) {
+            return Err(Error::BlockPosOutOfDimension { pos: r_pos });
+        }
+        let blk_id = match self.palette.iter().position(|blk| blk == block) {
+            Some(idx) => idx,
+            None => {
+                if self.palette.len() >= 65536 {
+                    return Err(Error::PaletteTooLong(self.palette.len() + 1));
+                }
+                self.palette.push(block.clone());
+                self.palette.len() - 1
+            }
+        };
+        let idx = (r_pos[1] * 256 + r_pos[2] * 16 + r_pos[0]) as usize;
+        self.block_id_array[idx] = blk_id as u16;
+        return Ok(());
+    }
 }
 
 impl HasPalette for SubChunk {
@@ -73,16 +103,17 @@ impl WorldSlice for SubChunk {
 
     fn block_index_at(&self, r_pos: [i32; 3]) -> Option<u16> {
         if self.contains_coord(r_pos) {
-            let r_pos = [r_pos[0] as usize, r_pos[1] as usize, r_pos[2] as usize];
-            return Some(self.block_id()[r_pos]);
+            // blocks are stored in yzx
+            let yzx = [r_pos[1] as usize, r_pos[2] as usize, r_pos[0] as usize];
+            return Some(self.block_id()[yzx]);
         }
         return None;
     }
 
     fn block_at(&self, r_pos: [i32; 3]) -> Option<&Block> {
         if self.contains_coord(r_pos) {
-            let r_pos = [r_pos[0] as usize, r_pos[1] as usize, r_pos[2] as usize];
-            let id = self.block_id()[r_pos];
+            let yzx = [r_pos[1] as usize, r_pos[2] as usize, r_pos[0] as usize];
+            let id = self.block_id()[yzx];
             return Some(&self.palette[id as usize]);
         }
         return None;
@@ -1,9 +1,13 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
+use fastnbt::Value;
+use flate2::read::GzDecoder;
 #[allow(unused_imports)]
 use std::time;
 use crate::Error;
 #[allow(unused_imports)]
-use crate::world::{Dimension, FilesInMemory, FilesRead, World, WorldLoadOption};
+use crate::world::{Dimension, FilesInMemory, FilesRead, LevelData, World, WorldLoadOption};
+use crate::schem::DataVersion;
+use crate::player::Player;
 
 impl Default for WorldLoadOption {
     fn default() -> Self {
@@ -13,12 +17,50 @@ impl Default for WorldLoadOption {
     }
 }
 
+/// Parse all `playerdata/<uuid>.dat`, backups like `<uuid>.dat_old` are ignored. Files that fail
+/// to be parsed are skipped, and the errors are returned in the second map, indexed by filename.
+fn players_from_files(files: &dyn FilesRead) -> (BTreeMap<String, Player>, BTreeMap<String, String>) {
+    let mut players = BTreeMap::new();
+    let mut errors = BTreeMap::new();
+    for info in files.files() {
+        let uuid = match info.name.strip_prefix("playerdata/").and_then(|n| n.strip_suffix(".dat")) {
+            Some(uuid) => uuid,
+            None => continue,
+        };
+        match player_from_file(files, &info.name) {
+            Ok(player) => {
+                players.insert(uuid.to_string(), player);
+            }
+            Err(e) => {
+                errors.insert(info.name.clone(), e.to_string());
+            }
+        }
+    }
+    return (players, errors);
+}
+
+fn player_from_file(files: &dyn FilesRead, filename: &str) -> Result<Player, Error> {
+    let src = files.open_file(filename)?;
+    let nbt: HashMap<String, Value> = match fastnbt::from_reader(GzDecoder::new(src)) {
+        Ok(nbt) => nbt,
+        Err(e) => return Err(Error::NBTReadError(e)),
+    };
+    return Player::from_nbt(&nbt, &format!("{}/{}", files.path(), filename));
+}
+
 impl World {
     pub fn from_files(files: &dyn FilesRead, option: &WorldLoadOption) -> Result<World, Error> {
-        let mut world = World { dimensions: BTreeMap::new() };
+        let level_data = LevelData::from_files(files)?;
+        // The overworld is higher since 1.18, assume the newest version if level.dat is missing
+        let overworld_y_range = match &level_data {
+            Some(ld) if ld.data_version < DataVersion::Java_1_18 as i32 => 0..256,
+            _ => -64..320,
+        };
+        let (players, player_errors) = players_from_files(files);
+        let mut world = World { dimensions: BTreeMap::new(), level_data, players, player_errors };
         for dim in [0, -1, 1] {
             let dimension = if dim == 0 {
-                Dimension::from_files(files, option.parse_directly, -64..320, dim)?
+                Dimension::from_files(files, option.parse_directly, overworld_y_range.clone(), dim)?
             } else {
                 let dir = format!("DIM{dim}");
                 // let y_range = if dim == -1 { 0..256 } else { -64..320 };
@@ -40,6 +82,11 @@ impl World {
         return self.dimensions.get(&1);
     }
 
+    /// Data version from `level.dat`, `None` if `level.dat` is missing
+    pub fn data_version(&self) -> Option<i32> {
+        return Some(self.level_data.as_ref()?.data_version);
+    }
+
     pub fn parse_all_dimensions(&mut self) -> Result<(), Error> {
         for (id, dim) in &mut self.dimensions {
             dim.parse_all(*id)?;
@@ -48,6 +95,85 @@ impl World {
     }
 }
 
+#[test]
+fn test_players() {
+    let src = FilesInMemory::from_7z_file("test_files/world/03_raids-1.20.2.7z", "").unwrap();
+    let (players, errors) = players_from_files(&src);
+    assert!(errors.is_empty());
+    let player = &players["065992de-c443-44b6-98f7-9f3470f3e9fa"];
+    assert_eq!(player.entity_fields.pos[1], -53.0);
+    assert_eq!(player.player_fields.dimension.id(), "minecraft:overworld");
+    assert_eq!(player.player_fields.inventory.0.len(), 3);
+    assert!(player.player_fields.ender_items.0.is_empty());
+    assert_eq!(player.player_fields.xp_info.level, 12);
+
+    // convert the player to the 1.21 format
+    fn rename(nbt: &mut HashMap<String, Value>, from: &str, to: &str) {
+        if let Some(v) = nbt.remove(from) {
+            nbt.insert(to.to_string(), v);
+        }
+    }
+    let filename = "playerdata/065992de-c443-44b6-98f7-9f3470f3e9fa.dat";
+    let mut nbt: HashMap<String, Value> = fastnbt::from_reader(GzDecoder::new(src.open_file(filename).unwrap())).unwrap();
+    if let Some(Value::List(items)) = nbt.get_mut("Inventory") {
+        for item in items {
+            if let Value::Compound(item) = item {
+                if let Some(Value::Byte(count)) = item.remove("Count") {
+                    item.insert("count".to_string(), Value::Int(count as i32));
+                }
+                rename(item, "tag", "components");
+            }
+        }
+    }
+    rename(&mut nbt, "Attributes", "attributes");
+    if let Some(Value::List(attributes)) = nbt.get_mut("attributes") {
+        for attr in attributes {
+            if let Value::Compound(attr) = attr {
+                rename(attr, "Name", "id");
+                rename(attr, "Base", "base");
+                rename(attr, "Modifiers", "modifiers");
+                if let Some(Value::List(modifiers)) = attr.get_mut("modifiers") {
+                    for modifier in modifiers {
+                        if let Value::Compound(modifier) = modifier {
+                            modifier.remove("UUID");
+                            rename(modifier, "Name", "id");
+                            rename(modifier, "Amount", "amount");
+                            modifier.insert("operation".to_string(), Value::String("add_value".to_string()));
+                        }
+                    }
+                }
+            }
+        }
+    }
+    let dir = std::env::temp_dir().join(format!("mc_schem_test_players_1_21_{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("playerdata")).unwrap();
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    std::io::Write::write_all(&mut encoder, &fastnbt::to_bytes(&nbt).unwrap()).unwrap();
+    std::fs::write(dir.join(filename), encoder.finish().unwrap()).unwrap();
+    let files = crate::world::FolderOnDisk::new(dir.to_str().unwrap());
+    let (new_players, errors) = players_from_files(&files);
+    assert!(errors.is_empty(), "{errors:?}");
+    let new_player = &new_players["065992de-c443-44b6-98f7-9f3470f3e9fa"];
+    assert_eq!(new_player.player_fields.inventory.0.len(), 3);
+    for (slot, item) in &player.player_fields.inventory.0 {
+        let new_item = &new_player.player_fields.inventory.0[slot];
+        assert_eq!((&new_item.id, new_item.count), (&item.id, item.count));
+    }
+    assert!(!new_player.mob_fields.attributes.is_empty());
+    assert_eq!(new_player.mob_fields.attributes.len(), player.mob_fields.attributes.len());
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    // corrupted player files are skipped
+    let dir = std::env::temp_dir().join(format!("mc_schem_test_players_{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("playerdata")).unwrap();
+    std::fs::write(dir.join("playerdata/broken.dat"), [1, 2, 3]).unwrap();
+    let files = crate::world::FolderOnDisk::new(dir.to_str().unwrap());
+    let (players, errors) = players_from_files(&files);
+    assert!(players.is_empty());
+    assert!(errors.contains_key("playerdata/broken.dat"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
 #[test]
 fn load_all_worlds() {
     let files = [
@@ -487,7 +487,562 @@ fn load_save_world_edit12() {
     //let src_dir = "./test_files/schematic";
     let out_dir = "./target/test/load_save_world_edit12";
     create_dir_all(out_dir).unwrap();
-    let _ = Schematic::from_world_edit_12_file("./test_files/schematic/full-blocks-1.12.2.schematic", &WorldEdit12LoadOption::default()).unwrap();
+    let schem = Schematic::from_world_edit_12_file("./test_files/schematic/full-blocks-1.12.2.schematic", &WorldEdit12LoadOption::default()).unwrap().0;
+
+    let dst_filename = format!("{}/full-blocks-1.12.2.schematic", out_dir);
+    schem.save_world_edit_12_file(&dst_filename, &schem::WorldEdit12SaveOption::default()).expect("Failed to save .schematic file");
+
+    let reloaded = Schematic::from_world_edit_12_file(&dst_filename, &WorldEdit12LoadOption::default()).expect("Failed to load saved .schematic file").0;
+    assert_eq!(schem.shape(), reloaded.shape());
+    for x in 0..schem.shape()[0] {
+        for y in 0..schem.shape()[1] {
+            for z in 0..schem.shape()[2] {
+                let pos = [x, y, z];
+                assert_eq!(schem.first_block_at(pos), reloaded.first_block_at(pos), "Block at {pos:?} changed after saving");
+            }
+        }
+    }
+}
+
+#[test]
+fn modern_block_to_old_number_id() {
+    let cases = [
+        ("minecraft:air", (0, 0)),
+        ("minecraft:granite", (1, 1)),
+        ("minecraft:grass_block[snowy=false]", (2, 0)),
+        ("minecraft:oak_log[axis=x]", (17, 4)),
+        ("minecraft:spruce_leaves[distance=1,persistent=true]", (18, 5)),
+        ("minecraft:white_wool", (35, 0)),
+        ("minecraft:light_gray_concrete", (251, 8)),
+        ("minecraft:wall_torch[facing=north]", (50, 4)),
+        ("minecraft:oak_slab[type=top,waterlogged=false]", (126, 8)),
+        ("minecraft:quartz_pillar[axis=x]", (155, 3)),
+    ];
+    for (id, expected) in cases {
+        let block = Block::from_id(id).unwrap();
+        assert_eq!(block.to_old(), Some(expected), "Wrong number id for {id}");
+    }
+
+    let block = Block::from_id("minecraft:cherry_planks").unwrap();
+    assert_eq!(block.to_old(), None);
+
+    let mut schem = Schematic::new();
+    let mut region = Region::with_shape([1, 1, 1]);
+    region.set_block([0, 0, 0], &block).unwrap();
+    schem.regions.push(region);
+    assert!(schem.to_nbt_world_edit_12(&schem::WorldEdit12SaveOption::default()).is_err());
+}
+
+#[test]
+fn java_bedrock_block_translation() {
+    use mc_schem::bedrock_block::BedrockBlock;
+    use fastnbt::Value;
+
+    let cases = [
+        ("minecraft:stone", "minecraft:stone"),
+        ("minecraft:oak_log[axis=x]", "minecraft:oak_log"),
+        ("minecraft:snow[layers=3]", "minecraft:snow_layer"),
+        ("minecraft:snow_block", "minecraft:snow"),
+        ("minecraft:furnace[facing=east,lit=true]", "minecraft:lit_furnace"),
+        ("minecraft:oak_slab[type=double]", "minecraft:oak_double_slab"),
+        ("minecraft:wall_torch[facing=north]", "minecraft:torch"),
+        ("minecraft:repeater[delay=2,facing=south,locked=false,powered=true]", "minecraft:powered_repeater"),
+    ];
+    for (java, bedrock) in cases {
+        let block = Block::from_id(java).unwrap();
+        assert_eq!(block.to_bedrock().name, bedrock, "Wrong bedrock name for {java}");
+    }
+
+    let stairs = Block::from_id("minecraft:oak_stairs[facing=north,half=top,shape=straight,waterlogged=false]").unwrap().to_bedrock();
+    assert_eq!(stairs.states.get("weirdo_direction"), Some(&Value::Int(3)));
+    assert_eq!(stairs.states.get("upside_down_bit"), Some(&Value::Byte(1)));
+    assert!(!stairs.states.contains_key("waterlogged"));
+
+    let mut snow = BedrockBlock::new("minecraft:snow_layer");
+    snow.states.insert("height".to_string(), Value::Int(7));
+    assert_eq!(Block::from_bedrock(&snow), Block::from_id("minecraft:snow[layers=8]").unwrap());
+
+    // these blocks have no properties that are dropped by bedrock
+    let round_trip = [
+        "minecraft:air",
+        "minecraft:oak_log[axis=z]",
+        "minecraft:stone_slab[type=top]",
+        "minecraft:stone_slab[type=double]",
+        "minecraft:oak_door[facing=west,half=upper,hinge=right,open=true]",
+        "minecraft:spruce_trapdoor[facing=south,half=bottom,open=false]",
+        "minecraft:observer[facing=up,powered=false]",
+        "minecraft:dispenser[facing=down,triggered=false]",
+        "minecraft:redstone_wall_torch[facing=east,lit=false]",
+        "minecraft:comparator[facing=west,mode=subtract,powered=false]",
+        "minecraft:lever[face=wall,facing=south,powered=true]",
+        "minecraft:stone_button[face=floor,facing=north,powered=false]",
+        "minecraft:water[level=0]",
+        "minecraft:wheat[age=5]",
+        "minecraft:beetroots[age=1]",
+        "minecraft:cobblestone_wall[east=low,north=none,south=tall,up=true,west=none]",
+        "minecraft:rail[shape=ascending_north]",
+        "minecraft:hopper[enabled=false,facing=down]",
+    ];
+    for id in round_trip {
+        let block = Block::from_id(id).unwrap();
+        assert_eq!(Block::from_bedrock(&block.to_bedrock()), block, "{id} changed after converting to bedrock and back");
+    }
+}
+
+#[test]
+fn load_save_bedrock_structure() {
+    use mc_schem::{BedrockStructureLoadOption, BedrockStructureSaveOption};
+    let out_dir = "./target/test/load_save_bedrock_structure";
+    create_dir_all(out_dir).unwrap();
+
+    let mut region = Region::with_shape([3, 2, 4]);
+    let blocks = ["minecraft:stone", "minecraft:oak_slab[type=bottom,waterlogged=true]",
+        "minecraft:chest[facing=east,waterlogged=false]", "minecraft:structure_void"];
+    for x in 0..3 {
+        for y in 0..2 {
+            for z in 0..4 {
+                let block = Block::from_id(blocks[((x + y + z) % 4) as usize]).unwrap();
+                region.set_block([x, y, z], &block).unwrap();
+            }
+        }
+    }
+    let mut be = BlockEntity::new();
+    be.tags.insert("id".to_string(), fastnbt::Value::String("Chest".to_string()));
+    region.block_entities.insert([0, 0, 2], be);
+    let mut schem = Schematic::new();
+    schem.regions.push(region);
+
+    let filename = format!("{out_dir}/out01.mcstructure");
+    schem.save_bedrock_structure_file(&filename, &BedrockStructureSaveOption::default()).expect("Failed to save .mcstructure file");
+    let loaded = Schematic::from_bedrock_structure_file(&filename, &BedrockStructureLoadOption::default())
+        .expect("Failed to load saved .mcstructure file").0;
+
+    assert_eq!(loaded.shape(), schem.shape());
+    for x in 0..3 {
+        for y in 0..2 {
+            for z in 0..4 {
+                let mut expected = schem.first_block_at([x, y, z]).unwrap().clone();
+                if expected.attributes.get("waterlogged").map(|w| w == "false").unwrap_or(false) {
+                    expected.attributes.remove("waterlogged");
+                }
+                assert_eq!(loaded.first_block_at([x, y, z]), Some(&expected), "Block at [{x}, {y}, {z}] changed");
+            }
+        }
+    }
+    assert!(loaded.first_block_entity_at([0, 0, 2]).is_some());
+
+    // convert a real schematic
+    let lite = Schematic::from_file("./test_files/litematica/test01.litematic").unwrap().0;
+    let filename = format!("{out_dir}/test01.mcstructure");
+    lite.save_to_file(&filename).expect("Failed to save .mcstructure file");
+    let loaded = Schematic::from_file(&filename).expect("Failed to load saved .mcstructure file").0;
+    assert_eq!(loaded.shape(), lite.shape());
+
+    // truncated byte array with a huge length should be an error rather than a huge allocation
+    let mut truncated: Vec<u8> = vec![10, 0, 0, 7, 1, 0, b'a'];
+    truncated.extend_from_slice(&i32::MAX.to_le_bytes());
+    truncated.extend_from_slice(&[1, 2, 3]);
+    assert!(Schematic::from_bedrock_structure_reader(&mut truncated.as_slice(), &BedrockStructureLoadOption::default()).is_err());
+
+    // java block entities and entities are translated
+    let mut region = Region::with_shape([1, 1, 1]);
+    region.set_block([0, 0, 0], &Block::from_id("minecraft:blast_furnace[facing=north,lit=false]").unwrap()).unwrap();
+    let mut be = BlockEntity::new();
+    be.tags.insert("id".to_string(), fastnbt::Value::String("minecraft:blast_furnace".to_string()));
+    let item = HashMap::from([
+        ("Slot".to_string(), fastnbt::Value::Byte(0)),
+        ("id".to_string(), fastnbt::Value::String("minecraft:coal".to_string())),
+        ("count".to_string(), fastnbt::Value::Int(5)),
+    ]);
+    be.tags.insert("Items".to_string(), fastnbt::Value::List(vec![fastnbt::Value::Compound(item)]));
+    region.block_entities.insert([0, 0, 0], be);
+    let mut pig = mc_schem::Entity::new();
+    pig.tags.insert("id".to_string(), fastnbt::Value::String("minecraft:pig".to_string()));
+    region.entities.push(pig);
+    let mut schem = Schematic::new();
+    schem.regions.push(region);
+    let nbt = schem.to_nbt_bedrock_structure(&BedrockStructureSaveOption::default()).unwrap();
+    let structure = match &nbt["structure"] {
+        fastnbt::Value::Compound(s) => s,
+        _ => panic!("structure should be a compound"),
+    };
+    let entity = match &structure["entities"] {
+        fastnbt::Value::List(list) => list[0].clone(),
+        _ => panic!("entities should be a list"),
+    };
+    assert_eq!(entity, fastnbt::Value::Compound(HashMap::from([
+        ("identifier".to_string(), fastnbt::Value::String("minecraft:pig".to_string())),
+        ("Pos".to_string(), fastnbt::Value::List(vec![fastnbt::Value::Float(0.0); 3])),
+    ])));
+    let palette = match &structure["palette"] {
+        fastnbt::Value::Compound(p) => match &p["default"] {
+            fastnbt::Value::Compound(d) => d,
+            _ => panic!("default palette should be a compound"),
+        },
+        _ => panic!("palette should be a compound"),
+    };
+    let be = match &palette["block_position_data"] {
+        fastnbt::Value::Compound(data) => match &data["0"] {
+            fastnbt::Value::Compound(d) => match &d["block_entity_data"] {
+                fastnbt::Value::Compound(be) => be,
+                _ => panic!("block_entity_data should be a compound"),
+            },
+            _ => panic!("block position data should be a compound"),
+        },
+        _ => panic!("block_position_data should be a compound"),
+    };
+    assert_eq!(be["id"], fastnbt::Value::String("BlastFurnace".to_string()));
+    assert_eq!(be["Items"], fastnbt::Value::List(vec![fastnbt::Value::Compound(HashMap::from([
+        ("Slot".to_string(), fastnbt::Value::Byte(0)),
+        ("Name".to_string(), fastnbt::Value::String("minecraft:coal".to_string())),
+        ("Count".to_string(), fastnbt::Value::Byte(5)),
+        ("Damage".to_string(), fastnbt::Value::Short(0)),
+        ("WasPickedUp".to_string(), fastnbt::Value::Byte(0)),
+    ]))]));
+
+    // strings longer than 65535 bytes can't be written
+    let mut be = BlockEntity::new();
+    be.tags.insert("CustomName".to_string(), fastnbt::Value::String("a".repeat(70000)));
+    schem.regions[0].block_entities.insert([0, 0, 0], be);
+    let mut bytes = Vec::new();
+    assert!(schem.save_bedrock_structure_writer(&mut bytes, &BedrockStructureSaveOption::default()).is_err());
+}
+
+#[test]
+fn rotate_and_mirror_blocks() {
+    use mc_schem::transform::{Axis, Rotation};
+    let cases = [
+        ("minecraft:oak_stairs[facing=north,half=bottom,shape=outer_left]", Rotation::Clockwise90, "minecraft:oak_stairs[facing=east,half=bottom,shape=outer_left]"),
+        ("minecraft:oak_log[axis=x]", Rotation::Clockwise90, "minecraft:oak_log[axis=z]"),
+        ("minecraft:oak_sign[rotation=0]", Rotation::Clockwise90, "minecraft:oak_sign[rotation=4]"),
+        ("minecraft:rail[shape=south_east]", Rotation::Clockwise90, "minecraft:rail[shape=south_west]"),
+        ("minecraft:rail[shape=ascending_north]", Rotation::Clockwise180, "minecraft:rail[shape=ascending_south]"),
+        ("minecraft:oak_fence[east=true,north=false,south=false,west=false]", Rotation::Counterclockwise90, "minecraft:oak_fence[east=false,north=true,south=false,west=false]"),
+    ];
+    for (src, rotation, expected) in cases {
+        let rotated = Block::from_id(src).unwrap().rotated_y(rotation);
+        assert_eq!(rotated, Block::from_id(expected).unwrap(), "Failed to rotate {src} by {rotation}");
+    }
+
+    let stairs = Block::from_id("minecraft:oak_stairs[facing=east,half=bottom,shape=inner_right]").unwrap();
+    assert_eq!(stairs.mirrored(Axis::X), Block::from_id("minecraft:oak_stairs[facing=west,half=bottom,shape=inner_left]").unwrap());
+    assert_eq!(stairs.mirrored(Axis::Y), Block::from_id("minecraft:oak_stairs[facing=east,half=top,shape=inner_right]").unwrap());
+
+    let piston = Block::from_id("minecraft:piston[extended=false,facing=up]").unwrap();
+    assert_eq!(piston.rotated_x(Rotation::Clockwise90), Block::from_id("minecraft:piston[extended=false,facing=north]").unwrap());
+    assert_eq!(piston.rotated_z(Rotation::Clockwise90), Block::from_id("minecraft:piston[extended=false,facing=east]").unwrap());
+}
+
+#[test]
+fn rotate_and_mirror_schematic() {
+    use mc_schem::transform::{Axis, Rotation};
+    let src = Schematic::from_litematica_file("./test_files/litematica/test01.litematic", &LitematicaLoadOption::default()).unwrap().0;
+    let shape = src.shape();
+
+    let mut rotated = Schematic::from_litematica_file("./test_files/litematica/test01.litematic", &LitematicaLoadOption::default()).unwrap().0;
+    rotated.rotate_y(Rotation::Clockwise90);
+    assert_eq!(rotated.shape(), [shape[2], shape[1], shape[0]]);
+    for x in 0..shape[0] {
+        for y in 0..shape[1] {
+            for z in 0..shape[2] {
+                let expected = src.first_block_at([x, y, z]).unwrap().rotated_y(Rotation::Clockwise90);
+                let new_pos = [shape[2] - 1 - z, y, x];
+                assert_eq!(rotated.first_block_at(new_pos), Some(&expected));
+                assert_eq!(rotated.first_block_entity_at(new_pos).is_some(), src.first_block_entity_at([x, y, z]).is_some());
+            }
+        }
+    }
+
+    for _ in 0..3 {
+        rotated.rotate_y(Rotation::Clockwise90);
+    }
+    rotated.mirror(Axis::Z);
+    rotated.mirror(Axis::Z);
+    rotated.rotate_x(Rotation::Clockwise180);
+    rotated.rotate_x(Rotation::Clockwise180);
+    assert_eq!(rotated.shape(), shape);
+    for x in 0..shape[0] {
+        for y in 0..shape[1] {
+            for z in 0..shape[2] {
+                assert_eq!(rotated.first_block_at([x, y, z]), src.first_block_at([x, y, z]));
+            }
+        }
+    }
+
+    let multi = Schematic::from_litematica_file("./test_files/litematica/multi-region01.litematic", &LitematicaLoadOption::default()).unwrap().0;
+    let mut rotated = Schematic::from_litematica_file("./test_files/litematica/multi-region01.litematic", &LitematicaLoadOption::default()).unwrap().0;
+    rotated.rotate_y(Rotation::Clockwise180);
+    assert_eq!(rotated.shape(), multi.shape());
+    rotated.rotate_y(Rotation::Clockwise180);
+    for a in &multi.regions {
+        let b = rotated.regions.iter().find(|r| r.name == a.name).unwrap();
+        assert_eq!(a.offset, b.offset, "Offset of region {} changed", a.name);
+    }
+}
+
+#[test]
+fn upgrade_schematic() {
+    use mc_schem::region::WorldSlice;
+    let mut region = Region::with_shape([4, 1, 1]);
+    region.set_block([0, 0, 0], &Block::from_id("grass_path").unwrap()).unwrap();
+    region.set_block([1, 0, 0], &Block::from_id("cobblestone_wall[north=true,south=false,east=false,west=false,up=true,waterlogged=false]").unwrap()).unwrap();
+    region.set_block([2, 0, 0], &Block::from_id("sign[rotation=0,waterlogged=false]").unwrap()).unwrap();
+    region.set_block([3, 0, 0], &Block::from_id("cauldron[level=2]").unwrap()).unwrap();
+    let mut sign = BlockEntity::new();
+    for idx in 1..=4 {
+        sign.tags.insert(format!("Text{idx}"), Value::String(format!("{{\"text\":\"line{idx}\"}}")));
+    }
+    sign.tags.insert("Color".to_string(), Value::String("red".to_string()));
+    region.block_entities.insert([2, 0, 0], sign);
+    let mut pigman = mc_schem::Entity::new();
+    pigman.tags.insert("id".to_string(), Value::String("minecraft:zombie_pigman".to_string()));
+    region.entities.push(pigman);
+
+    let mut schem = Schematic::new();
+    schem.metadata.mc_data_version = DataVersion::Java_1_13 as i32;
+    schem.regions.push(region);
+
+    schem.upgrade_to(DataVersion::Java_1_20_4).unwrap();
+    assert_eq!(schem.metadata.mc_data_version, DataVersion::Java_1_20_4 as i32);
+    let region = &schem.regions[0];
+    assert_eq!(region.block_at([0, 0, 0]).unwrap().id, "dirt_path");
+    let wall = region.block_at([1, 0, 0]).unwrap();
+    assert_eq!(wall.attributes.get("north").unwrap(), "low");
+    assert_eq!(wall.attributes.get("south").unwrap(), "none");
+    assert_eq!(region.block_at([2, 0, 0]).unwrap().id, "oak_sign");
+    assert_eq!(region.block_at([3, 0, 0]).unwrap().id, "water_cauldron");
+    let sign = &region.block_entities[&[2, 0, 0]];
+    assert!(!sign.tags.contains_key("Text1"));
+    if let Some(Value::Compound(front)) = sign.tags.get("front_text") {
+        assert_eq!(front.get("color"), Some(&Value::String("red".to_string())));
+    } else {
+        panic!("front_text is missing");
+    }
+    assert_eq!(region.entities[0].tags.get("id"), Some(&Value::String("minecraft:zombified_piglin".to_string())));
+
+    assert!(schem.upgrade_to(DataVersion::Java_1_16).is_err());
+    schem.metadata.mc_data_version = DataVersion::Java_1_12_2 as i32;
+    assert!(schem.upgrade_to(DataVersion::Java_1_20_4).is_err());
+
+    // blocks that become identical after renaming are merged
+    let mut region = Region::with_shape([2, 1, 1]);
+    region.set_block([0, 0, 0], &Block::from_id("grass_path").unwrap()).unwrap();
+    region.set_block([1, 0, 0], &Block::from_id("dirt_path").unwrap()).unwrap();
+    let mut schem = Schematic::new();
+    schem.metadata.mc_data_version = DataVersion::Java_1_13 as i32;
+    schem.regions.push(region);
+    schem.upgrade_to(DataVersion::Java_1_20_4).unwrap();
+    let region = &schem.regions[0];
+    assert_eq!(region.block_index_at([0, 0, 0]), region.block_index_at([1, 0, 0]));
+    assert_eq!(region.palette.iter().filter(|b| b.id == "dirt_path").count(), 1);
+}
+
+#[test]
+fn downgrade_schematic() {
+    use mc_schem::region::WorldSlice;
+    use mc_schem::DowngradePolicy;
+    let ids = [
+        "cherry_stairs[facing=east,half=top,shape=straight,waterlogged=false]",
+        "short_grass",
+        "dirt_path",
+        "deepslate_copper_ore",
+        "pink_petals[facing=north,flower_amount=2]",
+        "cobblestone_wall[north=low,south=none,east=tall,west=none,up=true,waterlogged=false]",
+    ];
+    let make_schem = || {
+        let mut region = Region::with_shape([ids.len() as i32, 1, 1]);
+        for (x, id) in ids.iter().enumerate() {
+            region.set_block([x as i32, 0, 0], &Block::from_id(id).unwrap()).unwrap();
+        }
+        region.block_entities.insert([4, 0, 0], BlockEntity::new());
+        let mut schem = Schematic::new();
+        schem.metadata.mc_data_version = DataVersion::Java_1_20_4 as i32;
+        schem.regions.push(region);
+        return schem;
+    };
+
+    let mut schem = make_schem();
+    let mut strict = make_schem();
+    let policy = DowngradePolicy { default_block: None, ..DowngradePolicy::default() };
+    assert!(strict.downgrade_to(DataVersion::Java_1_15_2, &policy).is_err());
+
+    let report = schem.downgrade_to(DataVersion::Java_1_15_2, &DowngradePolicy::default()).unwrap();
+    assert_eq!(schem.metadata.mc_data_version, DataVersion::Java_1_15_2 as i32);
+    assert_eq!(report.substitutions.len(), 3);
+    assert_eq!(report.removed_block_entities, 1);
+    let region = &schem.regions[0];
+    let stairs = region.block_at([0, 0, 0]).unwrap();
+    assert_eq!(stairs.id, "oak_stairs");
+    assert_eq!(stairs.attributes.get("facing").unwrap(), "east");
+    assert_eq!(region.block_at([1, 0, 0]).unwrap().id, "grass");
+    assert_eq!(region.block_at([2, 0, 0]).unwrap().id, "grass_path");
+    assert_eq!(region.block_at([3, 0, 0]).unwrap().id, "iron_ore");
+    assert!(region.block_at([4, 0, 0]).unwrap().is_air());
+    let wall = region.block_at([5, 0, 0]).unwrap();
+    assert_eq!(wall.attributes.get("north").unwrap(), "true");
+    assert_eq!(wall.attributes.get("south").unwrap(), "false");
+
+    let mut custom = DowngradePolicy::default();
+    custom.fallbacks.insert("pink_petals".to_string(), Block::from_id("poppy").unwrap());
+    let report = make_schem().downgrade_to(DataVersion::Java_1_15_2, &custom).unwrap();
+    assert!(report.substitutions.iter().any(|s| s.original.id == "pink_petals" && s.replacement.id == "poppy" && s.count == 1));
+
+    // block entities are kept only if the replacement has a compatible one
+    let mut region = Region::with_shape([3, 1, 1]);
+    region.set_block([0, 0, 0], &Block::from_id("chiseled_bookshelf[facing=north]").unwrap()).unwrap();
+    region.set_block([1, 0, 0], &Block::from_id("cherry_hanging_sign[rotation=0,attached=false,waterlogged=false]").unwrap()).unwrap();
+    region.set_block([2, 0, 0], &Block::from_id("copper_block").unwrap()).unwrap();
+    for x in 0..2 {
+        region.block_entities.insert([x, 0, 0], BlockEntity::new());
+    }
+    let mut camel = mc_schem::Entity::new();
+    camel.tags.insert("id".to_string(), Value::String("minecraft:camel".to_string()));
+    region.entities.push(camel);
+    let mut schem = Schematic::new();
+    schem.metadata.mc_data_version = DataVersion::Java_1_20_4 as i32;
+    schem.regions.push(region);
+    let mut policy = DowngradePolicy::default();
+    policy.fallbacks.insert("copper_block".to_string(), Block::from_id("iron_block").unwrap());
+    let report = schem.downgrade_to(DataVersion::Java_1_16_5, &policy).unwrap();
+    assert_eq!(report.removed_block_entities, 1);
+    assert_eq!(report.removed_entities, 1);
+    let region = &schem.regions[0];
+    assert_eq!(region.block_at([0, 0, 0]).unwrap().id, "bookshelf");
+    assert!(region.block_entities.get(&[0, 0, 0]).is_none());
+    assert_eq!(region.block_at([1, 0, 0]).unwrap().id, "oak_sign");
+    assert_eq!(region.block_entities[&[1, 0, 0]].tags.get("id"), Some(&Value::String("minecraft:sign".to_string())));
+    assert_eq!(region.block_at([2, 0, 0]).unwrap().id, "iron_block");
+    assert!(region.entities.is_empty());
+    assert_eq!(schem.metadata.litematica_version, 5);
+    assert_eq!(schem.metadata.litematica_subversion, None);
+}
+
+#[test]
+fn detect_schem_format() {
+    use mc_schem::SchemFormat;
+    let files = [
+        ("./test_files/litematica/test01.litematic", SchemFormat::Litematica),
+        ("./test_files/schem/test01.schem", SchemFormat::WorldEdit13),
+        ("./test_files/schem/full-blocks-1.20.2.schem", SchemFormat::WorldEdit13),
+        ("./test_files/schematic/full-blocks-1.12.2.schematic", SchemFormat::WorldEdit12),
+        ("./test_files/vanilla_structure/test01.nbt", SchemFormat::VanillaStructure),
+    ];
+    create_dir_all("./target/test/detect_schem_format").unwrap();
+    for (filename, format) in files {
+        let bytes = std::fs::read(filename).unwrap();
+        assert_eq!(SchemFormat::detect(&bytes), Some(format.clone()), "Failed to detect {filename}");
+        let (schem, _) = Schematic::from_bytes_auto(&bytes).unwrap();
+        let (expected, _) = Schematic::from_file(filename).unwrap();
+        assert_eq!(schem.shape(), expected.shape());
+
+        let renamed = format!("./target/test/detect_schem_format/{format}.bin");
+        std::fs::write(&renamed, &bytes).unwrap();
+        Schematic::from_file(&renamed).unwrap();
+    }
+
+    let schem = Schematic::from_file("./test_files/vanilla_structure/test01.nbt").unwrap().0;
+    let filename = "./target/test/detect_schem_format/out.mcstructure";
+    schem.save_to_file(filename).unwrap();
+    let mut file = File::open(filename).unwrap();
+    let (loaded, _) = Schematic::from_reader_auto(&mut file).unwrap();
+    assert_eq!(loaded.shape(), schem.shape());
+
+    assert_eq!(SchemFormat::detect(b"not a schematic"), None);
+    assert!(Schematic::from_bytes_auto(&[10, 0, 0, 0]).is_err());
+}
+
+#[test]
+fn material_list() {
+    use mc_schem::{MaterialList, MaterialListOption};
+    use mc_schem::material_list::{ItemBreakdown, stack_size};
+    let ids = [
+        "stone_slab[type=double,waterlogged=false]",
+        "stone_slab[type=bottom,waterlogged=false]",
+        "oak_door[facing=north,half=lower,hinge=left,open=false,powered=false]",
+        "oak_door[facing=north,half=upper,hinge=left,open=false,powered=false]",
+        "water[level=0]",
+        "water[level=3]",
+        "redstone_wire[east=none,north=none,power=0,south=none,west=none]",
+        "chest[facing=north,type=single,waterlogged=false]",
+        "air",
+    ];
+    let mut region = Region::with_shape([ids.len() as i32, 1, 1]);
+    for (x, id) in ids.iter().enumerate() {
+        region.set_block([x as i32, 0, 0], &Block::from_id(id).unwrap()).unwrap();
+    }
+    let mut chest = BlockEntity::new();
+    let item = HashMap::from([
+        ("id".to_string(), Value::String("minecraft:diamond".to_string())),
+        ("Count".to_string(), Value::Byte(5)),
+        ("Slot".to_string(), Value::Byte(0)),
+    ]);
+    chest.tags.insert("Items".to_string(), Value::List(vec![Value::Compound(item)]));
+    region.block_entities.insert([7, 0, 0], chest);
+    let mut schem = Schematic::new();
+    schem.regions.push(region);
+
+    let list = schem.material_list(&MaterialListOption::default());
+    assert_eq!(list.items.get("minecraft:stone_slab"), Some(&3));
+    assert_eq!(list.items.get("minecraft:oak_door"), Some(&1));
+    assert_eq!(list.items.get("minecraft:water_bucket"), Some(&1));
+    assert_eq!(list.items.get("minecraft:redstone"), Some(&1));
+    assert_eq!(list.items.get("minecraft:chest"), Some(&1));
+    assert!(!list.items.contains_key("minecraft:air"));
+    assert!(!list.items.contains_key("minecraft:diamond"));
+    assert_eq!(list.sorted()[0], ("minecraft:stone_slab", 3));
+
+    let list = MaterialList::from_schematic(&schem, &MaterialListOption { include_containers: true });
+    assert_eq!(list.items.get("minecraft:diamond"), Some(&5));
+    assert!(list.to_csv().starts_with("item,count,stack_size,shulker_boxes,stacks,items\nminecraft:diamond,5,64,0,0,5\n"));
+    assert!(list.to_json().contains("{\"item\": \"minecraft:diamond\", \"count\": 5,"));
+
+    assert_eq!(stack_size("minecraft:water_bucket"), 1);
+    assert_eq!(stack_size("oak_sign"), 16);
+    assert_eq!(ItemBreakdown::new(64 * 27 * 2 + 64 * 3 + 5, 64), ItemBreakdown { shulker_boxes: 2, stacks: 3, items: 5 });
+}
+
+#[test]
+fn trim_schematic() {
+    use mc_schem::region::WorldSlice;
+    let stone = Block::from_id("minecraft:stone").unwrap();
+    let not_air = |blk: &Block| !blk.is_air() && !blk.is_structure_void();
+    let mut region = Region::with_shape([6, 5, 7]);
+    region.offset = [10, 0, 10];
+    region.set_block([1, 2, 3], &stone).unwrap();
+    region.set_block([3, 3, 5], &Block::from_id("minecraft:chest").unwrap()).unwrap();
+    region.set_block([4, 4, 6], &Block::from_id("minecraft:structure_void").unwrap()).unwrap();
+    region.block_entities.insert([3, 3, 5], BlockEntity::new());
+    region.block_entities.insert([0, 0, 0], BlockEntity::new());
+    let mut entity = mc_schem::Entity::new();
+    entity.block_pos = [2, 2, 4];
+    entity.position = [2.5, 2.0, 4.5];
+    region.entities.push(entity);
+    let mut outside = mc_schem::Entity::new();
+    outside.block_pos = [5, 0, 0];
+    outside.position = [5.5, 0.0, 0.5];
+    region.entities.push(outside);
+
+    assert!(region.trim(not_air).unwrap());
+    assert_eq!(region.shape(), [3, 2, 3]);
+    assert_eq!(region.offset, [11, 2, 13]);
+    assert_eq!(region.block_at([0, 0, 0]), Some(&stone));
+    assert_eq!(region.block_at([2, 1, 2]).unwrap().id, "chest");
+    assert_eq!(region.block_entities.len(), 1);
+    assert!(region.block_entities.contains_key(&[2, 1, 2]));
+    assert_eq!(region.entities.len(), 1);
+    assert_eq!(region.entities[0].block_pos, [1, 0, 1]);
+    assert_eq!(region.palette.len(), 3);
+
+    let mut empty = Region::with_shape([2, 2, 2]);
+    empty.name = "empty".to_string();
+    let mut schem = Schematic::new();
+    schem.regions.push(region);
+    schem.regions.push(empty);
+    schem.trim(not_air).unwrap();
+    assert_eq!(schem.regions.len(), 1);
+    assert_eq!(schem.regions[0].offset, [0, 0, 0]);
+    assert_eq!(schem.shape(), [3, 2, 3]);
+    assert_eq!(schem.metadata.schem_offset, [11, 2, 13]);
 }
 
 #[test]